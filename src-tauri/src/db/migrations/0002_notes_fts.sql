@@ -0,0 +1,32 @@
+-- full-text index over note titles and content.
+-- external content table: the text lives in `notes`, the index only stores tokens,
+-- and the triggers below keep the two in step.
+
+CREATE VIRTUAL TABLE notes_fts USING fts5(
+    title,
+    content,
+    content = 'notes',
+    content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+CREATE TRIGGER notes_fts_after_insert AFTER INSERT ON notes BEGIN
+    INSERT INTO notes_fts (rowid, title, content)
+    VALUES (new.id, new.title, new.content);
+END;
+
+CREATE TRIGGER notes_fts_after_delete AFTER DELETE ON notes BEGIN
+    INSERT INTO notes_fts (notes_fts, rowid, title, content)
+    VALUES ('delete', old.id, old.title, old.content);
+END;
+
+-- only reindex when the searchable text actually changed (pin/archive toggles don't)
+CREATE TRIGGER notes_fts_after_update AFTER UPDATE OF title, content ON notes BEGIN
+    INSERT INTO notes_fts (notes_fts, rowid, title, content)
+    VALUES ('delete', old.id, old.title, old.content);
+    INSERT INTO notes_fts (rowid, title, content)
+    VALUES (new.id, new.title, new.content);
+END;
+
+-- index the notes that already exist
+INSERT INTO notes_fts (notes_fts) VALUES ('rebuild');
@@ -0,0 +1,17 @@
+-- wiki-style [[links]] between notes, rebuilt from a note's content whenever it is saved.
+-- targets are stored by title and resolved against `notes` when read, so a link starts
+-- resolving as soon as a note with that title exists
+
+CREATE TABLE note_links (
+    id              INTEGER PRIMARY KEY AUTOINCREMENT,
+    source_note_id  INTEGER NOT NULL,
+    target_title    TEXT NOT NULL,
+    heading         TEXT,
+    alias           TEXT,
+    position        INTEGER NOT NULL, -- byte offset of the title in the source content
+    FOREIGN KEY (source_note_id) REFERENCES notes (id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_note_links_source ON note_links (source_note_id);
+CREATE INDEX idx_note_links_target ON note_links (target_title COLLATE NOCASE);
+CREATE INDEX idx_notes_title ON notes (title COLLATE NOCASE);
@@ -0,0 +1,14 @@
+-- where imported notes came from, so importing the same source again skips what is
+-- already there. rows go away with their note, after which the note can be imported again
+
+CREATE TABLE note_imports (
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    note_id      INTEGER NOT NULL,
+    source       TEXT NOT NULL, -- importer that created the note, e.g. 'markdown'
+    source_key   TEXT NOT NULL, -- identifies the note within that importer, e.g. its file path
+    imported_at  TEXT NOT NULL,
+    FOREIGN KEY (note_id) REFERENCES notes (id) ON DELETE CASCADE,
+    UNIQUE (source, source_key)
+);
+
+CREATE INDEX idx_note_imports_note ON note_imports (note_id);
@@ -0,0 +1,11 @@
+-- app-wide preferences the backend acts on by itself, such as the revision retention
+-- policy. values are JSON so a setting can grow fields without a migration
+
+CREATE TABLE app_settings (
+    key         TEXT PRIMARY KEY,
+    value       TEXT NOT NULL,
+    updated_at  TEXT NOT NULL
+);
+
+-- retention walks each note's revisions in date order
+CREATE INDEX idx_note_revisions_note_created ON note_revisions (note_id, created_at);
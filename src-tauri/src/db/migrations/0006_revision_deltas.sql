@@ -0,0 +1,18 @@
+-- revisions are stored compressed, most of them as a delta against the revision before.
+-- the old table is kept aside so the backfill can encode its rows, then dropped by it
+
+ALTER TABLE note_revisions RENAME TO note_revisions_legacy;
+DROP INDEX idx_note_revisions_note_created;
+
+CREATE TABLE note_revisions (
+    id                INTEGER PRIMARY KEY AUTOINCREMENT,
+    note_id           INTEGER NOT NULL,
+    base_revision_id  INTEGER,       -- the revision `data` is a delta against, NULL for a keyframe
+    data              BLOB NOT NULL, -- compressed content or delta, see features::notes::delta
+    created_at        TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (note_id) REFERENCES notes (id) ON DELETE CASCADE,
+    FOREIGN KEY (base_revision_id) REFERENCES note_revisions (id)
+);
+
+CREATE INDEX idx_note_revisions_note_created ON note_revisions (note_id, created_at);
+CREATE INDEX idx_note_revisions_base ON note_revisions (base_revision_id);
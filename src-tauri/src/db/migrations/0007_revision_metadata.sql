@@ -0,0 +1,5 @@
+-- revisions keep the rest of the note too: title, folder, tags, color, pinned and archived,
+-- as JSON so a field can be added without a migration. NULL for revisions saved before
+-- this, which only have their content
+
+ALTER TABLE note_revisions ADD COLUMN metadata TEXT;
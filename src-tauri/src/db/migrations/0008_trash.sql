@@ -0,0 +1,11 @@
+-- deleting a note, folder or habit moves it to the trash by setting deleted_at. the row
+-- and everything that cascades from it (revisions, attachments, completions) stay until
+-- the item is purged
+
+ALTER TABLE notes ADD COLUMN deleted_at TEXT;
+ALTER TABLE note_folders ADD COLUMN deleted_at TEXT;
+ALTER TABLE habits ADD COLUMN deleted_at TEXT;
+
+CREATE INDEX idx_notes_deleted ON notes (deleted_at);
+CREATE INDEX idx_note_folders_deleted ON note_folders (deleted_at);
+CREATE INDEX idx_habits_deleted ON habits (deleted_at);
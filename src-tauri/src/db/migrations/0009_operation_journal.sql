@@ -0,0 +1,20 @@
+-- undo/redo history. every mutating command is one operation, and each row it changed
+-- gets an entry with the SQL that reverts the change and the SQL that replays it. the
+-- triggers that write entries are TEMP, created on startup by features::journal
+
+CREATE TABLE operation_journal (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    label       TEXT NOT NULL, -- the command that made the changes, e.g. 'delete_note'
+    state       TEXT NOT NULL CHECK (state IN ('done', 'undone', 'final')), -- 'final' can't be undone
+    created_at  TEXT NOT NULL
+);
+
+CREATE TABLE operation_journal_entries (
+    id            INTEGER PRIMARY KEY AUTOINCREMENT,
+    operation_id  INTEGER NOT NULL,
+    undo_sql      TEXT NOT NULL,
+    redo_sql      TEXT NOT NULL,
+    FOREIGN KEY (operation_id) REFERENCES operation_journal (id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_operation_journal_entries_operation ON operation_journal_entries (operation_id);
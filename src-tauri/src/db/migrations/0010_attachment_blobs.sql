@@ -0,0 +1,36 @@
+-- attachment files are stored once per distinct content, named by their SHA-256 hash, and
+-- shared by every note_attachments row with that content. ref_count is kept up to date by
+-- the triggers below, so it also drops when attachments cascade away with their note; the
+-- file itself is removed once nothing refers to it any more
+
+CREATE TABLE attachment_blobs (
+    hash        TEXT PRIMARY KEY,  -- hex SHA-256 of the content
+    file_path   TEXT NOT NULL,     -- relative to the app data directory
+    file_size   INTEGER NOT NULL,
+    ref_count   INTEGER NOT NULL DEFAULT 0,
+    created_at  TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+-- NULL for an attachment whose file was already missing when blobs were introduced
+ALTER TABLE note_attachments ADD COLUMN blob_hash TEXT REFERENCES attachment_blobs (hash);
+
+CREATE INDEX idx_note_attachments_blob ON note_attachments (blob_hash);
+
+CREATE TRIGGER attachment_blobs_ref_insert AFTER INSERT ON note_attachments
+WHEN NEW.blob_hash IS NOT NULL
+BEGIN
+    UPDATE attachment_blobs SET ref_count = ref_count + 1 WHERE hash = NEW.blob_hash;
+END;
+
+CREATE TRIGGER attachment_blobs_ref_delete AFTER DELETE ON note_attachments
+WHEN OLD.blob_hash IS NOT NULL
+BEGIN
+    UPDATE attachment_blobs SET ref_count = ref_count - 1 WHERE hash = OLD.blob_hash;
+END;
+
+CREATE TRIGGER attachment_blobs_ref_update AFTER UPDATE OF blob_hash ON note_attachments
+WHEN OLD.blob_hash IS NOT NEW.blob_hash
+BEGIN
+    UPDATE attachment_blobs SET ref_count = ref_count - 1 WHERE hash = OLD.blob_hash;
+    UPDATE attachment_blobs SET ref_count = ref_count + 1 WHERE hash = NEW.blob_hash;
+END;
@@ -0,0 +1,5 @@
+-- what was read from an attachment's content: image size, page count, duration or text
+-- encoding, as JSON (see features::notes::sniff). it belongs to the content, so it's kept on
+-- the blob and shared by every attachment of it. NULL when the file was missing
+
+ALTER TABLE attachment_blobs ADD COLUMN metadata TEXT;
@@ -0,0 +1,14 @@
+-- attachments the frontend sends in chunks, e.g. a large file dropped from a browser. the
+-- content is appended to note_attachments/.upload_{id} until the upload is finished, then
+-- stored like any other attachment and the row deleted
+
+CREATE TABLE attachment_uploads (
+    id             INTEGER PRIMARY KEY AUTOINCREMENT,
+    note_id        INTEGER NOT NULL,
+    file_name      TEXT NOT NULL,
+    file_type      TEXT NOT NULL,
+    received_size  INTEGER NOT NULL DEFAULT 0,
+    created_at     TEXT NOT NULL,
+    updated_at     TEXT NOT NULL,
+    FOREIGN KEY (note_id) REFERENCES notes (id) ON DELETE CASCADE
+);
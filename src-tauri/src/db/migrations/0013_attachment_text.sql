@@ -0,0 +1,35 @@
+-- text extracted from PDF and text attachments when they're added (see
+-- features::notes::extract), so search finds notes by what their attachments say. NULL for
+-- types without text. indexed like notes_fts, together with the file name
+
+ALTER TABLE note_attachments ADD COLUMN extracted_text TEXT;
+
+CREATE VIRTUAL TABLE attachments_fts USING fts5(
+    file_name,
+    extracted_text,
+    content = 'note_attachments',
+    content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+CREATE TRIGGER attachments_fts_after_insert AFTER INSERT ON note_attachments BEGIN
+    INSERT INTO attachments_fts (rowid, file_name, extracted_text)
+    VALUES (new.id, new.file_name, new.extracted_text);
+END;
+
+CREATE TRIGGER attachments_fts_after_delete AFTER DELETE ON note_attachments BEGIN
+    INSERT INTO attachments_fts (attachments_fts, rowid, file_name, extracted_text)
+    VALUES ('delete', old.id, old.file_name, old.extracted_text);
+END;
+
+CREATE TRIGGER attachments_fts_after_update AFTER UPDATE OF file_name, extracted_text
+ON note_attachments BEGIN
+    INSERT INTO attachments_fts (attachments_fts, rowid, file_name, extracted_text)
+    VALUES ('delete', old.id, old.file_name, old.extracted_text);
+    INSERT INTO attachments_fts (rowid, file_name, extracted_text)
+    VALUES (new.id, new.file_name, new.extracted_text);
+END;
+
+-- the text of existing attachments is filled in by the migration's backfill, through the
+-- update trigger; this indexes their file names
+INSERT INTO attachments_fts (attachments_fts) VALUES ('rebuild');
@@ -0,0 +1,8 @@
+-- attachments that point at a file elsewhere on disk instead of a stored copy, for files too
+-- big to copy. their file_path is absolute and they have no blob. file_size, linked_hash and
+-- linked_modified_at are what the file was when it was linked, to notice it being moved or
+-- changed later
+
+ALTER TABLE note_attachments ADD COLUMN is_linked INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE note_attachments ADD COLUMN linked_hash TEXT;
+ALTER TABLE note_attachments ADD COLUMN linked_modified_at TEXT;
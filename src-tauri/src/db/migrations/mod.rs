@@ -0,0 +1,350 @@
+use super::init::DbError;
+use crate::features::notes::repository::blobs::{
+    dedupe_attachment_files, describe_attachment_blobs, extract_attachment_text,
+};
+use crate::features::notes::repository::links::index_all_note_links;
+use crate::features::notes::repository::revisions::encode_legacy_revisions;
+use chrono::Utc;
+use log::{info, warn};
+use rusqlite::Connection;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// data work that SQL alone can't do, run after a migration's `sql` in the same transaction.
+// it returns the files it made redundant, which are only deleted once the migration has
+// committed: the pre-migration backup covers the database but not the files, so a rollback
+// must still find every file its rows point at
+pub type Backfill = fn(&Connection) -> rusqlite::Result<Vec<PathBuf>>;
+
+// a single schema change, identified by the `PRAGMA user_version` it brings the database to
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+    pub backfill: Option<Backfill>,
+}
+
+// every migration ever shipped, in order. never edit or reorder an entry once released,
+// add a new one with the next version number instead
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial schema",
+        sql: include_str!("0001_initial_schema.sql"),
+        backfill: None,
+    },
+    Migration {
+        version: 2,
+        description: "full-text search for notes",
+        sql: include_str!("0002_notes_fts.sql"),
+        backfill: None,
+    },
+    Migration {
+        version: 3,
+        description: "links between notes",
+        sql: include_str!("0003_note_links.sql"),
+        backfill: Some(index_all_note_links),
+    },
+    Migration {
+        version: 4,
+        description: "import sources of notes",
+        sql: include_str!("0004_note_imports.sql"),
+        backfill: None,
+    },
+    Migration {
+        version: 5,
+        description: "app settings",
+        sql: include_str!("0005_app_settings.sql"),
+        backfill: None,
+    },
+    Migration {
+        version: 6,
+        description: "delta-compressed revisions",
+        sql: include_str!("0006_revision_deltas.sql"),
+        backfill: Some(encode_legacy_revisions),
+    },
+    Migration {
+        version: 7,
+        description: "note metadata in revisions",
+        sql: include_str!("0007_revision_metadata.sql"),
+        backfill: None,
+    },
+    Migration {
+        version: 8,
+        description: "trash",
+        sql: include_str!("0008_trash.sql"),
+        backfill: None,
+    },
+    Migration {
+        version: 9,
+        description: "undo/redo journal",
+        sql: include_str!("0009_operation_journal.sql"),
+        backfill: None,
+    },
+    Migration {
+        version: 10,
+        description: "content-addressed attachment storage",
+        sql: include_str!("0010_attachment_blobs.sql"),
+        backfill: Some(dedupe_attachment_files),
+    },
+    Migration {
+        version: 11,
+        description: "attachment types and metadata from content",
+        sql: include_str!("0011_attachment_metadata.sql"),
+        backfill: Some(describe_attachment_blobs),
+    },
+    Migration {
+        version: 12,
+        description: "chunked attachment uploads",
+        sql: include_str!("0012_attachment_uploads.sql"),
+        backfill: None,
+    },
+    Migration {
+        version: 13,
+        description: "searchable attachment text",
+        sql: include_str!("0013_attachment_text.sql"),
+        backfill: Some(extract_attachment_text),
+    },
+    Migration {
+        version: 14,
+        description: "linked attachments",
+        sql: include_str!("0014_linked_attachments.sql"),
+        backfill: None,
+    },
+];
+
+// latest schema version this build knows about
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+pub fn current_version(conn: &Connection) -> Result<i64, DbError> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    Ok(version)
+}
+
+// migrations that still need to run against this database
+pub fn pending_migrations(conn: &Connection) -> Result<Vec<&'static Migration>, DbError> {
+    let version = current_version(conn)?;
+    let latest = latest_version();
+
+    // refuse to touch a database written by a newer build
+    if version > latest {
+        return Err(DbError::UnsupportedSchemaVersion {
+            found: version,
+            supported: latest,
+        });
+    }
+
+    Ok(MIGRATIONS.iter().filter(|m| m.version > version).collect())
+}
+
+// whether the database has any user tables yet (a brand new file has nothing worth backing up)
+fn has_tables(conn: &Connection) -> Result<bool, DbError> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+// apply all pending migrations, each in its own transaction.
+// returns the number of migrations that were applied
+pub fn run_migrations(conn: &mut Connection) -> Result<usize, DbError> {
+    let pending = pending_migrations(conn)?;
+
+    for migration in &pending {
+        let tx = conn.transaction()?;
+
+        let redundant_files = tx
+            .execute_batch(migration.sql)
+            .and_then(|_| match migration.backfill {
+                Some(backfill) => backfill(&tx),
+                None => Ok(Vec::new()),
+            })
+            .map_err(|e| DbError::Migration {
+                version: migration.version,
+                description: migration.description,
+                source: e,
+            })?;
+
+        // user_version lives in the database header, so it commits or rolls back with the rest
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit().map_err(|e| DbError::Migration {
+            version: migration.version,
+            description: migration.description,
+            source: e,
+        })?;
+
+        info!(
+            "Applied migration {} ({})",
+            migration.version, migration.description
+        );
+
+        // a file left behind is harmless, attachment garbage collection removes it later
+        for file in redundant_files {
+            if let Err(e) = fs::remove_file(&file) {
+                warn!("Failed to delete redundant file {:?}: {}", file, e);
+            }
+        }
+    }
+
+    Ok(pending.len())
+}
+
+// copy the database file aside before migrating it, so a failed or buggy migration
+// never costs the user their data. returns the backup path, or None if there was nothing to back up
+pub fn backup_before_migration(
+    conn: &Connection,
+    db_path: &Path,
+    backup_dir: &Path,
+) -> Result<Option<PathBuf>, DbError> {
+    if pending_migrations(conn)?.is_empty() || !has_tables(conn)? {
+        return Ok(None);
+    }
+
+    fs::create_dir_all(backup_dir)?;
+
+    let version = current_version(conn)?;
+    let file_stem = db_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "database".to_string());
+    let backup_path = backup_dir.join(format!(
+        "{}.v{}.{}.db",
+        file_stem,
+        version,
+        Utc::now().format("%Y%m%dT%H%M%S")
+    ));
+
+    fs::copy(db_path, &backup_path)?;
+
+    info!("Backed up database to {:?} before migrating", backup_path);
+    Ok(Some(backup_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::TestDir;
+    use rusqlite::params;
+
+    // bring a database up to an older schema version, the way an older build left it
+    fn migrate_to(conn: &Connection, version: i64) {
+        for migration in MIGRATIONS.iter().take_while(|m| m.version <= version) {
+            conn.execute_batch(migration.sql).unwrap();
+            if let Some(backfill) = migration.backfill {
+                backfill(conn).unwrap();
+            }
+        }
+        conn.pragma_update(None, "user_version", version).unwrap();
+    }
+
+    // a version 9 database next to two attachments with the same content and one other
+    fn database_with_duplicate_attachments(dir: &TestDir) -> Connection {
+        let conn = Connection::open(dir.path().join("apto.db")).unwrap();
+        migrate_to(&conn, 9);
+
+        conn.execute("INSERT INTO notes (title, content) VALUES ('Note', '')", [])
+            .unwrap();
+        for (name, contents) in [("a.txt", "same"), ("b.txt", "same"), ("c.txt", "other")] {
+            let file_path = format!("note_attachments/{}", name);
+            dir.write(&file_path, contents.as_bytes());
+            conn.execute(
+                "INSERT INTO note_attachments (note_id, file_name, file_path, file_type, file_size)
+                 VALUES (1, ?1, ?2, 'txt', 4)",
+                params![name, file_path],
+            )
+            .unwrap();
+        }
+
+        conn
+    }
+
+    #[test]
+    fn migrates_an_empty_database_to_the_latest_version() {
+        let mut conn = Connection::open_in_memory().unwrap();
+
+        assert_eq!(run_migrations(&mut conn).unwrap(), MIGRATIONS.len());
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+        assert!(pending_migrations(&conn).unwrap().is_empty());
+
+        // a second run has nothing left to do
+        assert_eq!(run_migrations(&mut conn).unwrap(), 0);
+    }
+
+    #[test]
+    fn versions_are_sequential() {
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, index as i64 + 1);
+        }
+    }
+
+    #[test]
+    fn refuses_a_database_from_a_newer_build() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", latest_version() + 1)
+            .unwrap();
+
+        assert!(matches!(
+            pending_migrations(&conn),
+            Err(DbError::UnsupportedSchemaVersion { .. })
+        ));
+    }
+
+    #[test]
+    fn nothing_to_back_up_for_an_empty_database() {
+        let conn = Connection::open_in_memory().unwrap();
+        let dir = std::env::temp_dir();
+
+        let backup = backup_before_migration(&conn, &dir.join("missing.db"), &dir).unwrap();
+        assert!(backup.is_none());
+    }
+
+    #[test]
+    fn duplicate_attachment_files_are_deleted_after_commit() {
+        let dir = TestDir::create();
+        let mut conn = database_with_duplicate_attachments(&dir);
+
+        run_migrations(&mut conn).unwrap();
+
+        assert!(dir.path().join("note_attachments/a.txt").exists());
+        assert!(!dir.path().join("note_attachments/b.txt").exists());
+        assert!(dir.path().join("note_attachments/c.txt").exists());
+
+        let paths: Vec<String> = conn
+            .prepare("SELECT file_path FROM note_attachments ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(
+            paths,
+            vec![
+                "note_attachments/a.txt",
+                "note_attachments/a.txt",
+                "note_attachments/c.txt"
+            ]
+        );
+    }
+
+    #[test]
+    fn rolled_back_dedupe_keeps_every_file() {
+        let dir = TestDir::create();
+        let mut conn = database_with_duplicate_attachments(&dir);
+        let migration = &MIGRATIONS[9];
+        assert_eq!(migration.version, 10);
+
+        let tx = conn.transaction().unwrap();
+        tx.execute_batch(migration.sql).unwrap();
+        let redundant = migration.backfill.unwrap()(&tx).unwrap();
+        tx.rollback().unwrap();
+
+        assert_eq!(redundant, vec![dir.path().join("note_attachments/b.txt")]);
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            assert!(dir.path().join("note_attachments").join(name).exists());
+        }
+    }
+}
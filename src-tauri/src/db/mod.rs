@@ -1 +1,7 @@
 pub mod init;
+pub mod migrations;
+pub mod settings;
+pub mod time;
+
+#[cfg(test)]
+pub mod testing;
@@ -0,0 +1,56 @@
+use crate::error::{AppError, AppResult, ErrorCode};
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// typed access to the `app_settings` table, one JSON value per key
+pub struct SettingsRepository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> SettingsRepository<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        SettingsRepository { conn }
+    }
+
+    // None when the setting was never saved
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> AppResult<Option<T>> {
+        let value: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT value FROM app_settings WHERE key = ?",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| AppError::database("Failed to get setting", e))?;
+
+        value
+            .map(|value| {
+                serde_json::from_str(&value).map_err(|e| {
+                    AppError::new(
+                        ErrorCode::Database,
+                        format!("Invalid value for setting {}: {}", key, e),
+                    )
+                })
+            })
+            .transpose()
+    }
+
+    pub fn set<T: Serialize>(&self, key: &str, value: &T) -> AppResult<()> {
+        let value = serde_json::to_string(value).map_err(|e| {
+            AppError::validation(format!("Failed to serialize setting {}: {}", key, e))
+        })?;
+
+        self.conn
+            .execute(
+                "INSERT INTO app_settings (key, value, updated_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+                params![key, value, Utc::now().to_rfc3339()],
+            )
+            .map_err(|e| AppError::database("Failed to save setting", e))?;
+
+        Ok(())
+    }
+}
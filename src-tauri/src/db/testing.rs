@@ -0,0 +1,69 @@
+use super::migrations::run_migrations;
+use crate::features::journal::repository::install_journal_triggers;
+use crate::features::notes::models::NoteInput;
+use crate::features::notes::repository::NoteRepository;
+use rusqlite::Connection;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// an empty in-memory database at the latest schema, set up the way initialize_database
+// sets up the real one
+pub fn open_test_db() -> Connection {
+    let mut conn = Connection::open_in_memory().expect("Failed to open in-memory database");
+    conn.execute("PRAGMA foreign_keys = ON;", [])
+        .expect("Failed to enable foreign keys");
+    run_migrations(&mut conn).expect("Failed to migrate in-memory database");
+    install_journal_triggers(&conn).expect("Failed to install journal triggers");
+    conn
+}
+
+// a note with no folder, flags or color
+pub fn note_input(title: &str, content: &str, tags: &[&str]) -> NoteInput {
+    NoteInput {
+        title: title.to_string(),
+        content: content.to_string(),
+        folder_id: None,
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        is_pinned: false,
+        is_archived: false,
+        color: None,
+    }
+}
+
+pub fn create_note(conn: &Connection, input: NoteInput) -> i64 {
+    NoteRepository::new(conn)
+        .create(input)
+        .expect("Failed to create note")
+}
+
+// a fresh directory under the system temp directory, removed again when dropped. stands in
+// for the app data directory in tests that store files
+pub struct TestDir(PathBuf);
+
+impl TestDir {
+    pub fn create() -> Self {
+        let path = std::env::temp_dir().join(format!("apto_test_{}", rand::random::<u64>()));
+        fs::create_dir_all(&path).expect("Failed to create test directory");
+        TestDir(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    // write a file at a path relative to the directory, creating its parents
+    pub fn write(&self, relative_path: &str, contents: &[u8]) -> PathBuf {
+        let path = self.0.join(relative_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("Failed to create test file directory");
+        }
+        fs::write(&path, contents).expect("Failed to write test file");
+        path
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
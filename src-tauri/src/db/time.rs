@@ -0,0 +1,22 @@
+use crate::error::{AppError, AppResult, ErrorCode};
+use chrono::{DateTime, Utc};
+
+// parse an RFC 3339 timestamp column, naming the field in the error
+pub fn parse_timestamp(value: &str, field: &str) -> AppResult<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|e| {
+            AppError::new(
+                ErrorCode::Database,
+                format!("Invalid {} date: {}", field, e),
+            )
+        })
+}
+
+// same as parse_timestamp for nullable columns
+pub fn parse_optional_timestamp(
+    value: Option<String>,
+    field: &str,
+) -> AppResult<Option<DateTime<Utc>>> {
+    value.map(|v| parse_timestamp(&v, field)).transpose()
+}
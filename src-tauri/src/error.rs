@@ -0,0 +1,153 @@
+use crate::db::init::DbError;
+use serde::Serialize;
+use std::fmt;
+use std::sync::PoisonError;
+use thiserror::Error;
+
+pub type AppResult<T> = Result<T, AppError>;
+
+// stable, machine-readable error category sent to the frontend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ErrorCode {
+    NotFound,          // the referenced row doesn't exist
+    Conflict,          // the change clashes with existing data (duplicate name, non-empty folder, ...)
+    Validation,        // the input itself is malformed
+    Database,          // sqlite failed or stored data couldn't be read back
+    Io,                // filesystem failure
+    QuotaExceeded,     // an attachment would take more space than the storage quota allows
+    NeedsConfirmation, // the action is risky; repeat it once the user has confirmed
+}
+
+// the kind of record an error refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityKind {
+    Note,
+    Folder,
+    NoteTag,
+    Revision,
+    Attachment,
+    AttachmentUpload,
+    Habit,
+    HabitTag,
+    Completion,
+    Reminder,
+}
+
+impl fmt::Display for EntityKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            EntityKind::Note => "note",
+            EntityKind::Folder => "folder",
+            EntityKind::NoteTag => "note tag",
+            EntityKind::Revision => "revision",
+            EntityKind::Attachment => "attachment",
+            EntityKind::AttachmentUpload => "attachment upload",
+            EntityKind::Habit => "habit",
+            EntityKind::HabitTag => "habit tag",
+            EntityKind::Completion => "completion",
+            EntityKind::Reminder => "reminder",
+        };
+        f.write_str(name)
+    }
+}
+
+// error returned by every command; serialized as { code, entity, id, message }
+#[derive(Debug, Error, Serialize)]
+#[error("{message}")]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub entity: Option<EntityKind>,
+    pub id: Option<i64>,
+    pub message: String,
+}
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        AppError {
+            code,
+            entity: None,
+            id: None,
+            message: message.into(),
+        }
+    }
+
+    // e.g. "note 42 not found"
+    pub fn not_found(entity: EntityKind, id: i64) -> Self {
+        AppError::new(ErrorCode::NotFound, format!("{} {} not found", entity, id))
+            .for_entity(entity, Some(id))
+    }
+
+    pub fn conflict(entity: EntityKind, id: Option<i64>, message: impl Into<String>) -> Self {
+        AppError::new(ErrorCode::Conflict, message).for_entity(entity, id)
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        AppError::new(ErrorCode::Validation, message)
+    }
+
+    // wrap a sqlite failure as "<context>: <error>". missing rows and constraint
+    // violations keep their own codes so callers don't have to inspect the error
+    pub fn database(context: &str, e: rusqlite::Error) -> Self {
+        let code = match &e {
+            rusqlite::Error::QueryReturnedNoRows => ErrorCode::NotFound,
+            rusqlite::Error::SqliteFailure(err, _)
+                if err.code == rusqlite::ErrorCode::ConstraintViolation =>
+            {
+                ErrorCode::Conflict
+            }
+            _ => ErrorCode::Database,
+        };
+        AppError::new(code, format!("{}: {}", context, e))
+    }
+
+    pub fn io(context: &str, e: std::io::Error) -> Self {
+        AppError::new(ErrorCode::Io, format!("{}: {}", context, e))
+    }
+
+    // attach the record this error is about
+    pub fn for_entity(mut self, entity: EntityKind, id: Option<i64>) -> Self {
+        self.entity = Some(entity);
+        self.id = id;
+        self
+    }
+
+    // like `database`, but a missing row becomes a proper not-found for that entity
+    pub fn lookup(entity: EntityKind, id: i64, context: &str, e: rusqlite::Error) -> Self {
+        match e {
+            rusqlite::Error::QueryReturnedNoRows => AppError::not_found(entity, id),
+            e => AppError::database(context, e).for_entity(entity, Some(id)),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        AppError::database("Database error", e)
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        AppError::io("Filesystem error", e)
+    }
+}
+
+impl<T> From<PoisonError<T>> for AppError {
+    fn from(e: PoisonError<T>) -> Self {
+        AppError::new(
+            ErrorCode::Database,
+            format!("Failed to lock DB mutex: {}", e),
+        )
+    }
+}
+
+impl From<DbError> for AppError {
+    fn from(e: DbError) -> Self {
+        let code = match e {
+            DbError::Io(_) | DbError::AppDataDir(_) => ErrorCode::Io,
+            _ => ErrorCode::Database,
+        };
+        AppError::new(code, e.to_string())
+    }
+}
@@ -1,54 +1,18 @@
 use crate::db::init::DbState;
-use rusqlite::params;
-use tauri::State;
-
+use crate::error::AppError;
 use crate::features::habits::models::HabitReminder;
+use crate::features::habits::repository::ReminderRepository;
+use crate::features::journal::repository::JournalRepository;
+use tauri::State;
 
 #[tauri::command]
 pub async fn get_habit_reminders(
     habit_id: i64,
     db_state: State<'_, DbState>,
-) -> Result<Vec<HabitReminder>, String> {
-    let conn = db_state
-        .0
-        .lock()
-        .map_err(|e| format!("Failed to lock DB mutex: {}", e))?;
-
-    let mut stmt = conn
-        .prepare(
-            "SELECT id, habit_id, time, days, is_enabled
-             FROM habit_reminders
-             WHERE habit_id = ?",
-        )
-        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
-
-    let reminders_iter = stmt
-        .query_map(params![habit_id], |row| {
-            let days_str: String = row.get(3)?;
-            let days: Vec<u32> = serde_json::from_str(&days_str).map_err(|_| {
-                rusqlite::Error::FromSqlConversionFailure(
-                    3,
-                    rusqlite::types::Type::Text,
-                    Box::new(std::fmt::Error),
-                )
-            })?;
-
-            Ok(HabitReminder {
-                id: row.get(0)?,
-                habit_id: row.get(1)?,
-                time: row.get(2)?,
-                days,
-                is_enabled: row.get::<_, i32>(4)? != 0,
-            })
-        })
-        .map_err(|e| format!("Failed to query reminders: {}", e))?;
-
-    let mut reminders = Vec::new();
-    for reminder_result in reminders_iter {
-        reminders.push(reminder_result.map_err(|e| format!("Failed to process reminder: {}", e))?);
-    }
+) -> Result<Vec<HabitReminder>, AppError> {
+    let conn = db_state.0.lock()?;
 
-    Ok(reminders)
+    ReminderRepository::new(&conn).get_for_habit(habit_id)
 }
 
 #[tauri::command]
@@ -58,23 +22,12 @@ pub async fn create_habit_reminder(
     days: Vec<u32>,
     is_enabled: bool,
     db_state: State<'_, DbState>,
-) -> Result<i64, String> {
-    let conn = db_state
-        .0
-        .lock()
-        .map_err(|e| format!("Failed to lock DB mutex: {}", e))?;
+) -> Result<i64, AppError> {
+    let conn = db_state.0.lock()?;
 
-    let days_json =
-        serde_json::to_string(&days).map_err(|e| format!("Failed to serialize days: {}", e))?;
-
-    conn.execute(
-        "INSERT INTO habit_reminders (habit_id, time, days, is_enabled) VALUES (?, ?, ?, ?)",
-        params![habit_id, time, days_json, is_enabled as i32],
-    )
-    .map_err(|e| format!("Failed to create reminder: {}", e))?;
-
-    let reminder_id = conn.last_insert_rowid();
-    Ok(reminder_id)
+    JournalRepository::new(&conn).record("create_habit_reminder", || {
+        ReminderRepository::new(&conn).create(habit_id, &time, &days, is_enabled)
+    })
 }
 
 #[tauri::command]
@@ -84,35 +37,21 @@ pub async fn update_habit_reminder(
     days: Vec<u32>,
     is_enabled: bool,
     db_state: State<'_, DbState>,
-) -> Result<(), String> {
-    let conn = db_state
-        .0
-        .lock()
-        .map_err(|e| format!("Failed to lock DB mutex: {}", e))?;
-
-    let days_json =
-        serde_json::to_string(&days).map_err(|e| format!("Failed to serialize days: {}", e))?;
+) -> Result<(), AppError> {
+    let conn = db_state.0.lock()?;
 
-    conn.execute(
-        "UPDATE habit_reminders SET time = ?, days = ?, is_enabled = ? WHERE id = ?",
-        params![time, days_json, is_enabled as i32, id],
-    )
-    .map_err(|e| format!("Failed to update reminder: {}", e))?;
-
-    Ok(())
+    JournalRepository::new(&conn).record("update_habit_reminder", || {
+        ReminderRepository::new(&conn).update(id, &time, &days, is_enabled)
+    })
 }
 
 #[tauri::command]
-pub async fn delete_habit_reminder(id: i64, db_state: State<'_, DbState>) -> Result<(), String> {
-    let conn = db_state
-        .0
-        .lock()
-        .map_err(|e| format!("Failed to lock DB mutex: {}", e))?;
-
-    conn.execute("DELETE FROM habit_reminders WHERE id = ?", params![id])
-        .map_err(|e| format!("Failed to delete reminder: {}", e))?;
+pub async fn delete_habit_reminder(id: i64, db_state: State<'_, DbState>) -> Result<(), AppError> {
+    let conn = db_state.0.lock()?;
 
-    Ok(())
+    JournalRepository::new(&conn).record("delete_habit_reminder", || {
+        ReminderRepository::new(&conn).delete(id)
+    })
 }
 
 #[tauri::command]
@@ -120,17 +59,10 @@ pub async fn toggle_reminder(
     id: i64,
     is_enabled: bool,
     db_state: State<'_, DbState>,
-) -> Result<(), String> {
-    let conn = db_state
-        .0
-        .lock()
-        .map_err(|e| format!("Failed to lock DB mutex: {}", e))?;
-
-    conn.execute(
-        "UPDATE habit_reminders SET is_enabled = ? WHERE id = ?",
-        params![is_enabled as i32, id],
-    )
-    .map_err(|e| format!("Failed to toggle reminder: {}", e))?;
+) -> Result<(), AppError> {
+    let conn = db_state.0.lock()?;
 
-    Ok(())
+    JournalRepository::new(&conn).record("toggle_reminder", || {
+        ReminderRepository::new(&conn).set_enabled(id, is_enabled)
+    })
 }
@@ -1,36 +1,15 @@
 use crate::db::init::DbState;
-use rusqlite::params;
-use tauri::State;
-
+use crate::error::AppError;
 use crate::features::habits::models::HabitTag;
+use crate::features::habits::repository::HabitTagRepository;
+use crate::features::journal::repository::JournalRepository;
+use tauri::State;
 
 #[tauri::command]
-pub async fn get_all_tags(db_state: State<'_, DbState>) -> Result<Vec<HabitTag>, String> {
-    let conn = db_state
-        .0
-        .lock()
-        .map_err(|e| format!("Failed to lock DB mutex: {}", e))?;
-
-    let mut stmt = conn
-        .prepare("SELECT id, name, color FROM habit_tags")
-        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
-
-    let tags_iter = stmt
-        .query_map([], |row| {
-            Ok(HabitTag {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                color: row.get(2)?,
-            })
-        })
-        .map_err(|e| format!("Failed to query tags: {}", e))?;
+pub async fn get_all_tags(db_state: State<'_, DbState>) -> Result<Vec<HabitTag>, AppError> {
+    let conn = db_state.0.lock()?;
 
-    let mut tags = Vec::new();
-    for tag_result in tags_iter {
-        tags.push(tag_result.map_err(|e| format!("Failed to process tag: {}", e))?);
-    }
-
-    Ok(tags)
+    HabitTagRepository::new(&conn).get_all()
 }
 
 #[tauri::command]
@@ -38,20 +17,12 @@ pub async fn create_tag(
     name: String,
     color: Option<String>,
     db_state: State<'_, DbState>,
-) -> Result<i64, String> {
-    let conn = db_state
-        .0
-        .lock()
-        .map_err(|e| format!("Failed to lock DB mutex: {}", e))?;
-
-    conn.execute(
-        "INSERT INTO habit_tags (name, color) VALUES (?, ?)",
-        params![name, color],
-    )
-    .map_err(|e| format!("Failed to create tag: {}", e))?;
+) -> Result<i64, AppError> {
+    let conn = db_state.0.lock()?;
 
-    let tag_id = conn.last_insert_rowid();
-    Ok(tag_id)
+    JournalRepository::new(&conn).record("create_tag", || {
+        HabitTagRepository::new(&conn).create(&name, color)
+    })
 }
 
 #[tauri::command]
@@ -60,30 +31,17 @@ pub async fn update_tag(
     name: String,
     color: Option<String>,
     db_state: State<'_, DbState>,
-) -> Result<(), String> {
-    let conn = db_state
-        .0
-        .lock()
-        .map_err(|e| format!("Failed to lock DB mutex: {}", e))?;
+) -> Result<(), AppError> {
+    let conn = db_state.0.lock()?;
 
-    conn.execute(
-        "UPDATE habit_tags SET name = ?, color = ? WHERE id = ?",
-        params![name, color, id],
-    )
-    .map_err(|e| format!("Failed to update tag: {}", e))?;
-
-    Ok(())
+    JournalRepository::new(&conn).record("update_tag", || {
+        HabitTagRepository::new(&conn).update(id, &name, color)
+    })
 }
 
 #[tauri::command]
-pub async fn delete_tag(id: i64, db_state: State<'_, DbState>) -> Result<(), String> {
-    let conn = db_state
-        .0
-        .lock()
-        .map_err(|e| format!("Failed to lock DB mutex: {}", e))?;
-
-    conn.execute("DELETE FROM habit_tags WHERE id = ?", params![id])
-        .map_err(|e| format!("Failed to delete tag: {}", e))?;
+pub async fn delete_tag(id: i64, db_state: State<'_, DbState>) -> Result<(), AppError> {
+    let conn = db_state.0.lock()?;
 
-    Ok(())
+    JournalRepository::new(&conn).record("delete_tag", || HabitTagRepository::new(&conn).delete(id))
 }
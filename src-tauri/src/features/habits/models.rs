@@ -73,3 +73,22 @@ pub struct HabitReminder {
     pub days: Vec<u32>, // days to remind (1-7 for weekly)
     pub is_enabled: bool,
 }
+
+// Fields accepted when creating or updating a habit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HabitInput {
+    pub name: String,
+    pub description: Option<String>,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+    pub frequency: FrequencyPattern,
+    pub target_value: Option<f64>,
+    pub target_unit: Option<String>,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+    pub is_active: bool,
+    pub priority: i32,
+    pub start_date: String, // YYYY-MM-DD
+    pub end_date: Option<String>,
+    pub reminder_time: Option<String>,
+}
@@ -0,0 +1,203 @@
+use crate::db::time::parse_optional_timestamp;
+use crate::error::{AppError, AppResult, EntityKind, ErrorCode};
+use crate::features::habits::models::HabitCompletion;
+use crate::features::habits::utils::deserialize_frequency;
+use crate::features::habits::utils::streaks::breaks_streak;
+use chrono::{DateTime, Utc};
+use log::info;
+use rusqlite::{params, Connection};
+
+pub struct CompletionRepository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> CompletionRepository<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        CompletionRepository { conn }
+    }
+
+    // record a completion now and advance (or reset) the habit's streak
+    pub fn add(
+        &self,
+        habit_id: i64,
+        value: Option<f64>,
+        notes: Option<String>,
+        mood: Option<i32>,
+        difficulty: Option<i32>,
+    ) -> AppResult<i64> {
+        let now = Utc::now();
+        let now_str = now.to_rfc3339();
+        let today = now.date_naive();
+
+        // get current habit info to calculate streaks
+        let (frequency_type, frequency_data, last_completed, current_streak, longest_streak): (
+            String,
+            String,
+            Option<String>,
+            i32,
+            i32,
+        ) = self
+            .conn
+            .query_row(
+                "SELECT frequency_type, frequency_data, last_completed, current_streak, longest_streak
+                 FROM habits WHERE id = ? AND deleted_at IS NULL",
+                params![habit_id],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                    ))
+                },
+            )
+            .map_err(|e| AppError::lookup(EntityKind::Habit, habit_id, "Failed to get habit info", e))?;
+
+        // parse frequency
+        let frequency = deserialize_frequency(&frequency_type, &frequency_data).map_err(|e| {
+            AppError::new(
+                ErrorCode::Database,
+                format!("Failed to deserialize frequency: {}", e),
+            )
+        })?;
+
+        let last_completed = parse_optional_timestamp(last_completed, "last_completed")?;
+
+        // determine if this completion continues or resets streak
+        let new_current_streak = match last_completed {
+            Some(last) => {
+                let last_date = last.date_naive();
+
+                // skip duplicate completions on the same day
+                if last_date == today {
+                    current_streak
+                } else if breaks_streak(&frequency, last, today) {
+                    // streak broken, reset to 1
+                    1
+                } else {
+                    // streak continues
+                    current_streak + 1
+                }
+            }
+            None => 1, // first completion, streak of 1
+        };
+
+        // calculate new longest streak
+        let new_longest_streak = std::cmp::max(longest_streak, new_current_streak);
+
+        // insert the completion
+        self.conn
+            .execute(
+                "INSERT INTO habit_completions (
+                    habit_id, completed_at, value, notes, mood, difficulty
+                ) VALUES (?, ?, ?, ?, ?, ?)",
+                params![habit_id, now_str, value, notes, mood, difficulty],
+            )
+            .map_err(|e| AppError::database("Failed to add completion", e))?;
+
+        let completion_id = self.conn.last_insert_rowid();
+
+        // update the habit's last_completed date and streak info
+        self.conn
+            .execute(
+                "UPDATE habits SET
+                    last_completed = ?,
+                    current_streak = ?,
+                    longest_streak = ?
+                WHERE id = ?",
+                params![now_str, new_current_streak, new_longest_streak, habit_id],
+            )
+            .map_err(|e| AppError::database("Failed to update habit", e))?;
+
+        info!(
+            "Added completion for habit ID {} with completion ID: {}. Streak: {}",
+            habit_id, completion_id, new_current_streak
+        );
+        Ok(completion_id)
+    }
+
+    // all completions of a habit, newest first
+    pub fn get_for_habit(&self, habit_id: i64) -> AppResult<Vec<HabitCompletion>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, habit_id, completed_at, value, notes, mood, difficulty
+                 FROM habit_completions
+                 WHERE habit_id = ?
+                 ORDER BY completed_at DESC",
+            )
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let completions_iter = stmt
+            .query_map(params![habit_id], |row| {
+                let completed_at_str: String = row.get(2)?;
+                let completed_at = DateTime::parse_from_rfc3339(&completed_at_str)
+                    .map_err(|_| {
+                        rusqlite::Error::FromSqlConversionFailure(
+                            2,
+                            rusqlite::types::Type::Text,
+                            Box::new(std::fmt::Error),
+                        )
+                    })?
+                    .with_timezone(&Utc);
+
+                Ok(HabitCompletion {
+                    id: row.get(0)?,
+                    habit_id: row.get(1)?,
+                    completed_at,
+                    value: row.get(3)?,
+                    notes: row.get(4)?,
+                    mood: row.get(5)?,
+                    difficulty: row.get(6)?,
+                })
+            })
+            .map_err(|e| AppError::database("Failed to query completions", e))?;
+
+        let mut completions = Vec::new();
+        for completion_result in completions_iter {
+            completions.push(
+                completion_result
+                    .map_err(|e| AppError::database("Failed to process completion", e))?,
+            );
+        }
+
+        Ok(completions)
+    }
+
+    pub fn update(
+        &self,
+        id: i64,
+        value: Option<f64>,
+        notes: Option<String>,
+        mood: Option<i32>,
+        difficulty: Option<i32>,
+    ) -> AppResult<()> {
+        let changed = self
+            .conn
+            .execute(
+                "UPDATE habit_completions SET value = ?, notes = ?, mood = ?, difficulty = ? WHERE id = ?",
+                params![value, notes, mood, difficulty, id],
+            )
+            .map_err(|e| AppError::database("Failed to update completion", e))?;
+
+        if changed == 0 {
+            return Err(AppError::not_found(EntityKind::Completion, id));
+        }
+
+        Ok(())
+    }
+
+    pub fn delete(&self, id: i64) -> AppResult<()> {
+        let changed = self
+            .conn
+            .execute("DELETE FROM habit_completions WHERE id = ?", params![id])
+            .map_err(|e| AppError::database("Failed to delete completion", e))?;
+
+        if changed == 0 {
+            return Err(AppError::not_found(EntityKind::Completion, id));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,788 @@
+use crate::db::time::{parse_optional_timestamp, parse_timestamp};
+use crate::error::{AppError, AppResult, EntityKind, ErrorCode};
+use crate::features::habits::models::{Habit, HabitInput, HabitStats};
+use crate::features::habits::utils::streaks::is_habit_due;
+use crate::features::habits::utils::{deserialize_frequency, serialize_frequency};
+use chrono::{NaiveDate, Utc};
+use log::info;
+use rusqlite::{params, Connection, Params, Row};
+use std::collections::HashMap;
+
+const HABIT_COLUMNS: &str = "id, name, description, category, frequency_type, frequency_data,
+    target_value, target_unit, color, icon, is_active, priority,
+    start_date, end_date, created_at, updated_at, reminder_time,
+    current_streak, longest_streak, last_completed";
+
+// raw column values of a `habits` row, before tags are attached and values parsed
+struct HabitRow {
+    id: i64,
+    name: String,
+    description: Option<String>,
+    category: Option<String>,
+    frequency_type: String,
+    frequency_data: String,
+    target_value: Option<f64>,
+    target_unit: Option<String>,
+    color: Option<String>,
+    icon: Option<String>,
+    is_active: i32,
+    priority: i32,
+    start_date: String,
+    end_date: Option<String>,
+    created_at: String,
+    updated_at: String,
+    reminder_time: Option<String>,
+    current_streak: i32,
+    longest_streak: i32,
+    last_completed: Option<String>,
+}
+
+impl HabitRow {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(HabitRow {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            category: row.get(3)?,
+            frequency_type: row.get(4)?,
+            frequency_data: row.get(5)?,
+            target_value: row.get(6)?,
+            target_unit: row.get(7)?,
+            color: row.get(8)?,
+            icon: row.get(9)?,
+            is_active: row.get(10)?,
+            priority: row.get(11)?,
+            start_date: row.get(12)?,
+            end_date: row.get(13)?,
+            created_at: row.get(14)?,
+            updated_at: row.get(15)?,
+            reminder_time: row.get(16)?,
+            current_streak: row.get(17)?,
+            longest_streak: row.get(18)?,
+            last_completed: row.get(19)?,
+        })
+    }
+}
+
+pub struct HabitRepository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> HabitRepository<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        HabitRepository { conn }
+    }
+
+    pub fn add(&self, input: HabitInput) -> AppResult<i64> {
+        let now = Utc::now().to_rfc3339();
+
+        // parse start_date
+        let start_date = NaiveDate::parse_from_str(&input.start_date, "%Y-%m-%d")
+            .map_err(|e| AppError::validation(format!("Invalid start date format: {}", e)))?;
+
+        // serialize frequency pattern
+        let (freq_type, freq_data) = serialize_frequency(&input.frequency)
+            .map_err(|e| AppError::validation(format!("Failed to serialize frequency: {}", e)))?;
+
+        // insert the habit
+        self.conn
+            .execute(
+                "INSERT INTO habits (
+                    name, description, category, frequency_type, frequency_data,
+                    target_value, target_unit, color, icon, is_active, priority,
+                    start_date, end_date, created_at, updated_at, reminder_time,
+                    current_streak, longest_streak
+                ) VALUES (
+                    ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, 0, 0
+                )",
+                params![
+                    input.name,
+                    input.description,
+                    input.category,
+                    freq_type,
+                    freq_data,
+                    input.target_value,
+                    input.target_unit,
+                    input.color,
+                    input.icon,
+                    input.is_active as i32,
+                    input.priority,
+                    start_date.to_string(),
+                    input.end_date,
+                    now,
+                    now,
+                    input.reminder_time
+                ],
+            )
+            .map_err(|e| AppError::database("Failed to add habit", e))?;
+
+        let habit_id = self.conn.last_insert_rowid();
+
+        // process tags
+        self.add_tag_mappings(habit_id, &input.tags)?;
+
+        // add reminder if specified
+        if let Some(time) = &input.reminder_time {
+            self.conn
+                .execute(
+                    "INSERT INTO habit_reminders (habit_id, time, days, is_enabled) VALUES (?, ?, ?, 1)",
+                    params![habit_id, time, default_reminder_days()?],
+                )
+                .map_err(|e| AppError::database("Failed to add reminder", e))?;
+        }
+
+        info!("Added habit '{}' with ID: {}", input.name, habit_id);
+        Ok(habit_id)
+    }
+
+    pub fn get_all(&self) -> AppResult<Vec<Habit>> {
+        self.query_habits(
+            &format!(
+                "SELECT {} FROM habits WHERE deleted_at IS NULL",
+                HABIT_COLUMNS
+            ),
+            [],
+        )
+    }
+
+    pub fn get_by_id(&self, id: i64) -> AppResult<Habit> {
+        let habit_row = self
+            .conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM habits WHERE id = ? AND deleted_at IS NULL",
+                    HABIT_COLUMNS
+                ),
+                params![id],
+                HabitRow::from_row,
+            )
+            .map_err(|e| AppError::lookup(EntityKind::Habit, id, "Failed to get habit", e))?;
+
+        self.build_habit(habit_row)
+    }
+
+    pub fn update(&self, id: i64, input: HabitInput) -> AppResult<()> {
+        let now = Utc::now().to_rfc3339();
+
+        // parse start_date
+        let start_date = NaiveDate::parse_from_str(&input.start_date, "%Y-%m-%d")
+            .map_err(|e| AppError::validation(format!("Invalid start date format: {}", e)))?;
+
+        // serialize frequency pattern
+        let (freq_type, freq_data) = serialize_frequency(&input.frequency)
+            .map_err(|e| AppError::validation(format!("Failed to serialize frequency: {}", e)))?;
+
+        // update the habit
+        let changed = self
+            .conn
+            .execute(
+                "UPDATE habits SET
+                    name = ?, description = ?, category = ?, frequency_type = ?, frequency_data = ?,
+                    target_value = ?, target_unit = ?, color = ?, icon = ?, is_active = ?, priority = ?,
+                    start_date = ?, end_date = ?, updated_at = ?, reminder_time = ?
+                 WHERE id = ? AND deleted_at IS NULL",
+                params![
+                    input.name,
+                    input.description,
+                    input.category,
+                    freq_type,
+                    freq_data,
+                    input.target_value,
+                    input.target_unit,
+                    input.color,
+                    input.icon,
+                    input.is_active as i32,
+                    input.priority,
+                    start_date.to_string(),
+                    input.end_date,
+                    now,
+                    input.reminder_time,
+                    id
+                ],
+            )
+            .map_err(|e| AppError::database("Failed to update habit", e))?;
+
+        if changed == 0 {
+            return Err(AppError::not_found(EntityKind::Habit, id));
+        }
+
+        // replace existing tag mappings for this habit
+        self.conn
+            .execute(
+                "DELETE FROM habit_tag_mappings WHERE habit_id = ?",
+                params![id],
+            )
+            .map_err(|e| AppError::database("Failed to delete tag mappings", e))?;
+
+        self.add_tag_mappings(id, &input.tags)?;
+
+        // keep the reminder in step with reminder_time
+        if let Some(time) = &input.reminder_time {
+            // check if a reminder exists
+            let reminder_exists: bool = self
+                .conn
+                .query_row(
+                    "SELECT 1 FROM habit_reminders WHERE habit_id = ? LIMIT 1",
+                    params![id],
+                    |_| Ok(true),
+                )
+                .unwrap_or(false);
+
+            if reminder_exists {
+                // update existing reminder
+                self.conn
+                    .execute(
+                        "UPDATE habit_reminders SET time = ?, days = ? WHERE habit_id = ?",
+                        params![time, default_reminder_days()?, id],
+                    )
+                    .map_err(|e| AppError::database("Failed to update reminder", e))?;
+            } else {
+                // create new reminder
+                self.conn
+                    .execute(
+                        "INSERT INTO habit_reminders (habit_id, time, days, is_enabled) VALUES (?, ?, ?, 1)",
+                        params![id, time, default_reminder_days()?],
+                    )
+                    .map_err(|e| AppError::database("Failed to add reminder", e))?;
+            }
+        } else {
+            // if reminder_time is None, delete existing reminders
+            self.conn
+                .execute(
+                    "DELETE FROM habit_reminders WHERE habit_id = ?",
+                    params![id],
+                )
+                .map_err(|e| AppError::database("Failed to delete reminders", e))?;
+        }
+
+        info!("Updated habit with ID: {}", id);
+        Ok(())
+    }
+
+    // move a habit to the trash. its completions and reminders stay until it's purged
+    pub fn delete(&self, id: i64) -> AppResult<()> {
+        let changed = self
+            .conn
+            .execute(
+                "UPDATE habits SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL",
+                params![Utc::now().to_rfc3339(), id],
+            )
+            .map_err(|e| AppError::database("Failed to delete habit", e))?;
+
+        if changed == 0 {
+            return Err(AppError::not_found(EntityKind::Habit, id));
+        }
+
+        info!("Moved habit with ID: {} to the trash", id);
+        Ok(())
+    }
+
+    pub fn set_active(&self, id: i64, is_active: bool) -> AppResult<()> {
+        let now = Utc::now().to_rfc3339();
+
+        let changed = self
+            .conn
+            .execute(
+                "UPDATE habits SET is_active = ?, updated_at = ? WHERE id = ? AND deleted_at IS NULL",
+                params![is_active as i32, now, id],
+            )
+            .map_err(|e| AppError::database("Failed to toggle habit active status", e))?;
+
+        if changed == 0 {
+            return Err(AppError::not_found(EntityKind::Habit, id));
+        }
+
+        info!(
+            "Toggled active status to {} for habit with ID: {}",
+            is_active, id
+        );
+        Ok(())
+    }
+
+    // reset the current streak of every active habit that missed a due day
+    pub fn refresh_streaks(&self) -> AppResult<()> {
+        let today = Utc::now().date_naive();
+
+        // get all active habits
+        let mut habit_stmt = self
+            .conn
+            .prepare(
+                "SELECT id, frequency_type, frequency_data, last_completed, current_streak
+                 FROM habits WHERE is_active = 1 AND deleted_at IS NULL",
+            )
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let habits_iter = habit_stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, i32>(4)?,
+                ))
+            })
+            .map_err(|e| AppError::database("Failed to query habits", e))?;
+
+        for habit_result in habits_iter {
+            let (id, frequency_type, frequency_data, last_completed, current_streak) =
+                habit_result.map_err(|e| AppError::database("Failed to process habit", e))?;
+
+            // parse frequency
+            let frequency =
+                deserialize_frequency(&frequency_type, &frequency_data).map_err(|e| {
+                    AppError::new(
+                        ErrorCode::Database,
+                        format!("Failed to deserialize frequency: {}", e),
+                    )
+                })?;
+
+            let last_completed = parse_optional_timestamp(last_completed, "last_completed")?;
+
+            // check if streak is broken
+            let mut streak_broken = false;
+            if let Some(last) = last_completed {
+                let last_date = last.date_naive();
+
+                if today > last_date {
+                    // check if habit was due on any day since last completion
+                    let mut check_date = last_date;
+                    while check_date < today {
+                        check_date = check_date.succ_opt().unwrap();
+                        if is_habit_due(&frequency, check_date, Some(last)) && check_date < today {
+                            streak_broken = true;
+                            break;
+                        }
+                    }
+                }
+            }
+
+            // reset streak if broken
+            if streak_broken && current_streak > 0 {
+                self.conn
+                    .execute(
+                        "UPDATE habits SET current_streak = 0 WHERE id = ?",
+                        params![id],
+                    )
+                    .map_err(|e| AppError::database("Failed to update streak", e))?;
+
+                info!("Reset streak for habit ID {} due to missed days", id);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn stats(&self, habit_id: i64) -> AppResult<HabitStats> {
+        // get total completions
+        let total_completions: i32 = self
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM habit_completions WHERE habit_id = ?",
+                params![habit_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| AppError::database("Failed to get completion count", e))?;
+
+        // get current and longest streaks from the habit table
+        let (current_streak, longest_streak): (i32, i32) = self
+            .conn
+            .query_row(
+                "SELECT current_streak, longest_streak FROM habits
+                 WHERE id = ? AND deleted_at IS NULL",
+                params![habit_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| {
+                AppError::lookup(EntityKind::Habit, habit_id, "Failed to get streak data", e)
+            })?;
+
+        // calculate average value if applicable
+        let average_value: Option<f64> = self
+            .conn
+            .query_row(
+                "SELECT AVG(value) FROM habit_completions WHERE habit_id = ? AND value IS NOT NULL",
+                params![habit_id],
+                |row| row.get(0),
+            )
+            .ok()
+            .flatten();
+
+        // get frequency data for the habit to calculate completion rate
+        let (frequency_type, frequency_data): (String, String) = self
+            .conn
+            .query_row(
+                "SELECT frequency_type, frequency_data FROM habits
+                 WHERE id = ? AND deleted_at IS NULL",
+                params![habit_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| {
+                AppError::lookup(
+                    EntityKind::Habit,
+                    habit_id,
+                    "Failed to get frequency data",
+                    e,
+                )
+            })?;
+
+        // get last 30 days completion status
+        let mut last_30_days = HashMap::new();
+        let today = Utc::now().date_naive();
+
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT strftime('%Y-%m-%d', completed_at) as completion_date
+                 FROM habit_completions
+                 WHERE habit_id = ?
+                 AND completed_at >= datetime('now', '-30 days')
+                 GROUP BY completion_date",
+            )
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let dates_iter = stmt
+            .query_map(params![habit_id], |row| row.get::<_, String>(0))
+            .map_err(|e| AppError::database("Failed to query completion dates", e))?;
+
+        // initialize all 30 days as false first
+        for i in 0..30 {
+            let date = today.checked_sub_days(chrono::Days::new(i as u64)).unwrap();
+            last_30_days.insert(date.format("%Y-%m-%d").to_string(), false);
+        }
+
+        // mark completed days as true
+        for date_result in dates_iter {
+            let date = date_result.map_err(|e| AppError::database("Failed to process date", e))?;
+            last_30_days.insert(date, true);
+        }
+
+        // calculate completion rate based on frequency and completed days
+        let expected_completions = match frequency_type.as_str() {
+            "daily" => 30, // daily for 30 days
+            "weekly" => {
+                let days: Vec<u32> = serde_json::from_str(&frequency_data).map_err(|e| {
+                    AppError::new(
+                        ErrorCode::Database,
+                        format!("Failed to parse frequency data: {}", e),
+                    )
+                })?;
+                (30 / 7) * days.len() as i32 + 1 // approx. number of occurrences in 30 days
+            }
+            "monthly" => 1, // only happens once a month
+            "interval" => {
+                let days: u32 = serde_json::from_str(&frequency_data).map_err(|e| {
+                    AppError::new(
+                        ErrorCode::Database,
+                        format!("Failed to parse frequency data: {}", e),
+                    )
+                })?;
+                30 / days as i32 // approx. number of occurrences in 30 days
+            }
+            _ => 30, // default to daily
+        };
+
+        let completion_rate = if expected_completions > 0 {
+            total_completions as f64 / expected_completions as f64
+        } else {
+            0.0
+        };
+
+        // clamp to 0.0-1.0 range
+        let completion_rate = completion_rate.clamp(0.0, 1.0);
+
+        Ok(HabitStats {
+            habit_id,
+            completion_rate,
+            current_streak,
+            longest_streak,
+            total_completions,
+            last_30_days,
+            average_value,
+        })
+    }
+
+    // tag names attached to a habit
+    pub fn get_tags(&self, habit_id: i64) -> AppResult<Vec<String>> {
+        let mut tags_stmt = self
+            .conn
+            .prepare(
+                "SELECT t.name FROM habit_tags t
+                 JOIN habit_tag_mappings m ON t.id = m.tag_id
+                 WHERE m.habit_id = ?",
+            )
+            .map_err(|e| AppError::database("Failed to prepare tags statement", e))?;
+
+        let tags_rows = tags_stmt
+            .query_map(params![habit_id], |row| row.get::<_, String>(0))
+            .map_err(|e| AppError::database("Failed to query tags", e))?;
+
+        let mut tags = Vec::new();
+        for tag_result in tags_rows {
+            tags.push(tag_result.map_err(|e| AppError::database("Failed to process tag", e))?);
+        }
+
+        Ok(tags)
+    }
+
+    fn query_habits<P: Params>(&self, sql: &str, params: P) -> AppResult<Vec<Habit>> {
+        let mut stmt = self
+            .conn
+            .prepare(sql)
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let habit_rows = stmt
+            .query_map(params, HabitRow::from_row)
+            .map_err(|e| AppError::database("Failed to query habits", e))?;
+
+        let mut habits = Vec::new();
+        for habit_result in habit_rows {
+            let habit_row =
+                habit_result.map_err(|e| AppError::database("Failed to process habit row", e))?;
+            habits.push(self.build_habit(habit_row)?);
+        }
+
+        Ok(habits)
+    }
+
+    // attach tags and parse frequency and dates for a raw row
+    fn build_habit(&self, habit_row: HabitRow) -> AppResult<Habit> {
+        let tags = self.get_tags(habit_row.id)?;
+
+        // parse frequency
+        let frequency = deserialize_frequency(&habit_row.frequency_type, &habit_row.frequency_data)
+            .map_err(|e| {
+                AppError::new(
+                    ErrorCode::Database,
+                    format!("Failed to deserialize frequency: {}", e),
+                )
+            })?;
+
+        // parse dates
+        let start_date =
+            NaiveDate::parse_from_str(&habit_row.start_date, "%Y-%m-%d").map_err(|e| {
+                AppError::new(ErrorCode::Database, format!("Invalid start date: {}", e))
+            })?;
+
+        let end_date = match habit_row.end_date {
+            Some(date) => Some(NaiveDate::parse_from_str(&date, "%Y-%m-%d").map_err(|e| {
+                AppError::new(ErrorCode::Database, format!("Invalid end date: {}", e))
+            })?),
+            None => None,
+        };
+
+        Ok(Habit {
+            id: habit_row.id,
+            name: habit_row.name,
+            description: habit_row.description,
+            category: habit_row.category,
+            tags,
+            frequency,
+            target_value: habit_row.target_value,
+            target_unit: habit_row.target_unit,
+            color: habit_row.color,
+            icon: habit_row.icon,
+            is_active: habit_row.is_active != 0,
+            priority: habit_row.priority,
+            start_date,
+            end_date,
+            created_at: parse_timestamp(&habit_row.created_at, "created_at")?,
+            updated_at: parse_timestamp(&habit_row.updated_at, "updated_at")?,
+            reminder_time: habit_row.reminder_time,
+            current_streak: habit_row.current_streak,
+            longest_streak: habit_row.longest_streak,
+            last_completed: parse_optional_timestamp(habit_row.last_completed, "last_completed")?,
+        })
+    }
+
+    // map tag names onto a habit, creating tags that don't exist yet
+    fn add_tag_mappings(&self, habit_id: i64, tags: &[String]) -> AppResult<()> {
+        for tag_name in tags {
+            // try to find if tag exists
+            let tag_id: Result<i64, rusqlite::Error> = self.conn.query_row(
+                "SELECT id FROM habit_tags WHERE name = ?",
+                params![tag_name],
+                |row| row.get(0),
+            );
+
+            let tag_id = match tag_id {
+                Ok(id) => id, // tag exists
+                Err(_) => {
+                    // tag doesn't exist, create it
+                    self.conn
+                        .execute(
+                            "INSERT INTO habit_tags (name) VALUES (?)",
+                            params![tag_name],
+                        )
+                        .map_err(|e| AppError::database("Failed to create tag", e))?;
+
+                    self.conn.last_insert_rowid()
+                }
+            };
+
+            // add tag mapping, ignoring mappings that already exist
+            self.conn
+                .execute(
+                    "INSERT OR IGNORE INTO habit_tag_mappings (habit_id, tag_id) VALUES (?, ?)",
+                    params![habit_id, tag_id],
+                )
+                .map_err(|e| AppError::database("Failed to add tag mapping", e))?;
+        }
+
+        Ok(())
+    }
+}
+
+// reminders created from reminder_time fire every day
+fn default_reminder_days() -> AppResult<String> {
+    let default_days = vec![1, 2, 3, 4, 5, 6, 7]; // all days
+    serde_json::to_string(&default_days)
+        .map_err(|e| AppError::validation(format!("Failed to serialize reminder days: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::open_test_db;
+    use crate::features::habits::models::FrequencyPattern;
+    use crate::features::habits::repository::{CompletionRepository, ReminderRepository};
+
+    fn input(name: &str) -> HabitInput {
+        HabitInput {
+            name: name.to_string(),
+            description: None,
+            category: None,
+            tags: vec!["health".to_string()],
+            frequency: FrequencyPattern::Weekly {
+                days: vec![1, 3, 5],
+            },
+            target_value: Some(8.0),
+            target_unit: Some("glasses".to_string()),
+            color: None,
+            icon: None,
+            is_active: true,
+            priority: 1,
+            start_date: "2026-01-01".to_string(),
+            end_date: None,
+            reminder_time: Some("08:00".to_string()),
+        }
+    }
+
+    fn reminder_count(conn: &Connection, habit_id: i64) -> i64 {
+        conn.query_row(
+            "SELECT COUNT(*) FROM habit_reminders WHERE habit_id = ?",
+            params![habit_id],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn add_and_get() {
+        let conn = open_test_db();
+        let habits = HabitRepository::new(&conn);
+
+        let id = habits.add(input("Water")).unwrap();
+        let habit = habits.get_by_id(id).unwrap();
+
+        assert_eq!(habit.name, "Water");
+        assert_eq!(habit.tags, vec!["health"]);
+        assert!(
+            matches!(habit.frequency, FrequencyPattern::Weekly { ref days } if days == &[1, 3, 5])
+        );
+        assert_eq!(
+            habit.start_date,
+            NaiveDate::from_ymd_opt(2026, 1, 1).unwrap()
+        );
+        assert_eq!(habit.current_streak, 0);
+        assert_eq!(reminder_count(&conn, id), 1);
+    }
+
+    #[test]
+    fn add_rejects_a_bad_start_date() {
+        let conn = open_test_db();
+        let mut bad = input("Water");
+        bad.start_date = "01/01/2026".to_string();
+
+        let err = HabitRepository::new(&conn).add(bad).unwrap_err();
+        assert_eq!(err.code, ErrorCode::Validation);
+    }
+
+    #[test]
+    fn update_without_a_reminder_time_drops_the_reminder() {
+        let conn = open_test_db();
+        let habits = HabitRepository::new(&conn);
+        let id = habits.add(input("Water")).unwrap();
+
+        let mut changed = input("Tea");
+        changed.reminder_time = None;
+        changed.frequency = FrequencyPattern::Daily;
+        habits.update(id, changed).unwrap();
+
+        let habit = habits.get_by_id(id).unwrap();
+        assert_eq!(habit.name, "Tea");
+        assert!(matches!(habit.frequency, FrequencyPattern::Daily));
+        assert_eq!(reminder_count(&conn, id), 0);
+    }
+
+    #[test]
+    fn deleted_habits_are_hidden() {
+        let conn = open_test_db();
+        let habits = HabitRepository::new(&conn);
+        let id = habits.add(input("Water")).unwrap();
+
+        habits.delete(id).unwrap();
+
+        assert_eq!(habits.get_by_id(id).unwrap_err().code, ErrorCode::NotFound);
+        assert!(habits.get_all().unwrap().is_empty());
+        assert_eq!(
+            habits.set_active(id, false).unwrap_err().code,
+            ErrorCode::NotFound
+        );
+    }
+
+    #[test]
+    fn trashed_habits_have_no_stats_or_new_reminders() {
+        let conn = open_test_db();
+        let habits = HabitRepository::new(&conn);
+        let id = habits.add(input("Water")).unwrap();
+        let reminders = reminder_count(&conn, id);
+
+        habits.delete(id).unwrap();
+
+        assert_eq!(habits.stats(id).unwrap_err().code, ErrorCode::NotFound);
+        let err = ReminderRepository::new(&conn)
+            .create(id, "08:00", &[1], true)
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::NotFound);
+        assert_eq!(reminder_count(&conn, id), reminders);
+    }
+
+    #[test]
+    fn completions_start_a_streak_once_per_day() {
+        let conn = open_test_db();
+        let id = HabitRepository::new(&conn).add(input("Water")).unwrap();
+        let completions = CompletionRepository::new(&conn);
+
+        completions.add(id, Some(6.0), None, None, None).unwrap();
+        completions.add(id, Some(8.0), None, None, None).unwrap();
+
+        let stats = HabitRepository::new(&conn).stats(id).unwrap();
+        assert_eq!(stats.total_completions, 2);
+        assert_eq!(stats.current_streak, 1);
+        assert_eq!(stats.longest_streak, 1);
+        assert_eq!(stats.average_value, Some(7.0));
+        assert_eq!(completions.get_for_habit(id).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn completing_a_missing_habit_is_not_found() {
+        let conn = open_test_db();
+        let err = CompletionRepository::new(&conn)
+            .add(5, None, None, None, None)
+            .unwrap_err();
+
+        assert_eq!(err.code, ErrorCode::NotFound);
+        assert_eq!(err.entity, Some(EntityKind::Habit));
+    }
+}
@@ -0,0 +1,9 @@
+pub mod completions;
+pub mod habits;
+pub mod reminders;
+pub mod tags;
+
+pub use completions::CompletionRepository;
+pub use habits::HabitRepository;
+pub use reminders::ReminderRepository;
+pub use tags::HabitTagRepository;
@@ -0,0 +1,131 @@
+use crate::error::{AppError, AppResult, EntityKind};
+use crate::features::habits::models::HabitReminder;
+use rusqlite::{params, Connection};
+
+pub struct ReminderRepository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> ReminderRepository<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        ReminderRepository { conn }
+    }
+
+    pub fn get_for_habit(&self, habit_id: i64) -> AppResult<Vec<HabitReminder>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, habit_id, time, days, is_enabled
+                 FROM habit_reminders
+                 WHERE habit_id = ?",
+            )
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let reminders_iter = stmt
+            .query_map(params![habit_id], |row| {
+                let days_str: String = row.get(3)?;
+                let days: Vec<u32> = serde_json::from_str(&days_str).map_err(|_| {
+                    rusqlite::Error::FromSqlConversionFailure(
+                        3,
+                        rusqlite::types::Type::Text,
+                        Box::new(std::fmt::Error),
+                    )
+                })?;
+
+                Ok(HabitReminder {
+                    id: row.get(0)?,
+                    habit_id: row.get(1)?,
+                    time: row.get(2)?,
+                    days,
+                    is_enabled: row.get::<_, i32>(4)? != 0,
+                })
+            })
+            .map_err(|e| AppError::database("Failed to query reminders", e))?;
+
+        let mut reminders = Vec::new();
+        for reminder_result in reminders_iter {
+            reminders.push(
+                reminder_result.map_err(|e| AppError::database("Failed to process reminder", e))?,
+            );
+        }
+
+        Ok(reminders)
+    }
+
+    pub fn create(
+        &self,
+        habit_id: i64,
+        time: &str,
+        days: &[u32],
+        is_enabled: bool,
+    ) -> AppResult<i64> {
+        let days_json = serde_json::to_string(days)
+            .map_err(|e| AppError::validation(format!("Failed to serialize days: {}", e)))?;
+
+        // a habit in the trash takes no new reminders
+        self.conn
+            .query_row(
+                "SELECT 1 FROM habits WHERE id = ? AND deleted_at IS NULL",
+                params![habit_id],
+                |_| Ok(()),
+            )
+            .map_err(|e| AppError::lookup(EntityKind::Habit, habit_id, "Failed to get habit", e))?;
+
+        self.conn
+            .execute(
+                "INSERT INTO habit_reminders (habit_id, time, days, is_enabled) VALUES (?, ?, ?, ?)",
+                params![habit_id, time, days_json, is_enabled as i32],
+            )
+            .map_err(|e| AppError::database("Failed to create reminder", e))?;
+
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn update(&self, id: i64, time: &str, days: &[u32], is_enabled: bool) -> AppResult<()> {
+        let days_json = serde_json::to_string(days)
+            .map_err(|e| AppError::validation(format!("Failed to serialize days: {}", e)))?;
+
+        let changed = self
+            .conn
+            .execute(
+                "UPDATE habit_reminders SET time = ?, days = ?, is_enabled = ? WHERE id = ?",
+                params![time, days_json, is_enabled as i32, id],
+            )
+            .map_err(|e| AppError::database("Failed to update reminder", e))?;
+
+        if changed == 0 {
+            return Err(AppError::not_found(EntityKind::Reminder, id));
+        }
+
+        Ok(())
+    }
+
+    pub fn delete(&self, id: i64) -> AppResult<()> {
+        let changed = self
+            .conn
+            .execute("DELETE FROM habit_reminders WHERE id = ?", params![id])
+            .map_err(|e| AppError::database("Failed to delete reminder", e))?;
+
+        if changed == 0 {
+            return Err(AppError::not_found(EntityKind::Reminder, id));
+        }
+
+        Ok(())
+    }
+
+    pub fn set_enabled(&self, id: i64, is_enabled: bool) -> AppResult<()> {
+        let changed = self
+            .conn
+            .execute(
+                "UPDATE habit_reminders SET is_enabled = ? WHERE id = ?",
+                params![is_enabled as i32, id],
+            )
+            .map_err(|e| AppError::database("Failed to toggle reminder", e))?;
+
+        if changed == 0 {
+            return Err(AppError::not_found(EntityKind::Reminder, id));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+use crate::error::{AppError, AppResult, EntityKind};
+use crate::features::habits::models::HabitTag;
+use rusqlite::{params, Connection};
+
+pub struct HabitTagRepository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> HabitTagRepository<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        HabitTagRepository { conn }
+    }
+
+    pub fn get_all(&self) -> AppResult<Vec<HabitTag>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, name, color FROM habit_tags")
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let tags_iter = stmt
+            .query_map([], |row| {
+                Ok(HabitTag {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    color: row.get(2)?,
+                })
+            })
+            .map_err(|e| AppError::database("Failed to query tags", e))?;
+
+        let mut tags = Vec::new();
+        for tag_result in tags_iter {
+            tags.push(tag_result.map_err(|e| AppError::database("Failed to process tag", e))?);
+        }
+
+        Ok(tags)
+    }
+
+    pub fn create(&self, name: &str, color: Option<String>) -> AppResult<i64> {
+        self.conn
+            .execute(
+                "INSERT INTO habit_tags (name, color) VALUES (?, ?)",
+                params![name, color],
+            )
+            .map_err(|e| AppError::database("Failed to create tag", e))?;
+
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn update(&self, id: i64, name: &str, color: Option<String>) -> AppResult<()> {
+        let changed = self
+            .conn
+            .execute(
+                "UPDATE habit_tags SET name = ?, color = ? WHERE id = ?",
+                params![name, color, id],
+            )
+            .map_err(|e| AppError::database("Failed to update tag", e))?;
+
+        if changed == 0 {
+            return Err(AppError::not_found(EntityKind::HabitTag, id));
+        }
+
+        Ok(())
+    }
+
+    pub fn delete(&self, id: i64) -> AppResult<()> {
+        let changed = self
+            .conn
+            .execute("DELETE FROM habit_tags WHERE id = ?", params![id])
+            .map_err(|e| AppError::database("Failed to delete tag", e))?;
+
+        if changed == 0 {
+            return Err(AppError::not_found(EntityKind::HabitTag, id));
+        }
+
+        Ok(())
+    }
+}
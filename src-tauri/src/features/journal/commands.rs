@@ -0,0 +1,28 @@
+use crate::db::init::DbState;
+use crate::error::AppError;
+use crate::features::journal::models::{JournalOperation, JournalState};
+use crate::features::journal::repository::JournalRepository;
+use tauri::State;
+
+// returns the operation that was undone, or None when there was nothing to undo
+#[tauri::command]
+pub async fn undo(db_state: State<'_, DbState>) -> Result<Option<JournalOperation>, AppError> {
+    let conn = db_state.0.lock()?;
+
+    JournalRepository::new(&conn).undo()
+}
+
+// returns the operation that was redone, or None when there was nothing to redo
+#[tauri::command]
+pub async fn redo(db_state: State<'_, DbState>) -> Result<Option<JournalOperation>, AppError> {
+    let conn = db_state.0.lock()?;
+
+    JournalRepository::new(&conn).redo()
+}
+
+#[tauri::command]
+pub async fn get_journal_state(db_state: State<'_, DbState>) -> Result<JournalState, AppError> {
+    let conn = db_state.0.lock()?;
+
+    JournalRepository::new(&conn).state()
+}
@@ -0,0 +1,17 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// a command recorded in the undo history
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JournalOperation {
+    pub id: i64,                   // unique identifier
+    pub label: String,             // the command, e.g. "delete_note"
+    pub created_at: DateTime<Utc>, // when the command ran
+}
+
+// what undo and redo would act on next
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JournalState {
+    pub undo: Option<JournalOperation>,
+    pub redo: Option<JournalOperation>,
+}
@@ -0,0 +1,622 @@
+use crate::db::time::parse_timestamp;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::features::journal::models::{JournalOperation, JournalState};
+use chrono::Utc;
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension};
+
+// tables whose changes can be undone. the commands that change anything else, or change
+// these outside an operation, are left out of the history on purpose:
+// - attachments (add, upload, link, relink, delete, thumbnails, garbage collection) write
+//   files next to their rows, so note_attachments isn't journaled. an undo or redo that
+//   would cascade into it is refused, see install_journal_triggers. imports are journaled,
+//   but one that brought in attachments can't be undone for the same reason
+// - purging and emptying the trash, by hand or by its policy, is meant to be final. it
+//   seals the history instead, see JournalRepository::seal
+// - update_habit_streaks only recomputes streaks from the completions, which are journaled.
+//   the frontend runs it on its own, so recording it would bury the user's edits, and the
+//   next refresh corrects any streak an undo brings back
+// - set_trash_policy, set_revision_retention and set_attachment_quota change settings in
+//   app_settings, not content
+const JOURNALED_TABLES: &[&str] = &[
+    "notes",
+    "note_folders",
+    "note_tags",
+    "note_tag_mappings",
+    "note_links",
+    "note_revisions",
+    "note_imports",
+    "habits",
+    "habit_tags",
+    "habit_tag_mappings",
+    "habit_completions",
+    "habit_reminders",
+];
+
+// how many operations the history keeps; older ones can no longer be undone
+const MAX_OPERATIONS: i64 = 100;
+
+// create the triggers that write an undo and a redo statement for every row a journaled
+// table gains, loses or changes. they're TEMP triggers built from the live schema, so they
+// cover columns added by later migrations, and they only record while an operation id is
+// in temp.journal_recording.
+// tables outside the journal that reference a journaled one get triggers too: while an
+// operation is replayed they note any row a cascade changes in temp.journal_untracked, so
+// the replay can be refused instead of silently dropping, say, a note's attachments
+pub fn install_journal_triggers(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TEMP TABLE IF NOT EXISTS journal_recording (operation_id INTEGER NOT NULL);
+         CREATE TEMP TABLE IF NOT EXISTS journal_replaying (operation_id INTEGER NOT NULL);
+         CREATE TEMP TABLE IF NOT EXISTS journal_untracked (table_name TEXT NOT NULL);",
+    )?;
+
+    for table in JOURNALED_TABLES {
+        let (columns, keys) = table_columns(conn, table)?;
+        conn.execute_batch(&triggers_sql(table, &columns, &keys))?;
+    }
+
+    for table in untracked_dependents(conn)? {
+        conn.execute_batch(&guard_triggers_sql(&table))?;
+    }
+
+    Ok(())
+}
+
+// tables that aren't journaled but hold foreign keys to one that is
+fn untracked_dependents(conn: &Connection) -> rusqlite::Result<Vec<String>> {
+    let tables = conn
+        .prepare(
+            "SELECT name FROM main.sqlite_master
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+        )?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut dependents = Vec::new();
+    for table in tables {
+        if JOURNALED_TABLES.contains(&table.as_str()) {
+            continue;
+        }
+
+        let referenced = conn
+            .prepare(&format!("PRAGMA main.foreign_key_list(\"{}\")", table))?
+            .query_map([], |row| row.get::<_, String>(2))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        if referenced
+            .iter()
+            .any(|parent| JOURNALED_TABLES.contains(&parent.as_str()))
+        {
+            dependents.push(table);
+        }
+    }
+
+    Ok(dependents)
+}
+
+// a replay never writes these tables itself, so any change to them is a cascade
+fn guard_triggers_sql(table: &str) -> String {
+    ["DELETE", "UPDATE"]
+        .iter()
+        .map(|event| {
+            format!(
+                "CREATE TEMP TRIGGER IF NOT EXISTS journal_guard_{table}_{name}
+                 AFTER {event} ON main.\"{table}\"
+                 WHEN EXISTS (SELECT 1 FROM journal_replaying)
+                 BEGIN
+                     INSERT INTO journal_untracked (table_name) VALUES ('{table}');
+                 END;",
+                table = table,
+                name = event.to_lowercase(),
+                event = event,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// a table's columns, and the ones making up its primary key
+fn table_columns(conn: &Connection, table: &str) -> rusqlite::Result<(Vec<String>, Vec<String>)> {
+    let mut stmt = conn.prepare(&format!("PRAGMA main.table_info({})", table))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(1)?, row.get::<_, i64>(5)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let columns = rows.iter().map(|(name, _)| name.clone()).collect();
+    let mut keys: Vec<&(String, i64)> = rows.iter().filter(|(_, pk)| *pk > 0).collect();
+    keys.sort_by_key(|(_, pk)| *pk);
+
+    Ok((
+        columns,
+        keys.into_iter().map(|(name, _)| name.clone()).collect(),
+    ))
+}
+
+fn triggers_sql(table: &str, columns: &[String], keys: &[String]) -> String {
+    // SQL expressions building a statement as text from the row `row` (NEW or OLD)
+    let insert = |row: &str| {
+        let names = columns
+            .iter()
+            .map(|column| format!("\"{}\"", column))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let values = columns
+            .iter()
+            .map(|column| format!("quote({}.\"{}\")", row, column))
+            .collect::<Vec<_>>()
+            .join(" || ', ' || ");
+        format!(
+            "'INSERT INTO \"{}\" ({}) VALUES (' || {} || ')'",
+            table, names, values
+        )
+    };
+    let matching = |row: &str| {
+        keys.iter()
+            .map(|key| format!("'\"{}\" = ' || quote({}.\"{}\")", key, row, key))
+            .collect::<Vec<_>>()
+            .join(" || ' AND ' || ")
+    };
+    let delete = |row: &str| format!("'DELETE FROM \"{}\" WHERE ' || {}", table, matching(row));
+    // set every column to `values`, on the row that currently has the key of `key_row`
+    let update = |values: &str, key_row: &str| {
+        let assignments = columns
+            .iter()
+            .map(|column| format!("'\"{}\" = ' || quote({}.\"{}\")", column, values, column))
+            .collect::<Vec<_>>()
+            .join(" || ', ' || ");
+        format!(
+            "'UPDATE \"{}\" SET ' || {} || ' WHERE ' || {}",
+            table,
+            assignments,
+            matching(key_row)
+        )
+    };
+
+    let trigger = |event: &str, undo_sql: String, redo_sql: String| {
+        format!(
+            "CREATE TEMP TRIGGER IF NOT EXISTS journal_{table}_{name} AFTER {event} ON main.{table}
+             BEGIN
+                 INSERT INTO operation_journal_entries (operation_id, undo_sql, redo_sql)
+                 SELECT operation_id, {undo_sql}, {redo_sql} FROM journal_recording;
+             END;",
+            table = table,
+            name = event.to_lowercase(),
+            event = event,
+            undo_sql = undo_sql,
+            redo_sql = redo_sql,
+        )
+    };
+
+    [
+        trigger("INSERT", delete("NEW"), insert("NEW")),
+        trigger("DELETE", insert("OLD"), delete("OLD")),
+        trigger("UPDATE", update("OLD", "NEW"), update("NEW", "OLD")),
+    ]
+    .join("\n")
+}
+
+pub struct JournalRepository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> JournalRepository<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        JournalRepository { conn }
+    }
+
+    // run a mutating command as one operation in the undo history. it's applied all or
+    // nothing, and recording a new operation drops whatever could be redone
+    pub fn record<T>(&self, label: &str, action: impl FnOnce() -> AppResult<T>) -> AppResult<T> {
+        self.conn
+            .execute_batch("SAVEPOINT journal_record")
+            .map_err(|e| AppError::database("Failed to start operation", e))?;
+
+        let result = self.record_in_savepoint(label, action);
+
+        let end = if result.is_ok() {
+            "RELEASE journal_record"
+        } else {
+            "ROLLBACK TO journal_record; RELEASE journal_record"
+        };
+        self.conn
+            .execute_batch(end)
+            .map_err(|e| AppError::database("Failed to finish operation", e))?;
+
+        result
+    }
+
+    fn record_in_savepoint<T>(
+        &self,
+        label: &str,
+        action: impl FnOnce() -> AppResult<T>,
+    ) -> AppResult<T> {
+        self.conn
+            .execute(
+                "INSERT INTO operation_journal (label, state, created_at) VALUES (?, 'done', ?)",
+                params![label, Utc::now().to_rfc3339()],
+            )
+            .map_err(|e| AppError::database("Failed to record operation", e))?;
+        let operation_id = self.conn.last_insert_rowid();
+
+        self.conn
+            .execute(
+                "INSERT INTO temp.journal_recording (operation_id) VALUES (?)",
+                params![operation_id],
+            )
+            .map_err(|e| AppError::database("Failed to record operation", e))?;
+
+        let result = action();
+
+        // stop recording whether or not the command worked
+        self.conn
+            .execute("DELETE FROM temp.journal_recording", [])
+            .map_err(|e| AppError::database("Failed to record operation", e))?;
+        let value = result?;
+
+        let changed_rows: bool = self
+            .conn
+            .query_row(
+                "SELECT EXISTS (
+                    SELECT 1 FROM operation_journal_entries WHERE operation_id = ?
+                )",
+                params![operation_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| AppError::database("Failed to record operation", e))?;
+
+        if changed_rows {
+            self.conn
+                .execute_batch(&format!(
+                    "DELETE FROM operation_journal WHERE state = 'undone';
+                     DELETE FROM operation_journal WHERE id NOT IN (
+                         SELECT id FROM operation_journal ORDER BY id DESC LIMIT {}
+                     );",
+                    MAX_OPERATIONS
+                ))
+                .map_err(|e| AppError::database("Failed to trim undo history", e))?;
+        } else {
+            // nothing to undo
+            self.conn
+                .execute(
+                    "DELETE FROM operation_journal WHERE id = ?",
+                    params![operation_id],
+                )
+                .map_err(|e| AppError::database("Failed to record operation", e))?;
+        }
+
+        Ok(value)
+    }
+
+    // record a change that can't be undone, such as purging the trash. the operations
+    // before it may have touched rows that are gone for good, so they're dropped, and undo
+    // stops at the change with an error instead of replaying them
+    pub fn seal(&self, label: &str) -> AppResult<()> {
+        self.conn
+            .execute_batch("DELETE FROM operation_journal")
+            .map_err(|e| AppError::database("Failed to clear undo history", e))?;
+        self.conn
+            .execute(
+                "INSERT INTO operation_journal (label, state, created_at) VALUES (?, 'final', ?)",
+                params![label, Utc::now().to_rfc3339()],
+            )
+            .map_err(|e| AppError::database("Failed to record operation", e))?;
+
+        info!("Sealed the undo history after {}", label);
+        Ok(())
+    }
+
+    // revert the latest operation that is still done
+    pub fn undo(&self) -> AppResult<Option<JournalOperation>> {
+        let Some(operation) = self.next_operation("done", "DESC")? else {
+            // sealing drops everything before it, so a final operation is only ever
+            // behind the done ones
+            if let Some(sealed) = self.next_operation("final", "DESC")? {
+                return Err(AppError::new(
+                    ErrorCode::Conflict,
+                    format!("Can't undo {}, or anything before it", sealed.label),
+                ));
+            }
+            return Ok(None);
+        };
+
+        self.replay(&operation, "undo_sql", "DESC", "undone")?;
+
+        info!("Undid {} (operation {})", operation.label, operation.id);
+        Ok(Some(operation))
+    }
+
+    // apply again the operation undone most recently
+    pub fn redo(&self) -> AppResult<Option<JournalOperation>> {
+        let Some(operation) = self.next_operation("undone", "ASC")? else {
+            return Ok(None);
+        };
+
+        self.replay(&operation, "redo_sql", "ASC", "done")?;
+
+        info!("Redid {} (operation {})", operation.label, operation.id);
+        Ok(Some(operation))
+    }
+
+    pub fn state(&self) -> AppResult<JournalState> {
+        Ok(JournalState {
+            undo: self.next_operation("done", "DESC")?,
+            redo: self.next_operation("undone", "ASC")?,
+        })
+    }
+
+    // undone operations always follow the done ones, so the next to undo is the newest
+    // done one and the next to redo the oldest undone one
+    fn next_operation(&self, state: &str, order: &str) -> AppResult<Option<JournalOperation>> {
+        let row = self
+            .conn
+            .query_row(
+                &format!(
+                    "SELECT id, label, created_at FROM operation_journal
+                     WHERE state = ? ORDER BY id {} LIMIT 1",
+                    order
+                ),
+                params![state],
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                },
+            )
+            .optional()
+            .map_err(|e| AppError::database("Failed to get undo history", e))?;
+
+        row.map(|(id, label, created_at)| {
+            Ok(JournalOperation {
+                id,
+                label,
+                created_at: parse_timestamp(&created_at, "created_at")?,
+            })
+        })
+        .transpose()
+    }
+
+    // run one side of an operation's entries in a savepoint and move it to `new_state`.
+    // undo walks the entries backwards, so each row ends up as it was before the operation
+    fn replay(
+        &self,
+        operation: &JournalOperation,
+        sql_column: &str,
+        order: &str,
+        new_state: &str,
+    ) -> AppResult<()> {
+        self.conn
+            .execute_batch("SAVEPOINT journal_replay")
+            .map_err(|e| AppError::database("Failed to start undo", e))?;
+
+        let result = self.replay_in_savepoint(operation, sql_column, order, new_state);
+
+        let end = if result.is_ok() {
+            "RELEASE journal_replay"
+        } else {
+            "ROLLBACK TO journal_replay; RELEASE journal_replay"
+        };
+        self.conn
+            .execute_batch(end)
+            .map_err(|e| AppError::database("Failed to finish undo", e))?;
+
+        result
+    }
+
+    fn replay_in_savepoint(
+        &self,
+        operation: &JournalOperation,
+        sql_column: &str,
+        order: &str,
+        new_state: &str,
+    ) -> AppResult<()> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT {} FROM operation_journal_entries WHERE operation_id = ? ORDER BY id {}",
+                sql_column, order
+            ))
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let statements = stmt
+            .query_map(params![operation.id], |row| row.get::<_, String>(0))
+            .map_err(|e| AppError::database("Failed to query undo history", e))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| AppError::database("Failed to process undo history", e))?;
+
+        // cascades can record a child row before its parent, so foreign keys are only
+        // checked once the whole operation has been applied. the pragma resets on commit
+        self.conn
+            .execute_batch("PRAGMA defer_foreign_keys = ON")
+            .map_err(|e| AppError::database("Failed to defer foreign keys", e))?;
+
+        self.conn
+            .execute(
+                "INSERT INTO temp.journal_replaying (operation_id) VALUES (?)",
+                params![operation.id],
+            )
+            .map_err(|e| AppError::database("Failed to start undo", e))?;
+
+        for sql in statements {
+            self.conn.execute_batch(&sql).map_err(|e| {
+                AppError::database(&format!("Failed to revert {}", operation.label), e)
+            })?;
+        }
+
+        // the savepoint is rolled back on error, which empties both temp tables again
+        let untracked = self
+            .conn
+            .prepare("SELECT DISTINCT table_name FROM temp.journal_untracked ORDER BY table_name")
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| row.get::<_, String>(0))?
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .map_err(|e| AppError::database("Failed to check undo", e))?;
+        self.conn
+            .execute_batch(
+                "DELETE FROM temp.journal_replaying; DELETE FROM temp.journal_untracked;",
+            )
+            .map_err(|e| AppError::database("Failed to finish undo", e))?;
+
+        if !untracked.is_empty() {
+            let action = if sql_column == "undo_sql" {
+                "undo"
+            } else {
+                "redo"
+            };
+            return Err(AppError::new(
+                ErrorCode::Conflict,
+                format!(
+                    "Can't {} {}: it would also change {}, which undo doesn't track",
+                    action,
+                    operation.label,
+                    untracked.join(", ")
+                ),
+            ));
+        }
+
+        self.conn
+            .execute(
+                "UPDATE operation_journal SET state = ? WHERE id = ?",
+                params![new_state, operation.id],
+            )
+            .map_err(|e| AppError::database("Failed to update undo history", e))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::{note_input, open_test_db, TestDir};
+    use crate::features::notes::importers::run_import;
+    use crate::features::notes::markdown::import::VaultImporter;
+    use crate::features::notes::repository::{AttachmentRepository, NoteRepository};
+    use crate::features::trash::models::TrashKind;
+    use crate::features::trash::repository::TrashRepository;
+
+    fn record_create_note(conn: &Connection) -> i64 {
+        JournalRepository::new(conn)
+            .record("create_note", || {
+                NoteRepository::new(conn).create(note_input("note", "body", &[]))
+            })
+            .unwrap()
+    }
+
+    fn note_exists(conn: &Connection, id: i64) -> bool {
+        conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM notes WHERE id = ?)",
+            params![id],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn undo_and_redo_a_note() {
+        let conn = open_test_db();
+        let journal = JournalRepository::new(&conn);
+        let note = record_create_note(&conn);
+
+        assert_eq!(journal.undo().unwrap().unwrap().label, "create_note");
+        assert!(!note_exists(&conn, note));
+        assert_eq!(journal.redo().unwrap().unwrap().label, "create_note");
+        assert!(note_exists(&conn, note));
+    }
+
+    #[test]
+    fn undo_refuses_to_drop_untracked_attachments() {
+        let conn = open_test_db();
+        let dir = TestDir::create();
+        let journal = JournalRepository::new(&conn);
+        let note = record_create_note(&conn);
+        let attachment = AttachmentRepository::new(&conn, dir.path())
+            .add_data(note, "a.txt", "text/plain", b"content")
+            .unwrap();
+
+        let err = journal.undo().unwrap_err();
+
+        assert_eq!(err.code, ErrorCode::Conflict);
+        assert!(err.message.contains("note_attachments"));
+        assert!(note_exists(&conn, note));
+        assert_eq!(
+            AttachmentRepository::new(&conn, dir.path())
+                .get_for_note(note)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(journal.state().unwrap().undo.is_some());
+
+        // without the attachment nothing untracked is in the way
+        AttachmentRepository::new(&conn, dir.path())
+            .delete(attachment)
+            .unwrap();
+        journal.undo().unwrap();
+        assert!(!note_exists(&conn, note));
+    }
+
+    #[test]
+    fn nothing_before_a_purge_can_be_undone() {
+        let conn = open_test_db();
+        let dir = TestDir::create();
+        let journal = JournalRepository::new(&conn);
+        let purged = record_create_note(&conn);
+        journal
+            .record("delete_note", || NoteRepository::new(&conn).delete(purged))
+            .unwrap();
+        TrashRepository::new(&conn, dir.path())
+            .purge(TrashKind::Note, purged)
+            .unwrap();
+
+        let err = journal.undo().unwrap_err();
+        assert_eq!(err.code, ErrorCode::Conflict);
+        assert!(err.message.contains("purge_from_trash"));
+        assert!(journal.state().unwrap().undo.is_none());
+
+        // what comes after it can be undone as usual
+        let note = record_create_note(&conn);
+        assert_eq!(journal.undo().unwrap().unwrap().label, "create_note");
+        assert!(!note_exists(&conn, note));
+        assert!(journal.undo().is_err());
+    }
+
+    #[test]
+    fn undo_removes_what_an_import_created() {
+        let conn = open_test_db();
+        let dir = TestDir::create();
+        let vault = TestDir::create();
+        vault.write("Projects/Plan.md", b"Steps #work");
+        let import = || {
+            JournalRepository::new(&conn)
+                .record("import_markdown_vault", || {
+                    run_import(
+                        &conn,
+                        dir.path(),
+                        &VaultImporter,
+                        &[vault.path().to_path_buf()],
+                        false,
+                    )
+                })
+                .unwrap()
+        };
+        let count = |table: &str| -> i64 {
+            conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+        };
+
+        assert_eq!(import().notes_imported, 1);
+        JournalRepository::new(&conn).undo().unwrap();
+
+        for table in ["notes", "note_folders", "note_tags", "note_imports"] {
+            assert_eq!(count(table), 0, "{}", table);
+        }
+        // the note isn't remembered as imported, so it comes in again
+        assert_eq!(import().notes_imported, 1);
+    }
+}
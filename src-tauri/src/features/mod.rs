@@ -1,2 +1,4 @@
 pub mod habits;
+pub mod journal;
 pub mod notes;
+pub mod trash;
@@ -1,162 +1,224 @@
 use crate::db::init::DbState;
-use crate::features::notes::models::NoteAttachment;
-use chrono::{DateTime, Utc};
-use log::{error, info};
-use rusqlite::params;
-use std::fs;
+use crate::error::{AppError, ErrorCode};
+use crate::features::notes::models::{
+    AttachmentThumbnail, LinkedFileCheck, NoteAttachment, ThumbnailReport,
+};
+use crate::features::notes::repository::{
+    check_linked_files, prepare_bytes, AttachmentRepository, LinkedFile, LinkedFileRepository,
+    PreparedAttachment, ThumbnailRepository, UploadRepository,
+};
+use log::info;
+use percent_encoding::percent_decode_str;
 use std::path::PathBuf;
+use tauri::ipc::{InvokeBody, Request};
 use tauri::{AppHandle, Manager, State};
+use tauri_plugin_opener::OpenerExt;
+
+// attachment paths are stored relative to the app data directory
+pub(crate) fn app_data_dir(app_handle: &AppHandle) -> Result<PathBuf, AppError> {
+    app_handle.path().app_data_dir().map_err(|e| {
+        AppError::new(
+            ErrorCode::Io,
+            format!("Failed to get app data directory: {}", e),
+        )
+    })
+}
 
-// get the attachments directory path
-fn get_attachments_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
-    let app_data_dir = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
-
-    let attachments_dir = app_data_dir.join("note_attachments");
+#[tauri::command]
+pub async fn add_attachment(
+    note_id: i64,
+    file_path: String,
+    app_handle: tauri::AppHandle,
+    db_state: State<'_, DbState>,
+) -> Result<i64, AppError> {
+    let app_data_dir = app_data_dir(&app_handle)?;
+    // copying the file, extracting its text and making thumbnails can take a while, and need
+    // no lock
+    let attachment = PreparedAttachment::from_file(&app_data_dir, &file_path)?;
+
+    let conn = db_state.0.lock()?;
+    AttachmentRepository::new(&conn, &app_data_dir).add_prepared(note_id, attachment)
+}
 
-    // create directory if it doesnt exist
-    if !attachments_dir.exists() {
-        fs::create_dir_all(&attachments_dir)
-            .map_err(|e| format!("Failed to create attachments directory: {}", e))?;
+// the content of a raw request, sent as `invoke(command, bytes, { headers })` so it isn't
+// serialised as a JSON array of numbers
+fn raw_body<'r>(request: &'r Request<'_>) -> Result<&'r [u8], AppError> {
+    match request.body() {
+        InvokeBody::Raw(bytes) => Ok(bytes.as_slice()),
+        InvokeBody::Json(_) => Err(AppError::validation(
+            "The content must be sent as a raw request body",
+        )),
     }
+}
 
-    Ok(attachments_dir)
+// an argument passed along with a raw body. header values can only be ASCII, so text
+// such as a file name comes percent-encoded, as encodeURIComponent writes it
+fn header(request: &Request<'_>, name: &str) -> Result<String, AppError> {
+    let value = request
+        .headers()
+        .get(name)
+        .ok_or_else(|| AppError::validation(format!("Missing '{}' header", name)))?
+        .to_str()
+        .map_err(|_| AppError::validation(format!("Invalid '{}' header", name)))?;
+
+    percent_decode_str(value)
+        .decode_utf8()
+        .map(|decoded| decoded.into_owned())
+        .map_err(|_| AppError::validation(format!("Invalid '{}' header", name)))
 }
 
+fn number_header(request: &Request<'_>, name: &str) -> Result<i64, AppError> {
+    header(request, name)?
+        .parse()
+        .map_err(|_| AppError::validation(format!("The '{}' header must be a number", name)))
+}
+
+// content the frontend holds rather than a file, e.g. a pasted image. the bytes are the
+// raw body, with note-id, file-name and mime headers. anything larger than one message may
+// carry goes through begin/append/finish_attachment_upload instead
 #[tauri::command]
-pub async fn add_attachment(
+pub async fn add_attachment_from_bytes(
+    request: Request<'_>,
+    app_handle: tauri::AppHandle,
+    db_state: State<'_, DbState>,
+) -> Result<i64, AppError> {
+    let bytes = raw_body(&request)?;
+    let note_id = number_header(&request, "note-id")?;
+    let file_name = header(&request, "file-name")?;
+    let mime = header(&request, "mime")?;
+
+    let app_data_dir = app_data_dir(&app_handle)?;
+    let attachment = prepare_bytes(&app_data_dir, &file_name, &mime, bytes)?;
+
+    let conn = db_state.0.lock()?;
+    AttachmentRepository::new(&conn, &app_data_dir).add_prepared(note_id, attachment)
+}
+
+#[tauri::command]
+pub async fn begin_attachment_upload(
     note_id: i64,
-    file_path: String,
+    file_name: String,
+    mime: String,
+    app_handle: tauri::AppHandle,
+    db_state: State<'_, DbState>,
+) -> Result<i64, AppError> {
+    let conn = db_state.0.lock()?;
+
+    let app_data_dir = app_data_dir(&app_handle)?;
+    UploadRepository::new(&conn, &app_data_dir).begin(note_id, &file_name, &mime)
+}
+
+// the chunk is the raw body, with upload-id and offset headers. returns the number of
+// bytes received so far
+#[tauri::command]
+pub async fn append_attachment_upload(
+    request: Request<'_>,
     app_handle: tauri::AppHandle,
     db_state: State<'_, DbState>,
-) -> Result<i64, String> {
-    let conn = db_state
-        .0
-        .lock()
-        .map_err(|e| format!("Failed to lock DB mutex: {}", e))?;
-
-    let now = Utc::now().to_rfc3339();
-
-    // get the source file path
-    let source_path = std::path::Path::new(&file_path);
-
-    // extract file information
-    let file_name = source_path
-        .file_name()
-        .ok_or_else(|| "Invalid file name".to_string())?
-        .to_string_lossy()
-        .to_string();
-
-    let file_type = source_path
-        .extension()
-        .map(|ext| ext.to_string_lossy().to_string())
-        .unwrap_or_else(|| "unknown".to_string());
-
-    // get file size
-    let metadata =
-        fs::metadata(&file_path).map_err(|e| format!("Failed to read file metadata: {}", e))?;
-    let file_size = metadata.len() as i64;
-
-    // generate a unique filename to avoid collisions
-    // using timestamp and random suffix
-    let timestamp = Utc::now().timestamp();
-    let random_suffix = rand::random::<u32>();
-    let unique_filename = format!("{}_{}_{}_{}", note_id, timestamp, random_suffix, file_name);
-
-    // get the destination directory
-    let attachments_dir = get_attachments_dir(&app_handle)?;
-    let destination_path = attachments_dir.join(&unique_filename);
-
-    // copy the file to the attachments directory
-    fs::copy(source_path, &destination_path)
-        .map_err(|e| format!("Failed to copy file to attachments directory: {}", e))?;
-
-    // store the relative path in the database
-    let stored_path = format!("note_attachments/{}", unique_filename);
-
-    // insert attachment record
-    conn.execute(
-        "INSERT INTO note_attachments (
-            note_id, file_name, file_path, file_type, file_size, created_at
-        ) VALUES (
-            ?1, ?2, ?3, ?4, ?5, ?6
-        )",
-        params![note_id, file_name, stored_path, file_type, file_size, now],
-    )
-    .map_err(|e| format!("Failed to add attachment record: {}", e))?;
-
-    let attachment_id = conn.last_insert_rowid();
-
-    info!(
-        "Added attachment '{}' with ID: {} to note ID: {}",
-        file_name, attachment_id, note_id
-    );
-    Ok(attachment_id)
+) -> Result<i64, AppError> {
+    let bytes = raw_body(&request)?;
+    let upload_id = number_header(&request, "upload-id")?;
+    let offset = number_header(&request, "offset")?;
+
+    let conn = db_state.0.lock()?;
+
+    let app_data_dir = app_data_dir(&app_handle)?;
+    UploadRepository::new(&conn, &app_data_dir).append(upload_id, offset, bytes)
+}
+
+// returns the new attachment's id
+#[tauri::command]
+pub async fn finish_attachment_upload(
+    upload_id: i64,
+    app_handle: tauri::AppHandle,
+    db_state: State<'_, DbState>,
+) -> Result<i64, AppError> {
+    let app_data_dir = app_data_dir(&app_handle)?;
+    let upload = {
+        let conn = db_state.0.lock()?;
+        UploadRepository::new(&conn, &app_data_dir).take(upload_id)?
+    };
+
+    let note_id = upload.note_id;
+    let attachment = upload.prepare(&app_data_dir)?;
+
+    let conn = db_state.0.lock()?;
+    AttachmentRepository::new(&conn, &app_data_dir).add_prepared(note_id, attachment)
+}
+
+#[tauri::command]
+pub async fn cancel_attachment_upload(
+    upload_id: i64,
+    app_handle: tauri::AppHandle,
+    db_state: State<'_, DbState>,
+) -> Result<(), AppError> {
+    let conn = db_state.0.lock()?;
+
+    let app_data_dir = app_data_dir(&app_handle)?;
+    UploadRepository::new(&conn, &app_data_dir).cancel(upload_id)
+}
+
+// attach a file where it is instead of copying it, for files too big to copy
+#[tauri::command]
+pub async fn link_attachment(
+    note_id: i64,
+    file_path: String,
+    db_state: State<'_, DbState>,
+) -> Result<i64, AppError> {
+    // hashing and reading the file can take a while, and needs no lock
+    let file = LinkedFile::read(&file_path)?;
+
+    let conn = db_state.0.lock()?;
+    LinkedFileRepository::new(&conn).link(note_id, &file)
+}
+
+// report linked files that were moved, changed or deleted, of one note or of all notes,
+// with files elsewhere that have their content
+#[tauri::command]
+pub async fn check_linked_attachments(
+    note_id: Option<i64>,
+    search_dirs: Option<Vec<String>>,
+    db_state: State<'_, DbState>,
+) -> Result<Vec<LinkedFileCheck>, AppError> {
+    let rows = {
+        let conn = db_state.0.lock()?;
+        LinkedFileRepository::new(&conn).linked_rows(note_id)?
+    };
+
+    // the search can walk whole directory trees, so the database stays unlocked meanwhile
+    let search_dirs: Vec<PathBuf> = search_dirs
+        .unwrap_or_default()
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+    let report = check_linked_files(rows, &search_dirs);
+
+    let conn = db_state.0.lock()?;
+    LinkedFileRepository::new(&conn).record_check(report)
+}
+
+#[tauri::command]
+pub async fn relink_attachment(
+    attachment_id: i64,
+    file_path: String,
+    db_state: State<'_, DbState>,
+) -> Result<(), AppError> {
+    let file = LinkedFile::read(&file_path)?;
+
+    let conn = db_state.0.lock()?;
+    LinkedFileRepository::new(&conn).relink(attachment_id, &file)
 }
 
 #[tauri::command]
 pub async fn get_note_attachments(
     note_id: i64,
+    app_handle: tauri::AppHandle,
     db_state: State<'_, DbState>,
-) -> Result<Vec<NoteAttachment>, String> {
-    let conn = db_state
-        .0
-        .lock()
-        .map_err(|e| format!("Failed to lock DB mutex: {}", e))?;
-
-    let mut attachments = Vec::new();
-
-    let mut stmt = conn
-        .prepare(
-            "SELECT
-                id, note_id, file_name, file_path, file_type, file_size, created_at
-             FROM note_attachments
-             WHERE note_id = ?",
-        )
-        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
-
-    let attachment_rows = stmt
-        .query_map(params![note_id], |row| {
-            let id: i64 = row.get(0)?;
-            let note_id: i64 = row.get(1)?;
-            let file_name: String = row.get(2)?;
-            let file_path: String = row.get(3)?;
-            let file_type: String = row.get(4)?;
-            let file_size: i64 = row.get(5)?;
-            let created_at: String = row.get(6)?;
-
-            Ok((
-                id, note_id, file_name, file_path, file_type, file_size, created_at,
-            ))
-        })
-        .map_err(|e| format!("Failed to query attachments: {}", e))?;
-
-    for attachment_result in attachment_rows {
-        let (id, note_id, file_name, file_path, file_type, file_size, created_at) =
-            attachment_result.map_err(|e| format!("Failed to process attachment row: {}", e))?;
-
-        // parse dates
-        let created_at = DateTime::parse_from_rfc3339(&created_at)
-            .map_err(|e| format!("Invalid created_at date: {}", e))?
-            .with_timezone(&Utc);
-
-        // create NoteAttachment struct
-        let attachment = NoteAttachment {
-            id,
-            note_id,
-            file_name,
-            file_path,
-            file_type,
-            file_size,
-            created_at,
-        };
-
-        attachments.push(attachment);
-    }
+) -> Result<Vec<NoteAttachment>, AppError> {
+    let conn = db_state.0.lock()?;
 
-    Ok(attachments)
+    let app_data_dir = app_data_dir(&app_handle)?;
+    AttachmentRepository::new(&conn, &app_data_dir).get_for_note(note_id)
 }
 
 #[tauri::command]
@@ -164,160 +226,72 @@ pub async fn delete_attachment(
     attachment_id: i64,
     app_handle: tauri::AppHandle,
     db_state: State<'_, DbState>,
-) -> Result<(), String> {
-    let conn = db_state
-        .0
-        .lock()
-        .map_err(|e| format!("Failed to lock DB mutex: {}", e))?;
-
-    // get the file path before deleting the record
-    let file_path: String = conn
-        .query_row(
-            "SELECT file_path FROM note_attachments WHERE id = ?",
-            params![attachment_id],
-            |row| row.get(0),
-        )
-        .map_err(|e| format!("Failed to get attachment file path: {}", e))?;
-
-    // delete the record from the database
-    conn.execute(
-        "DELETE FROM note_attachments WHERE id = ?",
-        params![attachment_id],
-    )
-    .map_err(|e| format!("Failed to delete attachment: {}", e))?;
-
-    // delete the physical file
-    let app_data_dir = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
-
-    let file_path = app_data_dir.join(file_path);
-
-    if file_path.exists() {
-        fs::remove_file(&file_path)
-            .map_err(|e| format!("Failed to delete attachment file: {}", e))?;
-    } else {
-        // log but don't fail if file doesn't exist
-        error!("Attachment file not found at path: {:?}", file_path);
-    }
+) -> Result<(), AppError> {
+    let conn = db_state.0.lock()?;
 
-    info!("Deleted attachment with ID: {}", attachment_id);
-    Ok(())
+    let app_data_dir = app_data_dir(&app_handle)?;
+    AttachmentRepository::new(&conn, &app_data_dir).delete(attachment_id)
 }
 
 #[tauri::command]
 pub async fn get_attachment_by_id(
     attachment_id: i64,
+    app_handle: tauri::AppHandle,
     db_state: State<'_, DbState>,
-) -> Result<NoteAttachment, String> {
-    let conn = db_state
-        .0
-        .lock()
-        .map_err(|e| format!("Failed to lock DB mutex: {}", e))?;
-
-    let attachment_data = conn
-        .query_row(
-            "SELECT
-                id, note_id, file_name, file_path, file_type, file_size, created_at
-             FROM note_attachments
-             WHERE id = ?",
-            params![attachment_id],
-            |row| {
-                Ok((
-                    row.get::<_, i64>(0)?,
-                    row.get::<_, i64>(1)?,
-                    row.get::<_, String>(2)?,
-                    row.get::<_, String>(3)?,
-                    row.get::<_, String>(4)?,
-                    row.get::<_, i64>(5)?,
-                    row.get::<_, String>(6)?,
-                ))
-            },
-        )
-        .map_err(|e| format!("Failed to get attachment: {}", e))?;
-
-    let (id, note_id, file_name, file_path, file_type, file_size, created_at) = attachment_data;
-
-    // parse dates
-    let created_at = DateTime::parse_from_rfc3339(&created_at)
-        .map_err(|e| format!("Invalid created_at date: {}", e))?
-        .with_timezone(&Utc);
-
-    // create NoteAttachment struct
-    let attachment = NoteAttachment {
-        id,
-        note_id,
-        file_name,
-        file_path,
-        file_type,
-        file_size,
-        created_at,
-    };
+) -> Result<NoteAttachment, AppError> {
+    let conn = db_state.0.lock()?;
 
-    Ok(attachment)
+    let app_data_dir = app_data_dir(&app_handle)?;
+    AttachmentRepository::new(&conn, &app_data_dir).get_by_id(attachment_id)
 }
 
+// the size is rounded up to one of the sizes thumbnails are kept in. None for attachments of
+// a type without thumbnails
 #[tauri::command]
-pub async fn open_attachment(
+pub async fn get_attachment_thumbnail(
     attachment_id: i64,
+    size: u32,
     app_handle: tauri::AppHandle,
     db_state: State<'_, DbState>,
-) -> Result<(), String> {
-    let conn = db_state
-        .0
-        .lock()
-        .map_err(|e| format!("Failed to lock DB mutex: {}", e))?;
-
-    // get file path
-    let file_path: String = conn
-        .query_row(
-            "SELECT file_path FROM note_attachments WHERE id = ?",
-            params![attachment_id],
-            |row| row.get(0),
-        )
-        .map_err(|e| format!("Failed to get attachment file path: {}", e))?;
-
-    // get full path
-    let app_data_dir = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
-
-    let full_path = app_data_dir.join(file_path);
-
-    // ensure file exists
-    if !full_path.exists() {
-        return Err(format!(
-            "Attachment file not found at path: {:?}",
-            full_path
-        ));
-    }
+) -> Result<Option<AttachmentThumbnail>, AppError> {
+    let conn = db_state.0.lock()?;
 
-    // open the file with the system's default application
-    #[cfg(target_os = "windows")]
-    {
-        std::process::Command::new("cmd")
-            .args(&["/C", "start", "", &full_path.to_string_lossy()])
-            .spawn()
-            .map_err(|e| format!("Failed to open attachment: {}", e))?;
-    }
+    let app_data_dir = app_data_dir(&app_handle)?;
+    ThumbnailRepository::new(&conn, &app_data_dir).get(attachment_id, size)
+}
 
-    #[cfg(target_os = "macos")]
-    {
-        std::process::Command::new("open")
-            .arg(&full_path)
-            .spawn()
-            .map_err(|e| format!("Failed to open attachment: {}", e))?;
-    }
+// remake the thumbnails of every attachment, e.g. for those added before thumbnails existed
+#[tauri::command]
+pub async fn regenerate_attachment_thumbnails(
+    app_handle: tauri::AppHandle,
+    db_state: State<'_, DbState>,
+) -> Result<ThumbnailReport, AppError> {
+    let conn = db_state.0.lock()?;
 
-    #[cfg(target_os = "linux")]
-    {
-        std::process::Command::new("xdg-open")
-            .arg(&full_path)
-            .spawn()
-            .map_err(|e| format!("Failed to open attachment: {}", e))?;
-    }
+    let app_data_dir = app_data_dir(&app_handle)?;
+    ThumbnailRepository::new(&conn, &app_data_dir).regenerate_all()
+}
+
+// open with the system's default application. programs and scripts fail with
+// NeedsConfirmation until the call is repeated with `confirmed` set
+#[tauri::command]
+pub async fn open_attachment(
+    attachment_id: i64,
+    confirmed: Option<bool>,
+    app_handle: tauri::AppHandle,
+    db_state: State<'_, DbState>,
+) -> Result<(), AppError> {
+    let conn = db_state.0.lock()?;
+
+    let app_data_dir = app_data_dir(&app_handle)?;
+    let full_path = AttachmentRepository::new(&conn, &app_data_dir)
+        .resolve_for_opening(attachment_id, confirmed.unwrap_or(false))?;
+
+    // the path goes to the system's opener as a single argument, never through a shell
+    app_handle
+        .opener()
+        .open_path(full_path.to_string_lossy(), None::<&str>)
+        .map_err(|e| AppError::new(ErrorCode::Io, format!("Failed to open attachment: {}", e)))?;
 
     info!("Opened attachment with ID: {}", attachment_id);
     Ok(())
@@ -0,0 +1,30 @@
+use crate::db::init::DbState;
+use crate::error::AppError;
+use crate::features::notes::models::{GraphQuery, NoteGraph};
+use crate::features::notes::repository::GraphRepository;
+use tauri::State;
+
+// hops around center_note_id when the caller doesn't say
+const DEFAULT_GRAPH_DEPTH: u32 = 1;
+
+#[tauri::command]
+pub async fn get_note_graph(
+    folder_id: Option<i64>,
+    center_note_id: Option<i64>,
+    depth: Option<u32>,
+    include_tags: Option<bool>,
+    include_folders: Option<bool>,
+    include_archived: Option<bool>,
+    db_state: State<'_, DbState>,
+) -> Result<NoteGraph, AppError> {
+    let conn = db_state.0.lock()?;
+
+    GraphRepository::new(&conn).get_graph(&GraphQuery {
+        folder_id,
+        center_note_id,
+        depth: depth.unwrap_or(DEFAULT_GRAPH_DEPTH),
+        include_tags: include_tags.unwrap_or(true),
+        include_folders: include_folders.unwrap_or(true),
+        include_archived: include_archived.unwrap_or(false),
+    })
+}
@@ -0,0 +1,54 @@
+use super::attachments::app_data_dir;
+use crate::db::init::DbState;
+use crate::error::AppError;
+use crate::features::journal::repository::JournalRepository;
+use crate::features::notes::importers::enex::EnexImporter;
+use crate::features::notes::importers::{importer_for, run_import};
+use crate::features::notes::models::{ImportFormat, ImportReport};
+use std::path::PathBuf;
+use tauri::State;
+
+#[tauri::command]
+pub async fn import_notes(
+    format: ImportFormat,
+    paths: Vec<String>,
+    dry_run: Option<bool>,
+    app_handle: tauri::AppHandle,
+    db_state: State<'_, DbState>,
+) -> Result<ImportReport, AppError> {
+    let conn = db_state.0.lock()?;
+
+    let app_data_dir = app_data_dir(&app_handle)?;
+    let paths: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+    JournalRepository::new(&conn).record("import_notes", || {
+        run_import(
+            &conn,
+            &app_data_dir,
+            importer_for(format).as_ref(),
+            &paths,
+            dry_run.unwrap_or(false),
+        )
+    })
+}
+
+#[tauri::command]
+pub async fn import_enex(
+    file_paths: Vec<String>,
+    dry_run: Option<bool>,
+    app_handle: tauri::AppHandle,
+    db_state: State<'_, DbState>,
+) -> Result<ImportReport, AppError> {
+    let conn = db_state.0.lock()?;
+
+    let app_data_dir = app_data_dir(&app_handle)?;
+    let files: Vec<PathBuf> = file_paths.iter().map(PathBuf::from).collect();
+    JournalRepository::new(&conn).record("import_enex", || {
+        run_import(
+            &conn,
+            &app_data_dir,
+            &EnexImporter,
+            &files,
+            dry_run.unwrap_or(false),
+        )
+    })
+}
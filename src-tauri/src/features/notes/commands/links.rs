@@ -0,0 +1,52 @@
+use crate::db::init::DbState;
+use crate::error::AppError;
+use crate::features::journal::repository::JournalRepository;
+use crate::features::notes::models::NoteLink;
+use crate::features::notes::repository::{LinkRepository, NoteRepository};
+use tauri::State;
+
+#[tauri::command]
+pub async fn get_note_links(
+    note_id: i64,
+    db_state: State<'_, DbState>,
+) -> Result<Vec<NoteLink>, AppError> {
+    let conn = db_state.0.lock()?;
+
+    LinkRepository::new(&conn).get_outgoing(note_id)
+}
+
+#[tauri::command]
+pub async fn get_note_backlinks(
+    note_id: i64,
+    db_state: State<'_, DbState>,
+) -> Result<Vec<NoteLink>, AppError> {
+    let conn = db_state.0.lock()?;
+
+    LinkRepository::new(&conn).get_backlinks(note_id)
+}
+
+// links to titles that no note has; all of them, or only those in one note
+#[tauri::command]
+pub async fn get_unresolved_links(
+    note_id: Option<i64>,
+    db_state: State<'_, DbState>,
+) -> Result<Vec<NoteLink>, AppError> {
+    let conn = db_state.0.lock()?;
+
+    LinkRepository::new(&conn).get_unresolved(note_id)
+}
+
+// returns the ids of the notes whose links were rewritten
+#[tauri::command]
+pub async fn rename_note(
+    id: i64,
+    title: String,
+    update_references: bool,
+    db_state: State<'_, DbState>,
+) -> Result<Vec<i64>, AppError> {
+    let conn = db_state.0.lock()?;
+
+    JournalRepository::new(&conn).record("rename_note", || {
+        NoteRepository::new(&conn).rename(id, &title, update_references)
+    })
+}
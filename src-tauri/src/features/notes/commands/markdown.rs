@@ -0,0 +1,45 @@
+use super::attachments::app_data_dir;
+use crate::db::init::DbState;
+use crate::error::AppError;
+use crate::features::journal::repository::JournalRepository;
+use crate::features::notes::importers::run_import;
+use crate::features::notes::markdown::export::MarkdownExporter;
+use crate::features::notes::markdown::import::VaultImporter;
+use crate::features::notes::models::{ImportReport, MarkdownExportSummary};
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+#[tauri::command]
+pub async fn export_notes_markdown(
+    target_dir: String,
+    include_archived: Option<bool>,
+    app_handle: tauri::AppHandle,
+    db_state: State<'_, DbState>,
+) -> Result<MarkdownExportSummary, AppError> {
+    let conn = db_state.0.lock()?;
+
+    let app_data_dir = app_data_dir(&app_handle)?;
+    MarkdownExporter::new(&conn, &app_data_dir)
+        .export(Path::new(&target_dir), include_archived.unwrap_or(true))
+}
+
+#[tauri::command]
+pub async fn import_markdown_vault(
+    vault_dir: String,
+    dry_run: Option<bool>,
+    app_handle: tauri::AppHandle,
+    db_state: State<'_, DbState>,
+) -> Result<ImportReport, AppError> {
+    let conn = db_state.0.lock()?;
+
+    let app_data_dir = app_data_dir(&app_handle)?;
+    JournalRepository::new(&conn).record("import_markdown_vault", || {
+        run_import(
+            &conn,
+            &app_data_dir,
+            &VaultImporter,
+            &[PathBuf::from(vault_dir)],
+            dry_run.unwrap_or(false),
+        )
+    })
+}
@@ -1,5 +1,10 @@
 pub mod attachments;
 pub mod crud;
 pub mod folders;
+pub mod graph;
+pub mod imports;
+pub mod links;
+pub mod markdown;
 pub mod revisions;
+pub mod storage;
 pub mod tags;
@@ -1,63 +1,21 @@
 use crate::db::init::DbState;
-use crate::features::notes::models::NoteRevision;
-use chrono::{DateTime, Utc};
-use log::info;
-use rusqlite::params;
+use crate::error::AppError;
+use crate::features::journal::repository::JournalRepository;
+use crate::features::notes::diff::DEFAULT_CONTEXT_LINES;
+use crate::features::notes::models::{
+    NoteRevision, RetentionPolicy, RetentionReport, RevisionDiff, RevisionField,
+};
+use crate::features::notes::repository::{RetentionRepository, RevisionRepository};
 use tauri::State;
 
 #[tauri::command]
 pub async fn get_note_revisions(
     note_id: i64,
     db_state: State<'_, DbState>,
-) -> Result<Vec<NoteRevision>, String> {
-    let conn = db_state
-        .0
-        .lock()
-        .map_err(|e| format!("Failed to lock DB mutex: {}", e))?;
+) -> Result<Vec<NoteRevision>, AppError> {
+    let conn = db_state.0.lock()?;
 
-    let mut revisions = Vec::new();
-
-    let mut stmt = conn
-        .prepare(
-            "SELECT id, note_id, content, created_at
-             FROM note_revisions
-             WHERE note_id = ?
-             ORDER BY created_at DESC",
-        )
-        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
-
-    let revision_rows = stmt
-        .query_map(params![note_id], |row| {
-            let id: i64 = row.get(0)?;
-            let note_id: i64 = row.get(1)?;
-            let content: String = row.get(2)?;
-            let created_at: String = row.get(3)?;
-
-            Ok((id, note_id, content, created_at))
-        })
-        .map_err(|e| format!("Failed to query revisions: {}", e))?;
-
-    for revision_result in revision_rows {
-        let (id, note_id, content, created_at) =
-            revision_result.map_err(|e| format!("Failed to process revision row: {}", e))?;
-
-        // parse dates
-        let created_at = DateTime::parse_from_rfc3339(&created_at)
-            .map_err(|e| format!("Invalid created_at date: {}", e))?
-            .with_timezone(&Utc);
-
-        // create NoteRevision struct
-        let revision = NoteRevision {
-            id,
-            note_id,
-            content,
-            created_at,
-        };
-
-        revisions.push(revision);
-    }
-
-    Ok(revisions)
+    RevisionRepository::new(&conn).get_for_note(note_id)
 }
 
 #[tauri::command]
@@ -65,210 +23,107 @@ pub async fn create_revision(
     note_id: i64,
     content: String,
     db_state: State<'_, DbState>,
-) -> Result<i64, String> {
-    let conn = db_state
-        .0
-        .lock()
-        .map_err(|e| format!("Failed to lock DB mutex: {}", e))?;
-
-    let now = Utc::now().to_rfc3339();
-
-    // insert revision
-    conn.execute(
-        "INSERT INTO note_revisions (
-            note_id, content, created_at
-        ) VALUES (
-            ?1, ?2, ?3
-        )",
-        params![note_id, content, now],
-    )
-    .map_err(|e| format!("Failed to create revision: {}", e))?;
+) -> Result<i64, AppError> {
+    let conn = db_state.0.lock()?;
 
-    let revision_id = conn.last_insert_rowid();
-
-    info!(
-        "Created revision for note ID: {} with revision ID: {}",
-        note_id, revision_id
-    );
-    Ok(revision_id)
+    JournalRepository::new(&conn).record("create_revision", || {
+        RevisionRepository::new(&conn).create(note_id, &content)
+    })
 }
 
+// leave out fields to roll back everything the revision kept
 #[tauri::command]
 pub async fn restore_revision(
     revision_id: i64,
+    fields: Option<Vec<RevisionField>>,
     db_state: State<'_, DbState>,
-) -> Result<(), String> {
-    let conn = db_state
-        .0
-        .lock()
-        .map_err(|e| format!("Failed to lock DB mutex: {}", e))?;
-
-    let now = Utc::now().to_rfc3339();
-
-    // get the revision data
-    let (note_id, content): (i64, String) = conn
-        .query_row(
-            "SELECT note_id, content FROM note_revisions WHERE id = ?",
-            params![revision_id],
-            |row| Ok((row.get(0)?, row.get(1)?)),
-        )
-        .map_err(|e| format!("Failed to get revision: {}", e))?;
-
-    // get current content of the note to save as a new revision
-    let current_content: String = conn
-        .query_row(
-            "SELECT content FROM notes WHERE id = ?",
-            params![note_id],
-            |row| row.get(0),
-        )
-        .map_err(|e| format!("Failed to get current note content: {}", e))?;
-
-    // save current content as a new revision
-    conn.execute(
-        "INSERT INTO note_revisions (
-            note_id, content, created_at
-        ) VALUES (
-            ?1, ?2, ?3
-        )",
-        params![note_id, current_content, now],
-    )
-    .map_err(|e| format!("Failed to save current content as revision: {}", e))?;
+) -> Result<(), AppError> {
+    let conn = db_state.0.lock()?;
 
-    // update the note with the revision content
-    conn.execute(
-        "UPDATE notes SET content = ?, updated_at = ? WHERE id = ?",
-        params![content, now, note_id],
-    )
-    .map_err(|e| format!("Failed to update note with revision content: {}", e))?;
-
-    info!(
-        "Restored revision ID: {} for note ID: {}",
-        revision_id, note_id
-    );
-    Ok(())
+    JournalRepository::new(&conn).record("restore_revision", || {
+        RevisionRepository::new(&conn).restore(revision_id, fields.as_deref())
+    })
 }
 
 #[tauri::command]
-pub async fn delete_revision(revision_id: i64, db_state: State<'_, DbState>) -> Result<(), String> {
-    let conn = db_state
-        .0
-        .lock()
-        .map_err(|e| format!("Failed to lock DB mutex: {}", e))?;
-
-    // get the note ID for logging
-    let note_id: i64 = conn
-        .query_row(
-            "SELECT note_id FROM note_revisions WHERE id = ?",
-            params![revision_id],
-            |row| row.get(0),
-        )
-        .map_err(|e| format!("Failed to get note ID for revision: {}", e))?;
-
-    // delete the revision
-    conn.execute(
-        "DELETE FROM note_revisions WHERE id = ?",
-        params![revision_id],
-    )
-    .map_err(|e| format!("Failed to delete revision: {}", e))?;
+pub async fn delete_revision(
+    revision_id: i64,
+    db_state: State<'_, DbState>,
+) -> Result<(), AppError> {
+    let conn = db_state.0.lock()?;
 
-    info!(
-        "Deleted revision ID: {} for note ID: {}",
-        revision_id, note_id
-    );
-    Ok(())
+    JournalRepository::new(&conn).record("delete_revision", || {
+        RevisionRepository::new(&conn).delete(revision_id)
+    })
 }
 
 #[tauri::command]
 pub async fn get_revision_by_id(
     revision_id: i64,
     db_state: State<'_, DbState>,
-) -> Result<NoteRevision, String> {
-    let conn = db_state
-        .0
-        .lock()
-        .map_err(|e| format!("Failed to lock DB mutex: {}", e))?;
+) -> Result<NoteRevision, AppError> {
+    let conn = db_state.0.lock()?;
 
-    let revision_data = conn
-        .query_row(
-            "SELECT id, note_id, content, created_at FROM note_revisions WHERE id = ?",
-            params![revision_id],
-            |row| {
-                Ok((
-                    row.get::<_, i64>(0)?,
-                    row.get::<_, i64>(1)?,
-                    row.get::<_, String>(2)?,
-                    row.get::<_, String>(3)?,
-                ))
-            },
-        )
-        .map_err(|e| format!("Failed to get revision: {}", e))?;
+    RevisionRepository::new(&conn).get_by_id(revision_id)
+}
 
-    let (id, note_id, content, created_at) = revision_data;
+#[tauri::command]
+pub async fn clean_old_revisions(
+    note_id: i64,
+    keep_count: u32,
+    db_state: State<'_, DbState>,
+) -> Result<u32, AppError> {
+    let conn = db_state.0.lock()?;
 
-    // parse dates
-    let created_at = DateTime::parse_from_rfc3339(&created_at)
-        .map_err(|e| format!("Invalid created_at date: {}", e))?
-        .with_timezone(&Utc);
+    JournalRepository::new(&conn).record("clean_old_revisions", || {
+        RevisionRepository::new(&conn).clean_old(note_id, keep_count)
+    })
+}
 
-    // create NoteRevision struct
-    let revision = NoteRevision {
-        id,
-        note_id,
-        content,
-        created_at,
-    };
+// leave out to_revision_id to compare with the note's current content
+#[tauri::command]
+pub async fn diff_revisions(
+    from_revision_id: i64,
+    to_revision_id: Option<i64>,
+    context_lines: Option<usize>,
+    db_state: State<'_, DbState>,
+) -> Result<RevisionDiff, AppError> {
+    let conn = db_state.0.lock()?;
 
-    Ok(revision)
+    RevisionRepository::new(&conn).diff(
+        from_revision_id,
+        to_revision_id,
+        context_lines.unwrap_or(DEFAULT_CONTEXT_LINES),
+    )
 }
 
 #[tauri::command]
-pub async fn clean_old_revisions(
-    note_id: i64,
-    keep_count: u32,
+pub async fn get_revision_retention(
     db_state: State<'_, DbState>,
-) -> Result<u32, String> {
-    let conn = db_state
-        .0
-        .lock()
-        .map_err(|e| format!("Failed to lock DB mutex: {}", e))?;
+) -> Result<RetentionPolicy, AppError> {
+    let conn = db_state.0.lock()?;
 
-    // count the total number of revisions
-    let total_revisions: u32 = conn
-        .query_row(
-            "SELECT COUNT(*) FROM note_revisions WHERE note_id = ?",
-            params![note_id],
-            |row| row.get(0),
-        )
-        .map_err(|e| format!("Failed to count revisions: {}", e))?;
+    RetentionRepository::new(&conn).policy()
+}
 
-    if total_revisions <= keep_count {
-        // no need to delete anything
-        return Ok(0);
-    }
+#[tauri::command]
+pub async fn set_revision_retention(
+    policy: RetentionPolicy,
+    db_state: State<'_, DbState>,
+) -> Result<(), AppError> {
+    let conn = db_state.0.lock()?;
 
-    // calculate how many to delete
-    let to_delete = total_revisions - keep_count;
+    RetentionRepository::new(&conn).set_policy(&policy)
+}
 
-    // delete oldest revisions beyond the keep_count
-    let result = conn.execute(
-        "DELETE FROM note_revisions
-         WHERE id IN (
-             SELECT id FROM note_revisions
-             WHERE note_id = ?
-             ORDER BY created_at ASC
-             LIMIT ?
-         )",
-        params![note_id, to_delete],
-    );
+// prune every note's revisions by the policy now, instead of waiting for the next start
+#[tauri::command]
+pub async fn apply_revision_retention(
+    db_state: State<'_, DbState>,
+) -> Result<RetentionReport, AppError> {
+    let conn = db_state.0.lock()?;
 
-    match result {
-        Ok(deleted_count) => {
-            info!(
-                "Cleaned {} old revisions for note ID: {}",
-                deleted_count, note_id
-            );
-            Ok(deleted_count as u32)
-        }
-        Err(e) => Err(format!("Failed to clean old revisions: {}", e)),
-    }
+    JournalRepository::new(&conn).record("apply_revision_retention", || {
+        RetentionRepository::new(&conn).apply(None)
+    })
 }
@@ -0,0 +1,54 @@
+use crate::db::init::DbState;
+use crate::error::AppError;
+use crate::features::notes::commands::attachments::app_data_dir;
+use crate::features::notes::models::{AttachmentGcReport, AttachmentQuota, StorageUsage};
+use crate::features::notes::repository::StorageRepository;
+use tauri::State;
+
+// remove attachment files and blobs nothing refers to, and report attachments whose file is
+// missing. with dry_run nothing is removed
+#[tauri::command]
+pub async fn collect_attachment_garbage(
+    dry_run: Option<bool>,
+    app_handle: tauri::AppHandle,
+    db_state: State<'_, DbState>,
+) -> Result<AttachmentGcReport, AppError> {
+    let conn = db_state.0.lock()?;
+
+    let app_data_dir = app_data_dir(&app_handle)?;
+    StorageRepository::new(&conn, &app_data_dir).collect_garbage(dry_run.unwrap_or(false))
+}
+
+#[tauri::command]
+pub async fn get_attachment_storage_usage(
+    app_handle: tauri::AppHandle,
+    db_state: State<'_, DbState>,
+) -> Result<StorageUsage, AppError> {
+    let conn = db_state.0.lock()?;
+
+    let app_data_dir = app_data_dir(&app_handle)?;
+    StorageRepository::new(&conn, &app_data_dir).usage()
+}
+
+#[tauri::command]
+pub async fn get_attachment_quota(
+    app_handle: tauri::AppHandle,
+    db_state: State<'_, DbState>,
+) -> Result<AttachmentQuota, AppError> {
+    let conn = db_state.0.lock()?;
+
+    let app_data_dir = app_data_dir(&app_handle)?;
+    StorageRepository::new(&conn, &app_data_dir).quota()
+}
+
+#[tauri::command]
+pub async fn set_attachment_quota(
+    quota: AttachmentQuota,
+    app_handle: tauri::AppHandle,
+    db_state: State<'_, DbState>,
+) -> Result<(), AppError> {
+    let conn = db_state.0.lock()?;
+
+    let app_data_dir = app_data_dir(&app_handle)?;
+    StorageRepository::new(&conn, &app_data_dir).set_quota(&quota)
+}
@@ -0,0 +1,221 @@
+use crate::error::{AppError, AppResult, ErrorCode};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use similar::{capture_diff_slices_deadline, Algorithm, DiffOp};
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+
+// stored revision data is a format byte followed by zlib data: the whole content for a
+// keyframe, or copy/insert instructions against the previous revision for a delta
+const FORMAT: u8 = 1;
+
+// delta instructions, each followed by varint arguments
+const OP_COPY: u8 = 0; // offset and length of a byte range of the base
+const OP_INSERT: u8 = 1; // length, then that many literal bytes
+
+// how long finding a delta may take before it settles for a larger one
+const DIFF_TIMEOUT: Duration = Duration::from_secs(1);
+
+pub fn encode_keyframe(content: &str) -> io::Result<Vec<u8>> {
+    compress(content.as_bytes())
+}
+
+pub fn decode_keyframe(data: &[u8]) -> AppResult<String> {
+    into_content(decompress(data)?)
+}
+
+// `content` as changes to `base`, line by line: unchanged lines are copied from the base
+pub fn encode_delta(base: &str, content: &str) -> io::Result<Vec<u8>> {
+    let old_lines: Vec<&str> = base.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let old_offsets = line_offsets(&old_lines);
+    let new_offsets = line_offsets(&new_lines);
+
+    let deadline = Some(Instant::now() + DIFF_TIMEOUT);
+    let ops = capture_diff_slices_deadline(Algorithm::Myers, &old_lines, &new_lines, deadline);
+
+    let mut instructions = Vec::new();
+    for op in ops {
+        match op {
+            DiffOp::Equal { old_index, len, .. } => {
+                let start = old_offsets[old_index];
+                instructions.push(OP_COPY);
+                write_varint(&mut instructions, start);
+                write_varint(&mut instructions, old_offsets[old_index + len] - start);
+            }
+            DiffOp::Insert {
+                new_index, new_len, ..
+            }
+            | DiffOp::Replace {
+                new_index, new_len, ..
+            } => {
+                let bytes =
+                    &content.as_bytes()[new_offsets[new_index]..new_offsets[new_index + new_len]];
+                instructions.push(OP_INSERT);
+                write_varint(&mut instructions, bytes.len());
+                instructions.extend_from_slice(bytes);
+            }
+            DiffOp::Delete { .. } => {}
+        }
+    }
+
+    compress(&instructions)
+}
+
+pub fn apply_delta(base: &str, data: &[u8]) -> AppResult<String> {
+    let instructions = decompress(data)?;
+    let base = base.as_bytes();
+    let mut content = Vec::with_capacity(base.len());
+    let mut i = 0;
+
+    while i < instructions.len() {
+        let op = instructions[i];
+        i += 1;
+        let bytes = match op {
+            OP_COPY => {
+                let start = read_varint(&instructions, &mut i)?;
+                let len = read_varint(&instructions, &mut i)?;
+                start.checked_add(len).and_then(|end| base.get(start..end))
+            }
+            OP_INSERT => {
+                let len = read_varint(&instructions, &mut i)?;
+                let bytes = i.checked_add(len).and_then(|end| instructions.get(i..end));
+                i += len;
+                bytes
+            }
+            _ => None,
+        };
+        content.extend_from_slice(bytes.ok_or_else(|| corrupt("invalid delta"))?);
+    }
+
+    into_content(content)
+}
+
+// byte offset where each line starts, plus the total length at the end
+fn line_offsets(lines: &[&str]) -> Vec<usize> {
+    let mut offsets = Vec::with_capacity(lines.len() + 1);
+    let mut offset = 0;
+    offsets.push(offset);
+    for line in lines {
+        offset += line.len();
+        offsets.push(offset);
+    }
+    offsets
+}
+
+fn compress(payload: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(vec![FORMAT], Compression::default());
+    encoder.write_all(payload)?;
+    encoder.finish()
+}
+
+fn decompress(data: &[u8]) -> AppResult<Vec<u8>> {
+    let Some((&FORMAT, compressed)) = data.split_first() else {
+        return Err(corrupt("unknown format"));
+    };
+
+    let mut payload = Vec::new();
+    ZlibDecoder::new(compressed)
+        .read_to_end(&mut payload)
+        .map_err(|e| corrupt(&e.to_string()))?;
+    Ok(payload)
+}
+
+fn into_content(bytes: Vec<u8>) -> AppResult<String> {
+    String::from_utf8(bytes).map_err(|_| corrupt("content is not UTF-8"))
+}
+
+// LEB128: seven bits at a time, low bits first, high bit set on all but the last byte
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(data: &[u8], i: &mut usize) -> AppResult<usize> {
+    let mut value: usize = 0;
+    for shift in (0..usize::BITS).step_by(7) {
+        let byte = *data.get(*i).ok_or_else(|| corrupt("truncated delta"))?;
+        *i += 1;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(corrupt("invalid delta"))
+}
+
+fn corrupt(reason: &str) -> AppError {
+    AppError::new(
+        ErrorCode::Database,
+        format!("Corrupt revision data: {}", reason),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyframe_round_trip() {
+        let content = "# Title\n\nSome text with ünïcode.\n";
+        let data = encode_keyframe(content).unwrap();
+
+        assert_eq!(data[0], FORMAT);
+        assert_eq!(decode_keyframe(&data).unwrap(), content);
+    }
+
+    #[test]
+    fn delta_round_trip() {
+        let base = "line one\nline two\nline three\nline four";
+        let content = "line one\nline 2\nline three\nline four\nline five\n";
+
+        let data = encode_delta(base, content).unwrap();
+        assert_eq!(apply_delta(base, &data).unwrap(), content);
+    }
+
+    #[test]
+    fn delta_between_empty_contents() {
+        for (base, content) in [("", "new"), ("old", ""), ("", "")] {
+            let data = encode_delta(base, content).unwrap();
+            assert_eq!(apply_delta(base, &data).unwrap(), content);
+        }
+    }
+
+    #[test]
+    fn unchanged_lines_are_copied_not_stored() {
+        let base = "unchanged line\n".repeat(2000);
+        let content = format!("{}one more line\n", base);
+
+        let delta = encode_delta(&base, &content).unwrap();
+        let keyframe = encode_keyframe(&content).unwrap();
+        assert!(delta.len() < keyframe.len());
+    }
+
+    #[test]
+    fn varints_round_trip() {
+        for value in [0, 1, 127, 128, 300, 16_384, usize::MAX] {
+            let mut out = Vec::new();
+            write_varint(&mut out, value);
+            let mut i = 0;
+            assert_eq!(read_varint(&out, &mut i).unwrap(), value);
+            assert_eq!(i, out.len());
+        }
+    }
+
+    #[test]
+    fn corrupt_data_is_an_error() {
+        assert!(decode_keyframe(&[]).is_err());
+        assert!(decode_keyframe(&[9, 1, 2, 3]).is_err());
+
+        // a copy past the end of the base
+        let mut instructions = vec![OP_COPY];
+        write_varint(&mut instructions, 0);
+        write_varint(&mut instructions, 100);
+        let data = compress(&instructions).unwrap();
+        assert!(apply_delta("short", &data).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+use crate::features::notes::models::{DiffHunk, DiffLine, DiffSpan, DiffTag};
+use similar::{ChangeTag, TextDiff};
+use std::time::{Duration, Instant};
+
+// unchanged lines kept around each change, as in `diff -u`
+pub const DEFAULT_CONTEXT_LINES: usize = 3;
+
+// how long a diff may take before it settles for a coarser result
+const DIFF_TIMEOUT: Duration = Duration::from_secs(1);
+
+// the changes from `old` to `new` as hunks of lines, with `context` unchanged lines around
+// each change. lines that were edited rather than replaced outright also get word-level
+// spans, so only the words that changed need highlighting
+pub fn diff_text(old: &str, new: &str, context: usize) -> Vec<DiffHunk> {
+    let diff = TextDiff::configure()
+        .timeout(DIFF_TIMEOUT)
+        .diff_lines(old, new);
+    let deadline = Some(Instant::now() + DIFF_TIMEOUT);
+
+    let mut hunks = Vec::new();
+    for group in diff.grouped_ops(context) {
+        let (Some(first), Some(last)) = (group.first(), group.last()) else {
+            continue;
+        };
+        let old_range = first.old_range().start..last.old_range().end;
+        let new_range = first.new_range().start..last.new_range().end;
+
+        let mut lines = Vec::new();
+        for op in &group {
+            for change in diff.iter_inline_changes_deadline(op, deadline) {
+                let tag = diff_tag(change.tag());
+
+                let mut spans: Vec<DiffSpan> = change
+                    .iter_strings_lossy()
+                    .map(|(emphasized, text)| DiffSpan {
+                        tag: if emphasized { tag } else { DiffTag::Equal },
+                        text: text.to_string(),
+                    })
+                    .collect();
+                if let Some(span) = spans.last_mut() {
+                    trim_newline(&mut span.text);
+                }
+                spans.retain(|span| !span.text.is_empty());
+
+                // a line with nothing emphasized was added or removed as a whole
+                let text: String = spans.iter().map(|span| span.text.as_str()).collect();
+                if tag == DiffTag::Equal || spans.iter().all(|span| span.tag == DiffTag::Equal) {
+                    spans.clear();
+                }
+
+                lines.push(DiffLine {
+                    tag,
+                    old_line: change.old_index().map(|index| index + 1),
+                    new_line: change.new_index().map(|index| index + 1),
+                    text,
+                    spans,
+                });
+            }
+        }
+
+        hunks.push(DiffHunk {
+            old_start: old_range.start + 1,
+            old_lines: old_range.len(),
+            new_start: new_range.start + 1,
+            new_lines: new_range.len(),
+            lines,
+        });
+    }
+
+    hunks
+}
+
+fn diff_tag(tag: ChangeTag) -> DiffTag {
+    match tag {
+        ChangeTag::Equal => DiffTag::Equal,
+        ChangeTag::Insert => DiffTag::Insert,
+        ChangeTag::Delete => DiffTag::Delete,
+    }
+}
+
+fn trim_newline(text: &mut String) {
+    if text.ends_with('\n') {
+        text.pop();
+        if text.ends_with('\r') {
+            text.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_text_has_no_hunks() {
+        assert!(diff_text("same\ntext\n", "same\ntext\n", DEFAULT_CONTEXT_LINES).is_empty());
+    }
+
+    #[test]
+    fn changed_line_gets_word_spans() {
+        let hunks = diff_text("a\nthe quick fox\nc\n", "a\nthe slow fox\nc\n", 1);
+
+        assert_eq!(hunks.len(), 1);
+        let hunk = &hunks[0];
+        assert_eq!((hunk.old_start, hunk.old_lines), (1, 3));
+        assert_eq!((hunk.new_start, hunk.new_lines), (1, 3));
+
+        let tags: Vec<DiffTag> = hunk.lines.iter().map(|line| line.tag).collect();
+        assert_eq!(
+            tags,
+            vec![
+                DiffTag::Equal,
+                DiffTag::Delete,
+                DiffTag::Insert,
+                DiffTag::Equal
+            ]
+        );
+
+        let deleted = &hunk.lines[1];
+        assert_eq!(deleted.text, "the quick fox");
+        assert_eq!(deleted.old_line, Some(2));
+        assert_eq!(deleted.new_line, None);
+        assert!(deleted
+            .spans
+            .iter()
+            .any(|span| span.tag == DiffTag::Delete && span.text == "quick"));
+
+        let inserted = &hunk.lines[2];
+        assert!(inserted
+            .spans
+            .iter()
+            .any(|span| span.tag == DiffTag::Insert && span.text == "slow"));
+    }
+
+    #[test]
+    fn whole_line_changes_have_no_spans() {
+        let hunks = diff_text("a\n", "a\nbrand new\n", 0);
+
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].lines.len(), 1);
+        let line = &hunks[0].lines[0];
+        assert_eq!(line.tag, DiffTag::Insert);
+        assert_eq!(line.text, "brand new");
+        assert_eq!(line.new_line, Some(2));
+        assert!(line.spans.is_empty());
+    }
+
+    #[test]
+    fn distant_changes_make_separate_hunks() {
+        let old: String = (1..=20).map(|n| format!("line {}\n", n)).collect();
+        let new = old
+            .replace("line 2\n", "line two\n")
+            .replace("line 19\n", "line nineteen\n");
+
+        let hunks = diff_text(&old, &new, DEFAULT_CONTEXT_LINES);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(hunks[1].old_start, 16);
+    }
+}
@@ -0,0 +1,73 @@
+use crate::features::notes::sniff::{text_encoding, ContentType};
+use encoding_rs::Encoding;
+use log::debug;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+// how much text is kept from one attachment. the rest of a huge file isn't searchable
+const MAX_TEXT_LEN: usize = 2 * 1024 * 1024;
+
+// the searchable text of an attachment: the text of a PDF's pages, or a text file decoded
+// from its encoding. None for other types, and for files the text couldn't be read from,
+// which is no reason to refuse the attachment
+pub fn extract_text(path: &Path, content_type: &ContentType) -> Option<String> {
+    let result = if content_type.is_pdf() {
+        pdf_text(path)
+    } else if content_type.is_text() {
+        plain_text(path).map_err(|e| e.to_string())
+    } else {
+        return None;
+    };
+
+    match result {
+        Ok(text) => {
+            let text = truncate(text.trim().to_string());
+            (!text.is_empty()).then_some(text)
+        }
+        Err(e) => {
+            debug!("Couldn't extract text from {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+// pages whose text can't be decoded are skipped rather than losing the whole document
+fn pdf_text(path: &Path) -> Result<String, String> {
+    let document = lopdf::Document::load(path).map_err(|e| e.to_string())?;
+    let pages: Vec<u32> = document.get_pages().into_keys().collect();
+
+    let mut text = String::new();
+    for chunk in document.extract_text_chunks(&pages).into_iter().flatten() {
+        text.push_str(&chunk);
+        if text.len() > MAX_TEXT_LEN {
+            break;
+        }
+    }
+    Ok(text)
+}
+
+fn plain_text(path: &Path) -> io::Result<String> {
+    let encoding =
+        Encoding::for_label(text_encoding(path)?.as_bytes()).unwrap_or(encoding_rs::UTF_8);
+
+    // a little more than is kept, since decoding can shrink it
+    let mut bytes = Vec::new();
+    File::open(path)?
+        .take(2 * MAX_TEXT_LEN as u64)
+        .read_to_end(&mut bytes)?;
+
+    let (text, _) = encoding.decode_with_bom_removal(&bytes);
+    Ok(text.into_owned())
+}
+
+fn truncate(mut text: String) -> String {
+    if text.len() > MAX_TEXT_LEN {
+        let mut end = MAX_TEXT_LEN;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    text
+}
@@ -0,0 +1,338 @@
+use super::enml::{enml_to_markdown, EnmlMedia};
+use super::writer::{
+    attachment_placeholder, AttachmentData, ImportWriter, ImportedAttachment, ImportedNote,
+};
+use super::{clean_tags, file_name_of, files_with_extension, NoteImporter};
+use crate::error::{AppError, AppResult};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use md5::{Digest, Md5};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+// the `source` recorded in note_imports for notes from ENEX files
+const SOURCE: &str = "enex";
+
+// a <note> as it appears in the export
+#[derive(Default)]
+struct EnexNote {
+    title: String,
+    content: String, // ENML
+    created: Option<String>,
+    updated: Option<String>,
+    tags: Vec<String>,
+    resources: Vec<EnexResource>,
+}
+
+#[derive(Default)]
+struct EnexResource {
+    data: String, // base64
+    mime: String,
+    file_name: Option<String>,
+}
+
+// imports Evernote .enex exports, picked as files or as directories holding them.
+// Evernote writes one file per notebook, so each file becomes a folder named after it
+pub struct EnexImporter;
+
+impl NoteImporter for EnexImporter {
+    fn source(&self) -> &'static str {
+        SOURCE
+    }
+
+    fn read(&self, paths: &[PathBuf], writer: &mut ImportWriter) -> AppResult<()> {
+        for file in files_with_extension(paths, "enex")? {
+            let notebook = file
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .ok_or_else(|| AppError::validation(format!("Invalid file {:?}", file)))?;
+            let file_name = file_name_of(&file);
+
+            let mut index = 0;
+            read_enex(&file, |note| {
+                index += 1;
+                add_note(
+                    writer,
+                    note,
+                    &notebook,
+                    &format!("{} #{}", file_name, index),
+                )
+            })?;
+        }
+        Ok(())
+    }
+}
+
+// stream the notes out of an export one at a time; resources make these files large
+fn read_enex(path: &Path, mut on_note: impl FnMut(EnexNote) -> AppResult<()>) -> AppResult<()> {
+    let file = File::open(path).map_err(|e| AppError::io("Failed to open ENEX file", e))?;
+    let mut reader = Reader::from_reader(BufReader::new(file));
+    let invalid =
+        |e: quick_xml::Error| AppError::validation(format!("Invalid ENEX file {:?}: {}", path, e));
+
+    let mut buffer = Vec::new();
+    let mut elements: Vec<String> = Vec::new();
+    let mut text = String::new();
+    let mut note: Option<EnexNote> = None;
+    let mut resource: Option<EnexResource> = None;
+
+    loop {
+        let event = reader.read_event_into(&mut buffer).map_err(invalid)?;
+        match event {
+            Event::Start(element) => {
+                let name = String::from_utf8_lossy(element.local_name().as_ref()).to_string();
+                match name.as_str() {
+                    "note" => note = Some(EnexNote::default()),
+                    "resource" => resource = Some(EnexResource::default()),
+                    _ => {}
+                }
+                elements.push(name);
+                text.clear();
+            }
+            Event::Text(content) => text.push_str(&content.unescape().map_err(invalid)?),
+            Event::CData(content) => text.push_str(&String::from_utf8_lossy(&content)),
+            Event::End(_) => {
+                let name = elements.pop().unwrap_or_default();
+                let parent = elements.last().map(String::as_str).unwrap_or_default();
+                let value = std::mem::take(&mut text);
+
+                if let (Some(resource), "resource" | "resource-attributes") =
+                    (resource.as_mut(), parent)
+                {
+                    match name.as_str() {
+                        "data" => resource.data = value,
+                        "mime" => resource.mime = value,
+                        "file-name" if !value.trim().is_empty() => resource.file_name = Some(value),
+                        _ => {}
+                    }
+                } else if let (Some(current), "note") = (note.as_mut(), parent) {
+                    match name.as_str() {
+                        "title" => current.title = value,
+                        "content" => current.content = value,
+                        "created" => current.created = Some(value),
+                        "updated" => current.updated = Some(value),
+                        "tag" => current.tags.push(value),
+                        "resource" => current.resources.extend(resource.take()),
+                        _ => {}
+                    }
+                } else if name == "note" {
+                    if let Some(note) = note.take() {
+                        on_note(note)?;
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buffer.clear();
+    }
+
+    Ok(())
+}
+
+fn add_note(
+    writer: &mut ImportWriter,
+    note: EnexNote,
+    notebook: &str,
+    source: &str,
+) -> AppResult<()> {
+    let title = match note.title.trim() {
+        "" => "Untitled".to_string(),
+        title => title.to_string(),
+    };
+
+    // resources are referenced from the content by the md5 of their data
+    let mut media = HashMap::new();
+    let mut attachments = Vec::new();
+
+    for (index, resource) in note.resources.into_iter().enumerate() {
+        let data: String = resource.data.split_whitespace().collect();
+        let bytes = match STANDARD.decode(data) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                writer.warn(format!(
+                    "{}: skipped an attachment that couldn't be decoded: {}",
+                    source, e
+                ));
+                continue;
+            }
+        };
+
+        let hash = format!("{:x}", Md5::digest(&bytes));
+        if media.contains_key(&hash) {
+            continue;
+        }
+
+        let mime_type = match resource.mime.trim() {
+            "" => "application/octet-stream".to_string(),
+            mime => mime.to_ascii_lowercase(),
+        };
+        let file_name = resource
+            .file_name
+            .unwrap_or_else(|| format!("attachment-{}.{}", index + 1, extension_for(&mime_type)));
+
+        media.insert(
+            hash.clone(),
+            EnmlMedia {
+                label: file_name.clone(),
+                destination: attachment_placeholder(&hash),
+                is_image: mime_type.starts_with("image/"),
+            },
+        );
+        attachments.push(ImportedAttachment {
+            key: hash,
+            file_name,
+            data: AttachmentData::Bytes { mime_type, bytes },
+        });
+    }
+
+    let content = match enml_to_markdown(&note.content, &media) {
+        Ok(content) => content,
+        Err(e) => {
+            writer.skip(source.to_string(), title, e.message);
+            return Ok(());
+        }
+    };
+
+    // ENEX has no note ids; the same note exported twice has the same title, date and body
+    let source_key = format!(
+        "{:x}",
+        Md5::digest(format!(
+            "{}\n{}\n{}",
+            title,
+            note.created.as_deref().unwrap_or_default(),
+            note.content
+        ))
+    );
+
+    writer.add(ImportedNote {
+        source: source.to_string(),
+        source_key,
+        title,
+        content,
+        folder: vec![notebook.to_string()],
+        tags: clean_tags(note.tags),
+        is_pinned: false,
+        is_archived: false,
+        color: None,
+        created_at: note.created.as_deref().and_then(parse_enex_date),
+        updated_at: note.updated.as_deref().and_then(parse_enex_date),
+        attachments,
+    })
+}
+
+// ENEX dates look like 20240131T093000Z and are always UTC
+fn parse_enex_date(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value.trim(), "%Y%m%dT%H%M%SZ")
+        .ok()
+        .map(|date_time| date_time.and_utc())
+}
+
+// file extension for resources that come without a file name
+fn extension_for(mime_type: &str) -> &str {
+    match mime_type {
+        "image/png" => "png",
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "image/heic" => "heic",
+        "application/pdf" => "pdf",
+        "audio/mpeg" => "mp3",
+        "audio/wav" | "audio/x-wav" => "wav",
+        "audio/amr" => "amr",
+        "video/mp4" => "mp4",
+        "text/plain" => "txt",
+        "text/html" => "html",
+        "application/zip" => "zip",
+        _ => "bin",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::{open_test_db, TestDir};
+    use crate::features::notes::importers::run_import;
+    use crate::features::notes::models::ImportReport;
+    use crate::features::notes::repository::{
+        AttachmentRepository, FolderRepository, NoteRepository,
+    };
+    use chrono::TimeZone;
+    use rusqlite::Connection;
+
+    fn import(conn: &Connection, dir: &TestDir, file: PathBuf) -> ImportReport {
+        run_import(conn, dir.path(), &EnexImporter, &[file], false).unwrap()
+    }
+
+    #[test]
+    fn a_notebook_export_becomes_a_folder_of_notes() {
+        let conn = open_test_db();
+        let dir = TestDir::create();
+        let mut png = Vec::new();
+        image::RgbImage::new(4, 4)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let export = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<en-export>
+  <note>
+    <title>Trip</title>
+    <content><![CDATA[<en-note><div>Beach day</div><en-media type="image/png" hash="{hash:x}"/></en-note>]]></content>
+    <created>20240131T093000Z</created>
+    <updated>20240201T100000Z</updated>
+    <tag>travel</tag>
+    <tag>2024</tag>
+    <resource>
+      <data encoding="base64">{data}</data>
+      <mime>image/png</mime>
+      <resource-attributes><file-name>beach.png</file-name></resource-attributes>
+    </resource>
+  </note>
+</en-export>"#,
+            hash = Md5::digest(&png),
+            data = STANDARD.encode(&png),
+        );
+        let file = dir.write("export/Travel.enex", export.as_bytes());
+
+        let report = import(&conn, &dir, file.clone());
+
+        assert_eq!(report.notes_imported, 1);
+        assert_eq!(report.attachments_imported, 1);
+        let note = NoteRepository::new(&conn)
+            .get_by_id(report.items[0].note_id.unwrap())
+            .unwrap();
+        assert_eq!(note.title, "Trip");
+        let folder = FolderRepository::new(&conn)
+            .get_by_id(note.folder_id.unwrap())
+            .unwrap();
+        assert_eq!(folder.name, "Travel");
+        let mut tags = note.tags.clone();
+        tags.sort();
+        assert_eq!(tags, ["2024", "travel"]);
+        assert_eq!(
+            note.created_at,
+            Utc.with_ymd_and_hms(2024, 1, 31, 9, 30, 0).unwrap()
+        );
+        assert_eq!(
+            note.updated_at,
+            Utc.with_ymd_and_hms(2024, 2, 1, 10, 0, 0).unwrap()
+        );
+
+        let attachments = AttachmentRepository::new(&conn, dir.path())
+            .get_for_note(note.id)
+            .unwrap();
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0].file_name, "beach.png");
+        assert_eq!(attachments[0].file_type, "image/png");
+        assert!(note.content.contains("Beach day"));
+        assert!(note.content.contains(&attachments[0].file_path));
+
+        assert_eq!(import(&conn, &dir, file).duplicates, 1);
+    }
+}
@@ -0,0 +1,547 @@
+use crate::error::{AppError, AppResult};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+
+// how an <en-media hash="..."/> is written out, keyed by the resource's md5 hash
+pub struct EnmlMedia {
+    pub label: String,       // shown as the link text or image alt
+    pub destination: String, // where the link points
+    pub is_image: bool,      // rendered as ![..](..) rather than [..](..)
+}
+
+// convert an <en-note> body to Markdown. formatting Markdown can't express (fonts,
+// colors, underline) is dropped and the text kept
+pub fn enml_to_markdown(enml: &str, media: &HashMap<String, EnmlMedia>) -> AppResult<String> {
+    let mut reader = Reader::from_str(enml);
+    // Evernote's own HTML isn't always balanced, losing a tag beats losing the note
+    reader.config_mut().check_end_names = false;
+    reader.config_mut().allow_unmatched_ends = true;
+
+    let mut markdown = MarkdownBuilder::default();
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| AppError::validation(format!("Invalid note content: {}", e)))?;
+
+        match event {
+            Event::Start(element) => markdown.open(&element, media),
+            Event::Empty(element) => {
+                markdown.open(&element, media);
+                markdown.close();
+            }
+            Event::End(_) => markdown.close(),
+            Event::Text(text) => {
+                let text = text
+                    .unescape()
+                    .map_err(|e| AppError::validation(format!("Invalid note content: {}", e)))?;
+                markdown.text(&text);
+            }
+            Event::CData(data) => markdown.text(&String::from_utf8_lossy(&data)),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(markdown.finish())
+}
+
+// the state of the Markdown written so far
+#[derive(Default)]
+struct MarkdownBuilder {
+    out: String,
+    quote_depth: usize,
+    lists: Vec<Option<usize>>, // open lists, with the next number for ordered ones
+    pre_depth: usize,          // inside <pre> or an Evernote code block
+    code_depth: usize,         // inside inline <code>
+    cell_depth: usize,         // inside a table cell, where line breaks can't go
+    table_rows: Vec<usize>,    // rows written so far in each open table
+    row_cells: usize,          // cells in the current table row
+    skip_depth: usize,         // inside elements whose text isn't content, like <en-crypt>
+    pending_space: bool,       // whitespace seen but not yet written
+    mark_end: usize,           // where the last opening ** or * ended
+    // what each open element needs when it closes
+    closers: Vec<Closer>,
+}
+
+enum Closer {
+    Nothing,
+    Block,
+    Paragraph,
+    Mark { marker: &'static str, start: usize },
+    Link { href: String, start: usize },
+    List,
+    Quote,
+    Pre { fenced: bool },
+    Code,
+    Cell,
+    Row,
+    Table,
+    Skip,
+}
+
+impl MarkdownBuilder {
+    fn open(&mut self, element: &BytesStart, media: &HashMap<String, EnmlMedia>) {
+        let name = String::from_utf8_lossy(element.name().as_ref()).to_ascii_lowercase();
+        let attribute = |key: &str| {
+            element
+                .attributes()
+                .flatten()
+                .find(|a| a.key.as_ref().eq_ignore_ascii_case(key.as_bytes()))
+                .and_then(|a| a.unescape_value().ok())
+                .map(|value| value.to_string())
+        };
+
+        if self.skip_depth > 0 {
+            self.skip_depth += 1;
+            self.closers.push(Closer::Skip);
+            return;
+        }
+
+        let closer = match name.as_str() {
+            "div" if attribute("style").is_some_and(|s| s.contains("-en-codeblock")) => {
+                self.open_pre()
+            }
+            "div" | "li" if self.pre_depth > 0 => {
+                self.block_break();
+                Closer::Block
+            }
+            "div" | "section" | "article" | "center" | "dd" | "dt" => {
+                self.block_break();
+                Closer::Block
+            }
+            "p" => {
+                self.paragraph_break();
+                Closer::Paragraph
+            }
+            "br" => {
+                self.line_break();
+                Closer::Nothing
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.paragraph_break();
+                let level = name[1..].parse().unwrap_or(1);
+                self.write_raw(&format!("{} ", "#".repeat(level)));
+                Closer::Paragraph
+            }
+            "b" | "strong" => self.open_mark("**"),
+            "i" | "em" => self.open_mark("*"),
+            "s" | "strike" | "del" => self.open_mark("~~"),
+            "code" if self.pre_depth == 0 => {
+                self.flush_space();
+                self.write_raw("`");
+                self.code_depth += 1;
+                Closer::Code
+            }
+            "pre" => self.open_pre(),
+            "a" => {
+                self.flush_space();
+                self.write_raw("[");
+                Closer::Link {
+                    href: attribute("href").unwrap_or_default(),
+                    start: self.out.len(),
+                }
+            }
+            "ul" | "ol" => {
+                if self.lists.is_empty() {
+                    self.paragraph_break();
+                } else {
+                    self.block_break();
+                }
+                self.lists.push((name == "ol").then_some(1));
+                Closer::List
+            }
+            "li" => {
+                self.block_break();
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.write_prefix(self.lists.len().saturating_sub(1));
+                self.out.push_str(&marker);
+                Closer::Block
+            }
+            "blockquote" => {
+                self.paragraph_break();
+                self.quote_depth += 1;
+                Closer::Quote
+            }
+            "hr" => {
+                self.paragraph_break();
+                self.write_raw("---");
+                self.paragraph_break();
+                Closer::Nothing
+            }
+            "table" => {
+                self.paragraph_break();
+                self.table_rows.push(0);
+                Closer::Table
+            }
+            "tr" => {
+                self.block_break();
+                self.write_raw("|");
+                self.row_cells = 0;
+                Closer::Row
+            }
+            "td" | "th" => {
+                self.out.push(' ');
+                self.cell_depth += 1;
+                self.row_cells += 1;
+                Closer::Cell
+            }
+            "en-todo" => {
+                let checked = attribute("checked").is_some_and(|c| c == "true");
+                let checkbox = if checked { "[x] " } else { "[ ] " };
+                if self.at_line_start() {
+                    self.write_prefix(self.lists.len());
+                    self.out.push_str("- ");
+                }
+                self.out.push_str(checkbox);
+                self.pending_space = false;
+                Closer::Nothing
+            }
+            "en-media" => {
+                let hash = attribute("hash").unwrap_or_default().to_ascii_lowercase();
+                if let Some(media) = media.get(&hash) {
+                    let bang = if media.is_image { "!" } else { "" };
+                    self.flush_space();
+                    self.write_raw(&format!("{}[{}]({})", bang, media.label, media.destination));
+                }
+                Closer::Nothing
+            }
+            "img" => {
+                if let Some(src) = attribute("src") {
+                    self.flush_space();
+                    let alt = attribute("alt").unwrap_or_default();
+                    self.write_raw(&format!("![{}]({})", alt, src));
+                }
+                Closer::Nothing
+            }
+            "en-crypt" => {
+                self.flush_space();
+                self.write_raw("[encrypted content]");
+                self.skip_depth = 1;
+                Closer::Skip
+            }
+            "style" | "script" | "title" | "head" => {
+                self.skip_depth = 1;
+                Closer::Skip
+            }
+            _ => Closer::Nothing,
+        };
+
+        self.closers.push(closer);
+    }
+
+    fn close(&mut self) {
+        let Some(closer) = self.closers.pop() else {
+            return;
+        };
+
+        match closer {
+            Closer::Nothing => {}
+            Closer::Block => self.block_break(),
+            Closer::Paragraph => self.paragraph_break(),
+            Closer::Mark { marker, start } => {
+                if self.out.len() == start {
+                    // nothing inside, drop the opening marker again
+                    self.out.truncate(start - marker.len());
+                } else {
+                    self.out.push_str(marker);
+                }
+            }
+            Closer::Link { href, start } => {
+                let is_web_link = !href.is_empty() && !href.starts_with("evernote:");
+                if self.out.len() == start && is_web_link {
+                    self.out.push_str(&href);
+                }
+                if is_web_link {
+                    self.out.push_str(&format!("]({})", href));
+                } else {
+                    // links between Evernote notes don't survive the move, keep the text
+                    self.out.remove(start - 1);
+                }
+            }
+            Closer::List => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.paragraph_break();
+                } else {
+                    self.block_break();
+                }
+            }
+            Closer::Quote => {
+                self.block_break();
+                self.quote_depth -= 1;
+                self.paragraph_break();
+            }
+            Closer::Pre { fenced } => {
+                self.pre_depth -= 1;
+                if fenced {
+                    self.block_break();
+                    self.write_raw("```");
+                    self.paragraph_break();
+                }
+            }
+            Closer::Code => {
+                self.code_depth -= 1;
+                self.out.push('`');
+            }
+            Closer::Cell => {
+                self.cell_depth -= 1;
+                self.out.push_str(" |");
+                self.pending_space = false;
+            }
+            Closer::Row => {
+                if let Some(rows) = self.table_rows.last_mut() {
+                    *rows += 1;
+                    // Markdown tables need a separator after the first row
+                    if *rows == 1 {
+                        let separator = "| --- ".repeat(self.row_cells.max(1));
+                        self.out.push('\n');
+                        self.write_prefix(self.lists.len());
+                        self.out.push_str(&separator);
+                        self.out.push('|');
+                    }
+                }
+                self.block_break();
+            }
+            Closer::Table => {
+                self.table_rows.pop();
+                self.paragraph_break();
+            }
+            Closer::Skip => self.skip_depth = self.skip_depth.saturating_sub(1),
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.skip_depth > 0 {
+            return;
+        }
+
+        if self.pre_depth > 0 {
+            for c in text.chars() {
+                if self.at_line_start() && c != '\n' {
+                    self.write_prefix(0);
+                }
+                self.out.push(c);
+            }
+            return;
+        }
+
+        // outside code, whitespace collapses the way a browser shows it
+        for c in text.chars() {
+            if c.is_whitespace() {
+                self.pending_space = true;
+                continue;
+            }
+
+            if self.at_line_start() {
+                self.write_prefix(self.lists.len());
+            } else {
+                self.flush_space();
+            }
+
+            if self.code_depth == 0 && matches!(c, '\\' | '*' | '`' | '[' | ']') {
+                self.out.push('\\');
+            }
+            self.out.push(c);
+        }
+    }
+
+    fn open_mark(&mut self, marker: &'static str) -> Closer {
+        if self.at_line_start() {
+            self.write_prefix(self.lists.len());
+        } else {
+            self.flush_space();
+        }
+        self.out.push_str(marker);
+        self.pending_space = false;
+        self.mark_end = self.out.len();
+        Closer::Mark {
+            marker,
+            start: self.out.len(),
+        }
+    }
+
+    fn open_pre(&mut self) -> Closer {
+        if self.pre_depth > 0 {
+            self.pre_depth += 1;
+            return Closer::Pre { fenced: false };
+        }
+
+        self.paragraph_break();
+        self.write_raw("```");
+        self.out.push('\n');
+        self.pre_depth = 1;
+        Closer::Pre { fenced: true }
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.out.is_empty() || self.out.ends_with('\n')
+    }
+
+    // "> " for each quote, then two spaces for each list level the line continues
+    fn write_prefix(&mut self, indent: usize) {
+        for _ in 0..self.quote_depth {
+            self.out.push_str("> ");
+        }
+        for _ in 0..indent {
+            self.out.push_str("  ");
+        }
+        self.pending_space = false;
+    }
+
+    // markup that isn't text, put on the current line
+    fn write_raw(&mut self, raw: &str) {
+        if self.at_line_start() {
+            self.write_prefix(self.lists.len());
+        }
+        self.out.push_str(raw);
+    }
+
+    fn flush_space(&mut self) {
+        // "<b> bold</b>" should read **bold**, not ** bold**
+        let after_mark = self.mark_end == self.out.len() && self.mark_end > 0;
+        if self.pending_space && !self.at_line_start() && !after_mark && !self.out.ends_with(' ') {
+            self.out.push(' ');
+        }
+        self.pending_space = false;
+    }
+
+    // inside a table cell, breaks become spaces so the row stays on one line
+    fn line_break(&mut self) {
+        if self.cell_depth > 0 {
+            self.pending_space = true;
+        } else {
+            self.pending_space = false;
+            self.out.push('\n');
+        }
+    }
+
+    // end the current line, if anything is on it
+    fn block_break(&mut self) {
+        if self.cell_depth > 0 {
+            self.pending_space = true;
+        } else {
+            self.pending_space = false;
+            if !self.at_line_start() {
+                self.out.push('\n');
+            }
+        }
+    }
+
+    // leave a blank line before what comes next
+    fn paragraph_break(&mut self) {
+        self.block_break();
+        if self.cell_depth == 0 && !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn finish(self) -> String {
+        // at most one blank line in a row, none at the ends
+        let mut markdown = String::with_capacity(self.out.len());
+        let mut blank_lines = 0;
+        for line in self.out.lines() {
+            let line = line.trim_end();
+            if line.is_empty() {
+                blank_lines += 1;
+                if blank_lines > 1 || markdown.is_empty() {
+                    continue;
+                }
+            } else {
+                blank_lines = 0;
+            }
+            markdown.push_str(line);
+            markdown.push('\n');
+        }
+
+        markdown.trim_end().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(body: &str) -> String {
+        enml_to_markdown(&format!("<en-note>{}</en-note>", body), &HashMap::new()).unwrap()
+    }
+
+    #[test]
+    fn paragraphs_headings_and_emphasis() {
+        assert_eq!(
+            convert("<h2>Title</h2><p>Some <b> bold</b> and <i>italic</i> text</p><p>Next</p>"),
+            "## Title\n\nSome **bold** and *italic* text\n\nNext"
+        );
+    }
+
+    #[test]
+    fn nested_lists_and_todos() {
+        assert_eq!(
+            convert(
+                "<ul><li>one</li><li>two<ol><li>a</li><li>b</li></ol></li></ul>\
+                 <div><en-todo checked=\"true\"/>done</div>"
+            ),
+            "- one\n- two\n  1. a\n  2. b\n\n- [x] done"
+        );
+    }
+
+    #[test]
+    fn links_and_code() {
+        assert_eq!(
+            convert(
+                "<div><a href=\"https://example.com\">site</a> \
+                 <a href=\"evernote:///view/1\">other note</a> <code>x*y</code></div>\
+                 <pre>fn main() {\n}</pre>"
+            ),
+            "[site](https://example.com) other note `x*y`\n\n```\nfn main() {\n}\n```"
+        );
+    }
+
+    #[test]
+    fn tables_and_quotes() {
+        assert_eq!(
+            convert(
+                "<table><tr><td>a</td><td>b<br/>c</td></tr><tr><td>1</td><td>2</td></tr></table>\
+                 <blockquote>quoted</blockquote>"
+            ),
+            "| a | b c |\n| --- | --- |\n| 1 | 2 |\n\n> quoted"
+        );
+    }
+
+    #[test]
+    fn media_and_encrypted_content() {
+        let mut media = HashMap::new();
+        media.insert(
+            "abc123".to_string(),
+            EnmlMedia {
+                label: "photo.png".to_string(),
+                destination: "attachments/photo.png".to_string(),
+                is_image: true,
+            },
+        );
+
+        let markdown = enml_to_markdown(
+            "<en-note><div><en-media hash=\"ABC123\" type=\"image/png\"/></div>\
+             <div><en-crypt>secret</en-crypt></div></en-note>",
+            &media,
+        )
+        .unwrap();
+
+        assert_eq!(
+            markdown,
+            "![photo.png](attachments/photo.png)\n[encrypted content]"
+        );
+    }
+
+    #[test]
+    fn markdown_characters_in_text_are_escaped() {
+        assert_eq!(convert("<div>a*b [c]</div>"), "a\\*b \\[c\\]");
+    }
+}
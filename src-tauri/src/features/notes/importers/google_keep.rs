@@ -0,0 +1,341 @@
+use super::writer::{
+    attachment_placeholder, AttachmentData, ImportWriter, ImportedAttachment, ImportedNote,
+};
+use super::NoteImporter;
+use super::{clean_tags, file_name_of, files_with_extension, read_json, title_from_text};
+use crate::error::AppResult;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// the `source` recorded in note_imports for notes from Google Keep
+const SOURCE: &str = "google_keep";
+
+// one note file from a Takeout export, e.g. Takeout/Keep/Groceries.json
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct KeepNote {
+    title: String,
+    text_content: Option<String>,
+    list_content: Option<Vec<KeepListItem>>,
+    labels: Vec<KeepLabel>,
+    attachments: Vec<KeepAttachment>,
+    annotations: Vec<KeepAnnotation>,
+    color: String,
+    is_pinned: bool,
+    is_archived: bool,
+    is_trashed: bool,
+    created_timestamp_usec: Option<i64>,
+    user_edited_timestamp_usec: Option<i64>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct KeepListItem {
+    text: String,
+    is_checked: bool,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct KeepLabel {
+    name: String,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct KeepAttachment {
+    file_path: String, // next to the note's .json
+    mimetype: String,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct KeepAnnotation {
+    title: String,
+    url: String,
+}
+
+// imports a Google Takeout export of Keep, picked as its Keep directory or as single
+// .json files. labels become tags, checklists become task lists and the note colors are
+// kept; notes in Keep's trash are skipped
+pub struct GoogleKeepImporter;
+
+impl NoteImporter for GoogleKeepImporter {
+    fn source(&self) -> &'static str {
+        SOURCE
+    }
+
+    fn read(&self, paths: &[PathBuf], writer: &mut ImportWriter) -> AppResult<()> {
+        for root in paths {
+            for file in files_with_extension(std::slice::from_ref(root), "json")? {
+                read_file(writer, root, &file)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_file(writer: &mut ImportWriter, root: &Path, file: &Path) -> AppResult<()> {
+    let source = file_name_of(file);
+    let fallback_title = file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    // Takeout also holds .json files that aren't notes, so skip rather than fail
+    let note: KeepNote = match read_json(file) {
+        Ok(note) => note,
+        Err(e) => {
+            writer.skip(source, fallback_title, e.message);
+            return Ok(());
+        }
+    };
+    if note.text_content.is_none() && note.list_content.is_none() {
+        writer.skip(source, fallback_title, "Not a Google Keep note".to_string());
+        return Ok(());
+    }
+
+    // Keep has no note ids and file names repeat across exports, e.g. Untitled.json,
+    // so the key is the file's place in the export plus when the note was created
+    let relative = match file.strip_prefix(root) {
+        Ok(relative) if !relative.as_os_str().is_empty() => relative.to_path_buf(),
+        _ => PathBuf::from(&source),
+    };
+    let source_key = format!(
+        "{}#{}",
+        relative.to_string_lossy().replace('\\', "/"),
+        note.created_timestamp_usec.unwrap_or_default()
+    );
+
+    add_note(writer, file, source, source_key, note)
+}
+
+fn add_note(
+    writer: &mut ImportWriter,
+    file: &Path,
+    source: String,
+    source_key: String,
+    note: KeepNote,
+) -> AppResult<()> {
+    let body = match &note.list_content {
+        Some(items) => items
+            .iter()
+            .map(|item| {
+                let mark = if item.is_checked { "x" } else { " " };
+                format!("- [{}] {}", mark, item.text.replace('\n', " ").trim())
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        None => note
+            .text_content
+            .clone()
+            .unwrap_or_default()
+            .trim_end()
+            .to_string(),
+    };
+
+    let title = match note.title.trim() {
+        "" => title_from_text(&body),
+        title => title.to_string(),
+    };
+
+    if note.is_trashed {
+        writer.skip(source, title, "In Google Keep's trash".to_string());
+        return Ok(());
+    }
+
+    let mut sections = vec![body];
+
+    // attached images and recordings sit next to the .json file
+    let note_dir = file.parent().unwrap_or(Path::new(""));
+    let mut attachments = Vec::new();
+    let mut embeds = Vec::new();
+
+    for attachment in &note.attachments {
+        let Some(path) = find_attachment(note_dir, &attachment.file_path) else {
+            writer.warn(format!(
+                "{}: attachment '{}' not found",
+                source, attachment.file_path
+            ));
+            continue;
+        };
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                writer.warn(format!(
+                    "{}: failed to read attachment '{}': {}",
+                    source, attachment.file_path, e
+                ));
+                continue;
+            }
+        };
+
+        let file_name = file_name_of(&path);
+        let mime_type = match attachment.mimetype.trim() {
+            "" => "application/octet-stream".to_string(),
+            mime => mime.to_ascii_lowercase(),
+        };
+        let bang = if mime_type.starts_with("image/") {
+            "!"
+        } else {
+            ""
+        };
+        embeds.push(format!(
+            "{}[{}]({})",
+            bang,
+            file_name,
+            attachment_placeholder(&attachment.file_path)
+        ));
+        attachments.push(ImportedAttachment {
+            key: attachment.file_path.clone(),
+            file_name,
+            data: AttachmentData::Bytes { mime_type, bytes },
+        });
+    }
+    if !embeds.is_empty() {
+        sections.push(embeds.join("\n\n"));
+    }
+
+    // links Keep previewed under the note
+    let links: Vec<String> = note
+        .annotations
+        .iter()
+        .filter(|annotation| !annotation.url.is_empty())
+        .map(|annotation| match annotation.title.trim() {
+            "" => format!("<{}>", annotation.url),
+            title => format!("[{}]({})", title.replace(['[', ']'], ""), annotation.url),
+        })
+        .collect();
+    if !links.is_empty() {
+        sections.push(links.join("\n"));
+    }
+
+    let content = sections
+        .into_iter()
+        .filter(|section| !section.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    writer.add(ImportedNote {
+        source_key,
+        source,
+        title,
+        content,
+        folder: Vec::new(),
+        tags: clean_tags(note.labels.into_iter().map(|label| label.name)),
+        is_pinned: note.is_pinned,
+        is_archived: note.is_archived,
+        color: keep_color(&note.color).map(str::to_string),
+        created_at: note.created_timestamp_usec.and_then(from_usec),
+        updated_at: note.user_edited_timestamp_usec.and_then(from_usec),
+        attachments,
+    })
+}
+
+// Takeout lists some attachments with a different extension than the file it wrote,
+// e.g. "abc.jpg" for "abc.jpeg", so fall back to any file with the same stem
+fn find_attachment(dir: &Path, file_path: &str) -> Option<PathBuf> {
+    let name = Path::new(file_path).file_name()?;
+    let exact = dir.join(name);
+    if exact.is_file() {
+        return Some(exact);
+    }
+
+    let stem = Path::new(name).file_stem()?;
+    let mut candidates: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.file_stem() == Some(stem) && path.is_file())
+        .filter(|path| path.extension().is_none_or(|ext| ext != "json"))
+        .collect();
+    candidates.sort();
+    candidates.into_iter().next()
+}
+
+fn from_usec(usec: i64) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp_micros(usec).filter(|date| date.timestamp() > 0)
+}
+
+// Keep's named note colors as the hex values its light theme shows them in
+fn keep_color(name: &str) -> Option<&'static str> {
+    let color = match name.to_ascii_uppercase().as_str() {
+        "RED" => "#f28b82",
+        "ORANGE" => "#fbbc04",
+        "YELLOW" => "#fff475",
+        "GREEN" => "#ccff90",
+        "TEAL" => "#a7ffeb",
+        "BLUE" => "#cbf0f8",
+        "CERULEAN" | "DARK_BLUE" => "#aecbfa",
+        "PURPLE" => "#d7aefb",
+        "PINK" => "#fdcfe8",
+        "BROWN" => "#e6c9a8",
+        "GRAY" | "GREY" => "#e8eaed",
+        _ => return None, // DEFAULT
+    };
+    Some(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::{open_test_db, TestDir};
+    use crate::features::notes::importers::run_import;
+    use crate::features::notes::repository::NoteRepository;
+
+    #[test]
+    fn a_keep_checklist_becomes_a_task_list() {
+        let conn = open_test_db();
+        let dir = TestDir::create();
+        dir.write(
+            "Keep/Groceries.json",
+            br#"{
+                "title": "Groceries",
+                "listContent": [
+                    {"text": "Milk", "isChecked": true},
+                    {"text": "Eggs", "isChecked": false}
+                ],
+                "labels": [{"name": "home"}, {"name": "home"}],
+                "color": "YELLOW",
+                "isPinned": true,
+                "isArchived": true,
+                "isTrashed": false,
+                "createdTimestampUsec": 1700000000000000,
+                "userEditedTimestampUsec": 1700000100000000
+            }"#,
+        );
+        dir.write(
+            "Keep/Old.json",
+            br#"{"title": "Old", "textContent": "gone", "isTrashed": true}"#,
+        );
+        let keep_dir = vec![dir.path().join("Keep")];
+
+        let report = run_import(&conn, dir.path(), &GoogleKeepImporter, &keep_dir, false).unwrap();
+
+        assert_eq!(report.notes_imported, 1);
+        assert_eq!(report.skipped, 1);
+        let note_id = report.items[0].note_id.unwrap();
+        let note = NoteRepository::new(&conn).get_by_id(note_id).unwrap();
+        assert_eq!(note.title, "Groceries");
+        assert_eq!(note.content, "- [x] Milk\n- [ ] Eggs");
+        assert_eq!(note.tags, ["home"]);
+        assert!(note.is_pinned);
+        assert!(note.is_archived);
+        assert_eq!(note.color.as_deref(), Some("#fff475"));
+        assert_eq!(note.created_at.timestamp(), 1_700_000_000);
+        assert_eq!(note.updated_at.timestamp(), 1_700_000_100);
+
+        let source_key: String = conn
+            .query_row(
+                "SELECT source_key FROM note_imports WHERE note_id = ?",
+                [note_id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(source_key, "Groceries.json#1700000000000000");
+
+        let again = run_import(&conn, dir.path(), &GoogleKeepImporter, &keep_dir, false).unwrap();
+        assert_eq!(again.duplicates, 1);
+    }
+}
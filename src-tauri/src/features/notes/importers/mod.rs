@@ -0,0 +1,121 @@
+use crate::error::{AppError, AppResult};
+use crate::features::notes::markdown::import::VaultImporter;
+use crate::features::notes::models::{ImportFormat, ImportReport};
+use rusqlite::Connection;
+use serde::de::DeserializeOwned;
+use std::fs;
+use std::path::{Path, PathBuf};
+use writer::ImportWriter;
+
+pub mod enex;
+pub mod enml;
+pub mod google_keep;
+pub mod simplenote;
+pub mod standard_notes;
+pub mod writer;
+
+// one export format. an importer only reads: it turns what it finds at the paths the
+// user picked into ImportedNotes and hands them to the writer, which takes care of
+// duplicates, dry runs and the report
+pub trait NoteImporter {
+    // recorded in note_imports with every note and reported, e.g. "enex"
+    fn source(&self) -> &'static str;
+
+    fn read(&self, paths: &[PathBuf], writer: &mut ImportWriter) -> AppResult<()>;
+}
+
+// the importer for each format the UI offers
+pub fn importer_for(format: ImportFormat) -> Box<dyn NoteImporter> {
+    match format {
+        ImportFormat::Markdown => Box::new(VaultImporter),
+        ImportFormat::Enex => Box::new(enex::EnexImporter),
+        ImportFormat::GoogleKeep => Box::new(google_keep::GoogleKeepImporter),
+        ImportFormat::Simplenote => Box::new(simplenote::SimplenoteImporter),
+        ImportFormat::StandardNotes => Box::new(standard_notes::StandardNotesImporter),
+    }
+}
+
+// run an importer over `paths` in one transaction
+pub fn run_import(
+    conn: &Connection,
+    app_data_dir: &Path,
+    importer: &dyn NoteImporter,
+    paths: &[PathBuf],
+    dry_run: bool,
+) -> AppResult<ImportReport> {
+    ImportWriter::run(conn, app_data_dir, importer.source(), dry_run, |writer| {
+        importer.read(paths, writer)
+    })
+}
+
+// the files to read: each path that is a file, and the files inside each path that is a
+// directory whose extension is `extension`, in name order
+fn files_with_extension(paths: &[PathBuf], extension: &str) -> AppResult<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+
+        let entries = fs::read_dir(path)
+            .map_err(|e| AppError::io(&format!("Failed to read directory {:?}", path), e))?;
+        let mut found = Vec::new();
+        for entry in entries {
+            let entry = entry
+                .map_err(|e| AppError::io(&format!("Failed to read directory {:?}", path), e))?;
+            let file = entry.path();
+            let matches = file
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case(extension));
+            if matches && file.is_file() {
+                found.push(file);
+            }
+        }
+        found.sort();
+        files.extend(found);
+    }
+
+    Ok(files)
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> AppResult<T> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| AppError::io(&format!("Failed to read {:?}", path), e))?;
+    serde_json::from_str(&text)
+        .map_err(|e| AppError::validation(format!("Invalid JSON in {:?}: {}", path, e)))
+}
+
+fn file_name_of(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+// a title for notes that don't have one: their first line of text, shortened
+fn title_from_text(text: &str) -> String {
+    let line = text
+        .lines()
+        .map(|line| line.trim().trim_start_matches('#').trim())
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+
+    match line.char_indices().nth(80) {
+        Some((end, _)) => format!("{}…", line[..end].trim_end()),
+        None if line.is_empty() => "Untitled".to_string(),
+        None => line.to_string(),
+    }
+}
+
+// trimmed, non-empty and without repeats, in order
+fn clean_tags(tags: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut cleaned: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_string();
+        if !tag.is_empty() && !cleaned.contains(&tag) {
+            cleaned.push(tag);
+        }
+    }
+    cleaned
+}
@@ -0,0 +1,158 @@
+use super::writer::{ImportWriter, ImportedNote};
+use super::{clean_tags, file_name_of, read_json, title_from_text, NoteImporter};
+use crate::error::{AppError, AppResult};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+// the `source` recorded in note_imports for notes from Simplenote
+const SOURCE: &str = "simplenote";
+
+// notes.json from a Simplenote export
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct SimplenoteExport {
+    active_notes: Vec<SimplenoteNote>,
+    trashed_notes: Vec<SimplenoteNote>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct SimplenoteNote {
+    id: String,
+    content: String, // the first line is the title
+    creation_date: Option<String>,
+    last_modified: Option<String>,
+    tags: Vec<String>,
+    pinned: bool,
+    system_tags: Vec<String>, // older exports mark pinned notes with "pinned" here
+}
+
+// imports a Simplenote export, picked as its notes.json or as the unzipped export
+// directory. Simplenote notes are plain text or Markdown and have no title of their own,
+// so the first line becomes the title; trashed notes are skipped
+pub struct SimplenoteImporter;
+
+impl NoteImporter for SimplenoteImporter {
+    fn source(&self) -> &'static str {
+        SOURCE
+    }
+
+    fn read(&self, paths: &[PathBuf], writer: &mut ImportWriter) -> AppResult<()> {
+        for path in paths {
+            let file = notes_json(path)?;
+            let file_name = file_name_of(&file);
+            let export: SimplenoteExport = read_json(&file)?;
+
+            for note in export.active_notes {
+                add_note(writer, &file_name, note)?;
+            }
+            for note in export.trashed_notes {
+                writer.skip(
+                    format!("{} #{}", file_name, note.id),
+                    title_from_text(&note.content),
+                    "In Simplenote's trash".to_string(),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+// the export keeps notes.json in source/, next to a .txt copy of every note
+fn notes_json(path: &Path) -> AppResult<PathBuf> {
+    if !path.is_dir() {
+        return Ok(path.to_path_buf());
+    }
+
+    [
+        path.join("notes.json"),
+        path.join("source").join("notes.json"),
+    ]
+    .into_iter()
+    .find(|file| file.is_file())
+    .ok_or_else(|| AppError::validation(format!("No notes.json found in {:?}", path)))
+}
+
+fn add_note(writer: &mut ImportWriter, file_name: &str, note: SimplenoteNote) -> AppResult<()> {
+    let text = note.content.replace("\r\n", "\n");
+    let text = text.trim_start();
+    let (first_line, rest) = text.split_once('\n').unwrap_or((text, ""));
+
+    // a first line too long to be the whole title stays in the content
+    let title = title_from_text(first_line);
+    let content = if title == first_line.trim().trim_start_matches('#').trim() {
+        rest.trim_start_matches('\n')
+    } else {
+        text
+    };
+
+    writer.add(ImportedNote {
+        source: format!("{} #{}", file_name, note.id),
+        source_key: note.id,
+        title,
+        content: content.trim_end().to_string(),
+        folder: Vec::new(),
+        tags: clean_tags(note.tags),
+        is_pinned: note.pinned || note.system_tags.iter().any(|tag| tag == "pinned"),
+        is_archived: false,
+        color: None,
+        created_at: note.creation_date.as_deref().and_then(parse_date),
+        updated_at: note.last_modified.as_deref().and_then(parse_date),
+        attachments: Vec::new(),
+    })
+}
+
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value.trim())
+        .ok()
+        .map(|date_time| date_time.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::{open_test_db, TestDir};
+    use crate::features::notes::importers::run_import;
+    use crate::features::notes::repository::NoteRepository;
+
+    #[test]
+    fn the_first_line_of_a_simplenote_note_is_its_title() {
+        let conn = open_test_db();
+        let dir = TestDir::create();
+        dir.write(
+            "export/source/notes.json",
+            br#"{
+                "activeNotes": [{
+                    "id": "a1",
+                    "content": "Shopping\r\nBuy milk\r\n",
+                    "creationDate": "2024-01-31T09:30:00.000Z",
+                    "lastModified": "2024-02-01T10:00:00.000Z",
+                    "tags": ["home"],
+                    "systemTags": ["pinned"]
+                }],
+                "trashedNotes": [{"id": "b2", "content": "Old note"}]
+            }"#,
+        );
+
+        let report = run_import(
+            &conn,
+            dir.path(),
+            &SimplenoteImporter,
+            &[dir.path().join("export")],
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(report.notes_imported, 1);
+        assert_eq!(report.skipped, 1);
+        let note = NoteRepository::new(&conn)
+            .get_by_id(report.items[0].note_id.unwrap())
+            .unwrap();
+        assert_eq!(note.title, "Shopping");
+        assert_eq!(note.content, "Buy milk");
+        assert_eq!(note.tags, ["home"]);
+        assert!(note.is_pinned);
+        assert_eq!(note.created_at.to_rfc3339(), "2024-01-31T09:30:00+00:00");
+    }
+}
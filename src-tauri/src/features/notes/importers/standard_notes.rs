@@ -0,0 +1,438 @@
+use super::writer::{ImportWriter, ImportedNote};
+use super::{clean_tags, file_name_of, read_json, title_from_text, NoteImporter};
+use crate::error::{AppError, AppResult};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+// the `source` recorded in note_imports for notes from Standard Notes
+const SOURCE: &str = "standard_notes";
+
+// a decrypted backup file ("Standard Notes Backup and Import File.txt")
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Backup {
+    items: Vec<BackupItem>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct BackupItem {
+    uuid: String,
+    content_type: String, // "Note", "Tag", and settings we don't care about
+    content: Value,       // an object, or a string when the backup is encrypted
+    created_at: Option<String>,
+    updated_at: Option<String>,
+    deleted: bool,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct NoteContent {
+    title: String,
+    text: String,
+    note_type: Option<String>, // "super" notes hold Lexical JSON instead of text
+    trashed: bool,
+    pinned: Option<bool>,
+    archived: Option<bool>,
+    app_data: Value, // older clients keep pinned/archived in here
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct TagContent {
+    title: String,
+    references: Vec<Reference>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Reference {
+    uuid: String,
+    content_type: String,
+    reference_type: Option<String>,
+}
+
+// imports decrypted Standard Notes backups. tags become tags, nested ones written as
+// "parent/child"; Super notes are converted to Markdown and trashed notes are skipped
+pub struct StandardNotesImporter;
+
+impl NoteImporter for StandardNotesImporter {
+    fn source(&self) -> &'static str {
+        SOURCE
+    }
+
+    fn read(&self, paths: &[PathBuf], writer: &mut ImportWriter) -> AppResult<()> {
+        for file in paths {
+            let file_name = file_name_of(file);
+            let backup: Backup = read_json(file)?;
+
+            let items: Vec<&BackupItem> = backup
+                .items
+                .iter()
+                .filter(|item| {
+                    !item.deleted && matches!(item.content_type.as_str(), "Note" | "Tag")
+                })
+                .collect();
+            if items.iter().any(|item| item.content.is_string()) {
+                return Err(AppError::validation(format!(
+                    "{} is encrypted; export a decrypted backup from Standard Notes",
+                    file_name
+                )));
+            }
+
+            let tags = note_tags(&items);
+
+            let notes = items.iter().filter(|item| item.content_type == "Note");
+            for (index, item) in notes.enumerate() {
+                let source = format!("{} #{}", file_name, index + 1);
+                let content = match NoteContent::deserialize(&item.content) {
+                    Ok(content) => content,
+                    Err(e) => {
+                        writer.skip(source, String::new(), format!("Invalid note: {}", e));
+                        continue;
+                    }
+                };
+                add_note(writer, source, item, content, &tags)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn add_note(
+    writer: &mut ImportWriter,
+    source: String,
+    item: &BackupItem,
+    note: NoteContent,
+    tags: &HashMap<String, Vec<String>>,
+) -> AppResult<()> {
+    let is_super = note.note_type.as_deref() == Some("super") || note.text.starts_with("{\"root\"");
+    let content = match is_super.then(|| super_to_markdown(&note.text)).flatten() {
+        Some(markdown) => markdown,
+        None => note.text.trim_end().to_string(),
+    };
+
+    let title = match note.title.trim() {
+        "" => title_from_text(&content),
+        title => title.to_string(),
+    };
+
+    if note.trashed {
+        writer.skip(source, title, "In Standard Notes' trash".to_string());
+        return Ok(());
+    }
+
+    let app_data = &note.app_data["org.standardnotes.sn"];
+    let updated_at = app_data["client_updated_at"]
+        .as_str()
+        .or(item.updated_at.as_deref())
+        .and_then(parse_date);
+
+    writer.add(ImportedNote {
+        source,
+        source_key: item.uuid.clone(),
+        title,
+        content,
+        folder: Vec::new(),
+        tags: clean_tags(tags.get(&item.uuid).cloned().unwrap_or_default()),
+        is_pinned: note
+            .pinned
+            .or(app_data["pinned"].as_bool())
+            .unwrap_or(false),
+        is_archived: note
+            .archived
+            .or(app_data["archived"].as_bool())
+            .unwrap_or(false),
+        color: None,
+        created_at: item.created_at.as_deref().and_then(parse_date),
+        updated_at,
+        attachments: Vec::new(),
+    })
+}
+
+// tag names by note uuid. tags point at their notes, and nested tags at their parent
+fn note_tags(items: &[&BackupItem]) -> HashMap<String, Vec<String>> {
+    let tags: HashMap<&str, TagContent> = items
+        .iter()
+        .filter(|item| item.content_type == "Tag")
+        .filter_map(|item| {
+            let content = TagContent::deserialize(&item.content).ok()?;
+            Some((item.uuid.as_str(), content))
+        })
+        .collect();
+
+    let full_name = |uuid: &str| {
+        let mut names = Vec::new();
+        let mut current = tags.get(uuid);
+        // the depth limit guards against a parent cycle in a damaged backup
+        while let Some(tag) = current.filter(|_| names.len() < 16) {
+            names.push(tag.title.trim().to_string());
+            current = tag
+                .references
+                .iter()
+                .find(|reference| reference.reference_type.as_deref() == Some("TagToParentTag"))
+                .and_then(|reference| tags.get(reference.uuid.as_str()));
+        }
+        names.reverse();
+        names.join("/")
+    };
+
+    let mut by_note: HashMap<String, Vec<String>> = HashMap::new();
+    for (uuid, tag) in &tags {
+        let name = full_name(uuid);
+        for reference in &tag.references {
+            if reference.content_type == "Note" {
+                by_note
+                    .entry(reference.uuid.clone())
+                    .or_default()
+                    .push(name.clone());
+            }
+        }
+    }
+    for names in by_note.values_mut() {
+        names.sort();
+    }
+    by_note
+}
+
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value.trim())
+        .ok()
+        .map(|date_time| date_time.with_timezone(&Utc))
+}
+
+// Super notes store the Lexical editor's state as JSON; this covers the nodes its
+// toolbar makes. None when the text isn't editor state after all
+fn super_to_markdown(text: &str) -> Option<String> {
+    let state: Value = serde_json::from_str(text).ok()?;
+    let root = state.get("root")?;
+    Some(blocks(root))
+}
+
+fn children(node: &Value) -> &[Value] {
+    node["children"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn node_type(node: &Value) -> &str {
+    node["type"].as_str().unwrap_or_default()
+}
+
+fn blocks(node: &Value) -> String {
+    children(node)
+        .iter()
+        .map(block)
+        .filter(|block| !block.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn block(node: &Value) -> String {
+    match node_type(node) {
+        "heading" => {
+            let level = node["tag"]
+                .as_str()
+                .and_then(|tag| tag.strip_prefix('h'))
+                .and_then(|level| level.parse().ok())
+                .unwrap_or(1)
+                .clamp(1, 6);
+            format!("{} {}", "#".repeat(level), inline(node))
+        }
+        "quote" => inline(node)
+            .lines()
+            .map(|line| format!("> {}", line))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        "code" => format!(
+            "```{}\n{}\n```",
+            node["language"].as_str().unwrap_or_default(),
+            plain_text(node)
+        ),
+        "list" => list(node, ""),
+        "horizontalrule" => "---".to_string(),
+        "table" => table(node),
+        "paragraph" => inline(node),
+        // containers such as collapsibles hold blocks of their own
+        _ if children(node).iter().any(is_block) => blocks(node),
+        _ => inline(node),
+    }
+}
+
+fn is_block(node: &Value) -> bool {
+    matches!(
+        node_type(node),
+        "paragraph" | "heading" | "quote" | "code" | "list" | "horizontalrule" | "table"
+    )
+}
+
+// a list with its nested lists, every line starting with `indent`. check lists become
+// task lists
+fn list(node: &Value, indent: &str) -> String {
+    let list_type = node["listType"].as_str().unwrap_or("bullet");
+    let mut number = node["start"].as_u64().unwrap_or(1);
+    let mut lines = Vec::new();
+
+    for item in children(node) {
+        let marker = match list_type {
+            "number" => format!("{}.", number),
+            "check" if item["checked"].as_bool() == Some(true) => "- [x]".to_string(),
+            "check" => "- [ ]".to_string(),
+            _ => "-".to_string(),
+        };
+        // nested items line up with the text after "-" or "1."
+        let nested_indent = format!(
+            "{}{}",
+            indent,
+            " ".repeat(marker.find(' ').unwrap_or(marker.len()) + 1)
+        );
+
+        let (nested, content): (Vec<&Value>, Vec<&Value>) = children(item)
+            .iter()
+            .partition(|child| node_type(child) == "list");
+
+        let text: String = content.into_iter().map(inline_node).collect();
+        if !text.trim().is_empty() {
+            lines.push(format!("{}{} {}", indent, marker, text.trim()));
+            number += 1;
+        }
+        for nested in nested {
+            lines.push(list(nested, &nested_indent));
+        }
+    }
+
+    lines.join("\n")
+}
+
+fn table(node: &Value) -> String {
+    let mut lines = Vec::new();
+
+    for (index, row) in children(node).iter().enumerate() {
+        let cells: Vec<String> = children(row)
+            .iter()
+            .map(|cell| blocks(cell).replace('\n', " ").replace('|', "\\|"))
+            .collect();
+        lines.push(format!("| {} |", cells.join(" | ")));
+        if index == 0 {
+            lines.push(format!("|{}", " --- |".repeat(cells.len())));
+        }
+    }
+
+    lines.join("\n")
+}
+
+fn inline(node: &Value) -> String {
+    children(node).iter().map(inline_node).collect()
+}
+
+fn inline_node(node: &Value) -> String {
+    match node_type(node) {
+        "text" => {
+            let text = node["text"].as_str().unwrap_or_default();
+            let format = node["format"].as_u64().unwrap_or(0);
+            if format & 16 != 0 {
+                return format!("`{}`", text);
+            }
+            let mut text = text.to_string();
+            if format & 4 != 0 {
+                text = format!("~~{}~~", text);
+            }
+            if format & 2 != 0 {
+                text = format!("*{}*", text);
+            }
+            if format & 1 != 0 {
+                text = format!("**{}**", text);
+            }
+            text
+        }
+        "linebreak" => "\n".to_string(),
+        "tab" => "\t".to_string(),
+        "link" | "autolink" => {
+            let url = node["url"].as_str().unwrap_or_default();
+            format!("[{}]({})", inline(node), url)
+        }
+        _ if node["children"].is_array() => inline(node),
+        _ => node["text"].as_str().unwrap_or_default().to_string(),
+    }
+}
+
+// the text of a node and everything in it, without formatting
+fn plain_text(node: &Value) -> String {
+    match node_type(node) {
+        "linebreak" => "\n".to_string(),
+        "tab" => "\t".to_string(),
+        _ if node["children"].is_array() => children(node).iter().map(plain_text).collect(),
+        _ => node["text"].as_str().unwrap_or_default().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::{open_test_db, TestDir};
+    use crate::features::notes::importers::run_import;
+    use crate::features::notes::repository::NoteRepository;
+
+    #[test]
+    fn nested_tags_and_super_notes_are_imported() {
+        let conn = open_test_db();
+        let dir = TestDir::create();
+        let super_text = serde_json::json!({
+            "root": {"children": [
+                {"type": "heading", "tag": "h2", "children": [{"type": "text", "text": "Goals"}]},
+                {"type": "list", "listType": "check", "children": [
+                    {"type": "listitem", "checked": true, "children": [{"type": "text", "text": "Plan"}]},
+                    {"type": "listitem", "children": [{"type": "text", "text": "Ship", "format": 1}]}
+                ]}
+            ]}
+        })
+        .to_string();
+        let backup = serde_json::json!({
+            "items": [
+                {
+                    "uuid": "n1",
+                    "content_type": "Note",
+                    "content": {"title": "Quarter", "text": super_text, "noteType": "super", "pinned": true},
+                    "created_at": "2024-01-31T09:30:00.000Z"
+                },
+                {
+                    "uuid": "n2",
+                    "content_type": "Note",
+                    "content": {"title": "Gone", "text": "old", "trashed": true}
+                },
+                {
+                    "uuid": "t1",
+                    "content_type": "Tag",
+                    "content": {"title": "work", "references": [{"uuid": "n1", "content_type": "Note"}]}
+                },
+                {
+                    "uuid": "t2",
+                    "content_type": "Tag",
+                    "content": {"title": "q1", "references": [
+                        {"uuid": "n1", "content_type": "Note"},
+                        {"uuid": "t1", "content_type": "Tag", "reference_type": "TagToParentTag"}
+                    ]}
+                }
+            ]
+        });
+        let file = dir.write("backup.txt", backup.to_string().as_bytes());
+
+        let report = run_import(&conn, dir.path(), &StandardNotesImporter, &[file], false).unwrap();
+
+        assert_eq!(report.notes_imported, 1);
+        assert_eq!(report.skipped, 1);
+        let note = NoteRepository::new(&conn)
+            .get_by_id(report.items[0].note_id.unwrap())
+            .unwrap();
+        assert_eq!(note.title, "Quarter");
+        assert_eq!(note.content, "## Goals\n\n- [x] Plan\n- [ ] **Ship**");
+        let mut tags = note.tags.clone();
+        tags.sort();
+        assert_eq!(tags, ["work", "work/q1"]);
+        assert!(note.is_pinned);
+    }
+}
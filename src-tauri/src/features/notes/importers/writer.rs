@@ -0,0 +1,416 @@
+use crate::error::{AppError, AppResult};
+use crate::features::notes::models::{ImportItem, ImportReport, ImportStatus, NoteInput};
+use crate::features::notes::repository::{
+    AttachmentRepository, FolderRepository, ImportRepository, NoteRepository,
+};
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// a note read from an export, before anything is written
+pub struct ImportedNote {
+    pub source: String,     // where it came from, shown in the report
+    pub source_key: String, // identifies it within the importer across runs, for duplicates
+    pub title: String,
+    pub content: String, // Markdown, pointing at attachments through `attachment_placeholder`
+    pub folder: Vec<String>, // folder names from the top level down, empty for no folder
+    pub tags: Vec<String>,
+    pub is_pinned: bool,
+    pub is_archived: bool,
+    pub color: Option<String>,
+    pub created_at: Option<DateTime<Utc>>, // None means now
+    pub updated_at: Option<DateTime<Utc>>, // None means created_at
+    pub attachments: Vec<ImportedAttachment>,
+}
+
+pub struct ImportedAttachment {
+    pub key: String, // what the placeholders in the content refer to
+    pub file_name: String,
+    pub data: AttachmentData,
+}
+
+pub enum AttachmentData {
+    File(PathBuf), // copied as is, its extension becomes the file type
+    Bytes { mime_type: String, bytes: Vec<u8> },
+}
+
+// stands in for an attachment's path in ImportedNote content, e.g. `![photo](placeholder)`.
+// replaced with the stored path once the attachment is written
+pub fn attachment_placeholder(key: &str) -> String {
+    format!("apto-attachment:{}", key)
+}
+
+// a note's content with the placeholder of every attachment replaced with `placeholder`
+fn without_attachment_keys(note: &ImportedNote, placeholder: &str) -> String {
+    let mut keys: Vec<&str> = note.attachments.iter().map(|a| a.key.as_str()).collect();
+    // longest first, so no key is replaced inside a longer one
+    keys.sort_by_key(|key| std::cmp::Reverse(key.len()));
+
+    keys.into_iter().fold(note.content.clone(), |content, key| {
+        content.replace(&attachment_placeholder(key), placeholder)
+    })
+}
+
+// note_folders ids by their path of names from the top level. in a dry run missing
+// folders are counted but not created, and resolve to None
+struct FolderPaths<'a> {
+    folders: FolderRepository<'a>,
+    create: bool,
+    ids: HashMap<Vec<String>, Option<i64>>,
+    created: usize,
+}
+
+impl FolderPaths<'_> {
+    fn resolve(&mut self, names: &[String]) -> AppResult<Option<i64>> {
+        let Some((name, parent_names)) = names.split_last() else {
+            return Ok(None);
+        };
+        if let Some(id) = self.ids.get(names) {
+            return Ok(*id);
+        }
+
+        let parent_id = self.resolve(parent_names)?;
+        let existing = if parent_names.is_empty() || parent_id.is_some() {
+            self.folders.find_child(parent_id, name)?
+        } else {
+            None
+        };
+
+        let id = match existing {
+            Some(id) => Some(id),
+            None => {
+                self.created += 1;
+                if self.create {
+                    Some(self.folders.create(name, parent_id, None)?)
+                } else {
+                    None
+                }
+            }
+        };
+
+        self.ids.insert(names.to_vec(), id);
+        Ok(id)
+    }
+}
+
+// turns ImportedNotes into notes, folders, tags and attachments, and keeps the report.
+// every importer goes through this, so duplicates and dry runs work the same everywhere
+pub struct ImportWriter<'a> {
+    conn: &'a Connection,
+    app_data_dir: &'a Path,
+    source: &'static str,
+    dry_run: bool,
+    folders: FolderPaths<'a>,
+    new_tags: HashSet<String>,
+    stored_files: HashSet<String>, // attachment paths written, relative to app_data_dir
+    report: ImportReport,
+}
+
+impl<'a> ImportWriter<'a> {
+    // run an import in one savepoint. if it fails nothing is kept, including attachment
+    // files already copied; in a dry run nothing is written at all
+    pub fn run(
+        conn: &'a Connection,
+        app_data_dir: &'a Path,
+        source: &'static str,
+        dry_run: bool,
+        import: impl FnOnce(&mut ImportWriter<'a>) -> AppResult<()>,
+    ) -> AppResult<ImportReport> {
+        // a savepoint rather than a transaction, so an import can be one operation of the
+        // undo history
+        conn.execute_batch("SAVEPOINT import")
+            .map_err(|e| AppError::database("Failed to start import transaction", e))?;
+
+        let mut writer = ImportWriter {
+            conn,
+            app_data_dir,
+            source,
+            dry_run,
+            folders: FolderPaths {
+                folders: FolderRepository::new(conn),
+                create: !dry_run,
+                ids: HashMap::new(),
+                created: 0,
+            },
+            new_tags: HashSet::new(),
+            stored_files: HashSet::new(),
+            report: ImportReport {
+                source: source.to_string(),
+                dry_run,
+                notes_imported: 0,
+                duplicates: 0,
+                skipped: 0,
+                folders_created: 0,
+                tags_created: 0,
+                attachments_imported: 0,
+                items: Vec::new(),
+                warnings: Vec::new(),
+            },
+        };
+
+        let result = import(&mut writer);
+        let end = if result.is_ok() && !dry_run {
+            "RELEASE import"
+        } else {
+            "ROLLBACK TO import; RELEASE import"
+        };
+        let ended = conn
+            .execute_batch(end)
+            .map_err(|e| AppError::database("Failed to commit import", e));
+        let result = result.and(ended);
+
+        // the rollback has already happened, so a file the import shares with an earlier
+        // attachment still has its blob and is kept
+        if let Err(e) = result {
+            for stored_path in &writer.stored_files {
+                let still_used = conn
+                    .query_row(
+                        "SELECT EXISTS (SELECT 1 FROM attachment_blobs WHERE file_path = ?)",
+                        params![stored_path],
+                        |row| row.get(0),
+                    )
+                    .unwrap_or(true);
+                if still_used {
+                    continue;
+                }
+
+                let file = app_data_dir.join(stored_path);
+                if let Err(remove_error) = fs::remove_file(&file) {
+                    warn!("Failed to remove {:?}: {}", file, remove_error);
+                }
+            }
+            return Err(e);
+        }
+
+        let mut report = writer.report;
+        report.folders_created = writer.folders.created;
+        report.tags_created = writer.new_tags.len();
+
+        info!(
+            "Imported {} notes from {} ({} duplicates, {} skipped, dry run: {})",
+            report.notes_imported, source, report.duplicates, report.skipped, dry_run
+        );
+        Ok(report)
+    }
+
+    // a note is a duplicate when an earlier import of this source recorded the same
+    // source_key, or when its folder already has a note with the same title and content
+    pub fn add(&mut self, note: ImportedNote) -> AppResult<()> {
+        let imports = ImportRepository::new(self.conn);
+
+        let folder_id = self.folders.resolve(&note.folder)?;
+        let folder_exists = note.folder.is_empty() || folder_id.is_some();
+
+        let existing = match imports.find_note(self.source, &note.source_key)? {
+            Some(note_id) => Some(note_id),
+            None if folder_exists => {
+                // an earlier import's content points at stored attachments, this one's at
+                // placeholders, so both sides are compared with one placeholder for all
+                let placeholder = attachment_placeholder("");
+                imports.find_identical_note(
+                    &note.title,
+                    &without_attachment_keys(&note, &placeholder),
+                    folder_id,
+                    &placeholder,
+                )?
+            }
+            None => None,
+        };
+
+        if let Some(note_id) = existing {
+            self.report.duplicates += 1;
+            self.report.items.push(ImportItem {
+                source: note.source,
+                title: note.title,
+                status: ImportStatus::Duplicate,
+                note_id: Some(note_id),
+                attachments: 0,
+                message: None,
+            });
+            return Ok(());
+        }
+
+        for tag in &note.tags {
+            if !self.new_tags.contains(tag) && !imports.tag_exists(tag)? {
+                self.new_tags.insert(tag.clone());
+            }
+        }
+
+        self.report.notes_imported += 1;
+        self.report.attachments_imported += note.attachments.len();
+
+        let note_id = if self.dry_run {
+            None
+        } else {
+            Some(self.write(folder_id, &note)?)
+        };
+
+        self.report.items.push(ImportItem {
+            source: note.source,
+            title: note.title,
+            status: ImportStatus::Imported,
+            note_id,
+            attachments: note.attachments.len(),
+            message: None,
+        });
+        Ok(())
+    }
+
+    // an item that couldn't be turned into a note
+    pub fn skip(&mut self, source: String, title: String, message: String) {
+        self.report.skipped += 1;
+        self.report.items.push(ImportItem {
+            source,
+            title,
+            status: ImportStatus::Skipped,
+            note_id: None,
+            attachments: 0,
+            message: Some(message),
+        });
+    }
+
+    // a problem worth telling the user about that didn't stop anything
+    pub fn warn(&mut self, message: String) {
+        self.report.warnings.push(message);
+    }
+
+    fn write(&mut self, folder_id: Option<i64>, note: &ImportedNote) -> AppResult<i64> {
+        let notes = NoteRepository::new(self.conn);
+        let attachments = AttachmentRepository::new(self.conn, self.app_data_dir);
+
+        let created_at = note.created_at.unwrap_or_else(Utc::now);
+        let updated_at = note.updated_at.unwrap_or(created_at).max(created_at);
+
+        let note_id = notes.create_with_timestamps(
+            NoteInput {
+                title: note.title.clone(),
+                content: note.content.clone(),
+                folder_id,
+                tags: note.tags.clone(),
+                is_pinned: note.is_pinned,
+                is_archived: note.is_archived,
+                color: note.color.clone(),
+            },
+            created_at,
+            updated_at,
+        )?;
+        ImportRepository::new(self.conn).record(note_id, self.source, &note.source_key)?;
+
+        if note.attachments.is_empty() {
+            return Ok(note_id);
+        }
+
+        let mut destinations = Vec::new();
+        for attachment in &note.attachments {
+            let attachment_id = match &attachment.data {
+                AttachmentData::File(path) => attachments.add(note_id, &path.to_string_lossy())?,
+                AttachmentData::Bytes { mime_type, bytes } => {
+                    attachments.add_data(note_id, &attachment.file_name, mime_type, bytes)?
+                }
+            };
+
+            let stored_path = attachments.get_by_id(attachment_id)?.file_path;
+            self.stored_files.insert(stored_path.clone());
+
+            // angle brackets keep spaces and parentheses from ending the link
+            let destination = if stored_path.contains([' ', '(', ')']) {
+                format!("<{}>", stored_path)
+            } else {
+                stored_path
+            };
+            destinations.push((attachment.key.as_str(), destination));
+        }
+
+        // longest key first, so no placeholder is replaced inside a longer one
+        destinations.sort_by_key(|(key, _)| std::cmp::Reverse(key.len()));
+        let content =
+            destinations
+                .iter()
+                .fold(note.content.clone(), |content, (key, destination)| {
+                    content.replace(&attachment_placeholder(key), destination)
+                });
+
+        if content != note.content {
+            notes.replace_imported_content(note_id, &content)?;
+        }
+
+        Ok(note_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::{open_test_db, TestDir};
+
+    fn imported(source_key: &str) -> ImportedNote {
+        ImportedNote {
+            source: format!("{}.md", source_key),
+            source_key: source_key.to_string(),
+            title: "Trip".to_string(),
+            content: "Photos: ![beach](apto-attachment:1) ![sea](apto-attachment:10)".to_string(),
+            folder: Vec::new(),
+            tags: Vec::new(),
+            is_pinned: false,
+            is_archived: false,
+            color: None,
+            created_at: None,
+            updated_at: None,
+            attachments: ["1", "10"]
+                .iter()
+                .map(|key| ImportedAttachment {
+                    key: key.to_string(),
+                    file_name: format!("{} photo.png", key),
+                    data: AttachmentData::Bytes {
+                        mime_type: "image/png".to_string(),
+                        bytes: format!("image {}", key).into_bytes(),
+                    },
+                })
+                .collect(),
+        }
+    }
+
+    fn import(conn: &Connection, dir: &TestDir, note: ImportedNote) -> ImportReport {
+        ImportWriter::run(conn, dir.path(), "test", false, |writer| writer.add(note)).unwrap()
+    }
+
+    #[test]
+    fn a_note_with_attachments_is_recognised_as_imported() {
+        let conn = open_test_db();
+        let dir = TestDir::create();
+
+        let first = import(&conn, &dir, imported("a"));
+        assert_eq!(first.notes_imported, 1);
+        let note_id = first.items[0].note_id.unwrap();
+        let content: String = conn
+            .query_row(
+                "SELECT content FROM notes WHERE id = ?",
+                params![note_id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(!content.contains("apto-attachment:"));
+
+        // under another key, as when the export was moved
+        let again = import(&conn, &dir, imported("b"));
+        assert_eq!(again.duplicates, 1);
+        assert_eq!(again.items[0].note_id, Some(note_id));
+    }
+
+    #[test]
+    fn trashed_notes_are_imported_again() {
+        let conn = open_test_db();
+        let dir = TestDir::create();
+        let first = import(&conn, &dir, imported("a"));
+        let note_id = first.items[0].note_id.unwrap();
+        NoteRepository::new(&conn).delete(note_id).unwrap();
+
+        let again = import(&conn, &dir, imported("a"));
+
+        assert_eq!(again.notes_imported, 1);
+        assert_ne!(again.items[0].note_id, Some(note_id));
+    }
+}
@@ -0,0 +1,179 @@
+use std::ops::Range;
+
+// a `[[Title#Heading|Alias]]` reference found in note content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WikiLink {
+    pub target_title: String,
+    pub heading: Option<String>,
+    pub alias: Option<String>,
+    pub title_range: Range<usize>, // byte range of the title inside the content, used for rewrites
+}
+
+// every wiki link in the content, in order. links inside fenced code blocks and inline
+// code spans are ignored, as are links without a title such as [[#Heading]]
+pub fn parse_wiki_links(content: &str) -> Vec<WikiLink> {
+    let mut links = Vec::new();
+    for (line_start, line) in prose_lines(content) {
+        parse_line(line, line_start, &mut links);
+    }
+    links
+}
+
+// the lines outside fenced code blocks, each with its byte offset in the content
+pub(crate) fn prose_lines(content: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut in_fence = false;
+    let mut line_start = 0;
+
+    content.split_inclusive('\n').filter_map(move |line| {
+        let offset = line_start;
+        line_start += line.len();
+
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            None
+        } else if in_fence {
+            None
+        } else {
+            Some((offset, line))
+        }
+    })
+}
+
+fn parse_line(line: &str, offset: usize, links: &mut Vec<WikiLink>) {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    let mut in_code = false;
+
+    while i < bytes.len() {
+        if bytes[i] == b'`' {
+            in_code = !in_code;
+            i += 1;
+            continue;
+        }
+
+        if in_code || !bytes[i..].starts_with(b"[[") {
+            i += 1;
+            continue;
+        }
+
+        let inner_start = i + 2;
+        let Some(inner_len) = line[inner_start..].find("]]") else {
+            break;
+        };
+        let inner = &line[inner_start..inner_start + inner_len];
+
+        // nested or stray brackets mean this isn't a link, carry on after the first "["
+        if inner.contains('[') || inner.contains(']') {
+            i += 1;
+            continue;
+        }
+
+        if let Some(link) = parse_inner(inner, offset + inner_start) {
+            links.push(link);
+        }
+        i = inner_start + inner_len + 2;
+    }
+}
+
+fn parse_inner(inner: &str, offset: usize) -> Option<WikiLink> {
+    let (target, alias) = match inner.split_once('|') {
+        Some((target, alias)) => (target, non_empty(alias)),
+        None => (inner, None),
+    };
+    let (title, heading) = match target.split_once('#') {
+        Some((title, heading)) => (title, non_empty(heading)),
+        None => (target, None),
+    };
+
+    let trimmed = title.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    let leading = title.len() - title.trim_start().len();
+    let start = offset + leading;
+
+    Some(WikiLink {
+        target_title: trimmed.to_string(),
+        heading,
+        alias,
+        title_range: start..start + trimmed.len(),
+    })
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+// point every link to `old_title` at `new_title`, keeping headings and aliases.
+// titles compare like sqlite's NOCASE (ASCII only). None when nothing referenced old_title
+pub fn rewrite_link_targets(content: &str, old_title: &str, new_title: &str) -> Option<String> {
+    let ranges: Vec<Range<usize>> = parse_wiki_links(content)
+        .into_iter()
+        .filter(|link| link.target_title.eq_ignore_ascii_case(old_title))
+        .map(|link| link.title_range)
+        .collect();
+
+    if ranges.is_empty() {
+        return None;
+    }
+
+    let mut rewritten = String::with_capacity(content.len());
+    let mut last = 0;
+    for range in ranges {
+        rewritten.push_str(&content[last..range.start]);
+        rewritten.push_str(new_title);
+        last = range.end;
+    }
+    rewritten.push_str(&content[last..]);
+
+    Some(rewritten)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_title_heading_and_alias() {
+        let content = "See [[ Project Plan #Goals| the goals ]] and [[Inbox]].";
+        let links = parse_wiki_links(content);
+
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].target_title, "Project Plan");
+        assert_eq!(links[0].heading.as_deref(), Some("Goals"));
+        assert_eq!(links[0].alias.as_deref(), Some("the goals"));
+        assert_eq!(&content[links[0].title_range.clone()], "Project Plan");
+        assert_eq!(links[1].target_title, "Inbox");
+        assert_eq!(links[1].heading, None);
+    }
+
+    #[test]
+    fn ignores_code_and_links_without_titles() {
+        let content = "`[[in code]]` [[#Heading only]]\n```\n[[fenced]]\n```\n[[after]] [[a[b]]";
+        let titles: Vec<String> = parse_wiki_links(content)
+            .into_iter()
+            .map(|link| link.target_title)
+            .collect();
+
+        assert_eq!(titles, vec!["after"]);
+    }
+
+    #[test]
+    fn unterminated_link_is_ignored() {
+        assert!(parse_wiki_links("[[never closed").is_empty());
+    }
+
+    #[test]
+    fn rewrites_matching_targets_only() {
+        let content = "[[old note]] [[Old Note#Part|alias]] [[Other]]";
+
+        assert_eq!(
+            rewrite_link_targets(content, "Old Note", "New").as_deref(),
+            Some("[[New]] [[New#Part|alias]] [[Other]]")
+        );
+        assert_eq!(rewrite_link_targets(content, "Missing", "New"), None);
+    }
+}
@@ -0,0 +1,231 @@
+use super::front_matter::render_front_matter;
+use super::names::{safe_file_name, UniqueNames};
+use crate::error::{AppError, AppResult};
+use crate::features::notes::models::{MarkdownExportSummary, NoteFolder};
+use crate::features::notes::repository::{AttachmentRepository, FolderRepository, NoteRepository};
+use log::{info, warn};
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// attachments are copied into this directory next to the notes that use them
+const ATTACHMENTS_DIR: &str = "attachments";
+
+// writes every note as a .md file into a directory tree mirroring the folders
+pub struct MarkdownExporter<'a> {
+    conn: &'a Connection,
+    app_data_dir: &'a Path,
+}
+
+impl<'a> MarkdownExporter<'a> {
+    pub fn new(conn: &'a Connection, app_data_dir: &'a Path) -> Self {
+        MarkdownExporter { conn, app_data_dir }
+    }
+
+    // existing files with the same names are overwritten, anything else in target_dir is left alone
+    pub fn export(
+        &self,
+        target_dir: &Path,
+        include_archived: bool,
+    ) -> AppResult<MarkdownExportSummary> {
+        fs::create_dir_all(target_dir)
+            .map_err(|e| AppError::io("Failed to create export directory", e))?;
+
+        let mut names = UniqueNames::default();
+        let folder_dirs = self.create_folder_dirs(target_dir, &mut names)?;
+
+        let mut notes = NoteRepository::new(self.conn).get_all()?;
+        notes.sort_by_key(|note| note.id);
+
+        let attachments = AttachmentRepository::new(self.conn, self.app_data_dir);
+        let mut summary = MarkdownExportSummary {
+            directory: target_dir.to_string_lossy().to_string(),
+            notes_exported: 0,
+            attachments_copied: 0,
+            missing_attachments: Vec::new(),
+        };
+
+        for note in notes {
+            if note.is_archived && !include_archived {
+                continue;
+            }
+
+            let note_dir = note
+                .folder_id
+                .and_then(|id| folder_dirs.get(&id))
+                .cloned()
+                .unwrap_or_else(|| target_dir.to_path_buf());
+
+            let file_name = names.claim(&note_dir, &safe_file_name(&note.title), "md");
+
+            let mut content = note.content.clone();
+            let mut attachment_links = Vec::new();
+
+            for attachment in attachments.get_for_note(note.id)? {
+                let source = self.app_data_dir.join(&attachment.file_path);
+                if !source.exists() {
+                    warn!("Skipping missing attachment file {:?}", source);
+                    summary.missing_attachments.push(attachment.file_name);
+                    continue;
+                }
+
+                let attachments_dir = note_dir.join(ATTACHMENTS_DIR);
+                fs::create_dir_all(&attachments_dir)
+                    .map_err(|e| AppError::io("Failed to create attachments directory", e))?;
+
+                let (stem, extension) = split_extension(&attachment.file_name);
+                let copied_name = names.claim(&attachments_dir, &safe_file_name(stem), extension);
+                fs::copy(&source, attachments_dir.join(&copied_name))
+                    .map_err(|e| AppError::io("Failed to copy attachment", e))?;
+                summary.attachments_copied += 1;
+
+                // links in the content point at the stored file, either relative to the
+                // app data directory or absolute; both become relative to the note
+                let relative_link = format!("{}/{}", ATTACHMENTS_DIR, encode_link(&copied_name));
+                content = content.replace(&source.to_string_lossy().to_string(), &relative_link);
+                content = content.replace(&attachment.file_path, &relative_link);
+
+                attachment_links.push(format!("{}/{}", ATTACHMENTS_DIR, copied_name));
+            }
+
+            let markdown = format!(
+                "{}\n{}",
+                render_front_matter(&note, &attachment_links),
+                content
+            );
+            fs::write(note_dir.join(&file_name), markdown)
+                .map_err(|e| AppError::io("Failed to write note file", e))?;
+            summary.notes_exported += 1;
+        }
+
+        info!(
+            "Exported {} notes and {} attachments to {:?}",
+            summary.notes_exported, summary.attachments_copied, target_dir
+        );
+        Ok(summary)
+    }
+
+    // create a directory for every folder, returning folder id -> directory. trashed folders
+    // get none, so a folder or note still inside one is exported at the top level
+    fn create_folder_dirs(
+        &self,
+        target_dir: &Path,
+        names: &mut UniqueNames,
+    ) -> AppResult<HashMap<i64, PathBuf>> {
+        let mut folders = FolderRepository::new(self.conn).get_all()?;
+        folders.sort_by_key(|folder| folder.id);
+
+        let by_id: HashMap<i64, &NoteFolder> =
+            folders.iter().map(|folder| (folder.id, folder)).collect();
+        let mut dirs = HashMap::new();
+
+        for folder in &folders {
+            resolve_folder_dir(folder.id, &by_id, target_dir, names, &mut dirs);
+        }
+
+        for dir in dirs.values() {
+            fs::create_dir_all(dir)
+                .map_err(|e| AppError::io("Failed to create folder directory", e))?;
+        }
+
+        Ok(dirs)
+    }
+}
+
+// directory of a folder, resolving its parents first. a folder whose parent chain is
+// broken or loops back on itself is placed at the top level
+fn resolve_folder_dir(
+    folder_id: i64,
+    by_id: &HashMap<i64, &NoteFolder>,
+    target_dir: &Path,
+    names: &mut UniqueNames,
+    dirs: &mut HashMap<i64, PathBuf>,
+) -> PathBuf {
+    // collect the chain of unresolved ancestors, nearest first
+    let mut chain = Vec::new();
+    let mut current = Some(folder_id);
+    while let Some(id) = current {
+        if dirs.contains_key(&id) || chain.contains(&id) {
+            break;
+        }
+        let Some(folder) = by_id.get(&id) else {
+            break;
+        };
+        chain.push(id);
+        current = folder.parent_id;
+    }
+
+    // then resolve from the top down
+    for id in chain.into_iter().rev() {
+        let folder = by_id[&id];
+        let parent_dir = folder
+            .parent_id
+            .and_then(|parent_id| dirs.get(&parent_id))
+            .cloned()
+            .unwrap_or_else(|| target_dir.to_path_buf());
+
+        let dir_name = names.claim(&parent_dir, &safe_file_name(&folder.name), "");
+        dirs.insert(id, parent_dir.join(dir_name));
+    }
+
+    dirs[&folder_id].clone()
+}
+
+// "photo.final.png" -> ("photo.final", "png"); names without a dot have no extension
+fn split_extension(file_name: &str) -> (&str, &str) {
+    match file_name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, extension),
+        _ => (file_name, ""),
+    }
+}
+
+// characters that would end or break a markdown link target
+fn encode_link(name: &str) -> String {
+    name.replace('%', "%25")
+        .replace(' ', "%20")
+        .replace('(', "%28")
+        .replace(')', "%29")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::{create_note, note_input, open_test_db, TestDir};
+    use crate::features::notes::models::NoteInput;
+    use chrono::Utc;
+    use rusqlite::params;
+
+    #[test]
+    fn trashed_notes_and_folders_are_not_exported() {
+        let conn = open_test_db();
+        let app_data = TestDir::create();
+        let target = TestDir::create();
+        let folders = FolderRepository::new(&conn);
+        let projects = folders.create("Projects", None, None).unwrap();
+        let apto = folders.create("Apto", Some(projects), None).unwrap();
+        create_note(
+            &conn,
+            NoteInput {
+                folder_id: Some(apto),
+                ..note_input("Plan", "", &[])
+            },
+        );
+        let trashed = create_note(&conn, note_input("Draft", "", &[]));
+        NoteRepository::new(&conn).delete(trashed).unwrap();
+        conn.execute(
+            "UPDATE note_folders SET deleted_at = ? WHERE id = ?",
+            params![Utc::now().to_rfc3339(), projects],
+        )
+        .unwrap();
+
+        let summary = MarkdownExporter::new(&conn, app_data.path())
+            .export(target.path(), false)
+            .unwrap();
+
+        assert_eq!(summary.notes_exported, 1);
+        assert!(target.path().join("Apto").join("Plan.md").exists());
+        assert!(!target.path().join("Projects").exists());
+        assert!(!target.path().join("Draft.md").exists());
+    }
+}
@@ -0,0 +1,360 @@
+use crate::features::notes::models::Note;
+
+// the YAML block at the top of an exported note. written by hand rather than through a
+// YAML library: the shape is fixed, and every string is double-quoted so no title or
+// tag can be misread as a number, boolean or nested structure
+pub fn render_front_matter(note: &Note, attachments: &[String]) -> String {
+    let mut yaml = String::from("---\n");
+
+    yaml.push_str(&format!("id: {}\n", note.id));
+    yaml.push_str(&format!("title: {}\n", quote(&note.title)));
+    push_list(&mut yaml, "tags", &note.tags);
+    yaml.push_str(&format!("pinned: {}\n", note.is_pinned));
+    yaml.push_str(&format!("archived: {}\n", note.is_archived));
+    if let Some(color) = &note.color {
+        yaml.push_str(&format!("color: {}\n", quote(color)));
+    }
+    yaml.push_str(&format!("created: {}\n", note.created_at.to_rfc3339()));
+    yaml.push_str(&format!("updated: {}\n", note.updated_at.to_rfc3339()));
+    if !attachments.is_empty() {
+        push_list(&mut yaml, "attachments", attachments);
+    }
+
+    yaml.push_str("---\n");
+    yaml
+}
+
+fn push_list(yaml: &mut String, key: &str, values: &[String]) {
+    if values.is_empty() {
+        yaml.push_str(&format!("{}: []\n", key));
+        return;
+    }
+
+    yaml.push_str(&format!("{}:\n", key));
+    for value in values {
+        yaml.push_str(&format!("  - {}\n", quote(value)));
+    }
+}
+
+// a YAML double-quoted scalar
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrontMatterValue {
+    Scalar(String),
+    List(Vec<String>),
+}
+
+// the top-level keys of a front matter block. only the YAML that note apps actually write
+// is understood: `key: value`, `key: [a, b]` and `key:` followed by `- item` lines.
+// nested maps and block scalars are skipped
+#[derive(Debug, Default)]
+pub struct FrontMatter {
+    entries: Vec<(String, FrontMatterValue)>,
+}
+
+impl FrontMatter {
+    // keys compare case-insensitively, the first occurrence wins
+    pub fn get(&self, key: &str) -> Option<&FrontMatterValue> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value)
+    }
+
+    pub fn text(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            FrontMatterValue::Scalar(value) if !value.is_empty() => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn flag(&self, key: &str) -> Option<bool> {
+        match self.text(key)?.to_ascii_lowercase().as_str() {
+            "true" | "yes" | "on" => Some(true),
+            "false" | "no" | "off" => Some(false),
+            _ => None,
+        }
+    }
+
+    // a list value as is, or a scalar split on commas and whitespace ("tags: a, b c")
+    pub fn list(&self, key: &str) -> Vec<String> {
+        match self.get(key) {
+            Some(FrontMatterValue::List(items)) => items.clone(),
+            Some(FrontMatterValue::Scalar(value)) => value
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+// separate a leading `---` front matter block from the body. text without a
+// complete block comes back unchanged with empty front matter
+pub fn split_front_matter(markdown: &str) -> (FrontMatter, &str) {
+    let text = markdown.strip_prefix('\u{feff}').unwrap_or(markdown);
+
+    let mut lines = text.split_inclusive('\n');
+    let mut offset = match lines.next() {
+        Some(first) if first.trim_end() == "---" => first.len(),
+        _ => return (FrontMatter::default(), markdown),
+    };
+    let mut block = Vec::new();
+
+    for line in lines {
+        offset += line.len();
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            // the exporter leaves a blank line between the block and the body
+            let body = &text[offset..];
+            let body = body
+                .strip_prefix("\r\n")
+                .or_else(|| body.strip_prefix('\n'))
+                .unwrap_or(body);
+            return (parse_block(&block), body);
+        }
+        block.push(trimmed);
+    }
+
+    (FrontMatter::default(), markdown)
+}
+
+fn parse_block(lines: &[&str]) -> FrontMatter {
+    let mut entries: Vec<(String, FrontMatterValue)> = Vec::new();
+    // the key waiting for `- item` lines, with the items so far
+    let mut pending_list: Option<(String, Vec<String>)> = None;
+
+    for line in lines {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        let indented = line.starts_with([' ', '\t']);
+        let item = line
+            .trim_start()
+            .strip_prefix('-')
+            .filter(|rest| rest.is_empty() || rest.starts_with([' ', '\t']));
+
+        if let (Some(item), Some((_, items))) = (item, pending_list.as_mut()) {
+            items.push(unquote(item.trim()));
+            continue;
+        }
+
+        if indented {
+            // nested maps and continuation lines
+            continue;
+        }
+
+        if let Some((key, items)) = pending_list.take() {
+            entries.push((key, list_or_empty(items)));
+        }
+
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = unquote(key.trim());
+        let value = strip_comment(value.trim());
+
+        if value.is_empty() {
+            pending_list = Some((key, Vec::new()));
+        } else if let Some(flow) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            let items = split_flow_list(flow)
+                .into_iter()
+                .map(|item| unquote(item.trim()))
+                .filter(|item| !item.is_empty())
+                .collect();
+            entries.push((key, FrontMatterValue::List(items)));
+        } else if value == "|" || value == ">" || value.starts_with(['|', '>']) {
+            // block scalars aren't needed by any importer
+            continue;
+        } else {
+            entries.push((key, FrontMatterValue::Scalar(unquote(value))));
+        }
+    }
+
+    if let Some((key, items)) = pending_list.take() {
+        entries.push((key, list_or_empty(items)));
+    }
+
+    FrontMatter { entries }
+}
+
+// `key:` with nothing after it and no items is an empty value, not an empty list
+fn list_or_empty(items: Vec<String>) -> FrontMatterValue {
+    if items.is_empty() {
+        FrontMatterValue::Scalar(String::new())
+    } else {
+        FrontMatterValue::List(items)
+    }
+}
+
+// drop a trailing ` # comment` from an unquoted value
+fn strip_comment(value: &str) -> &str {
+    if value.starts_with(['"', '\'']) {
+        return value;
+    }
+    match value.find(" #") {
+        Some(index) => value[..index].trim_end(),
+        None => value,
+    }
+}
+
+// split `a, "b, c", d` on the commas outside quotes
+fn split_flow_list(flow: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut quote_char = None;
+    let mut start = 0;
+
+    for (index, c) in flow.char_indices() {
+        match (quote_char, c) {
+            (None, '"' | '\'') => quote_char = Some(c),
+            (Some(q), c) if c == q => quote_char = None,
+            (None, ',') => {
+                items.push(&flow[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&flow[start..]);
+
+    items
+}
+
+// the value of a plain, single-quoted or double-quoted YAML scalar
+fn unquote(value: &str) -> String {
+    if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        return value[1..value.len() - 1].replace("''", "'");
+    }
+
+    if !(value.len() >= 2 && value.starts_with('"') && value.ends_with('"')) {
+        return value.to_string();
+    }
+
+    let inner = &value[1..value.len() - 1];
+    let mut unquoted = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unquoted.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => unquoted.push('\n'),
+            Some('r') => unquoted.push('\r'),
+            Some('t') => unquoted.push('\t'),
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                if let Some(c) = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    unquoted.push(c);
+                }
+            }
+            Some(other) => unquoted.push(other),
+            None => unquoted.push('\\'),
+        }
+    }
+
+    unquoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn note() -> Note {
+        Note {
+            id: 3,
+            title: "Say \"hi\"\nagain".to_string(),
+            content: String::new(),
+            folder_id: None,
+            tags: vec!["work".to_string(), "true".to_string()],
+            is_pinned: true,
+            is_archived: false,
+            color: Some("#ff0000".to_string()),
+            created_at: Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap(),
+            updated_at: Utc.with_ymd_and_hms(2026, 2, 3, 4, 5, 6).unwrap(),
+        }
+    }
+
+    #[test]
+    fn rendered_front_matter_reads_back() {
+        let markdown = format!(
+            "{}\nbody",
+            render_front_matter(&note(), &["files/a.png".to_string()])
+        );
+        let (front_matter, body) = split_front_matter(&markdown);
+
+        assert_eq!(body, "body");
+        assert_eq!(front_matter.text("id"), Some("3"));
+        assert_eq!(front_matter.text("title"), Some("Say \"hi\"\nagain"));
+        assert_eq!(front_matter.list("tags"), vec!["work", "true"]);
+        assert_eq!(front_matter.flag("pinned"), Some(true));
+        assert_eq!(front_matter.flag("archived"), Some(false));
+        assert_eq!(front_matter.text("color"), Some("#ff0000"));
+        assert_eq!(front_matter.list("attachments"), vec!["files/a.png"]);
+    }
+
+    #[test]
+    fn empty_tags_are_an_empty_list() {
+        let mut note = note();
+        note.tags.clear();
+
+        assert!(render_front_matter(&note, &[]).contains("tags: []\n"));
+    }
+
+    #[test]
+    fn parses_the_yaml_other_apps_write() {
+        let markdown = "\u{feff}---\r\n\
+                        Title: 'It''s here'\r\n\
+                        tags: [a, \"b, c\"]\r\n\
+                        aliases: one, two three\r\n\
+                        nested:\r\n  key: value\r\n\
+                        description: |\r\n  block\r\n\
+                        empty:\r\n\
+                        draft: yes # a comment\r\n\
+                        ...\r\n\
+                        \r\nbody\r\n";
+        let (front_matter, body) = split_front_matter(markdown);
+
+        assert_eq!(body, "body\r\n");
+        assert_eq!(front_matter.text("title"), Some("It's here"));
+        assert_eq!(front_matter.list("tags"), vec!["a", "b, c"]);
+        assert_eq!(front_matter.list("aliases"), vec!["one", "two", "three"]);
+        assert_eq!(front_matter.text("nested"), None);
+        assert_eq!(front_matter.get("description"), None);
+        assert_eq!(front_matter.text("empty"), None);
+        assert_eq!(front_matter.flag("draft"), Some(true));
+    }
+
+    #[test]
+    fn text_without_a_complete_block_is_unchanged() {
+        for markdown in ["no front matter", "---\ntitle: x\nnever closed"] {
+            let (front_matter, body) = split_front_matter(markdown);
+            assert_eq!(body, markdown);
+            assert_eq!(front_matter.get("title"), None);
+        }
+    }
+}
@@ -0,0 +1,406 @@
+use super::front_matter::{split_front_matter, FrontMatter};
+use super::inline::{parse_file_references, parse_inline_tags, FileReference};
+use crate::error::{AppError, AppResult};
+use crate::features::notes::importers::writer::{
+    attachment_placeholder, AttachmentData, ImportWriter, ImportedAttachment, ImportedNote,
+};
+use crate::features::notes::importers::NoteImporter;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+// the `source` recorded in note_imports for notes from a vault
+const SOURCE: &str = "markdown";
+
+// what a vault directory holds, paths relative to its root
+#[derive(Default)]
+struct Vault {
+    root: PathBuf,
+    notes: Vec<PathBuf>,
+    // lowercased file name -> every non-note file with that name, for `![[name.png]]`
+    files_by_name: HashMap<String, Vec<PathBuf>>,
+}
+
+// imports directories of .md files (an Obsidian vault or any folder of Markdown):
+// subdirectories become folders, files become notes, and the images and files they
+// embed become attachments. notes already imported from the same file are reported as
+// duplicates and left alone, so importing a vault again only brings in what's new
+pub struct VaultImporter;
+
+impl NoteImporter for VaultImporter {
+    fn source(&self) -> &'static str {
+        SOURCE
+    }
+
+    fn read(&self, paths: &[PathBuf], writer: &mut ImportWriter) -> AppResult<()> {
+        for vault_dir in paths {
+            let root = fs::canonicalize(vault_dir)
+                .map_err(|e| AppError::io(&format!("Failed to open vault {:?}", vault_dir), e))?;
+            if !root.is_dir() {
+                return Err(AppError::validation(format!(
+                    "{:?} is not a directory",
+                    vault_dir
+                )));
+            }
+
+            let mut vault = Vault {
+                root,
+                ..Default::default()
+            };
+            scan_dir(&mut vault, Path::new(""))?;
+
+            for relative in &vault.notes {
+                read_note(&vault, relative, writer)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_note(vault: &Vault, relative: &Path, writer: &mut ImportWriter) -> AppResult<()> {
+    let display_path = display_path_of(relative);
+    let absolute = vault.root.join(relative);
+    let file_title = relative
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let text = match fs::read_to_string(&absolute) {
+        Ok(text) => text,
+        Err(e) => {
+            writer.skip(
+                display_path,
+                file_title,
+                format!("Failed to read file: {}", e),
+            );
+            return Ok(());
+        }
+    };
+
+    let (front_matter, body) = split_front_matter(&text);
+
+    // point each reference to a vault file at a placeholder for its attachment
+    let note_dir = relative.parent().unwrap_or(Path::new(""));
+    let mut attachments: Vec<ImportedAttachment> = Vec::new();
+    let mut content = String::with_capacity(body.len());
+    let mut last = 0;
+
+    for reference in parse_file_references(body) {
+        let file = match resolve_reference(vault, note_dir, &reference) {
+            Some(file) if !is_markdown(&file) => file,
+            Some(_) => continue, // a link or transclusion of another note
+            None => {
+                if reference.embed || has_file_extension(&reference.target) {
+                    writer.warn(format!(
+                        "{}: attachment '{}' not found in the vault",
+                        display_path, reference.target
+                    ));
+                }
+                continue;
+            }
+        };
+
+        let key = display_path_of(&file);
+        if !attachments.iter().any(|attachment| attachment.key == key) {
+            attachments.push(ImportedAttachment {
+                key: key.clone(),
+                file_name: file
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                data: AttachmentData::File(vault.root.join(&file)),
+            });
+        }
+
+        content.push_str(&body[last..reference.range.start]);
+        content.push_str(&render_reference(&reference, &file, &key));
+        last = reference.range.end;
+    }
+    content.push_str(&body[last..]);
+
+    let (created_at, updated_at) = note_dates(&front_matter, &absolute);
+
+    writer.add(ImportedNote {
+        source: display_path,
+        source_key: absolute.to_string_lossy().to_string(),
+        title: front_matter
+            .text("title")
+            .map(str::to_string)
+            .unwrap_or(file_title),
+        content,
+        folder: note_dir
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .collect(),
+        tags: collect_tags(&front_matter, body),
+        is_pinned: front_matter.flag("pinned").unwrap_or(false),
+        is_archived: front_matter.flag("archived").unwrap_or(false),
+        color: front_matter.text("color").map(str::to_string),
+        created_at: Some(created_at),
+        updated_at: Some(updated_at),
+        attachments,
+    })
+}
+
+// collect notes and other files below `relative`, in name order. hidden entries such as
+// .obsidian and .trash are skipped, and symlinks aren't followed
+fn scan_dir(vault: &mut Vault, relative: &Path) -> AppResult<()> {
+    let entries = fs::read_dir(vault.root.join(relative))
+        .map_err(|e| AppError::io(&format!("Failed to read directory {:?}", relative), e))?;
+
+    let mut entries = entries
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::io(&format!("Failed to read directory {:?}", relative), e))?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        let file_type = entry
+            .file_type()
+            .map_err(|e| AppError::io(&format!("Failed to read {:?}", entry.path()), e))?;
+        let path = relative.join(&name);
+
+        if file_type.is_dir() {
+            scan_dir(vault, &path)?;
+        } else if file_type.is_file() {
+            if is_markdown(&path) {
+                vault.notes.push(path);
+            } else {
+                vault
+                    .files_by_name
+                    .entry(name.to_string_lossy().to_lowercase())
+                    .or_default()
+                    .push(path);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// the vault file a reference points at: relative to the note, relative to the vault root,
+// or, the way Obsidian resolves bare names, the file with that name nearest the root.
+// nothing outside the vault is ever returned
+fn resolve_reference(vault: &Vault, note_dir: &Path, reference: &FileReference) -> Option<PathBuf> {
+    let target = Path::new(&reference.target);
+
+    for candidate in [note_dir.join(target), target.to_path_buf()] {
+        let Ok(resolved) = fs::canonicalize(vault.root.join(&candidate)) else {
+            continue;
+        };
+        if resolved.is_file() {
+            if let Ok(relative) = resolved.strip_prefix(&vault.root) {
+                return Some(relative.to_path_buf());
+            }
+        }
+    }
+
+    let name = target.file_name()?.to_string_lossy().to_lowercase();
+    vault
+        .files_by_name
+        .get(&name)?
+        .iter()
+        .min_by_key(|path| (path.components().count(), path.as_os_str().len()))
+        .cloned()
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        extension.eq_ignore_ascii_case("md") || extension.eq_ignore_ascii_case("markdown")
+    })
+}
+
+// whether a link target names a non-note file, like "report.pdf"
+fn has_file_extension(target: &str) -> bool {
+    let path = Path::new(target);
+    path.extension().is_some() && !is_markdown(path)
+}
+
+// front matter `tags`/`tag` followed by inline #tags, without "#" and without repeats
+fn collect_tags(front_matter: &FrontMatter, body: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+
+    let listed = front_matter
+        .list("tags")
+        .into_iter()
+        .chain(front_matter.list("tag"));
+    for tag in listed.chain(parse_inline_tags(body)) {
+        let tag = tag.trim().trim_start_matches('#').to_string();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+
+    tags
+}
+
+// created/updated from front matter, else from the file, else now
+fn note_dates(front_matter: &FrontMatter, file: &Path) -> (DateTime<Utc>, DateTime<Utc>) {
+    let metadata = fs::metadata(file).ok();
+    let modified = metadata
+        .as_ref()
+        .and_then(|m| m.modified().ok())
+        .map(DateTime::<Utc>::from);
+    let created = metadata
+        .as_ref()
+        .and_then(|m| m.created().ok())
+        .map(DateTime::<Utc>::from);
+
+    let created_at = ["created", "date"]
+        .iter()
+        .find_map(|key| front_matter.text(key).and_then(parse_date))
+        .or(created)
+        .or(modified)
+        .unwrap_or_else(Utc::now);
+
+    let updated_at = ["updated", "modified"]
+        .iter()
+        .find_map(|key| front_matter.text(key).and_then(parse_date))
+        .or(modified)
+        .unwrap_or(created_at);
+
+    (created_at, updated_at.max(created_at))
+}
+
+// RFC 3339, or the plainer "2024-01-31 09:30" and "2024-01-31" forms, taken as UTC
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(value) {
+        return Some(date_time.with_timezone(&Utc));
+    }
+
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(date_time) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(date_time.and_utc());
+        }
+    }
+
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|date_time| date_time.and_utc())
+}
+
+// the reference as a plain Markdown link to the attachment's placeholder
+fn render_reference(reference: &FileReference, file: &Path, key: &str) -> String {
+    let label = if reference.label.is_empty() {
+        file.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    } else {
+        reference.label.clone()
+    };
+
+    let bang = if reference.embed { "!" } else { "" };
+    format!("{}[{}]({})", bang, label, attachment_placeholder(key))
+}
+
+// "Projects/Plan.md" on every platform
+fn display_path_of(relative: &Path) -> String {
+    relative
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::{open_test_db, TestDir};
+    use crate::features::notes::importers::run_import;
+    use crate::features::notes::models::ImportReport;
+    use crate::features::notes::repository::{
+        AttachmentRepository, FolderRepository, NoteRepository,
+    };
+    use rusqlite::Connection;
+
+    fn import(
+        conn: &Connection,
+        app_dir: &TestDir,
+        vault: &TestDir,
+        dry_run: bool,
+    ) -> ImportReport {
+        let vault_dir = vec![vault.path().to_path_buf()];
+        run_import(conn, app_dir.path(), &VaultImporter, &vault_dir, dry_run).unwrap()
+    }
+
+    fn count(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn a_vault_is_imported_once() {
+        let conn = open_test_db();
+        let app_dir = TestDir::create();
+        let vault = TestDir::create();
+        vault.write(
+            "Projects/Plan.md",
+            b"---\ntitle: The plan\ntags: [work, q1]\ncreated: 2024-01-31\n---\n\
+              See ![[diagram.png]] and [the report](files/report.pdf).\n\
+              #urgent, but not `#code` or #42\n",
+        );
+        vault.write("Projects/files/report.pdf", b"%PDF-1.4 report");
+        vault.write("assets/diagram.png", b"diagram");
+        vault.write("Inbox.md", b"Just text, see [[The plan]]");
+        vault.write(".obsidian/workspace.md", b"settings");
+
+        let dry_run = import(&conn, &app_dir, &vault, true);
+        assert_eq!(dry_run.notes_imported, 2);
+        assert_eq!(dry_run.folders_created, 1);
+        assert_eq!(dry_run.tags_created, 3);
+        assert_eq!(dry_run.attachments_imported, 2);
+        assert!(dry_run.warnings.is_empty());
+        assert_eq!(count(&conn, "notes"), 0);
+        assert_eq!(count(&conn, "note_folders"), 0);
+        assert_eq!(fs::read_dir(app_dir.path()).unwrap().count(), 0);
+
+        let report = import(&conn, &app_dir, &vault, false);
+        assert_eq!(report.notes_imported, 2);
+        assert_eq!(report.attachments_imported, 2);
+        let plan_item = report
+            .items
+            .iter()
+            .find(|item| item.source == "Projects/Plan.md")
+            .unwrap();
+        let plan = NoteRepository::new(&conn)
+            .get_by_id(plan_item.note_id.unwrap())
+            .unwrap();
+        assert_eq!(plan.title, "The plan");
+        assert_eq!(plan.tags, ["work", "q1", "urgent"]);
+        assert_eq!(plan.created_at.to_rfc3339(), "2024-01-31T00:00:00+00:00");
+        let folder = FolderRepository::new(&conn)
+            .get_by_id(plan.folder_id.unwrap())
+            .unwrap();
+        assert_eq!(folder.name, "Projects");
+
+        let attachments = AttachmentRepository::new(&conn, app_dir.path())
+            .get_for_note(plan.id)
+            .unwrap();
+        assert_eq!(attachments.len(), 2);
+        for attachment in &attachments {
+            assert!(plan.content.contains(&attachment.file_path));
+        }
+        assert!(plan.content.contains("![diagram.png]("));
+        assert!(plan.content.contains("[the report]("));
+        assert!(!plan.content.contains("apto-attachment:"));
+
+        let again = import(&conn, &app_dir, &vault, false);
+        assert_eq!(again.notes_imported, 0);
+        assert_eq!(again.duplicates, 2);
+        assert_eq!(again.folders_created, 0);
+        assert_eq!(again.tags_created, 0);
+        assert_eq!(count(&conn, "notes"), 2);
+        assert_eq!(count(&conn, "note_attachments"), 2);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::features::notes::links::prose_lines;
+use std::ops::Range;
+
+// a reference from note content to another file: `![[file.png]]`, `![alt](file.png)`
+// or `[text](file.pdf)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileReference {
+    pub range: Range<usize>, // byte range of the whole reference, replaced on rewrite
+    pub target: String,      // the path as written, percent-decoded and without #fragment
+    pub label: String,       // alt text, link text or wiki alias, possibly empty
+    pub embed: bool,         // written with a leading "!"
+}
+
+// `#tag` and `#nested/tag` words outside code, without the "#", in order of appearance.
+// a tag needs at least one non-digit so issue numbers like #42 don't count
+pub fn parse_inline_tags(content: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+
+    for (_, line) in prose_lines(content) {
+        let mut in_code = false;
+        let mut previous = ' ';
+        let mut chars = line.char_indices().peekable();
+
+        while let Some((index, c)) = chars.next() {
+            if c == '`' {
+                in_code = !in_code;
+            } else if c == '#' && !in_code && previous.is_whitespace() {
+                let rest = &line[index + 1..];
+                let length = rest
+                    .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | '/')))
+                    .unwrap_or(rest.len());
+                let tag = rest[..length].trim_end_matches('/');
+
+                if tag.chars().any(|c| !c.is_ascii_digit()) && !tags.iter().any(|t| t == tag) {
+                    tags.push(tag.to_string());
+                }
+                while chars.peek().is_some_and(|(i, _)| *i <= index + length) {
+                    chars.next();
+                }
+            }
+            previous = c;
+        }
+    }
+
+    tags
+}
+
+// references to local files outside code. links with a scheme (https:, mailto:, ...) and
+// plain [[wiki links]] to notes are not file references
+pub fn parse_file_references(content: &str) -> Vec<FileReference> {
+    let mut references = Vec::new();
+
+    for (offset, line) in prose_lines(content) {
+        let bytes = line.as_bytes();
+        let mut i = 0;
+        let mut in_code = false;
+
+        while i < bytes.len() {
+            if bytes[i] == b'`' {
+                in_code = !in_code;
+                i += 1;
+                continue;
+            }
+
+            let parsed = if in_code {
+                None
+            } else if bytes[i..].starts_with(b"![[") {
+                parse_wiki_embed(line, i)
+            } else if bytes[i..].starts_with(b"![") {
+                parse_markdown_link(line, i, true)
+            } else if bytes[i] == b'[' && !bytes[i..].starts_with(b"[[") {
+                parse_markdown_link(line, i, false)
+            } else {
+                None
+            };
+
+            match parsed {
+                Some((end, mut reference)) => {
+                    reference.range = offset + reference.range.start..offset + end;
+                    references.push(reference);
+                    i = end;
+                }
+                None => i += 1,
+            }
+        }
+    }
+
+    references
+}
+
+// `![[target#heading|alias]]` starting at `start`, returning where it ends
+fn parse_wiki_embed(line: &str, start: usize) -> Option<(usize, FileReference)> {
+    let inner_start = start + 3;
+    let inner_len = line[inner_start..].find("]]")?;
+    let inner = &line[inner_start..inner_start + inner_len];
+
+    let (target, alias) = match inner.split_once('|') {
+        Some((target, alias)) => (target, alias.trim()),
+        None => (inner, ""),
+    };
+    let target = target.split('#').next().unwrap_or_default().trim();
+    if target.is_empty() || target.contains(['[', ']']) {
+        return None;
+    }
+
+    let end = inner_start + inner_len + 2;
+    Some((
+        end,
+        FileReference {
+            range: start..end,
+            target: target.to_string(),
+            // obsidian puts image sizes like |300 or |300x200 where the alias would go
+            label: if alias.chars().all(|c| c.is_ascii_digit() || c == 'x') {
+                String::new()
+            } else {
+                alias.to_string()
+            },
+            embed: true,
+        },
+    ))
+}
+
+// `[label](destination "title")`, optionally with a leading "!", starting at `start`
+fn parse_markdown_link(line: &str, start: usize, embed: bool) -> Option<(usize, FileReference)> {
+    let label_start = start + if embed { 2 } else { 1 };
+    let label_len = line[label_start..].find(']')?;
+    let label = &line[label_start..label_start + label_len];
+    if label.contains('[') {
+        return None;
+    }
+
+    let destination_start = label_start + label_len + 1;
+    let rest = line[destination_start..].strip_prefix('(')?;
+    let rest_start = destination_start + 1;
+
+    let (destination, after) = if let Some(bracketed) = rest.strip_prefix('<') {
+        let close = bracketed.find('>')?;
+        (&bracketed[..close], rest_start + 1 + close + 1)
+    } else {
+        // plain destinations end at whitespace or the ")" that balances the opening one
+        let mut depth = 0;
+        let mut length = rest.len();
+        for (index, c) in rest.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' if depth == 0 => {
+                    length = index;
+                    break;
+                }
+                ')' => depth -= 1,
+                c if c.is_whitespace() => {
+                    length = index;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        (&rest[..length], rest_start + length)
+    };
+
+    // skip an optional "title" up to the closing parenthesis
+    let close = line[after..].find(')')?;
+    let between = line[after..after + close].trim();
+    if !(between.is_empty() || between.starts_with(['"', '\'', '('])) {
+        return None;
+    }
+    let end = after + close + 1;
+
+    let path = destination.split(['#', '?']).next().unwrap_or_default();
+    if path.is_empty() || has_scheme(path) {
+        return None;
+    }
+
+    Some((
+        end,
+        FileReference {
+            range: start..end,
+            target: percent_decode(path),
+            label: label.to_string(),
+            embed,
+        },
+    ))
+}
+
+// "https://...", "mailto:...", "data:..." and the like, but not relative paths
+fn has_scheme(destination: &str) -> bool {
+    match destination.find(':') {
+        Some(colon) => !destination[..colon].contains('/'),
+        None => false,
+    }
+}
+
+// undo %XX escapes such as %20, leaving malformed ones as they are
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escape = bytes
+            .get(i + 1..i + 3)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit));
+        if let (b'%', Some(hex)) = (bytes[i], escape) {
+            let hex = std::str::from_utf8(hex).unwrap_or_default();
+            decoded.push(u8::from_str_radix(hex, 16).unwrap_or_default());
+            i += 3;
+            continue;
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inline_tags_skip_code_headings_and_numbers() {
+        let content = "# Heading\n#todo and #area/home/, #todo again\n\
+                       `#code` #42 issue#7\n```\n#fenced\n```";
+
+        assert_eq!(parse_inline_tags(content), ["todo", "area/home"]);
+    }
+
+    #[test]
+    fn file_references_of_every_kind_are_found() {
+        let content = "![[photo one.png|Holiday]] ![alt](img/a%20b.png#x) \
+                       [doc](report.pdf) [[Other note]] [site](https://example.com) `![[code.png]]`";
+
+        let references = parse_file_references(content);
+
+        let targets: Vec<(&str, &str, bool)> = references
+            .iter()
+            .map(|r| (r.target.as_str(), r.label.as_str(), r.embed))
+            .collect();
+        assert_eq!(
+            targets,
+            [
+                ("photo one.png", "Holiday", true),
+                ("img/a b.png", "alt", true),
+                ("report.pdf", "doc", false),
+            ]
+        );
+        assert_eq!(
+            &content[references[0].range.clone()],
+            "![[photo one.png|Holiday]]"
+        );
+    }
+}
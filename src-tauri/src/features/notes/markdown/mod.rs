@@ -0,0 +1,5 @@
+pub mod export;
+pub mod front_matter;
+pub mod import;
+pub mod inline;
+pub mod names;
@@ -0,0 +1,79 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+// names Windows refuses as file names, whatever the extension
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// longest file name we produce, leaving room for an extension and a " (n)" suffix
+const MAX_NAME_LENGTH: usize = 120;
+
+// turn a note title or folder name into something every OS accepts as a file name
+pub fn safe_file_name(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect();
+
+    // trailing dots and spaces are dropped silently on Windows, leading dots hide files
+    let mut trimmed: String = replaced
+        .trim()
+        .trim_matches(|c| c == '.' || c == ' ')
+        .chars()
+        .take(MAX_NAME_LENGTH)
+        .collect();
+    trimmed = trimmed.trim_end().to_string();
+
+    if trimmed.is_empty() {
+        return "Untitled".to_string();
+    }
+
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| trimmed.eq_ignore_ascii_case(reserved))
+    {
+        trimmed.insert(0, '_');
+    }
+
+    trimmed
+}
+
+// hands out file names that don't collide within a directory. names are compared
+// case-insensitively because macOS and Windows file systems usually are
+#[derive(Default)]
+pub struct UniqueNames {
+    taken: HashMap<PathBuf, HashSet<String>>,
+}
+
+impl UniqueNames {
+    // `stem` plus `extension` (without the dot, may be empty), with " (2)", " (3)", ...
+    // appended when the name is already used in `dir`
+    pub fn claim(&mut self, dir: &Path, stem: &str, extension: &str) -> String {
+        let taken = self.taken.entry(dir.to_path_buf()).or_default();
+
+        let mut counter = 1;
+        loop {
+            let candidate_stem = if counter == 1 {
+                stem.to_string()
+            } else {
+                format!("{} ({})", stem, counter)
+            };
+            let candidate = if extension.is_empty() {
+                candidate_stem
+            } else {
+                format!("{}.{}", candidate_stem, extension)
+            };
+
+            if taken.insert(candidate.to_lowercase()) {
+                return candidate;
+            }
+            counter += 1;
+        }
+    }
+}
@@ -1,2 +1,12 @@
 pub mod commands;
+pub mod delta;
+pub mod diff;
+pub mod extract;
+pub mod importers;
+pub mod links;
+pub mod markdown;
 pub mod models;
+pub mod repository;
+pub mod search;
+pub mod sniff;
+pub mod thumbnails;
@@ -15,6 +15,79 @@ pub struct Note {
     pub updated_at: DateTime<Utc>, // when the note was last updated
 }
 
+// a full-text search hit; serializes as the note's own fields plus the ranking extras
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NoteSearchResult {
+    #[serde(flatten)]
+    pub note: Note,
+    pub rank: f64,                       // bm25 score, lower is more relevant
+    pub title_highlight: String,         // title with matches wrapped in <mark>
+    pub snippet: String,                 // best matching fragment of the content or attachment, in <mark>
+    pub attachment_id: Option<i64>,      // set when the best match is in an attachment's name or text
+    pub attachment_name: Option<String>, // that attachment's file name
+}
+
+// a [[wiki link]] from one note to another, by title
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NoteLink {
+    pub source_note_id: i64,         // note containing the link
+    pub source_title: String,        // title of that note
+    pub target_title: String,        // title as written in the link
+    pub target_note_id: Option<i64>, // resolved note, None while no note has that title
+    pub heading: Option<String>,     // [[Title#Heading]]
+    pub alias: Option<String>,       // [[Title|Alias]]
+    pub position: i64,               // byte offset of the link title in the source content
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphNodeKind {
+    Note,
+    Tag,
+    Folder,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphNode {
+    pub id: String,          // unique across kinds, e.g. "note:12", "tag:3", "folder:5"
+    pub kind: GraphNodeKind, // what the node stands for
+    pub entity_id: i64,      // id of the note, tag or folder
+    pub label: String,       // note title, tag name or folder name
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphEdgeKind {
+    Link,        // note -> note, from a [[link]]
+    TaggedWith,  // note -> tag
+    ContainedIn, // note -> folder, or folder -> parent folder
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub source: String,      // GraphNode id
+    pub target: String,      // GraphNode id
+    pub kind: GraphEdgeKind, // relationship type
+    pub weight: u32,         // number of links for Link edges, 1 otherwise
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NoteGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+// which part of the graph to return
+#[derive(Debug, Clone, Default)]
+pub struct GraphQuery {
+    pub folder_id: Option<i64>, // only notes in this folder and its subfolders
+    pub center_note_id: Option<i64>, // only notes within `depth` link hops of this note
+    pub depth: u32,             // hops from center_note_id, links followed both ways
+    pub include_tags: bool,     // add tag nodes and tagged-with edges
+    pub include_folders: bool,  // add folder nodes and contained-in edges
+    pub include_archived: bool, // keep archived notes
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NoteFolder {
     pub id: i64,                   // unique identifier
@@ -35,20 +108,283 @@ pub struct NoteTag {
 // For tracking revision history of notes
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NoteRevision {
-    pub id: i64,                   // unique identifier
-    pub note_id: i64,              // foreign key linking to the Note
-    pub content: String,           // previous content
-    pub created_at: DateTime<Utc>, // when this revision was created
+    pub id: i64,                            // unique identifier
+    pub note_id: i64,                       // foreign key linking to the Note
+    pub content: String,                    // previous content
+    pub metadata: Option<RevisionMetadata>, // the rest of the note, None for older revisions
+    pub created_at: DateTime<Utc>,          // when this revision was created
+}
+
+// a note's fields other than its content, as they were when a revision was saved
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RevisionMetadata {
+    pub title: String,
+    pub folder_id: Option<i64>,
+    pub tags: Vec<String>,
+    pub is_pinned: bool,
+    pub is_archived: bool,
+    pub color: Option<String>,
+}
+
+// a part of a note that restoring a revision can roll back on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevisionField {
+    Title,
+    Content,
+    Folder,
+    Tags,
+    Color,
+    Pinned,
+    Archived,
+}
+
+// what a span or line of a diff is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffTag {
+    Equal,  // in both versions
+    Insert, // only in the newer version
+    Delete, // only in the older version
+}
+
+// a run of words within a changed line
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiffSpan {
+    pub tag: DiffTag,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiffLine {
+    pub tag: DiffTag,
+    pub old_line: Option<usize>, // 1-based line number in the older version
+    pub new_line: Option<usize>, // 1-based line number in the newer version
+    pub text: String,            // the line without its line break
+    pub spans: Vec<DiffSpan>, // word-level changes, empty when the whole line changed or none did
+}
+
+// a run of changed lines with the unchanged lines around them
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiffHunk {
+    pub old_start: usize, // 1-based first line in the older version
+    pub old_lines: usize, // lines the hunk covers in the older version
+    pub new_start: usize, // 1-based first line in the newer version
+    pub new_lines: usize, // lines the hunk covers in the newer version
+    pub lines: Vec<DiffLine>,
+}
+
+// the changes between two revisions of a note, or a revision and the note as it is now
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RevisionDiff {
+    pub note_id: i64,
+    pub from_revision_id: i64,
+    pub to_revision_id: Option<i64>, // None when compared with the note's current content
+    pub insertions: usize,           // inserted lines
+    pub deletions: usize,            // deleted lines
+    pub hunks: Vec<DiffHunk>,        // empty when nothing changed
+}
+
+// one step of a retention policy: revisions younger than `max_age_secs` are thinned to one
+// per `interval_secs`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionRule {
+    pub max_age_secs: Option<i64>, // None covers revisions of any age
+    pub interval_secs: i64,        // 0 keeps every revision
+}
+
+// how revision history is thinned out as it ages. rules are checked in order and the first
+// whose max age covers a revision decides; revisions older than every rule are deleted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    pub enabled: bool,
+    pub rules: Vec<RetentionRule>,
+}
+
+// what applying a retention policy removed
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RetentionReport {
+    pub notes_checked: usize,
+    pub revisions_pruned: usize,
+    pub bytes_pruned: u64, // how much less revision data is stored, net of rewritten ones
 }
 
 // For attachments within notes
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NoteAttachment {
-    pub id: i64,                   // unique identifier
-    pub note_id: i64,              // foreign key linking to the Note
-    pub file_name: String,         // original file name
-    pub file_path: String,         // path to the stored file
-    pub file_type: String,         // MIME type or file extension
-    pub file_size: i64,            // size in bytes
-    pub created_at: DateTime<Utc>, // when the attachment was added
+    pub id: i64,                              // unique identifier
+    pub note_id: i64,                         // foreign key linking to the Note
+    pub file_name: String,                    // original file name
+    pub file_path: String,                    // path to the stored file, absolute if linked
+    pub file_type: String,                    // MIME type, sniffed from the content if possible
+    pub file_size: i64,                       // size in bytes
+    pub metadata: Option<AttachmentMetadata>, // None when the file was missing or is linked
+    pub is_linked: bool,                      // points at a file elsewhere instead of a copy
+    pub created_at: DateTime<Utc>,            // when the attachment was added
+}
+
+// what could be read from an attachment's content; only the fields that apply to its type
+// are set
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AttachmentMetadata {
+    pub width: Option<u32>,            // images, in pixels
+    pub height: Option<u32>,           // images, in pixels
+    pub page_count: Option<u32>,       // PDFs
+    pub duration_secs: Option<f64>,    // audio and video
+    pub text_encoding: Option<String>, // text files, e.g. "UTF-8" or "windows-1252"
+}
+
+// a scaled-down PNG of an image attachment, or of a PDF's first page
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AttachmentThumbnail {
+    pub attachment_id: i64,
+    pub file_path: String, // relative to the app data directory, like attachment paths
+    pub width: u32,
+    pub height: u32,
+}
+
+// what regenerating every attachment's thumbnails did
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ThumbnailReport {
+    pub generated: usize,   // files that now have thumbnails
+    pub unsupported: usize, // files of a type without thumbnails
+    pub failed: usize,      // files that couldn't be read or decoded
+}
+
+// size limits adding an attachment is checked against. sizes are what's stored on disk, so
+// content already stored for another attachment doesn't count again towards the total
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttachmentQuota {
+    pub per_note_bytes: Option<u64>, // attachments of one note together; None for no limit
+    pub total_bytes: Option<u64>,    // every attachment file together; None for no limit
+}
+
+// attachment storage taken by one note. files it shares with other notes count for each
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NoteStorageUsage {
+    pub note_id: i64,
+    pub title: String,
+    pub folder_id: Option<i64>,
+    pub attachment_count: usize,
+    pub bytes: u64,
+}
+
+// attachment storage taken by the notes of a folder
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FolderStorageUsage {
+    pub folder_id: Option<i64>,  // None for the notes outside any folder
+    pub name: String,            // empty for the notes outside any folder
+    pub attachment_count: usize, // of the notes directly in the folder
+    pub bytes: u64,              // of the notes directly in the folder
+    pub total_bytes: u64,        // including every subfolder
+}
+
+// where attachment storage goes, biggest first
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StorageUsage {
+    pub stored_bytes: u64,                // size of the stored files, each distinct content once
+    pub attachment_count: usize,
+    pub quota: AttachmentQuota,
+    pub notes: Vec<NoteStorageUsage>,     // notes with attachments
+    pub folders: Vec<FolderStorageUsage>, // folders whose notes, or subfolders, have attachments
+}
+
+// what reconciling the attachments directory with the database found, and removed unless
+// it was a dry run
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AttachmentGcReport {
+    pub orphaned_files: Vec<String>, // files nothing refers to, relative to the app data directory
+    pub unreferenced_blobs: usize,   // stored contents whose attachments are all gone
+    pub orphaned_thumbnails: usize,  // thumbnails of contents that are gone
+    pub ref_counts_fixed: usize,     // blobs whose reference count had drifted
+    pub bytes_freed: u64,
+    pub missing_files: Vec<i64>,     // attachments whose file is gone; they're kept and reported
+    pub dry_run: bool,
+}
+
+// how a linked attachment's file compares with the file that was linked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkedFileState {
+    Ok,      // the file is where it was linked, with the same content
+    Changed, // the file is where it was linked, with different content
+    Moved,   // the file is gone, but files with its content were found elsewhere
+    Missing, // the file is gone and no file with its content was found
+}
+
+// what checking a linked attachment found
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LinkedFileCheck {
+    pub attachment_id: i64,
+    pub note_id: i64,
+    pub file_name: String,
+    pub file_path: String, // where the file was linked
+    pub state: LinkedFileState,
+    pub candidates: Vec<String>, // files with the linked content, to relink to
+}
+
+// Fields accepted when creating or updating a note
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteInput {
+    pub title: String,
+    pub content: String,
+    pub folder_id: Option<i64>,
+    pub tags: Vec<String>,
+    pub is_pinned: bool,
+    pub is_archived: bool,
+    pub color: Option<String>,
+}
+
+// what export_notes_markdown wrote
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MarkdownExportSummary {
+    pub directory: String,                // the export root
+    pub notes_exported: usize,            // .md files written
+    pub attachments_copied: usize,        // attachment files copied next to their notes
+    pub missing_attachments: Vec<String>, // attachments whose stored file no longer exists
+}
+
+// the export formats notes can be imported from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportFormat {
+    Markdown,      // directories of .md files, e.g. an Obsidian vault
+    Enex,          // Evernote .enex files
+    GoogleKeep,    // the Keep directory of a Google Takeout export
+    Simplenote,    // notes.json from a Simplenote export
+    StandardNotes, // a decrypted Standard Notes backup file
+}
+
+// what happened to one item of an import
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+    Imported,  // a new note was (or, in a dry run, would be) created
+    Duplicate, // an earlier import already created this note
+    Skipped,   // the item couldn't be read or turned into a note
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportItem {
+    pub source: String, // where the item came from, e.g. its path inside the vault
+    pub title: String,  // title the note gets
+    pub status: ImportStatus, // what happened to it
+    pub note_id: Option<i64>, // the created note, or the existing one for duplicates
+    pub attachments: usize, // attachments stored with it
+    pub message: Option<String>, // why it was skipped
+}
+
+// the outcome of an import. a dry run reports what would happen without writing anything
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub source: String,              // importer that ran, e.g. "markdown"
+    pub dry_run: bool,               // nothing was written
+    pub notes_imported: usize,       // items with status imported
+    pub duplicates: usize,           // items with status duplicate
+    pub skipped: usize,              // items with status skipped
+    pub folders_created: usize,      // new note_folders rows
+    pub tags_created: usize,         // new note_tags rows
+    pub attachments_imported: usize, // new note_attachments rows
+    pub items: Vec<ImportItem>,      // one entry per note found in the source
+    pub warnings: Vec<String>,       // problems that didn't stop an item, like missing images
 }
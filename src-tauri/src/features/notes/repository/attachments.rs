@@ -0,0 +1,597 @@
+use crate::db::time::parse_timestamp;
+use crate::error::{AppError, AppResult, EntityKind, ErrorCode};
+use crate::features::notes::extract::extract_text;
+use crate::features::notes::models::NoteAttachment;
+use crate::features::notes::repository::blobs::{Blob, BlobStore, PreparedBlob, BLOBS_DIR};
+use crate::features::notes::repository::storage::StorageRepository;
+use crate::features::notes::repository::thumbnails::pregenerate_thumbnails;
+use crate::features::notes::sniff::{runs_as_program, sniff, UNKNOWN_MIME_TYPE};
+use chrono::Utc;
+use log::{error, info};
+use rusqlite::{params, Connection, Row};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// an attachment with the metadata of its blob, in AttachmentRow's column order
+const ATTACHMENT_SELECT: &str = "
+    SELECT a.id, a.note_id, a.file_name, a.file_path, a.file_type, a.file_size, b.metadata,
+           a.is_linked, a.created_at
+    FROM note_attachments a
+    LEFT JOIN attachment_blobs b ON b.hash = a.blob_hash";
+
+// raw column values of a `note_attachments` row
+struct AttachmentRow {
+    id: i64,
+    note_id: i64,
+    file_name: String,
+    file_path: String,
+    file_type: String,
+    file_size: i64,
+    metadata: Option<String>,
+    is_linked: bool,
+    created_at: String,
+}
+
+impl AttachmentRow {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(AttachmentRow {
+            id: row.get(0)?,
+            note_id: row.get(1)?,
+            file_name: row.get(2)?,
+            file_path: row.get(3)?,
+            file_type: row.get(4)?,
+            file_size: row.get(5)?,
+            metadata: row.get(6)?,
+            is_linked: row.get(7)?,
+            created_at: row.get(8)?,
+        })
+    }
+
+    fn into_attachment(self) -> AppResult<NoteAttachment> {
+        let metadata = self
+            .metadata
+            .map(|metadata| {
+                serde_json::from_str(&metadata).map_err(|e| {
+                    AppError::new(
+                        ErrorCode::Database,
+                        format!("Invalid metadata for attachment {}: {}", self.id, e),
+                    )
+                })
+            })
+            .transpose()?;
+
+        Ok(NoteAttachment {
+            id: self.id,
+            note_id: self.note_id,
+            file_name: self.file_name,
+            file_path: self.file_path,
+            file_type: self.file_type,
+            file_size: self.file_size,
+            metadata,
+            is_linked: self.is_linked,
+            created_at: parse_timestamp(&self.created_at, "created_at")?,
+        })
+    }
+}
+
+// an attachment read before the database is locked: its content waiting to be stored, with
+// the text to index and the thumbnails already made, which for a big PDF take the longest
+pub struct PreparedAttachment {
+    file_name: String,
+    file_type: String,
+    extracted_text: Option<String>,
+    blob: PreparedBlob,
+}
+
+impl PreparedAttachment {
+    // a copy of a file, typed by its content
+    pub fn from_file(app_data_dir: &Path, file_path: &str) -> AppResult<Self> {
+        let source_path = Path::new(file_path);
+
+        let file_name = source_path
+            .file_name()
+            .ok_or_else(|| AppError::validation("Invalid file name"))?
+            .to_string_lossy()
+            .to_string();
+
+        // fail early with a clear error when the source isn't there
+        fs::metadata(file_path).map_err(|e| AppError::io("Failed to read file metadata", e))?;
+
+        let blob = PreparedBlob::copy_of(app_data_dir, source_path, &file_name)?;
+        Ok(Self::describe(
+            app_data_dir,
+            file_name,
+            UNKNOWN_MIME_TYPE,
+            blob,
+        ))
+    }
+
+    // bytes that never existed as a file of their own, such as a resource decoded from an
+    // import. file_type is whatever the source says, usually a MIME type
+    pub fn from_data(
+        app_data_dir: &Path,
+        file_name: &str,
+        file_type: &str,
+        data: &[u8],
+    ) -> AppResult<Self> {
+        let blob = PreparedBlob::from_bytes(app_data_dir, data, file_name)?;
+        Ok(Self::describe(
+            app_data_dir,
+            file_name.to_string(),
+            file_type,
+            blob,
+        ))
+    }
+
+    // content already written to a file in the blobs directory, such as a finished upload.
+    // the file is moved into storage, so it's gone afterwards
+    pub fn from_incoming(
+        app_data_dir: &Path,
+        file_name: &str,
+        file_type: &str,
+        incoming: &Path,
+    ) -> AppResult<Self> {
+        let blob = PreparedBlob::read(incoming.to_path_buf(), file_name)?;
+        Ok(Self::describe(
+            app_data_dir,
+            file_name.to_string(),
+            file_type,
+            blob,
+        ))
+    }
+
+    // the type the source named is kept unless the content was recognised or the source
+    // didn't know
+    fn describe(
+        app_data_dir: &Path,
+        file_name: String,
+        file_type: &str,
+        blob: PreparedBlob,
+    ) -> Self {
+        let file_type = if blob.content_type.is_recognised() || file_type == UNKNOWN_MIME_TYPE {
+            blob.content_type.mime_type.clone()
+        } else {
+            file_type.to_string()
+        };
+
+        // indexed for search by a trigger
+        let extracted_text = extract_text(blob.path(), &blob.content_type);
+        pregenerate_thumbnails(app_data_dir, &blob.hash, blob.path(), &file_type);
+
+        PreparedAttachment {
+            file_name,
+            file_type,
+            extracted_text,
+            blob,
+        }
+    }
+}
+
+// checked before anything is stored or linked, so a note that's gone or in the trash takes no
+// new attachments
+pub(crate) fn ensure_note_accepts(conn: &Connection, note_id: i64) -> AppResult<()> {
+    let note_exists: bool = conn
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM notes WHERE id = ? AND deleted_at IS NULL)",
+            params![note_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| AppError::database("Failed to get note", e))?;
+    if !note_exists {
+        return Err(AppError::not_found(EntityKind::Note, note_id));
+    }
+
+    Ok(())
+}
+
+// attachment files live under the app data directory; the table stores paths relative to it.
+// linked attachments are the exception, see LinkedFileRepository
+pub struct AttachmentRepository<'a> {
+    conn: &'a Connection,
+    app_data_dir: &'a Path,
+}
+
+impl<'a> AttachmentRepository<'a> {
+    pub fn new(conn: &'a Connection, app_data_dir: &'a Path) -> Self {
+        AttachmentRepository { conn, app_data_dir }
+    }
+
+    // copy a file into attachment storage and record it against a note. a file with the
+    // same content as an existing attachment shares its stored copy
+    pub fn add(&self, note_id: i64, file_path: &str) -> AppResult<i64> {
+        ensure_note_accepts(self.conn, note_id)?;
+        self.add_prepared(
+            note_id,
+            PreparedAttachment::from_file(self.app_data_dir, file_path)?,
+        )
+    }
+
+    // store bytes that never existed as a file of their own, see PreparedAttachment::from_data
+    pub fn add_data(
+        &self,
+        note_id: i64,
+        file_name: &str,
+        file_type: &str,
+        data: &[u8],
+    ) -> AppResult<i64> {
+        ensure_note_accepts(self.conn, note_id)?;
+        self.add_prepared(
+            note_id,
+            PreparedAttachment::from_data(self.app_data_dir, file_name, file_type, data)?,
+        )
+    }
+
+    // store an attachment read without the database, and record it against a note
+    pub fn add_prepared(&self, note_id: i64, attachment: PreparedAttachment) -> AppResult<i64> {
+        let blobs = BlobStore::new(self.conn, self.app_data_dir);
+        if let Err(e) = ensure_note_accepts(self.conn, note_id) {
+            blobs.discard(attachment.blob)?;
+            return Err(e);
+        }
+
+        let blob = blobs.store(&attachment.blob)?;
+        self.insert_record(note_id, &attachment, &blob)
+    }
+
+    fn insert_record(
+        &self,
+        note_id: i64,
+        attachment: &PreparedAttachment,
+        blob: &Blob,
+    ) -> AppResult<i64> {
+        // content stored for this attachment alone goes again when it's over quota
+        if let Err(e) =
+            StorageRepository::new(self.conn, self.app_data_dir).check_quota(note_id, blob)
+        {
+            BlobStore::new(self.conn, self.app_data_dir).release(&blob.hash)?;
+            return Err(e);
+        }
+
+        let now = Utc::now().to_rfc3339();
+
+        // insert attachment record; a trigger counts the reference on the blob
+        let inserted = self.conn.execute(
+            "INSERT INTO note_attachments (
+                note_id, file_name, file_path, file_type, file_size, blob_hash,
+                extracted_text, created_at
+            ) VALUES (
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8
+            )",
+            params![
+                note_id,
+                attachment.file_name,
+                blob.file_path,
+                attachment.file_type,
+                blob.file_size,
+                blob.hash,
+                attachment.extracted_text,
+                now
+            ],
+        );
+        // as with the quota, a blob stored only for this attachment mustn't be left behind
+        if let Err(e) = inserted {
+            BlobStore::new(self.conn, self.app_data_dir).release(&blob.hash)?;
+            return Err(AppError::database("Failed to add attachment record", e));
+        }
+
+        let attachment_id = self.conn.last_insert_rowid();
+
+        info!(
+            "Added attachment '{}' with ID: {} to note ID: {}",
+            attachment.file_name, attachment_id, note_id
+        );
+        Ok(attachment_id)
+    }
+
+    pub fn get_for_note(&self, note_id: i64) -> AppResult<Vec<NoteAttachment>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("{} WHERE a.note_id = ?", ATTACHMENT_SELECT))
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let attachment_rows = stmt
+            .query_map(params![note_id], AttachmentRow::from_row)
+            .map_err(|e| AppError::database("Failed to query attachments", e))?;
+
+        let mut attachments = Vec::new();
+        for attachment_result in attachment_rows {
+            let attachment_row = attachment_result
+                .map_err(|e| AppError::database("Failed to process attachment row", e))?;
+            attachments.push(attachment_row.into_attachment()?);
+        }
+
+        Ok(attachments)
+    }
+
+    // remove the record, and the stored file if no other attachment shares it
+    pub fn delete(&self, attachment_id: i64) -> AppResult<()> {
+        // get the blob before deleting the record
+        let (blob_hash, is_linked): (Option<String>, bool) = self
+            .conn
+            .query_row(
+                "SELECT blob_hash, is_linked FROM note_attachments WHERE id = ?",
+                params![attachment_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| {
+                AppError::lookup(
+                    EntityKind::Attachment,
+                    attachment_id,
+                    "Failed to get attachment",
+                    e,
+                )
+            })?;
+
+        // delete the record from the database
+        self.conn
+            .execute(
+                "DELETE FROM note_attachments WHERE id = ?",
+                params![attachment_id],
+            )
+            .map_err(|e| AppError::database("Failed to delete attachment", e))?;
+
+        match blob_hash {
+            Some(hash) => BlobStore::new(self.conn, self.app_data_dir).release(&hash)?,
+            // a linked file isn't ours to delete
+            None if is_linked => {}
+            // only attachments whose file was already gone have no blob
+            None => error!("Attachment {} had no stored file", attachment_id),
+        }
+
+        info!("Deleted attachment with ID: {}", attachment_id);
+        Ok(())
+    }
+
+    pub fn get_by_id(&self, attachment_id: i64) -> AppResult<NoteAttachment> {
+        self.conn
+            .query_row(
+                &format!("{} WHERE a.id = ?", ATTACHMENT_SELECT),
+                params![attachment_id],
+                AttachmentRow::from_row,
+            )
+            .map_err(|e| {
+                AppError::lookup(
+                    EntityKind::Attachment,
+                    attachment_id,
+                    "Failed to get attachment",
+                    e,
+                )
+            })?
+            .into_attachment()
+    }
+
+    // the attachment's file on disk. whatever the database says, a stored file has to be
+    // inside the attachments directory once `..` and links are resolved. a linked file can be
+    // anywhere, but only at the absolute path it was linked at. that path was stored already
+    // resolved, so if it resolves elsewhere now a link has been put in its place since
+    pub fn resolve_file(&self, attachment_id: i64) -> AppResult<PathBuf> {
+        let (stored_path, is_linked) = self.stored_path(attachment_id)?;
+        let not_found = || {
+            AppError::new(
+                ErrorCode::NotFound,
+                format!("Attachment file not found at path: {:?}", stored_path),
+            )
+            .for_entity(EntityKind::Attachment, Some(attachment_id))
+        };
+
+        let full_path = self
+            .app_data_dir
+            .join(&stored_path)
+            .canonicalize()
+            .map_err(|_| not_found())?;
+
+        let allowed = if is_linked {
+            Path::new(&stored_path).is_absolute() && full_path == Path::new(&stored_path)
+        } else {
+            let root = self
+                .app_data_dir
+                .join(BLOBS_DIR)
+                .canonicalize()
+                .map_err(|_| not_found())?;
+            full_path.starts_with(&root)
+        };
+
+        if !allowed || !full_path.is_file() {
+            let message = if is_linked {
+                format!("Attachment {} isn't linked to a file", attachment_id)
+            } else {
+                format!(
+                    "Attachment {} isn't a file in the attachments directory",
+                    attachment_id
+                )
+            };
+            return Err(AppError::validation(message)
+                .for_entity(EntityKind::Attachment, Some(attachment_id)));
+        }
+
+        Ok(full_path)
+    }
+
+    // the file to hand to the system to open an attachment. programs and scripts would run
+    // rather than open, so they need `confirmed`, which the user gives after a warning
+    pub fn resolve_for_opening(&self, attachment_id: i64, confirmed: bool) -> AppResult<PathBuf> {
+        let full_path = self.resolve_file(attachment_id)?;
+        if confirmed {
+            return Ok(full_path);
+        }
+
+        let file_name: String = self
+            .conn
+            .query_row(
+                "SELECT file_name FROM note_attachments WHERE id = ?",
+                params![attachment_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| {
+                AppError::lookup(
+                    EntityKind::Attachment,
+                    attachment_id,
+                    "Failed to get attachment",
+                    e,
+                )
+            })?;
+
+        // the name the system sees decides how it's opened, and the content can be a program
+        // under any name
+        let executable = runs_as_program(&full_path.to_string_lossy())
+            || runs_as_program(&file_name)
+            || sniff(&full_path, &file_name)
+                .map(|content_type| content_type.is_executable())
+                .map_err(|e| AppError::io("Failed to read attachment file", e))?;
+
+        if executable {
+            return Err(AppError::new(
+                ErrorCode::NeedsConfirmation,
+                format!(
+                    "'{}' is a program or script and would run when opened",
+                    file_name
+                ),
+            )
+            .for_entity(EntityKind::Attachment, Some(attachment_id)));
+        }
+
+        Ok(full_path)
+    }
+
+    // the file_path column, relative to the app data directory unless the attachment is
+    // linked, and whether it is
+    fn stored_path(&self, attachment_id: i64) -> AppResult<(String, bool)> {
+        self.conn
+            .query_row(
+                "SELECT file_path, is_linked FROM note_attachments WHERE id = ?",
+                params![attachment_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| {
+                AppError::lookup(
+                    EntityKind::Attachment,
+                    attachment_id,
+                    "Failed to get attachment file path",
+                    e,
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::{create_note, note_input, open_test_db, TestDir};
+    use crate::features::notes::repository::thumbnails::THUMBNAILS_DIR;
+    use crate::features::notes::repository::NoteRepository;
+
+    fn blob_count(conn: &Connection) -> i64 {
+        conn.query_row("SELECT COUNT(*) FROM attachment_blobs", [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+    }
+
+    fn stored_files(dir: &TestDir) -> usize {
+        fs::read_dir(dir.path().join(BLOBS_DIR))
+            .map(|entries| entries.count())
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn nothing_is_stored_for_a_missing_or_trashed_note() {
+        let conn = open_test_db();
+        let dir = TestDir::create();
+        let attachments = AttachmentRepository::new(&conn, dir.path());
+        let trashed = create_note(&conn, note_input("note", "", &[]));
+        NoteRepository::new(&conn).delete(trashed).unwrap();
+        let source = dir.write("source.txt", b"content");
+
+        for note_id in [trashed, 999] {
+            let err = attachments
+                .add_data(note_id, "a.txt", "text/plain", b"content")
+                .unwrap_err();
+            assert_eq!(err.code, ErrorCode::NotFound);
+            let err = attachments
+                .add(note_id, source.to_str().unwrap())
+                .unwrap_err();
+            assert_eq!(err.code, ErrorCode::NotFound);
+        }
+
+        let incoming = dir.write("incoming.part", b"content");
+        let prepared =
+            PreparedAttachment::from_incoming(dir.path(), "a.txt", "text/plain", &incoming)
+                .unwrap();
+        let err = attachments.add_prepared(trashed, prepared).unwrap_err();
+        assert_eq!(err.code, ErrorCode::NotFound);
+        assert!(!incoming.exists());
+
+        assert_eq!(blob_count(&conn), 0);
+        assert_eq!(stored_files(&dir), 0);
+    }
+
+    #[test]
+    fn content_prepared_for_a_trashed_note_is_discarded() {
+        let conn = open_test_db();
+        let dir = TestDir::create();
+        let trashed = create_note(&conn, note_input("note", "", &[]));
+        NoteRepository::new(&conn).delete(trashed).unwrap();
+        let mut png = Vec::new();
+        image::RgbImage::new(300, 200)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let prepared =
+            PreparedAttachment::from_data(dir.path(), "photo.png", "image/png", &png).unwrap();
+        let thumbnails = dir.path().join(THUMBNAILS_DIR);
+        assert!(fs::read_dir(&thumbnails).unwrap().count() > 0);
+
+        let err = AttachmentRepository::new(&conn, dir.path())
+            .add_prepared(trashed, prepared)
+            .unwrap_err();
+
+        assert_eq!(err.code, ErrorCode::NotFound);
+        assert_eq!(stored_files(&dir), 0);
+        assert_eq!(fs::read_dir(&thumbnails).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn a_failed_insert_releases_the_blob() {
+        let conn = open_test_db();
+        let dir = TestDir::create();
+        let note = create_note(&conn, note_input("note", "", &[]));
+        conn.execute_batch(
+            "CREATE TEMP TRIGGER reject_attachments BEFORE INSERT ON note_attachments
+             BEGIN SELECT RAISE(ABORT, 'rejected'); END;",
+        )
+        .unwrap();
+
+        AttachmentRepository::new(&conn, dir.path())
+            .add_data(note, "a.txt", "text/plain", b"content")
+            .unwrap_err();
+
+        assert_eq!(blob_count(&conn), 0);
+        assert_eq!(stored_files(&dir), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_linked_file_swapped_for_a_symlink_is_refused() {
+        use crate::features::notes::repository::{LinkedFile, LinkedFileRepository};
+
+        let conn = open_test_db();
+        let dir = TestDir::create();
+        let note = create_note(&conn, note_input("note", "", &[]));
+        let path = dir.write("linked.txt", b"content");
+        let file = LinkedFile::read(path.to_str().unwrap()).unwrap();
+        let attachment = LinkedFileRepository::new(&conn).link(note, &file).unwrap();
+        let attachments = AttachmentRepository::new(&conn, dir.path());
+
+        assert_eq!(
+            attachments.resolve_file(attachment).unwrap(),
+            path.canonicalize().unwrap()
+        );
+
+        let secret = dir.write("secret.txt", b"secret");
+        fs::remove_file(&path).unwrap();
+        std::os::unix::fs::symlink(&secret, &path).unwrap();
+
+        let err = attachments.resolve_file(attachment).unwrap_err();
+        assert_eq!(err.code, ErrorCode::Validation);
+    }
+}
@@ -0,0 +1,426 @@
+use crate::error::{AppError, AppResult};
+use crate::features::notes::extract::extract_text;
+use crate::features::notes::models::AttachmentMetadata;
+use crate::features::notes::repository::thumbnails::remove_thumbnails;
+use crate::features::notes::sniff::{extract_metadata, sniff, ContentType};
+use chrono::Utc;
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+// directory blobs are stored in, relative to the app data directory
+pub const BLOBS_DIR: &str = "note_attachments";
+
+// a stored file, shared by every attachment with the same content
+#[derive(Debug)]
+pub struct Blob {
+    pub hash: String,
+    pub file_path: String, // relative to the app data directory
+    pub file_size: i64,
+    pub ref_count: i64,
+}
+
+// hex SHA-256 of a file's content, read in chunks
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// blobs are named by their hash, with the extension of their type so the system still knows
+// which application opens them. it comes from the content when that was recognised, so a
+// misleading file name doesn't carry over, else from the first file stored
+fn blob_path(hash: &str, file_name: &str, content_type: &ContentType) -> String {
+    let extension = content_type
+        .extension
+        .map(str::to_string)
+        .or_else(|| {
+            Path::new(file_name)
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+        })
+        .filter(|ext| ext.len() <= 10 && ext.chars().all(|c| c.is_ascii_alphanumeric()));
+
+    match extension {
+        Some(ext) => format!("{}/{}.{}", BLOBS_DIR, hash, ext),
+        None => format!("{}/{}", BLOBS_DIR, hash),
+    }
+}
+
+// a copy keeps the permissions of its source, so a copied program could still be run.
+// attachments are only ever opened
+#[cfg(unix)]
+fn clear_execute_bits(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_mode(permissions.mode() & !0o111);
+    fs::set_permissions(path, permissions)
+}
+
+#[cfg(not(unix))]
+fn clear_execute_bits(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+// migration backfill: give every existing attachment a blob, keeping the first file of each
+// distinct content. the copies are returned for deletion once the migration has committed
+pub fn dedupe_attachment_files(conn: &Connection) -> rusqlite::Result<Vec<PathBuf>> {
+    let Some(app_data_dir) = app_data_dir_of(conn)? else {
+        return Ok(Vec::new());
+    };
+
+    let mut stmt = conn.prepare("SELECT id, file_path FROM note_attachments ORDER BY id")?;
+    let attachments = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let to_sql_error = |e: io::Error| rusqlite::Error::ToSqlConversionFailure(Box::new(e));
+    let mut duplicates = Vec::new();
+
+    for (id, file_path) in attachments {
+        let full_path = app_data_dir.join(&file_path);
+        // missing files keep a NULL blob_hash
+        if !full_path.is_file() {
+            continue;
+        }
+
+        let hash = hash_file(&full_path).map_err(to_sql_error)?;
+        let kept_path: Option<String> = conn
+            .query_row(
+                "SELECT file_path FROM attachment_blobs WHERE hash = ?",
+                params![hash],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        let blob_file_path = match kept_path {
+            Some(kept_path) => {
+                duplicates.push(full_path);
+                kept_path
+            }
+            None => {
+                // existing files keep their names, only new blobs are named by hash
+                let file_size = fs::metadata(&full_path).map_err(to_sql_error)?.len() as i64;
+                conn.execute(
+                    "INSERT INTO attachment_blobs (hash, file_path, file_size, created_at)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![hash, file_path, file_size, Utc::now().to_rfc3339()],
+                )?;
+                file_path
+            }
+        };
+
+        conn.execute(
+            "UPDATE note_attachments SET blob_hash = ?1, file_path = ?2 WHERE id = ?3",
+            params![hash, blob_file_path, id],
+        )?;
+    }
+
+    Ok(duplicates)
+}
+
+// migration backfill: sniff the files stored before types and metadata were read from the
+// content. an attachment's file_type is replaced when the content was recognised, or when it
+// was only an extension
+pub fn describe_attachment_blobs(conn: &Connection) -> rusqlite::Result<Vec<PathBuf>> {
+    let Some(app_data_dir) = app_data_dir_of(conn)? else {
+        return Ok(Vec::new());
+    };
+
+    let mut stmt = conn.prepare("SELECT hash, file_path FROM attachment_blobs")?;
+    let blobs = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for (hash, file_path) in blobs {
+        let full_path = app_data_dir.join(&file_path);
+        let content_type = match sniff(&full_path, &file_path) {
+            Ok(content_type) => content_type,
+            Err(e) => {
+                warn!("Failed to read attachment {:?}: {}", full_path, e);
+                continue;
+            }
+        };
+
+        set_blob_metadata(conn, &hash, &extract_metadata(&full_path, &content_type))?;
+        conn.execute(
+            "UPDATE note_attachments SET file_type = ?1
+             WHERE blob_hash = ?2 AND (?3 OR instr(file_type, '/') = 0)",
+            params![content_type.mime_type, hash, content_type.is_recognised()],
+        )?;
+    }
+
+    Ok(Vec::new())
+}
+
+// migration backfill: extract the searchable text of attachments added before it was
+pub fn extract_attachment_text(conn: &Connection) -> rusqlite::Result<Vec<PathBuf>> {
+    let Some(app_data_dir) = app_data_dir_of(conn)? else {
+        return Ok(Vec::new());
+    };
+
+    let mut stmt = conn.prepare("SELECT hash, file_path FROM attachment_blobs")?;
+    let blobs = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for (hash, file_path) in blobs {
+        let full_path = app_data_dir.join(&file_path);
+        let text = match sniff(&full_path, &file_path) {
+            Ok(content_type) => extract_text(&full_path, &content_type),
+            Err(e) => {
+                warn!("Failed to read attachment {:?}: {}", full_path, e);
+                continue;
+            }
+        };
+
+        if text.is_some() {
+            conn.execute(
+                "UPDATE note_attachments SET extracted_text = ?1 WHERE blob_hash = ?2",
+                params![text, hash],
+            )?;
+        }
+    }
+
+    Ok(Vec::new())
+}
+
+// attachment paths are relative to the app data directory, which is where the database
+// lives. None for a database without a file, which has no attachments on disk either
+fn app_data_dir_of(conn: &Connection) -> rusqlite::Result<Option<PathBuf>> {
+    let db_file: String = conn.query_row(
+        "SELECT file FROM pragma_database_list WHERE name = 'main'",
+        [],
+        |row| row.get(0),
+    )?;
+    if db_file.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Path::new(&db_file).parent().map(Path::to_path_buf))
+}
+
+fn set_blob_metadata(
+    conn: &Connection,
+    hash: &str,
+    metadata: &AttachmentMetadata,
+) -> rusqlite::Result<()> {
+    let metadata = serde_json::to_string(metadata)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    conn.execute(
+        "UPDATE attachment_blobs SET metadata = ? WHERE hash = ?",
+        params![metadata, hash],
+    )?;
+    Ok(())
+}
+
+// a temporary file in the blobs directory, so moving it into place is a rename
+fn incoming_path(app_data_dir: &Path) -> AppResult<PathBuf> {
+    let blobs_dir = app_data_dir.join(BLOBS_DIR);
+    fs::create_dir_all(&blobs_dir)
+        .map_err(|e| AppError::io("Failed to create attachments directory", e))?;
+
+    Ok(blobs_dir.join(format!(".incoming_{}", rand::random::<u64>())))
+}
+
+// content waiting in the blobs directory to be stored, already hashed, sniffed and described.
+// reading a big file takes a while and needs no database, so it's done before locking it.
+// the waiting file is deleted if this is dropped without being stored
+pub struct PreparedBlob {
+    incoming: PathBuf,
+    file_name: String,
+    pub hash: String,
+    pub content_type: ContentType,
+    metadata: AttachmentMetadata,
+}
+
+impl PreparedBlob {
+    // a copy of a file. copied first and hashed after, so the blob is exactly the bytes that
+    // will be stored
+    pub fn copy_of(app_data_dir: &Path, source: &Path, file_name: &str) -> AppResult<Self> {
+        let incoming = incoming_path(app_data_dir)?;
+        if let Err(e) = fs::copy(source, &incoming).and_then(|_| clear_execute_bits(&incoming)) {
+            let _ = fs::remove_file(&incoming);
+            return Err(AppError::io(
+                "Failed to copy file to attachments directory",
+                e,
+            ));
+        }
+
+        Self::read(incoming, file_name)
+    }
+
+    pub fn from_bytes(app_data_dir: &Path, data: &[u8], file_name: &str) -> AppResult<Self> {
+        let incoming = incoming_path(app_data_dir)?;
+        if let Err(e) = fs::write(&incoming, data) {
+            let _ = fs::remove_file(&incoming);
+            return Err(AppError::io("Failed to write attachment file", e));
+        }
+
+        Self::read(incoming, file_name)
+    }
+
+    // content already written to a file in the blobs directory, such as a finished upload.
+    // the type is sniffed from the content, whichever name it came with
+    pub fn read(incoming: PathBuf, file_name: &str) -> AppResult<Self> {
+        let described = sniff(&incoming, file_name).and_then(|content_type| {
+            let hash = hash_file(&incoming)?;
+            Ok((content_type, hash))
+        });
+        let (content_type, hash) = match described {
+            Ok(described) => described,
+            Err(e) => {
+                let _ = fs::remove_file(&incoming);
+                return Err(AppError::io("Failed to read attachment file", e));
+            }
+        };
+
+        let metadata = extract_metadata(&incoming, &content_type);
+        Ok(PreparedBlob {
+            incoming,
+            file_name: file_name.to_string(),
+            hash,
+            content_type,
+            metadata,
+        })
+    }
+
+    // where the content is until it's stored
+    pub fn path(&self) -> &Path {
+        &self.incoming
+    }
+}
+
+impl Drop for PreparedBlob {
+    fn drop(&mut self) {
+        // left over when the content was already stored, or when storing it failed
+        if self.incoming.exists() {
+            let _ = fs::remove_file(&self.incoming);
+        }
+    }
+}
+
+// content-addressed storage for attachment files. ref_count is maintained by triggers on
+// note_attachments, so callers insert and delete rows and then release what they dropped
+pub struct BlobStore<'a> {
+    conn: &'a Connection,
+    app_data_dir: &'a Path,
+}
+
+impl<'a> BlobStore<'a> {
+    pub fn new(conn: &'a Connection, app_data_dir: &'a Path) -> Self {
+        BlobStore { conn, app_data_dir }
+    }
+
+    // move prepared content into place, or find the blob that already has it
+    pub fn store(&self, prepared: &PreparedBlob) -> AppResult<Blob> {
+        if let Some(blob) = self.get(&prepared.hash)? {
+            let full_path = self.app_data_dir.join(&blob.file_path);
+            // the stored copy went missing, this one takes its place
+            if !full_path.exists() {
+                fs::rename(&prepared.incoming, &full_path)
+                    .map_err(|e| AppError::io("Failed to store attachment file", e))?;
+                self.set_metadata(&prepared.hash, &prepared.metadata)?;
+            }
+            return Ok(blob);
+        }
+
+        let file_size = fs::metadata(&prepared.incoming)
+            .map_err(|e| AppError::io("Failed to read file metadata", e))?
+            .len() as i64;
+        let file_path = blob_path(&prepared.hash, &prepared.file_name, &prepared.content_type);
+        fs::rename(&prepared.incoming, self.app_data_dir.join(&file_path))
+            .map_err(|e| AppError::io("Failed to store attachment file", e))?;
+
+        self.conn
+            .execute(
+                "INSERT INTO attachment_blobs (hash, file_path, file_size, created_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![prepared.hash, file_path, file_size, Utc::now().to_rfc3339()],
+            )
+            .map_err(|e| AppError::database("Failed to add attachment blob", e))?;
+        self.set_metadata(&prepared.hash, &prepared.metadata)?;
+
+        Ok(Blob {
+            hash: prepared.hash.clone(),
+            file_path,
+            file_size,
+            ref_count: 0,
+        })
+    }
+
+    // content that won't be stored after all. the thumbnails made for it go too, unless a
+    // stored blob has the same content
+    pub fn discard(&self, prepared: PreparedBlob) -> AppResult<()> {
+        if self.get(&prepared.hash)?.is_none() {
+            remove_thumbnails(self.app_data_dir, &prepared.hash)
+                .map_err(|e| AppError::io("Failed to delete attachment thumbnails", e))?;
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, hash: &str) -> AppResult<Option<Blob>> {
+        self.conn
+            .query_row(
+                "SELECT hash, file_path, file_size, ref_count FROM attachment_blobs
+                 WHERE hash = ?",
+                params![hash],
+                |row| {
+                    Ok(Blob {
+                        hash: row.get(0)?,
+                        file_path: row.get(1)?,
+                        file_size: row.get(2)?,
+                        ref_count: row.get(3)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(|e| AppError::database("Failed to get attachment blob", e))
+    }
+
+    // delete a blob and its file once no attachment refers to it any more
+    pub fn release(&self, hash: &str) -> AppResult<()> {
+        let Some(blob) = self.get(hash)? else {
+            return Ok(());
+        };
+        if blob.ref_count > 0 {
+            return Ok(());
+        }
+
+        self.conn
+            .execute(
+                "DELETE FROM attachment_blobs WHERE hash = ? AND ref_count <= 0",
+                params![hash],
+            )
+            .map_err(|e| AppError::database("Failed to delete attachment blob", e))?;
+
+        let full_path = self.app_data_dir.join(&blob.file_path);
+        if full_path.exists() {
+            fs::remove_file(&full_path)
+                .map_err(|e| AppError::io("Failed to delete attachment file", e))?;
+        } else {
+            // log but don't fail if file doesn't exist
+            warn!("Attachment file not found at path: {:?}", full_path);
+        }
+        remove_thumbnails(self.app_data_dir, hash)
+            .map_err(|e| AppError::io("Failed to delete attachment thumbnails", e))?;
+
+        info!("Deleted unreferenced attachment blob {}", hash);
+        Ok(())
+    }
+
+    fn set_metadata(&self, hash: &str, metadata: &AttachmentMetadata) -> AppResult<()> {
+        set_blob_metadata(self.conn, hash, metadata)
+            .map_err(|e| AppError::database("Failed to save attachment metadata", e))
+    }
+}
@@ -0,0 +1,360 @@
+use crate::db::time::parse_timestamp;
+use crate::error::{AppError, AppResult, EntityKind};
+use crate::features::notes::models::NoteFolder;
+use chrono::Utc;
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension, Params, Row};
+
+const FOLDER_COLUMNS: &str = "id, name, parent_id, color, created_at, updated_at";
+
+// raw column values of a `note_folders` row
+struct FolderRow {
+    id: i64,
+    name: String,
+    parent_id: Option<i64>,
+    color: Option<String>,
+    created_at: String,
+    updated_at: String,
+}
+
+impl FolderRow {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(FolderRow {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            parent_id: row.get(2)?,
+            color: row.get(3)?,
+            created_at: row.get(4)?,
+            updated_at: row.get(5)?,
+        })
+    }
+
+    fn into_folder(self) -> AppResult<NoteFolder> {
+        Ok(NoteFolder {
+            id: self.id,
+            name: self.name,
+            parent_id: self.parent_id,
+            color: self.color,
+            created_at: parse_timestamp(&self.created_at, "created_at")?,
+            updated_at: parse_timestamp(&self.updated_at, "updated_at")?,
+        })
+    }
+}
+
+pub struct FolderRepository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> FolderRepository<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        FolderRepository { conn }
+    }
+
+    pub fn create(
+        &self,
+        name: &str,
+        parent_id: Option<i64>,
+        color: Option<String>,
+    ) -> AppResult<i64> {
+        let now = Utc::now().to_rfc3339();
+
+        // insert folder
+        self.conn
+            .execute(
+                "INSERT INTO note_folders (
+                    name, parent_id, color, created_at, updated_at
+                ) VALUES (
+                    ?1, ?2, ?3, ?4, ?5
+                )",
+                params![name, parent_id, color, now, now],
+            )
+            .map_err(|e| AppError::database("Failed to create folder", e))?;
+
+        let folder_id = self.conn.last_insert_rowid();
+
+        info!("Created folder '{}' with ID: {}", name, folder_id);
+        Ok(folder_id)
+    }
+
+    pub fn get_all(&self) -> AppResult<Vec<NoteFolder>> {
+        self.query_folders(
+            &format!(
+                "SELECT {} FROM note_folders WHERE deleted_at IS NULL",
+                FOLDER_COLUMNS
+            ),
+            [],
+        )
+    }
+
+    pub fn get_by_id(&self, id: i64) -> AppResult<NoteFolder> {
+        self.conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM note_folders WHERE id = ? AND deleted_at IS NULL",
+                    FOLDER_COLUMNS
+                ),
+                params![id],
+                FolderRow::from_row,
+            )
+            .map_err(|e| AppError::lookup(EntityKind::Folder, id, "Failed to get folder", e))?
+            .into_folder()
+    }
+
+    // a folder directly under parent_id (or at the top level) with this name, ignoring case
+    pub fn find_child(&self, parent_id: Option<i64>, name: &str) -> AppResult<Option<i64>> {
+        self.conn
+            .query_row(
+                "SELECT id FROM note_folders
+                 WHERE parent_id IS ? AND name = ? COLLATE NOCASE AND deleted_at IS NULL
+                 ORDER BY id LIMIT 1",
+                params![parent_id, name],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| AppError::database("Failed to look up folder", e))
+    }
+
+    pub fn update(
+        &self,
+        id: i64,
+        name: &str,
+        parent_id: Option<i64>,
+        color: Option<String>,
+    ) -> AppResult<()> {
+        let now = Utc::now().to_rfc3339();
+
+        // update folder
+        let changed = self
+            .conn
+            .execute(
+                "UPDATE note_folders SET
+                    name = ?, parent_id = ?, color = ?, updated_at = ?
+                 WHERE id = ? AND deleted_at IS NULL",
+                params![name, parent_id, color, now, id],
+            )
+            .map_err(|e| AppError::database("Failed to update folder", e))?;
+
+        if changed == 0 {
+            return Err(AppError::not_found(EntityKind::Folder, id));
+        }
+
+        info!("Updated folder with ID: {}", id);
+        Ok(())
+    }
+
+    // only empty folders can be deleted, which moves them to the trash. notes and subfolders
+    // that are already in the trash don't count
+    pub fn delete(&self, id: i64) -> AppResult<()> {
+        // check if there are notes in this folder
+        let note_count: i64 = self
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM notes WHERE folder_id = ? AND deleted_at IS NULL",
+                params![id],
+                |row| row.get(0),
+            )
+            .map_err(|e| AppError::database("Failed to count notes in folder", e))?;
+
+        if note_count > 0 {
+            return Err(AppError::conflict(
+                EntityKind::Folder,
+                Some(id),
+                format!("Cannot delete folder: it contains {} notes", note_count),
+            ));
+        }
+
+        // check if there are subfolders
+        let subfolder_count: i64 = self
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM note_folders WHERE parent_id = ? AND deleted_at IS NULL",
+                params![id],
+                |row| row.get(0),
+            )
+            .map_err(|e| AppError::database("Failed to count subfolders", e))?;
+
+        if subfolder_count > 0 {
+            return Err(AppError::conflict(
+                EntityKind::Folder,
+                Some(id),
+                format!(
+                    "Cannot delete folder: it contains {} subfolders",
+                    subfolder_count
+                ),
+            ));
+        }
+
+        // trash the folder
+        let changed = self
+            .conn
+            .execute(
+                "UPDATE note_folders SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL",
+                params![Utc::now().to_rfc3339(), id],
+            )
+            .map_err(|e| AppError::database("Failed to delete folder", e))?;
+
+        if changed == 0 {
+            return Err(AppError::not_found(EntityKind::Folder, id));
+        }
+
+        info!("Moved folder with ID: {} to the trash", id);
+        Ok(())
+    }
+
+    // direct children of a folder, or the root folders when parent_id is None
+    pub fn get_subfolders(&self, parent_id: Option<i64>) -> AppResult<Vec<NoteFolder>> {
+        match parent_id {
+            Some(id) => self.query_folders(
+                &format!(
+                    "SELECT {} FROM note_folders WHERE parent_id = ? AND deleted_at IS NULL",
+                    FOLDER_COLUMNS
+                ),
+                params![id],
+            ),
+            None => self.query_folders(
+                &format!(
+                    "SELECT {} FROM note_folders WHERE parent_id IS NULL AND deleted_at IS NULL",
+                    FOLDER_COLUMNS
+                ),
+                [],
+            ),
+        }
+    }
+
+    pub fn get_direct_subfolder_ids(&self, parent_id: Option<i64>) -> AppResult<Vec<i64>> {
+        // `IS` matches NULL as well, so None selects the root folders
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id FROM note_folders WHERE parent_id IS ? AND deleted_at IS NULL")
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let subfolder_rows = stmt
+            .query_map(params![parent_id], |row| row.get(0))
+            .map_err(|e| AppError::database("Failed to query subfolders", e))?;
+
+        let mut subfolder_ids = Vec::new();
+        for subfolder_id in subfolder_rows {
+            subfolder_ids.push(
+                subfolder_id.map_err(|e| AppError::database("Failed to get subfolder ID", e))?,
+            );
+        }
+
+        Ok(subfolder_ids)
+    }
+
+    // every folder below parent_id, depth first
+    pub fn get_all_subfolder_ids(&self, parent_id: Option<i64>) -> AppResult<Vec<i64>> {
+        let mut all_subfolder_ids = Vec::new();
+
+        for subfolder_id in self.get_direct_subfolder_ids(parent_id)? {
+            all_subfolder_ids.push(subfolder_id);
+            all_subfolder_ids.extend(self.get_all_subfolder_ids(Some(subfolder_id))?);
+        }
+
+        Ok(all_subfolder_ids)
+    }
+
+    fn query_folders<P: Params>(&self, sql: &str, params: P) -> AppResult<Vec<NoteFolder>> {
+        let mut stmt = self
+            .conn
+            .prepare(sql)
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let folder_rows = stmt
+            .query_map(params, FolderRow::from_row)
+            .map_err(|e| AppError::database("Failed to query folders", e))?;
+
+        let mut folders = Vec::new();
+        for folder_result in folder_rows {
+            let folder_row =
+                folder_result.map_err(|e| AppError::database("Failed to process folder row", e))?;
+            folders.push(folder_row.into_folder()?);
+        }
+
+        Ok(folders)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::open_test_db;
+    use crate::error::ErrorCode;
+    use crate::features::notes::models::NoteInput;
+    use crate::features::notes::repository::NoteRepository;
+
+    #[test]
+    fn subfolders_are_listed_depth_first() {
+        let conn = open_test_db();
+        let folders = FolderRepository::new(&conn);
+        let root = folders.create("Projects", None, None).unwrap();
+        let apto = folders.create("Apto", Some(root), None).unwrap();
+        let design = folders.create("Design", Some(apto), None).unwrap();
+        let other = folders.create("Other", Some(root), None).unwrap();
+
+        assert_eq!(
+            folders.get_all_subfolder_ids(Some(root)).unwrap(),
+            vec![apto, design, other]
+        );
+        assert_eq!(folders.get_direct_subfolder_ids(None).unwrap(), vec![root]);
+        assert_eq!(folders.get_subfolders(Some(root)).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn find_child_ignores_case() {
+        let conn = open_test_db();
+        let folders = FolderRepository::new(&conn);
+        let root = folders.create("Projects", None, None).unwrap();
+        let apto = folders.create("Apto", Some(root), None).unwrap();
+
+        assert_eq!(folders.find_child(Some(root), "APTO").unwrap(), Some(apto));
+        assert_eq!(folders.find_child(None, "apto").unwrap(), None);
+    }
+
+    #[test]
+    fn only_empty_folders_can_be_deleted() {
+        let conn = open_test_db();
+        let folders = FolderRepository::new(&conn);
+        let root = folders.create("Projects", None, None).unwrap();
+        let child = folders.create("Apto", Some(root), None).unwrap();
+
+        let err = folders.delete(root).unwrap_err();
+        assert_eq!(err.code, ErrorCode::Conflict);
+        assert_eq!(err.id, Some(root));
+
+        let note_id = NoteRepository::new(&conn)
+            .create(NoteInput {
+                title: "Inside".to_string(),
+                content: String::new(),
+                folder_id: Some(child),
+                tags: Vec::new(),
+                is_pinned: false,
+                is_archived: false,
+                color: None,
+            })
+            .unwrap();
+        assert_eq!(folders.delete(child).unwrap_err().code, ErrorCode::Conflict);
+
+        // notes in the trash don't keep a folder from being deleted
+        NoteRepository::new(&conn).delete(note_id).unwrap();
+        folders.delete(child).unwrap();
+        folders.delete(root).unwrap();
+
+        assert_eq!(
+            folders.get_by_id(root).unwrap_err().code,
+            ErrorCode::NotFound
+        );
+        assert!(folders.get_all().unwrap().is_empty());
+    }
+
+    #[test]
+    fn update_of_a_missing_folder_is_not_found() {
+        let conn = open_test_db();
+        let err = FolderRepository::new(&conn)
+            .update(7, "Nope", None, None)
+            .unwrap_err();
+
+        assert_eq!(err.code, ErrorCode::NotFound);
+        assert_eq!(err.entity, Some(EntityKind::Folder));
+    }
+}
@@ -0,0 +1,519 @@
+use super::folders::FolderRepository;
+use super::links::LINK_SELECT;
+use crate::error::{AppError, AppResult, EntityKind};
+use crate::features::notes::models::{
+    GraphEdge, GraphEdgeKind, GraphNode, GraphNodeKind, GraphQuery, NoteGraph,
+};
+use rusqlite::{params, Connection};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+fn note_node_id(id: i64) -> String {
+    format!("note:{}", id)
+}
+
+fn tag_node_id(id: i64) -> String {
+    format!("tag:{}", id)
+}
+
+fn folder_node_id(id: i64) -> String {
+    format!("folder:{}", id)
+}
+
+pub struct GraphRepository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> GraphRepository<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        GraphRepository { conn }
+    }
+
+    // notes, tags and folders with the relationships between them. only titles and ids are
+    // read, never note content, so this stays cheap on large vaults
+    pub fn get_graph(&self, query: &GraphQuery) -> AppResult<NoteGraph> {
+        // id -> (title, folder_id) of every note the filters allow
+        let mut notes = self.load_notes(query)?;
+
+        // (source, target) -> number of links, both ends allowed
+        let mut links: BTreeMap<(i64, i64), u32> = self
+            .load_link_counts()?
+            .into_iter()
+            .filter(|((source, target), _)| {
+                notes.contains_key(source) && notes.contains_key(target)
+            })
+            .collect();
+
+        if let Some(center) = query.center_note_id {
+            if !notes.contains_key(&center) {
+                return Err(AppError::not_found(EntityKind::Note, center));
+            }
+
+            let reachable = neighbourhood(center, query.depth, links.keys());
+            notes.retain(|id, _| reachable.contains(id));
+            links.retain(|(source, target), _| {
+                reachable.contains(source) && reachable.contains(target)
+            });
+        }
+
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+
+        for (id, (title, _)) in &notes {
+            nodes.push(GraphNode {
+                id: note_node_id(*id),
+                kind: GraphNodeKind::Note,
+                entity_id: *id,
+                label: title.clone(),
+            });
+        }
+
+        for ((source, target), count) in links {
+            edges.push(GraphEdge {
+                source: note_node_id(source),
+                target: note_node_id(target),
+                kind: GraphEdgeKind::Link,
+                weight: count,
+            });
+        }
+
+        if query.include_tags {
+            self.add_tags(&notes, &mut nodes, &mut edges)?;
+        }
+
+        if query.include_folders {
+            self.add_folders(&notes, query.folder_id, &mut nodes, &mut edges)?;
+        }
+
+        Ok(NoteGraph { nodes, edges })
+    }
+
+    fn load_notes(&self, query: &GraphQuery) -> AppResult<BTreeMap<i64, (String, Option<i64>)>> {
+        let folder_ids = match query.folder_id {
+            Some(folder_id) => {
+                let folders = FolderRepository::new(self.conn);
+                // make sure the folder exists so a typo doesn't look like an empty graph
+                folders.get_by_id(folder_id)?;
+
+                let mut ids = BTreeSet::from([folder_id]);
+                ids.extend(folders.get_all_subfolder_ids(Some(folder_id))?);
+                Some(ids)
+            }
+            None => None,
+        };
+
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, title, folder_id FROM notes
+                 WHERE (?1 OR is_archived = 0) AND deleted_at IS NULL",
+            )
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let note_rows = stmt
+            .query_map(params![query.include_archived], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<i64>>(2)?,
+                ))
+            })
+            .map_err(|e| AppError::database("Failed to query notes", e))?;
+
+        let mut notes = BTreeMap::new();
+        for note_result in note_rows {
+            let (id, title, folder_id) =
+                note_result.map_err(|e| AppError::database("Failed to process note row", e))?;
+
+            let in_scope = match (&folder_ids, folder_id) {
+                (None, _) => true,
+                (Some(ids), Some(folder_id)) => ids.contains(&folder_id),
+                (Some(_), None) => false,
+            };
+
+            if in_scope {
+                notes.insert(id, (title, folder_id));
+            }
+        }
+
+        Ok(notes)
+    }
+
+    fn load_link_counts(&self) -> AppResult<Vec<((i64, i64), u32)>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT source_note_id, target_note_id, COUNT(*)
+                 FROM ({})
+                 WHERE target_note_id IS NOT NULL
+                 GROUP BY source_note_id, target_note_id",
+                LINK_SELECT
+            ))
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let link_rows = stmt
+            .query_map([], |row| Ok(((row.get(0)?, row.get(1)?), row.get(2)?)))
+            .map_err(|e| AppError::database("Failed to query links", e))?;
+
+        let mut links = Vec::new();
+        for link_result in link_rows {
+            links.push(link_result.map_err(|e| AppError::database("Failed to process link", e))?);
+        }
+
+        Ok(links)
+    }
+
+    fn add_tags(
+        &self,
+        notes: &BTreeMap<i64, (String, Option<i64>)>,
+        nodes: &mut Vec<GraphNode>,
+        edges: &mut Vec<GraphEdge>,
+    ) -> AppResult<()> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT m.note_id, t.id, t.name
+                 FROM note_tag_mappings m
+                 JOIN note_tags t ON t.id = m.tag_id
+                 ORDER BY t.id, m.note_id",
+            )
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let mapping_rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })
+            .map_err(|e| AppError::database("Failed to query tag mappings", e))?;
+
+        let mut seen_tags = BTreeSet::new();
+        for mapping_result in mapping_rows {
+            let (note_id, tag_id, tag_name) = mapping_result
+                .map_err(|e| AppError::database("Failed to process tag mapping", e))?;
+
+            // tags only appear when one of the returned notes carries them
+            if !notes.contains_key(&note_id) {
+                continue;
+            }
+
+            if seen_tags.insert(tag_id) {
+                nodes.push(GraphNode {
+                    id: tag_node_id(tag_id),
+                    kind: GraphNodeKind::Tag,
+                    entity_id: tag_id,
+                    label: tag_name,
+                });
+            }
+
+            edges.push(GraphEdge {
+                source: note_node_id(note_id),
+                target: tag_node_id(tag_id),
+                kind: GraphEdgeKind::TaggedWith,
+                weight: 1,
+            });
+        }
+
+        Ok(())
+    }
+
+    // the folders holding the returned notes plus their ancestors, up to the subtree root
+    // when the graph is limited to one folder
+    fn add_folders(
+        &self,
+        notes: &BTreeMap<i64, (String, Option<i64>)>,
+        root_folder_id: Option<i64>,
+        nodes: &mut Vec<GraphNode>,
+        edges: &mut Vec<GraphEdge>,
+    ) -> AppResult<()> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, name, parent_id FROM note_folders WHERE deleted_at IS NULL")
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let folder_rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    (row.get::<_, String>(1)?, row.get::<_, Option<i64>>(2)?),
+                ))
+            })
+            .map_err(|e| AppError::database("Failed to query folders", e))?;
+
+        let mut folders = HashMap::new();
+        for folder_result in folder_rows {
+            let (id, folder) =
+                folder_result.map_err(|e| AppError::database("Failed to process folder row", e))?;
+            folders.insert(id, folder);
+        }
+
+        let mut included = BTreeSet::new();
+        for (note_id, (_, folder_id)) in notes {
+            // only folders that get a node of their own can be pointed at
+            let Some(folder_id) = folder_id.filter(|id| folders.contains_key(id)) else {
+                continue;
+            };
+
+            edges.push(GraphEdge {
+                source: note_node_id(*note_id),
+                target: folder_node_id(folder_id),
+                kind: GraphEdgeKind::ContainedIn,
+                weight: 1,
+            });
+
+            // walk up until we meet a folder that's already in, the subtree root or the top
+            let mut current = Some(folder_id);
+            while let Some(id) = current {
+                if !included.insert(id) {
+                    break;
+                }
+
+                let Some((_, parent_id)) = folders.get(&id) else {
+                    break;
+                };
+
+                current = match parent_id {
+                    Some(parent_id) if Some(id) != root_folder_id => {
+                        edges.push(GraphEdge {
+                            source: folder_node_id(id),
+                            target: folder_node_id(*parent_id),
+                            kind: GraphEdgeKind::ContainedIn,
+                            weight: 1,
+                        });
+                        Some(*parent_id)
+                    }
+                    _ => None,
+                };
+            }
+        }
+
+        for id in included {
+            if let Some((name, _)) = folders.get(&id) {
+                nodes.push(GraphNode {
+                    id: folder_node_id(id),
+                    kind: GraphNodeKind::Folder,
+                    entity_id: id,
+                    label: name.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// notes reachable from `center` in at most `depth` hops, following links in either direction
+fn neighbourhood<'l>(
+    center: i64,
+    depth: u32,
+    links: impl Iterator<Item = &'l (i64, i64)>,
+) -> BTreeSet<i64> {
+    let mut adjacent: HashMap<i64, Vec<i64>> = HashMap::new();
+    for (source, target) in links {
+        adjacent.entry(*source).or_default().push(*target);
+        adjacent.entry(*target).or_default().push(*source);
+    }
+
+    let mut reached = BTreeSet::from([center]);
+    let mut queue = VecDeque::from([(center, 0)]);
+
+    while let Some((id, distance)) = queue.pop_front() {
+        if distance == depth {
+            continue;
+        }
+
+        for next in adjacent.get(&id).into_iter().flatten() {
+            if reached.insert(*next) {
+                queue.push_back((*next, distance + 1));
+            }
+        }
+    }
+
+    reached
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::{create_note, note_input, open_test_db};
+    use crate::features::notes::models::NoteInput;
+    use crate::features::notes::repository::NoteRepository;
+
+    fn node_ids(graph: &NoteGraph) -> BTreeSet<String> {
+        graph.nodes.iter().map(|node| node.id.clone()).collect()
+    }
+
+    fn has_edge(graph: &NoteGraph, source: &str, target: &str) -> bool {
+        graph
+            .edges
+            .iter()
+            .any(|edge| edge.source == source && edge.target == target)
+    }
+
+    #[test]
+    fn neighbourhood_follows_links_both_ways_up_to_the_depth() {
+        let links = [(1, 2), (3, 2), (3, 4), (5, 6)];
+
+        assert_eq!(neighbourhood(1, 0, links.iter()), BTreeSet::from([1]));
+        assert_eq!(neighbourhood(1, 1, links.iter()), BTreeSet::from([1, 2]));
+        assert_eq!(neighbourhood(1, 2, links.iter()), BTreeSet::from([1, 2, 3]));
+        assert_eq!(
+            neighbourhood(1, 10, links.iter()),
+            BTreeSet::from([1, 2, 3, 4])
+        );
+    }
+
+    #[test]
+    fn a_centred_graph_keeps_only_notes_within_the_depth() {
+        let conn = open_test_db();
+        let first = create_note(&conn, note_input("First", "see [[Second]]", &[]));
+        let second = create_note(&conn, note_input("Second", "see [[Third]]", &[]));
+        let third = create_note(&conn, note_input("Third", "", &[]));
+
+        let graph = GraphRepository::new(&conn)
+            .get_graph(&GraphQuery {
+                center_note_id: Some(first),
+                depth: 1,
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(
+            node_ids(&graph),
+            BTreeSet::from([note_node_id(first), note_node_id(second)])
+        );
+        assert!(has_edge(
+            &graph,
+            &note_node_id(first),
+            &note_node_id(second)
+        ));
+        assert!(!has_edge(
+            &graph,
+            &note_node_id(second),
+            &note_node_id(third)
+        ));
+    }
+
+    #[test]
+    fn a_folder_graph_stops_at_the_folder() {
+        let conn = open_test_db();
+        let folders = FolderRepository::new(&conn);
+        let top = folders.create("Top", None, None).unwrap();
+        let middle = folders.create("Middle", Some(top), None).unwrap();
+        let leaf = folders.create("Leaf", Some(middle), None).unwrap();
+        let inside = create_note(
+            &conn,
+            NoteInput {
+                folder_id: Some(leaf),
+                ..note_input("Inside", "", &[])
+            },
+        );
+        let outside = create_note(
+            &conn,
+            NoteInput {
+                folder_id: Some(top),
+                ..note_input("Outside", "", &[])
+            },
+        );
+
+        let graph = GraphRepository::new(&conn)
+            .get_graph(&GraphQuery {
+                folder_id: Some(middle),
+                include_folders: true,
+                ..Default::default()
+            })
+            .unwrap();
+
+        let ids = node_ids(&graph);
+        assert!(ids.contains(&note_node_id(inside)));
+        assert!(!ids.contains(&note_node_id(outside)));
+        assert!(ids.contains(&folder_node_id(middle)));
+        assert!(ids.contains(&folder_node_id(leaf)));
+        assert!(!ids.contains(&folder_node_id(top)));
+        assert!(has_edge(
+            &graph,
+            &note_node_id(inside),
+            &folder_node_id(leaf)
+        ));
+        assert!(has_edge(
+            &graph,
+            &folder_node_id(leaf),
+            &folder_node_id(middle)
+        ));
+        assert!(!has_edge(
+            &graph,
+            &folder_node_id(middle),
+            &folder_node_id(top)
+        ));
+    }
+
+    #[test]
+    fn archived_and_trashed_notes_are_left_out() {
+        let conn = open_test_db();
+        let notes = NoteRepository::new(&conn);
+        let kept = create_note(&conn, note_input("Kept", "[[Archived]] [[Trashed]]", &[]));
+        let archived = create_note(&conn, note_input("Archived", "", &[]));
+        let trashed = create_note(&conn, note_input("Trashed", "", &[]));
+        notes.set_archived(archived, true).unwrap();
+        notes.delete(trashed).unwrap();
+
+        let repo = GraphRepository::new(&conn);
+        let graph = repo.get_graph(&GraphQuery::default()).unwrap();
+        assert_eq!(node_ids(&graph), BTreeSet::from([note_node_id(kept)]));
+        assert!(graph.edges.is_empty());
+
+        let graph = repo
+            .get_graph(&GraphQuery {
+                include_archived: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(
+            node_ids(&graph),
+            BTreeSet::from([note_node_id(kept), note_node_id(archived)])
+        );
+        assert!(has_edge(
+            &graph,
+            &note_node_id(kept),
+            &note_node_id(archived)
+        ));
+        assert!(!has_edge(
+            &graph,
+            &note_node_id(kept),
+            &note_node_id(trashed)
+        ));
+    }
+
+    #[test]
+    fn notes_dont_point_at_a_trashed_folder() {
+        let conn = open_test_db();
+        let folder = FolderRepository::new(&conn)
+            .create("Old", None, None)
+            .unwrap();
+        let note = create_note(
+            &conn,
+            NoteInput {
+                folder_id: Some(folder),
+                ..note_input("Note", "", &[])
+            },
+        );
+        // only an empty folder can be trashed, so this is how a database written before
+        // that rule could look
+        conn.execute(
+            "UPDATE note_folders SET deleted_at = '2024-01-01T00:00:00Z' WHERE id = ?",
+            params![folder],
+        )
+        .unwrap();
+
+        let graph = GraphRepository::new(&conn)
+            .get_graph(&GraphQuery {
+                include_folders: true,
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(node_ids(&graph), BTreeSet::from([note_node_id(note)]));
+        assert!(graph.edges.is_empty());
+    }
+}
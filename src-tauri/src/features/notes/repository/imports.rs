@@ -0,0 +1,119 @@
+use crate::error::{AppError, AppResult};
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+
+// bookkeeping for importers: which notes came from which source
+pub struct ImportRepository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> ImportRepository<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        ImportRepository { conn }
+    }
+
+    // the note an earlier import created for this source item, if it still exists and
+    // isn't in the trash
+    pub fn find_note(&self, source: &str, source_key: &str) -> AppResult<Option<i64>> {
+        self.conn
+            .query_row(
+                "SELECT i.note_id FROM note_imports i
+                 JOIN notes n ON n.id = i.note_id AND n.deleted_at IS NULL
+                 WHERE i.source = ? AND i.source_key = ?",
+                params![source, source_key],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| AppError::database("Failed to look up imported note", e))
+    }
+
+    pub fn record(&self, note_id: i64, source: &str, source_key: &str) -> AppResult<()> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO note_imports (note_id, source, source_key, imported_at)
+                 VALUES (?, ?, ?, ?)",
+                params![note_id, source, source_key, Utc::now().to_rfc3339()],
+            )
+            .map_err(|e| AppError::database("Failed to record imported note", e))?;
+
+        Ok(())
+    }
+
+    // a note outside the trash with exactly this title and content in the folder, for
+    // sources imported before they were tracked or moved since. `content` has every
+    // attachment reference replaced with `placeholder`, and so has each note's content
+    // before comparing, since the stored one points at where its attachments were stored
+    pub fn find_identical_note(
+        &self,
+        title: &str,
+        content: &str,
+        folder_id: Option<i64>,
+        placeholder: &str,
+    ) -> AppResult<Option<i64>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, content FROM notes
+                 WHERE title = ? AND folder_id IS ? AND deleted_at IS NULL
+                 ORDER BY id",
+            )
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+        let candidates = stmt
+            .query_map(params![title, folder_id], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|e| AppError::database("Failed to look up identical note", e))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| AppError::database("Failed to process note row", e))?;
+
+        for (note_id, note_content) in candidates {
+            if note_content == content
+                || self.without_attachment_paths(note_id, note_content, placeholder)? == content
+            {
+                return Ok(Some(note_id));
+            }
+        }
+
+        Ok(None)
+    }
+
+    // a note's content with the stored paths of its attachments, as the import writer
+    // links them, replaced with `placeholder`
+    fn without_attachment_paths(
+        &self,
+        note_id: i64,
+        mut content: String,
+        placeholder: &str,
+    ) -> AppResult<String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT file_path FROM note_attachments WHERE note_id = ?")
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+        let mut paths = stmt
+            .query_map(params![note_id], |row| row.get::<_, String>(0))
+            .map_err(|e| AppError::database("Failed to query attachments", e))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| AppError::database("Failed to process attachment row", e))?;
+
+        // longest first, so no path is replaced inside a longer one
+        paths.sort_by_key(|path| std::cmp::Reverse(path.len()));
+        for path in paths {
+            content = content
+                .replace(&format!("<{}>", path), placeholder)
+                .replace(&path, placeholder);
+        }
+
+        Ok(content)
+    }
+
+    // whether a tag with this exact name exists, matching how notes look tags up
+    pub fn tag_exists(&self, name: &str) -> AppResult<bool> {
+        self.conn
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM note_tags WHERE name = ?)",
+                params![name],
+                |row| row.get(0),
+            )
+            .map_err(|e| AppError::database("Failed to look up tag", e))
+    }
+}
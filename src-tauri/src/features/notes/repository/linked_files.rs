@@ -0,0 +1,566 @@
+use crate::error::{AppError, AppResult, EntityKind};
+use crate::features::notes::extract::extract_text;
+use crate::features::notes::models::{LinkedFileCheck, LinkedFileState};
+use crate::features::notes::repository::attachments::ensure_note_accepts;
+use crate::features::notes::repository::blobs::hash_file;
+use crate::features::notes::sniff::sniff;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+
+// how many directories deep a search for a moved file goes
+const MAX_SEARCH_DEPTH: usize = 8;
+
+// how many files a search looks at before it gives up, so pointing it at a whole disk
+// doesn't hang the check
+const MAX_SEARCHED_FILES: usize = 100_000;
+
+// a linked attachment as it was recorded, read under the lock and checked after it
+#[derive(Clone)]
+pub struct LinkedRow {
+    id: i64,
+    note_id: i64,
+    file_name: String,
+    file_path: String,
+    file_size: i64,
+    hash: String,
+    modified_at: Option<String>,
+}
+
+// a file read for linking: everything the row needs, taken from the file before the
+// database is locked
+pub struct LinkedFile {
+    stored_path: String,
+    file_name: String,
+    file_size: i64,
+    hash: String,
+    modified_at: Option<String>,
+    mime_type: String,
+    extracted_text: Option<String>,
+}
+
+impl LinkedFile {
+    pub fn read(file_path: &str) -> AppResult<Self> {
+        let (path, stored_path, metadata) = existing_file(file_path)?;
+
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| AppError::validation("Invalid file name"))?
+            .to_string_lossy()
+            .to_string();
+
+        let hash = hash_file(&path).map_err(|e| AppError::io("Failed to read linked file", e))?;
+        let content_type =
+            sniff(&path, &file_name).map_err(|e| AppError::io("Failed to read linked file", e))?;
+        // indexed for search by a trigger
+        let extracted_text = extract_text(&path, &content_type);
+
+        Ok(LinkedFile {
+            stored_path,
+            file_name,
+            file_size: metadata.len() as i64,
+            hash,
+            modified_at: modified_at(&metadata),
+            mime_type: content_type.mime_type,
+            extracted_text,
+        })
+    }
+}
+
+// what checking linked files found, before the times of files that were only touched
+// are written back
+pub struct LinkedFileReport {
+    checks: Vec<LinkedFileCheck>,
+    // (row as it was checked, new modification time)
+    touched: Vec<(LinkedRow, Option<String>)>,
+}
+
+// the modification time recorded for a file, None where the platform doesn't keep one
+fn modified_at(metadata: &Metadata) -> Option<String> {
+    metadata
+        .modified()
+        .ok()
+        .map(|modified| DateTime::<Utc>::from(modified).to_rfc3339())
+}
+
+// a file to link, which has to be given by absolute path since it's kept as one
+fn existing_file(file_path: &str) -> AppResult<(PathBuf, String, Metadata)> {
+    let path = Path::new(file_path.trim());
+    if !path.is_absolute() {
+        return Err(AppError::validation(
+            "A linked file must be given by its absolute path",
+        ));
+    }
+
+    let path = path
+        .canonicalize()
+        .map_err(|e| AppError::io("Failed to find linked file", e))?;
+    let metadata =
+        fs::metadata(&path).map_err(|e| AppError::io("Failed to read file metadata", e))?;
+    if !metadata.is_file() {
+        return Err(AppError::validation(format!(
+            "{:?} isn't a file",
+            path.to_string_lossy()
+        )));
+    }
+
+    // the path is stored as text, so it has to survive the round trip
+    let stored = path
+        .to_str()
+        .ok_or_else(|| AppError::validation("The path of a linked file must be valid Unicode"))?
+        .to_string();
+    Ok((path, stored, metadata))
+}
+
+// every file below the search directories with one of the wanted sizes. hidden directories
+// and symbolic links are skipped
+fn files_with_sizes(dirs: &[PathBuf], sizes: &HashSet<u64>) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    let mut visited = HashSet::new();
+    let mut searched = 0;
+
+    let mut pending: Vec<(PathBuf, usize)> = dirs.iter().map(|dir| (dir.clone(), 0)).collect();
+    while let Some((dir, depth)) = pending.pop() {
+        // search directories can overlap
+        if !visited.insert(dir.clone()) {
+            continue;
+        }
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                let hidden = entry.file_name().to_string_lossy().starts_with('.');
+                if !hidden && depth < MAX_SEARCH_DEPTH {
+                    pending.push((entry.path(), depth + 1));
+                }
+                continue;
+            }
+            if !file_type.is_file() {
+                continue;
+            }
+
+            searched += 1;
+            if searched > MAX_SEARCHED_FILES {
+                warn!(
+                    "Stopped searching for linked files after {} files",
+                    MAX_SEARCHED_FILES
+                );
+                return found;
+            }
+            if entry
+                .metadata()
+                .is_ok_and(|metadata| sizes.contains(&metadata.len()))
+            {
+                found.push(entry.path());
+            }
+        }
+    }
+
+    found
+}
+
+// compare linked files with what was linked. files that are gone or changed are looked
+// for by content in search_dirs, and around where they were: below the directory above
+// theirs, or the nearest one still there. only reads files, so it runs without the lock
+pub fn check_linked_files(rows: Vec<LinkedRow>, search_dirs: &[PathBuf]) -> LinkedFileReport {
+    let mut checks = Vec::new();
+    let mut touched = Vec::new();
+    let mut lost = Vec::new();
+
+    for row in rows {
+        let state = match fs::metadata(&row.file_path) {
+            Ok(metadata) if metadata.is_file() => compare(&row, &metadata),
+            _ => Comparison::Missing,
+        };
+        let state = match state {
+            Comparison::Same => LinkedFileState::Ok,
+            Comparison::Touched(modified) => {
+                touched.push((row.clone(), modified));
+                LinkedFileState::Ok
+            }
+            Comparison::Changed => LinkedFileState::Changed,
+            Comparison::Missing => LinkedFileState::Missing,
+        };
+        if state != LinkedFileState::Ok {
+            lost.push((checks.len(), row.hash.clone(), row.file_size as u64));
+        }
+
+        let dir = Path::new(&row.file_path)
+            .ancestors()
+            .skip(2)
+            // the root of a disk is too much to search
+            .filter(|dir| dir.parent().is_some())
+            .find(|dir| dir.is_dir())
+            .map(Path::to_path_buf);
+        checks.push((
+            LinkedFileCheck {
+                attachment_id: row.id,
+                note_id: row.note_id,
+                file_name: row.file_name,
+                file_path: row.file_path,
+                state,
+                candidates: Vec::new(),
+            },
+            dir,
+        ));
+    }
+
+    if !lost.is_empty() {
+        let mut dirs = search_dirs.to_vec();
+        dirs.extend(
+            lost.iter()
+                .filter_map(|(index, _, _)| checks[*index].1.clone()),
+        );
+        let sizes = lost.iter().map(|(_, _, size)| *size).collect();
+
+        // only files of the right size are hashed, each once
+        let mut hashes: HashMap<String, Vec<String>> = HashMap::new();
+        for path in files_with_sizes(&dirs, &sizes) {
+            match hash_file(&path) {
+                Ok(hash) => hashes
+                    .entry(hash)
+                    .or_default()
+                    .push(path.to_string_lossy().to_string()),
+                Err(e) => warn!("Failed to read {:?} while searching: {}", path, e),
+            }
+        }
+
+        for (index, hash, _) in lost {
+            let check = &mut checks[index].0;
+            check.candidates = hashes.get(&hash).cloned().unwrap_or_default();
+            check.candidates.sort();
+            if check.state == LinkedFileState::Missing && !check.candidates.is_empty() {
+                check.state = LinkedFileState::Moved;
+            }
+        }
+    }
+
+    LinkedFileReport {
+        checks: checks.into_iter().map(|(check, _)| check).collect(),
+        touched,
+    }
+}
+
+enum Comparison {
+    Same,
+    Touched(Option<String>), // same content, with this new modification time
+    Changed,
+    Missing,
+}
+
+// a file that's still there is only hashed again when its size or time changed. one that
+// can't be read any more, say for its permissions, can't be the file that was linked either
+fn compare(row: &LinkedRow, metadata: &Metadata) -> Comparison {
+    let modified = modified_at(metadata);
+    if metadata.len() as i64 == row.file_size && modified == row.modified_at {
+        return Comparison::Same;
+    }
+
+    match hash_file(Path::new(&row.file_path)) {
+        Ok(hash) if hash == row.hash => Comparison::Touched(modified),
+        Ok(_) => Comparison::Changed,
+        Err(e) => {
+            warn!("Failed to read linked file {:?}: {}", row.file_path, e);
+            Comparison::Changed
+        }
+    }
+}
+
+// attachments that point at a file elsewhere on disk, like a video or a dataset too big to
+// copy into attachment storage. they can't be deduplicated, thumbnailed or counted towards
+// the quota, and the file can move or change without us knowing, which check finds out.
+// the files are read beforehand with LinkedFile::read and check_linked_files, so the
+// database is only locked for the rows
+pub struct LinkedFileRepository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> LinkedFileRepository<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        LinkedFileRepository { conn }
+    }
+
+    // record a file against a note where it is, without copying it
+    pub fn link(&self, note_id: i64, file: &LinkedFile) -> AppResult<i64> {
+        ensure_note_accepts(self.conn, note_id)?;
+
+        self.conn
+            .execute(
+                "INSERT INTO note_attachments (
+                    note_id, file_name, file_path, file_type, file_size, is_linked,
+                    linked_hash, linked_modified_at, extracted_text, created_at
+                ) VALUES (
+                    ?1, ?2, ?3, ?4, ?5, 1, ?6, ?7, ?8, ?9
+                )",
+                params![
+                    note_id,
+                    file.file_name,
+                    file.stored_path,
+                    file.mime_type,
+                    file.file_size,
+                    file.hash,
+                    file.modified_at,
+                    file.extracted_text,
+                    Utc::now().to_rfc3339()
+                ],
+            )
+            .map_err(|e| AppError::database("Failed to add attachment record", e))?;
+
+        let attachment_id = self.conn.last_insert_rowid();
+
+        info!(
+            "Linked '{}' as attachment {} of note ID: {}",
+            file.stored_path, attachment_id, note_id
+        );
+        Ok(attachment_id)
+    }
+
+    // finish a check: a file that was only touched gets its new time recorded, so it isn't
+    // hashed every check. rows relinked since they were read are left as they are
+    pub fn record_check(&self, report: LinkedFileReport) -> AppResult<Vec<LinkedFileCheck>> {
+        for (row, modified) in &report.touched {
+            self.conn
+                .execute(
+                    "UPDATE note_attachments SET linked_modified_at = ?
+                     WHERE id = ? AND file_path = ? AND linked_hash = ?",
+                    params![modified, row.id, row.file_path, row.hash],
+                )
+                .map_err(|e| AppError::database("Failed to update attachment", e))?;
+        }
+
+        Ok(report.checks)
+    }
+
+    // point a linked attachment at another file with the content that was linked, such as a
+    // candidate check found
+    pub fn relink(&self, attachment_id: i64, file: &LinkedFile) -> AppResult<()> {
+        let row = self.linked_row(attachment_id)?;
+
+        if file.hash != row.hash {
+            return Err(AppError::validation(format!(
+                "'{}' doesn't have the content that was linked as '{}'",
+                file.stored_path, row.file_name
+            ))
+            .for_entity(EntityKind::Attachment, Some(attachment_id)));
+        }
+
+        self.conn
+            .execute(
+                "UPDATE note_attachments
+                 SET file_path = ?, file_size = ?, linked_modified_at = ?
+                 WHERE id = ?",
+                params![
+                    file.stored_path,
+                    file.file_size,
+                    file.modified_at,
+                    attachment_id
+                ],
+            )
+            .map_err(|e| AppError::database("Failed to update attachment", e))?;
+
+        info!(
+            "Relinked attachment {} from '{}' to '{}'",
+            attachment_id, row.file_path, file.stored_path
+        );
+        Ok(())
+    }
+
+    // the linked attachments of one note or all of them, for check_linked_files
+    pub fn linked_rows(&self, note_id: Option<i64>) -> AppResult<Vec<LinkedRow>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, note_id, file_name, file_path, file_size, linked_hash,
+                        linked_modified_at
+                 FROM note_attachments
+                 WHERE is_linked = 1 AND (?1 IS NULL OR note_id = ?1)
+                 ORDER BY id",
+            )
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let rows = stmt
+            .query_map(params![note_id], |row| {
+                Ok(LinkedRow {
+                    id: row.get(0)?,
+                    note_id: row.get(1)?,
+                    file_name: row.get(2)?,
+                    file_path: row.get(3)?,
+                    file_size: row.get(4)?,
+                    hash: row.get(5)?,
+                    modified_at: row.get(6)?,
+                })
+            })
+            .map_err(|e| AppError::database("Failed to query attachments", e))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| AppError::database("Failed to process attachment row", e))?;
+        Ok(rows)
+    }
+
+    fn linked_row(&self, attachment_id: i64) -> AppResult<LinkedRow> {
+        let (file_name, file_path, file_size, hash, modified_at, note_id) = self
+            .conn
+            .query_row(
+                "SELECT file_name, file_path, file_size, linked_hash, linked_modified_at, note_id
+                 FROM note_attachments WHERE id = ?",
+                params![attachment_id],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, i64>(2)?,
+                        row.get::<_, Option<String>>(3)?,
+                        row.get::<_, Option<String>>(4)?,
+                        row.get::<_, i64>(5)?,
+                    ))
+                },
+            )
+            .map_err(|e| {
+                AppError::lookup(
+                    EntityKind::Attachment,
+                    attachment_id,
+                    "Failed to get attachment",
+                    e,
+                )
+            })?;
+
+        // stored attachments have no linked hash
+        let hash = hash.ok_or_else(|| {
+            AppError::validation(format!(
+                "Attachment {} is a stored copy, not a linked file",
+                attachment_id
+            ))
+            .for_entity(EntityKind::Attachment, Some(attachment_id))
+        })?;
+
+        Ok(LinkedRow {
+            id: attachment_id,
+            note_id,
+            file_name,
+            file_path,
+            file_size,
+            hash,
+            modified_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::{create_note, note_input, open_test_db, TestDir};
+    use crate::error::ErrorCode;
+    use crate::features::notes::repository::NoteRepository;
+
+    fn check(conn: &Connection, search_dirs: &[PathBuf]) -> Vec<LinkedFileCheck> {
+        let rows = LinkedFileRepository::new(conn).linked_rows(None).unwrap();
+        let report = check_linked_files(rows, search_dirs);
+        LinkedFileRepository::new(conn)
+            .record_check(report)
+            .unwrap()
+    }
+
+    #[test]
+    fn a_moved_file_is_found_and_relinked() {
+        let conn = open_test_db();
+        let dir = TestDir::create();
+        let note = create_note(&conn, note_input("note", "", &[]));
+        let original = dir.write("videos/clip.mp4", b"frames");
+        let file = LinkedFile::read(original.to_str().unwrap()).unwrap();
+        let attachment = LinkedFileRepository::new(&conn).link(note, &file).unwrap();
+
+        assert_eq!(check(&conn, &[])[0].state, LinkedFileState::Ok);
+
+        let moved = dir.write("archive/clip.mp4", b"frames");
+        fs::remove_file(&original).unwrap();
+        let checks = check(&conn, &[dir.path().to_path_buf()]);
+        assert_eq!(checks[0].state, LinkedFileState::Moved);
+        assert_eq!(checks[0].candidates.len(), 1);
+
+        let other = dir.write("archive/other.mp4", b"different");
+        let err = LinkedFileRepository::new(&conn)
+            .relink(
+                attachment,
+                &LinkedFile::read(other.to_str().unwrap()).unwrap(),
+            )
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::Validation);
+
+        LinkedFileRepository::new(&conn)
+            .relink(
+                attachment,
+                &LinkedFile::read(&checks[0].candidates[0]).unwrap(),
+            )
+            .unwrap();
+        assert_eq!(
+            moved.canonicalize().unwrap(),
+            Path::new(&check(&conn, &[])[0].file_path)
+        );
+        assert_eq!(check(&conn, &[])[0].state, LinkedFileState::Ok);
+    }
+
+    #[test]
+    fn a_changed_file_is_reported() {
+        let conn = open_test_db();
+        let dir = TestDir::create();
+        let note = create_note(&conn, note_input("note", "", &[]));
+        let path = dir.write("data.csv", b"a,b");
+        let file = LinkedFile::read(path.to_str().unwrap()).unwrap();
+        LinkedFileRepository::new(&conn).link(note, &file).unwrap();
+
+        dir.write("data.csv", b"a,b,c");
+
+        assert_eq!(check(&conn, &[])[0].state, LinkedFileState::Changed);
+    }
+
+    #[test]
+    fn a_file_that_cant_be_read_counts_as_changed() {
+        let dir = TestDir::create();
+        let path = dir.write("data.csv", b"a,b");
+        let metadata = fs::metadata(&path).unwrap();
+        // the size recorded doesn't match, so the file is read again, and it's gone by then
+        let row = LinkedRow {
+            id: 1,
+            note_id: 1,
+            file_name: "data.csv".to_string(),
+            file_path: dir.path().join("gone.csv").to_string_lossy().to_string(),
+            file_size: 0,
+            hash: String::new(),
+            modified_at: None,
+        };
+
+        assert!(matches!(compare(&row, &metadata), Comparison::Changed));
+    }
+
+    #[test]
+    fn files_cant_be_linked_to_a_trashed_note() {
+        let conn = open_test_db();
+        let dir = TestDir::create();
+        let note = create_note(&conn, note_input("note", "", &[]));
+        NoteRepository::new(&conn).delete(note).unwrap();
+        let path = dir.write("data.csv", b"a,b");
+        let file = LinkedFile::read(path.to_str().unwrap()).unwrap();
+
+        let err = LinkedFileRepository::new(&conn)
+            .link(note, &file)
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::NotFound);
+        let err = LinkedFileRepository::new(&conn)
+            .link(note + 1, &file)
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::NotFound);
+    }
+
+    #[test]
+    fn only_absolute_paths_can_be_linked() {
+        let err = LinkedFile::read("relative/file.txt").err().unwrap();
+        assert_eq!(err.code, ErrorCode::Validation);
+    }
+}
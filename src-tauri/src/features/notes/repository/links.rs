@@ -0,0 +1,167 @@
+use crate::error::{AppError, AppResult};
+use crate::features::notes::links::parse_wiki_links;
+use crate::features::notes::models::NoteLink;
+use rusqlite::{params, Connection, Params, Row};
+use std::path::PathBuf;
+
+// every link with its source title and the note it resolves to. a title shared by
+// several notes resolves to the oldest of them. notes in the trash neither link nor are
+// linked to
+pub(crate) const LINK_SELECT: &str = "SELECT
+        l.source_note_id,
+        s.title,
+        l.target_title,
+        (SELECT t.id FROM notes t
+         WHERE t.title = l.target_title COLLATE NOCASE AND t.deleted_at IS NULL
+         ORDER BY t.id LIMIT 1) AS target_note_id,
+        l.heading,
+        l.alias,
+        l.position
+     FROM note_links l
+     JOIN notes s ON s.id = l.source_note_id AND s.deleted_at IS NULL";
+
+fn link_from_row(row: &Row) -> rusqlite::Result<NoteLink> {
+    Ok(NoteLink {
+        source_note_id: row.get(0)?,
+        source_title: row.get(1)?,
+        target_title: row.get(2)?,
+        target_note_id: row.get(3)?,
+        heading: row.get(4)?,
+        alias: row.get(5)?,
+        position: row.get(6)?,
+    })
+}
+
+// replace the stored links of a note with the ones in `content`
+fn index_note_links(conn: &Connection, note_id: i64, content: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "DELETE FROM note_links WHERE source_note_id = ?",
+        params![note_id],
+    )?;
+
+    let mut insert = conn.prepare(
+        "INSERT INTO note_links (source_note_id, target_title, heading, alias, position)
+         VALUES (?, ?, ?, ?, ?)",
+    )?;
+
+    for link in parse_wiki_links(content) {
+        insert.execute(params![
+            note_id,
+            link.target_title,
+            link.heading,
+            link.alias,
+            link.title_range.start as i64
+        ])?;
+    }
+
+    Ok(())
+}
+
+// index the links of every existing note, used when the links table is first created
+pub fn index_all_note_links(conn: &Connection) -> rusqlite::Result<Vec<PathBuf>> {
+    let notes = conn
+        .prepare("SELECT id, content FROM notes")?
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for (note_id, content) in notes {
+        index_note_links(conn, note_id, &content)?;
+    }
+
+    Ok(Vec::new())
+}
+
+pub struct LinkRepository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> LinkRepository<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        LinkRepository { conn }
+    }
+
+    // re-parse a note's content after it was saved
+    pub fn sync_for_note(&self, note_id: i64, content: &str) -> AppResult<()> {
+        index_note_links(self.conn, note_id, content)
+            .map_err(|e| AppError::database("Failed to index note links", e))
+    }
+
+    // links written in a note, in the order they appear
+    pub fn get_outgoing(&self, note_id: i64) -> AppResult<Vec<NoteLink>> {
+        self.query_links(
+            &format!(
+                "{} WHERE l.source_note_id = ? ORDER BY l.position",
+                LINK_SELECT
+            ),
+            params![note_id],
+        )
+    }
+
+    // links in other notes (or this one) that resolve to a note
+    pub fn get_backlinks(&self, note_id: i64) -> AppResult<Vec<NoteLink>> {
+        self.query_links(
+            &format!(
+                "SELECT * FROM ({}) WHERE target_note_id = ?
+                 ORDER BY title COLLATE NOCASE, position",
+                LINK_SELECT
+            ),
+            params![note_id],
+        )
+    }
+
+    // links pointing at titles no note has, across all notes or within one note
+    pub fn get_unresolved(&self, note_id: Option<i64>) -> AppResult<Vec<NoteLink>> {
+        self.query_links(
+            &format!(
+                "SELECT * FROM ({}) WHERE target_note_id IS NULL
+                 AND (?1 IS NULL OR source_note_id = ?1)
+                 ORDER BY target_title COLLATE NOCASE, source_note_id, position",
+                LINK_SELECT
+            ),
+            params![note_id],
+        )
+    }
+
+    // ids of the notes that link to a title
+    pub fn get_sources_linking_to(&self, title: &str) -> AppResult<Vec<i64>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT DISTINCT source_note_id FROM note_links
+                 WHERE target_title = ? COLLATE NOCASE",
+            )
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let source_rows = stmt
+            .query_map(params![title], |row| row.get::<_, i64>(0))
+            .map_err(|e| AppError::database("Failed to query linking notes", e))?;
+
+        let mut source_ids = Vec::new();
+        for source_id in source_rows {
+            source_ids
+                .push(source_id.map_err(|e| AppError::database("Failed to process note ID", e))?);
+        }
+
+        Ok(source_ids)
+    }
+
+    fn query_links<P: Params>(&self, sql: &str, params: P) -> AppResult<Vec<NoteLink>> {
+        let mut stmt = self
+            .conn
+            .prepare(sql)
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let link_rows = stmt
+            .query_map(params, link_from_row)
+            .map_err(|e| AppError::database("Failed to query links", e))?;
+
+        let mut links = Vec::new();
+        for link_result in link_rows {
+            links.push(link_result.map_err(|e| AppError::database("Failed to process link", e))?);
+        }
+
+        Ok(links)
+    }
+}
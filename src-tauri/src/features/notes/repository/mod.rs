@@ -0,0 +1,29 @@
+pub mod attachments;
+pub mod blobs;
+pub mod folders;
+pub mod graph;
+pub mod imports;
+pub mod linked_files;
+pub mod links;
+pub mod notes;
+pub mod retention;
+pub mod revisions;
+pub mod storage;
+pub mod tags;
+pub mod thumbnails;
+pub mod uploads;
+
+pub use attachments::{AttachmentRepository, PreparedAttachment};
+pub use blobs::BlobStore;
+pub use folders::FolderRepository;
+pub use graph::GraphRepository;
+pub use imports::ImportRepository;
+pub use linked_files::{check_linked_files, LinkedFile, LinkedFileRepository};
+pub use links::LinkRepository;
+pub use notes::NoteRepository;
+pub use retention::RetentionRepository;
+pub use revisions::RevisionRepository;
+pub use storage::StorageRepository;
+pub use tags::NoteTagRepository;
+pub use thumbnails::ThumbnailRepository;
+pub use uploads::{prepare_bytes, ReceivedUpload, UploadRepository};
@@ -0,0 +1,846 @@
+use super::folders::FolderRepository;
+use super::links::LinkRepository;
+use super::retention::RetentionRepository;
+use super::revisions::RevisionRepository;
+use crate::db::time::parse_timestamp;
+use crate::error::{AppError, AppResult, EntityKind};
+use crate::features::notes::links::rewrite_link_targets;
+use crate::features::notes::models::{Note, NoteInput, NoteSearchResult};
+use crate::features::notes::search::sql::CompiledSearch;
+use crate::features::notes::search::{compile_search, parse_search_query};
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use rusqlite::{params, params_from_iter, Connection, Params, Row};
+
+pub(crate) const NOTE_COLUMNS: &str =
+    "id, title, content, folder_id, is_pinned, is_archived, color, created_at, updated_at";
+
+// NOTE_COLUMNS qualified with a table alias, for queries that join other tables
+pub(crate) fn note_columns(alias: &str) -> String {
+    NOTE_COLUMNS
+        .split(", ")
+        .map(|column| format!("{}.{}", alias, column))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// a note's fields in the shape update() takes, for changing some of them and saving it back
+pub(crate) fn into_input(note: Note) -> NoteInput {
+    NoteInput {
+        title: note.title,
+        content: note.content,
+        folder_id: note.folder_id,
+        tags: note.tags,
+        is_pinned: note.is_pinned,
+        is_archived: note.is_archived,
+        color: note.color,
+    }
+}
+
+// raw column values of a `notes` row, before tags are attached and dates parsed
+pub(crate) struct NoteRow {
+    id: i64,
+    title: String,
+    content: String,
+    folder_id: Option<i64>,
+    is_pinned: i32,
+    is_archived: i32,
+    color: Option<String>,
+    created_at: String,
+    updated_at: String,
+}
+
+impl NoteRow {
+    // expects the columns in NOTE_COLUMNS order, starting at index 0
+    pub(crate) fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(NoteRow {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            content: row.get(2)?,
+            folder_id: row.get(3)?,
+            is_pinned: row.get(4)?,
+            is_archived: row.get(5)?,
+            color: row.get(6)?,
+            created_at: row.get(7)?,
+            updated_at: row.get(8)?,
+        })
+    }
+}
+
+pub struct NoteRepository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> NoteRepository<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        NoteRepository { conn }
+    }
+
+    pub fn create(&self, input: NoteInput) -> AppResult<i64> {
+        let now = Utc::now();
+        self.create_with_timestamps(input, now, now)
+    }
+
+    // create a note that already has a history elsewhere, keeping its original dates
+    pub fn create_with_timestamps(
+        &self,
+        input: NoteInput,
+        created_at: DateTime<Utc>,
+        updated_at: DateTime<Utc>,
+    ) -> AppResult<i64> {
+        let created_at = created_at.to_rfc3339();
+        let updated_at = updated_at.to_rfc3339();
+        self.ensure_folder_accepts(input.folder_id)?;
+
+        // insert the note
+        self.conn
+            .execute(
+                "INSERT INTO notes (
+                    title, content, folder_id, is_pinned, is_archived, color, created_at, updated_at
+                ) VALUES (
+                    ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8
+                )",
+                params![
+                    input.title,
+                    input.content,
+                    input.folder_id,
+                    input.is_pinned as i32,
+                    input.is_archived as i32,
+                    input.color,
+                    created_at,
+                    updated_at
+                ],
+            )
+            .map_err(|e| AppError::database("Failed to create note", e))?;
+
+        let note_id = self.conn.last_insert_rowid();
+
+        // process tags
+        self.add_tag_mappings(note_id, &input.tags)?;
+
+        LinkRepository::new(self.conn).sync_for_note(note_id, &input.content)?;
+
+        // create initial revision
+        RevisionRepository::new(self.conn).insert(note_id, &input, &updated_at)?;
+
+        info!("Created note '{}' with ID: {}", input.title, note_id);
+        Ok(note_id)
+    }
+
+    pub fn get_all(&self) -> AppResult<Vec<Note>> {
+        self.query_notes(
+            &format!(
+                "SELECT {} FROM notes WHERE deleted_at IS NULL",
+                NOTE_COLUMNS
+            ),
+            [],
+        )
+    }
+
+    pub fn get_by_id(&self, id: i64) -> AppResult<Note> {
+        let note_row = self
+            .conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM notes WHERE id = ? AND deleted_at IS NULL",
+                    NOTE_COLUMNS
+                ),
+                params![id],
+                NoteRow::from_row,
+            )
+            .map_err(|e| AppError::lookup(EntityKind::Note, id, "Failed to get note", e))?;
+
+        self.build_note(note_row)
+    }
+
+    pub fn update(&self, id: i64, input: NoteInput, create_revision: bool) -> AppResult<()> {
+        let now = Utc::now().to_rfc3339();
+
+        // the note as it is now, to keep as a revision
+        let current = if create_revision {
+            Some(self.get_by_id(id)?)
+        } else {
+            None
+        };
+        self.ensure_folder_accepts(input.folder_id)?;
+
+        // update the note
+        let updated = self
+            .conn
+            .execute(
+                "UPDATE notes SET
+                    title = ?, content = ?, folder_id = ?, is_pinned = ?, is_archived = ?,
+                    color = ?, updated_at = ?
+                 WHERE id = ? AND deleted_at IS NULL",
+                params![
+                    input.title,
+                    input.content,
+                    input.folder_id,
+                    input.is_pinned as i32,
+                    input.is_archived as i32,
+                    input.color,
+                    now,
+                    id
+                ],
+            )
+            .map_err(|e| AppError::database("Failed to update note", e))?;
+
+        if updated == 0 {
+            return Err(AppError::not_found(EntityKind::Note, id));
+        }
+
+        // create a revision if requested
+        if let Some(current) = current {
+            RevisionRepository::new(self.conn).insert(id, &into_input(current), &now)?;
+
+            // the note is saved either way, so a failed prune is rolled back and only logged
+            if let Err(e) = self.prune_revisions(id) {
+                warn!("Failed to apply revision retention to note {}: {}", id, e);
+            }
+        }
+
+        // replace existing tag mappings for this note
+        self.conn
+            .execute(
+                "DELETE FROM note_tag_mappings WHERE note_id = ?",
+                params![id],
+            )
+            .map_err(|e| AppError::database("Failed to delete tag mappings", e))?;
+
+        self.add_tag_mappings(id, &input.tags)?;
+
+        LinkRepository::new(self.conn).sync_for_note(id, &input.content)?;
+
+        info!("Updated note with ID: {}", id);
+        Ok(())
+    }
+
+    // change a note's title. with update_references, [[links]] to the old title in other
+    // notes are rewritten to the new one; returns the ids of the notes that were rewritten
+    pub fn rename(&self, id: i64, new_title: &str, update_references: bool) -> AppResult<Vec<i64>> {
+        let now = Utc::now().to_rfc3339();
+
+        let old_title: String = self
+            .conn
+            .query_row(
+                "SELECT title FROM notes WHERE id = ? AND deleted_at IS NULL",
+                params![id],
+                |row| row.get(0),
+            )
+            .map_err(|e| AppError::lookup(EntityKind::Note, id, "Failed to get note title", e))?;
+
+        self.conn
+            .execute(
+                "UPDATE notes SET title = ?, updated_at = ? WHERE id = ?",
+                params![new_title, now, id],
+            )
+            .map_err(|e| AppError::database("Failed to rename note", e))?;
+
+        info!(
+            "Renamed note {} from '{}' to '{}'",
+            id, old_title, new_title
+        );
+
+        if !update_references || old_title == new_title {
+            return Ok(Vec::new());
+        }
+
+        // if another note still has the old title, existing links now resolve to it
+        let other_holders: i64 = self
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM notes
+                 WHERE title = ? COLLATE NOCASE AND id != ? AND deleted_at IS NULL",
+                params![old_title, id],
+                |row| row.get(0),
+            )
+            .map_err(|e| AppError::database("Failed to check for duplicate titles", e))?;
+
+        if other_holders > 0 {
+            info!(
+                "Not rewriting links to '{}', another note still has that title",
+                old_title
+            );
+            return Ok(Vec::new());
+        }
+
+        let links = LinkRepository::new(self.conn);
+        let mut rewritten = Vec::new();
+
+        for source_id in links.get_sources_linking_to(&old_title)? {
+            let content: String = self
+                .conn
+                .query_row(
+                    "SELECT content FROM notes WHERE id = ?",
+                    params![source_id],
+                    |row| row.get(0),
+                )
+                .map_err(|e| AppError::database("Failed to get note content", e))?;
+
+            let Some(new_content) = rewrite_link_targets(&content, &old_title, new_title) else {
+                continue;
+            };
+
+            self.conn
+                .execute(
+                    "UPDATE notes SET content = ?, updated_at = ? WHERE id = ?",
+                    params![new_content, now, source_id],
+                )
+                .map_err(|e| AppError::database("Failed to rewrite links", e))?;
+
+            links.sync_for_note(source_id, &new_content)?;
+            rewritten.push(source_id);
+        }
+
+        info!(
+            "Rewrote links to '{}' in {} notes",
+            old_title,
+            rewritten.len()
+        );
+        Ok(rewritten)
+    }
+
+    // swap in the final content of a note that was just imported, once its attachments are
+    // stored. the initial revision is rewritten too, and updated_at keeps the imported date
+    pub fn replace_imported_content(&self, id: i64, content: &str) -> AppResult<()> {
+        let changed = self
+            .conn
+            .execute(
+                "UPDATE notes SET content = ? WHERE id = ?",
+                params![content, id],
+            )
+            .map_err(|e| AppError::database("Failed to replace note content", e))?;
+
+        if changed == 0 {
+            return Err(AppError::not_found(EntityKind::Note, id));
+        }
+
+        RevisionRepository::new(self.conn).overwrite_for_note(id, content)?;
+
+        LinkRepository::new(self.conn).sync_for_note(id, content)
+    }
+
+    // apply revision retention to one note in a savepoint of its own, so a prune that fails
+    // part way leaves the revisions as they were without undoing the update around it
+    fn prune_revisions(&self, id: i64) -> AppResult<()> {
+        self.conn
+            .execute_batch("SAVEPOINT prune_revisions")
+            .map_err(|e| AppError::database("Failed to start pruning revisions", e))?;
+
+        let result = RetentionRepository::new(self.conn).apply(Some(id));
+
+        let end = if result.is_ok() {
+            "RELEASE prune_revisions"
+        } else {
+            "ROLLBACK TO prune_revisions; RELEASE prune_revisions"
+        };
+        self.conn
+            .execute_batch(end)
+            .map_err(|e| AppError::database("Failed to finish pruning revisions", e))?;
+
+        result.map(|_| ())
+    }
+
+    // move a note to the trash. it keeps its revisions and attachments until it's purged,
+    // see TrashRepository
+    pub fn delete(&self, id: i64) -> AppResult<()> {
+        let changed = self
+            .conn
+            .execute(
+                "UPDATE notes SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL",
+                params![Utc::now().to_rfc3339(), id],
+            )
+            .map_err(|e| AppError::database("Failed to delete note", e))?;
+
+        if changed == 0 {
+            return Err(AppError::not_found(EntityKind::Note, id));
+        }
+
+        info!("Moved note with ID: {} to the trash", id);
+        Ok(())
+    }
+
+    pub fn set_pinned(&self, id: i64, is_pinned: bool) -> AppResult<()> {
+        let now = Utc::now().to_rfc3339();
+
+        let changed = self
+            .conn
+            .execute(
+                "UPDATE notes SET is_pinned = ?, updated_at = ? WHERE id = ? AND deleted_at IS NULL",
+                params![is_pinned as i32, now, id],
+            )
+            .map_err(|e| AppError::database("Failed to toggle note pin status", e))?;
+
+        if changed == 0 {
+            return Err(AppError::not_found(EntityKind::Note, id));
+        }
+
+        info!(
+            "Toggled pin status to {} for note with ID: {}",
+            is_pinned, id
+        );
+        Ok(())
+    }
+
+    pub fn set_archived(&self, id: i64, is_archived: bool) -> AppResult<()> {
+        let now = Utc::now().to_rfc3339();
+
+        let changed = self
+            .conn
+            .execute(
+                "UPDATE notes SET is_archived = ?, updated_at = ? WHERE id = ? AND deleted_at IS NULL",
+                params![is_archived as i32, now, id],
+            )
+            .map_err(|e| AppError::database("Failed to toggle note archive status", e))?;
+
+        if changed == 0 {
+            return Err(AppError::not_found(EntityKind::Note, id));
+        }
+
+        info!(
+            "Toggled archive status to {} for note with ID: {}",
+            is_archived, id
+        );
+        Ok(())
+    }
+
+    // notes directly inside a folder, or at the root when folder_id is None
+    pub fn get_by_folder(&self, folder_id: Option<i64>) -> AppResult<Vec<Note>> {
+        match folder_id {
+            Some(id) => self.query_notes(
+                &format!(
+                    "SELECT {} FROM notes WHERE folder_id = ? AND deleted_at IS NULL",
+                    NOTE_COLUMNS
+                ),
+                params![id],
+            ),
+            None => self.query_notes(
+                &format!(
+                    "SELECT {} FROM notes WHERE folder_id IS NULL AND deleted_at IS NULL",
+                    NOTE_COLUMNS
+                ),
+                [],
+            ),
+        }
+    }
+
+    // notes in a folder and, optionally, all of its subfolders
+    pub fn get_by_folder_recursive(
+        &self,
+        folder_id: Option<i64>,
+        include_subfolders: bool,
+    ) -> AppResult<Vec<Note>> {
+        // root notes have no subtree to expand
+        let id = match folder_id {
+            Some(id) if include_subfolders => id,
+            _ => return self.get_by_folder(folder_id),
+        };
+
+        let mut all_folder_ids = vec![id];
+        all_folder_ids.extend(FolderRepository::new(self.conn).get_all_subfolder_ids(Some(id))?);
+
+        let placeholders = all_folder_ids
+            .iter()
+            .map(|_| "?")
+            .collect::<Vec<_>>()
+            .join(",");
+
+        self.query_notes(
+            &format!(
+                "SELECT {} FROM notes WHERE folder_id IN ({}) AND deleted_at IS NULL",
+                NOTE_COLUMNS, placeholders
+            ),
+            params_from_iter(all_folder_ids.iter()),
+        )
+    }
+
+    // search with the query language in search::query: filters plus full-text terms.
+    // text matches come back best first, everything else newest first
+    pub fn search(&self, query: &str, limit: u32) -> AppResult<Vec<NoteSearchResult>> {
+        let query = parse_search_query(query)?;
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let CompiledSearch { sql, params } = compile_search(&query, limit);
+
+        let mut stmt = self
+            .conn
+            .prepare(&sql)
+            .map_err(|e| AppError::database("Failed to prepare search statement", e))?;
+
+        let result_rows = stmt
+            .query_map(params_from_iter(params), |row| {
+                Ok((
+                    NoteRow::from_row(row)?,
+                    row.get::<_, f64>(9)?,
+                    row.get::<_, String>(10)?,
+                    row.get::<_, String>(11)?,
+                    row.get::<_, Option<i64>>(12)?,
+                    row.get::<_, Option<String>>(13)?,
+                ))
+            })
+            .map_err(|e| AppError::database("Failed to search notes", e))?;
+
+        let mut results = Vec::new();
+        for result in result_rows {
+            let (note_row, rank, title_highlight, snippet, attachment_id, attachment_name) =
+                result.map_err(|e| AppError::database("Failed to process search result", e))?;
+
+            results.push(NoteSearchResult {
+                note: self.build_note(note_row)?,
+                rank,
+                title_highlight,
+                snippet,
+                attachment_id,
+                attachment_name,
+            });
+        }
+
+        Ok(results)
+    }
+
+    // tag names attached to a note
+    pub fn get_tags(&self, note_id: i64) -> AppResult<Vec<String>> {
+        let mut tags_stmt = self
+            .conn
+            .prepare(
+                "SELECT t.name FROM note_tags t
+                 JOIN note_tag_mappings m ON t.id = m.tag_id
+                 WHERE m.note_id = ?",
+            )
+            .map_err(|e| AppError::database("Failed to prepare tags statement", e))?;
+
+        let tags_rows = tags_stmt
+            .query_map(params![note_id], |row| row.get::<_, String>(0))
+            .map_err(|e| AppError::database("Failed to query tags", e))?;
+
+        let mut tags = Vec::new();
+        for tag_result in tags_rows {
+            tags.push(tag_result.map_err(|e| AppError::database("Failed to process tag", e))?);
+        }
+
+        Ok(tags)
+    }
+
+    // run a query selecting NOTE_COLUMNS and turn every row into a Note
+    pub(crate) fn query_notes<P: Params>(&self, sql: &str, params: P) -> AppResult<Vec<Note>> {
+        let mut stmt = self
+            .conn
+            .prepare(sql)
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let note_rows = stmt
+            .query_map(params, NoteRow::from_row)
+            .map_err(|e| AppError::database("Failed to query notes", e))?;
+
+        let mut notes = Vec::new();
+        for note_result in note_rows {
+            let note_row =
+                note_result.map_err(|e| AppError::database("Failed to process note row", e))?;
+            notes.push(self.build_note(note_row)?);
+        }
+
+        Ok(notes)
+    }
+
+    // attach tags and parse dates for a raw row
+    pub(crate) fn build_note(&self, note_row: NoteRow) -> AppResult<Note> {
+        let tags = self.get_tags(note_row.id)?;
+
+        Ok(Note {
+            id: note_row.id,
+            title: note_row.title,
+            content: note_row.content,
+            folder_id: note_row.folder_id,
+            tags,
+            is_pinned: note_row.is_pinned != 0,
+            is_archived: note_row.is_archived != 0,
+            color: note_row.color,
+            created_at: parse_timestamp(&note_row.created_at, "created_at")?,
+            updated_at: parse_timestamp(&note_row.updated_at, "updated_at")?,
+        })
+    }
+
+    // a note can only be filed in a folder that exists and isn't in the trash
+    fn ensure_folder_accepts(&self, folder_id: Option<i64>) -> AppResult<()> {
+        let Some(folder_id) = folder_id else {
+            return Ok(());
+        };
+
+        let exists: bool = self
+            .conn
+            .query_row(
+                "SELECT EXISTS (
+                    SELECT 1 FROM note_folders WHERE id = ? AND deleted_at IS NULL
+                 )",
+                params![folder_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| AppError::database("Failed to look up folder", e))?;
+
+        if !exists {
+            return Err(AppError::validation(format!(
+                "Folder {} doesn't exist or is in the trash",
+                folder_id
+            ))
+            .for_entity(EntityKind::Folder, Some(folder_id)));
+        }
+
+        Ok(())
+    }
+
+    // map tag names onto a note, creating tags that don't exist yet
+    fn add_tag_mappings(&self, note_id: i64, tags: &[String]) -> AppResult<()> {
+        for tag_name in tags {
+            // find if tag exists
+            let tag_id: Result<i64, rusqlite::Error> = self.conn.query_row(
+                "SELECT id FROM note_tags WHERE name = ?",
+                params![tag_name],
+                |row| row.get(0),
+            );
+
+            let tag_id = match tag_id {
+                Ok(id) => id, // tag exists
+                Err(_) => {
+                    // tag doesn't exist, create it
+                    self.conn
+                        .execute("INSERT INTO note_tags (name) VALUES (?)", params![tag_name])
+                        .map_err(|e| AppError::database("Failed to create tag", e))?;
+
+                    self.conn.last_insert_rowid()
+                }
+            };
+
+            // add tag mapping
+            self.conn
+                .execute(
+                    "INSERT OR IGNORE INTO note_tag_mappings (note_id, tag_id) VALUES (?, ?)",
+                    params![note_id, tag_id],
+                )
+                .map_err(|e| AppError::database("Failed to add tag mapping", e))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::{note_input, open_test_db};
+    use crate::error::ErrorCode;
+
+    fn sorted(mut tags: Vec<String>) -> Vec<String> {
+        tags.sort();
+        tags
+    }
+
+    #[test]
+    fn create_and_get() {
+        let conn = open_test_db();
+        let notes = NoteRepository::new(&conn);
+
+        let id = notes
+            .create(note_input("Groceries", "milk, eggs", &["home", "lists"]))
+            .unwrap();
+        let note = notes.get_by_id(id).unwrap();
+
+        assert_eq!(note.title, "Groceries");
+        assert_eq!(note.content, "milk, eggs");
+        assert_eq!(sorted(note.tags), vec!["home", "lists"]);
+        assert!(!note.is_pinned);
+        assert_eq!(notes.get_all().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn update_replaces_tags_and_keeps_a_revision() {
+        let conn = open_test_db();
+        let notes = NoteRepository::new(&conn);
+        let id = notes
+            .create(note_input("Plan", "first", &["a", "b"]))
+            .unwrap();
+
+        notes
+            .update(id, note_input("Plan", "second", &["b", "c"]), true)
+            .unwrap();
+
+        let note = notes.get_by_id(id).unwrap();
+        assert_eq!(note.content, "second");
+        assert_eq!(sorted(note.tags), vec!["b", "c"]);
+
+        let revisions: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM note_revisions WHERE note_id = ?",
+                params![id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(revisions, 2);
+    }
+
+    #[test]
+    fn update_of_a_missing_note_is_not_found() {
+        let conn = open_test_db();
+        let err = NoteRepository::new(&conn)
+            .update(42, note_input("Nope", "", &[]), false)
+            .unwrap_err();
+
+        assert_eq!(err.code, ErrorCode::NotFound);
+        assert_eq!(err.entity, Some(EntityKind::Note));
+        assert_eq!(err.id, Some(42));
+    }
+
+    #[test]
+    fn deleted_notes_are_hidden() {
+        let conn = open_test_db();
+        let notes = NoteRepository::new(&conn);
+        let id = notes.create(note_input("Old", "", &[])).unwrap();
+
+        notes.delete(id).unwrap();
+
+        assert_eq!(notes.get_by_id(id).unwrap_err().code, ErrorCode::NotFound);
+        assert!(notes.get_all().unwrap().is_empty());
+        assert!(notes.get_by_folder(None).unwrap().is_empty());
+        assert_eq!(notes.delete(id).unwrap_err().code, ErrorCode::NotFound);
+    }
+
+    #[test]
+    fn pin_and_archive() {
+        let conn = open_test_db();
+        let notes = NoteRepository::new(&conn);
+        let id = notes.create(note_input("Flags", "", &[])).unwrap();
+
+        notes.set_pinned(id, true).unwrap();
+        notes.set_archived(id, true).unwrap();
+
+        let note = notes.get_by_id(id).unwrap();
+        assert!(note.is_pinned);
+        assert!(note.is_archived);
+    }
+
+    #[test]
+    fn folder_listing_can_include_subfolders() {
+        let conn = open_test_db();
+        let folders = FolderRepository::new(&conn);
+        let notes = NoteRepository::new(&conn);
+        let parent = folders.create("Projects", None, None).unwrap();
+        let child = folders.create("Apto", Some(parent), None).unwrap();
+
+        let mut top = note_input("Top", "", &[]);
+        top.folder_id = Some(parent);
+        notes.create(top).unwrap();
+        let mut nested = note_input("Nested", "", &[]);
+        nested.folder_id = Some(child);
+        notes.create(nested).unwrap();
+        notes.create(note_input("Root", "", &[])).unwrap();
+
+        let direct = notes.get_by_folder_recursive(Some(parent), false).unwrap();
+        assert_eq!(direct.len(), 1);
+        assert_eq!(direct[0].title, "Top");
+
+        let all = notes.get_by_folder_recursive(Some(parent), true).unwrap();
+        assert_eq!(all.len(), 2);
+
+        let root = notes.get_by_folder(None).unwrap();
+        assert_eq!(root.len(), 1);
+        assert_eq!(root[0].title, "Root");
+    }
+
+    #[test]
+    fn search_combines_filters_and_text() {
+        let conn = open_test_db();
+        let notes = NoteRepository::new(&conn);
+        let budget = notes
+            .create(note_input("Budget", "quarterly budget review", &["work"]))
+            .unwrap();
+        notes
+            .create(note_input("Holiday", "holiday budget", &["home"]))
+            .unwrap();
+        notes
+            .create(note_input("Standup", "daily notes", &["work"]))
+            .unwrap();
+
+        let results = notes.search("tag:work budget", 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].note.id, budget);
+        assert!(results[0].snippet.contains("<mark>"));
+
+        assert_eq!(notes.search("budget", 10).unwrap().len(), 2);
+        assert_eq!(notes.search("-tag:work", 10).unwrap().len(), 1);
+        assert!(notes.search("", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn search_skips_trashed_notes() {
+        let conn = open_test_db();
+        let notes = NoteRepository::new(&conn);
+        let id = notes.create(note_input("Secret", "needle", &[])).unwrap();
+        notes.delete(id).unwrap();
+
+        assert!(notes.search("needle", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn folder_search_skips_trashed_folders() {
+        let conn = open_test_db();
+        let folders = FolderRepository::new(&conn);
+        let projects = folders.create("Projects", None, None).unwrap();
+        let apto = folders.create("Apto", Some(projects), None).unwrap();
+        let notes = NoteRepository::new(&conn);
+        let mut note = note_input("Plan", "", &[]);
+        note.folder_id = Some(apto);
+        notes.create(note).unwrap();
+
+        assert_eq!(notes.search("folder:Projects", 10).unwrap().len(), 1);
+
+        conn.execute(
+            "UPDATE note_folders SET deleted_at = ? WHERE id = ?",
+            params![Utc::now().to_rfc3339(), projects],
+        )
+        .unwrap();
+
+        assert!(notes.search("folder:Projects", 10).unwrap().is_empty());
+        assert!(notes.search("folder:Projects/Apto", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn notes_cant_be_filed_in_a_trashed_folder() {
+        let conn = open_test_db();
+        let folders = FolderRepository::new(&conn);
+        let folder = folders.create("Old", None, None).unwrap();
+        folders.delete(folder).unwrap();
+        let notes = NoteRepository::new(&conn);
+        let mut note = note_input("Plan", "", &[]);
+
+        note.folder_id = Some(folder);
+        let err = notes.create(note.clone()).unwrap_err();
+        assert_eq!(err.code, ErrorCode::Validation);
+
+        note.folder_id = None;
+        let id = notes.create(note.clone()).unwrap();
+        note.folder_id = Some(folder);
+        let err = notes.update(id, note, false).unwrap_err();
+        assert_eq!(err.code, ErrorCode::Validation);
+        assert_eq!(notes.get_by_id(id).unwrap().folder_id, None);
+    }
+
+    #[test]
+    fn rename_rewrites_links_in_other_notes() {
+        let conn = open_test_db();
+        let notes = NoteRepository::new(&conn);
+        let target = notes.create(note_input("Old title", "", &[])).unwrap();
+        let source = notes
+            .create(note_input("Index", "see [[Old title#Intro|here]]", &[]))
+            .unwrap();
+
+        let rewritten = notes.rename(target, "New title", true).unwrap();
+
+        assert_eq!(rewritten, vec![source]);
+        assert_eq!(
+            notes.get_by_id(source).unwrap().content,
+            "see [[New title#Intro|here]]"
+        );
+        assert_eq!(notes.get_by_id(target).unwrap().title, "New title");
+    }
+}
@@ -0,0 +1,386 @@
+use super::revisions::RevisionRepository;
+use crate::db::settings::SettingsRepository;
+use crate::db::time::parse_timestamp;
+use crate::error::{AppError, AppResult};
+use crate::features::notes::models::{RetentionPolicy, RetentionReport, RetentionRule};
+use chrono::{DateTime, Utc};
+use log::info;
+use rusqlite::{params, Connection};
+use std::collections::HashSet;
+
+// app_settings key the policy is saved under
+const POLICY_KEY: &str = "revision_retention";
+
+const HOUR: i64 = 60 * 60;
+const DAY: i64 = 24 * HOUR;
+
+// every revision from the last hour, hourly for a day, daily for a month, weekly after that
+pub fn default_policy() -> RetentionPolicy {
+    RetentionPolicy {
+        enabled: true,
+        rules: vec![
+            RetentionRule {
+                max_age_secs: Some(HOUR),
+                interval_secs: 0,
+            },
+            RetentionRule {
+                max_age_secs: Some(DAY),
+                interval_secs: HOUR,
+            },
+            RetentionRule {
+                max_age_secs: Some(30 * DAY),
+                interval_secs: DAY,
+            },
+            RetentionRule {
+                max_age_secs: None,
+                interval_secs: 7 * DAY,
+            },
+        ],
+    }
+}
+
+// a revision as retention sees it, without its data
+struct RevisionAge {
+    id: i64,
+    note_id: i64,
+    created_at: DateTime<Utc>,
+    size: u64,
+}
+
+pub struct RetentionRepository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> RetentionRepository<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        RetentionRepository { conn }
+    }
+
+    // the saved policy, or the default one if none was saved
+    pub fn policy(&self) -> AppResult<RetentionPolicy> {
+        Ok(SettingsRepository::new(self.conn)
+            .get(POLICY_KEY)?
+            .unwrap_or_else(default_policy))
+    }
+
+    pub fn set_policy(&self, policy: &RetentionPolicy) -> AppResult<()> {
+        validate_policy(policy)?;
+        SettingsRepository::new(self.conn).set(POLICY_KEY, policy)?;
+
+        info!("Updated revision retention policy");
+        Ok(())
+    }
+
+    // prune the revisions of one note, or of every note, by the saved policy
+    pub fn apply(&self, note_id: Option<i64>) -> AppResult<RetentionReport> {
+        let policy = self.policy()?;
+        let mut report = RetentionReport::default();
+        if !policy.enabled {
+            return Ok(report);
+        }
+
+        let revisions = self.revision_ages(note_id)?;
+        let now = Utc::now();
+
+        let mut pruned: Vec<&RevisionAge> = Vec::new();
+        for note_revisions in revisions.chunk_by(|a, b| a.note_id == b.note_id) {
+            report.notes_checked += 1;
+            let mut ages: Vec<(i64, DateTime<Utc>)> = note_revisions
+                .iter()
+                .map(|revision| (revision.id, revision.created_at))
+                .collect();
+            // sort on the parsed dates, the stored text isn't always in one format
+            ages.sort_by(|a, b| b.1.cmp(&a.1).then(b.0.cmp(&a.0)));
+            let to_prune = revisions_to_prune(&ages, &policy, now);
+            pruned.extend(
+                note_revisions
+                    .iter()
+                    .filter(|revision| to_prune.contains(&revision.id)),
+            );
+        }
+
+        if pruned.is_empty() {
+            return Ok(report);
+        }
+
+        let ids: Vec<i64> = pruned.iter().map(|revision| revision.id).collect();
+        RevisionRepository::new(self.conn).remove(&ids)?;
+
+        // removing a revision rewrites the deltas built on it as keyframes, which are larger,
+        // so the saving is measured rather than summed from the removed revisions
+        let stored_before: u64 = revisions.iter().map(|revision| revision.size).sum();
+        report.revisions_pruned = pruned.len();
+        report.bytes_pruned = stored_before.saturating_sub(self.stored_size(note_id)?);
+
+        info!(
+            "Pruned {} revisions ({} bytes) across {} notes",
+            report.revisions_pruned, report.bytes_pruned, report.notes_checked
+        );
+        Ok(report)
+    }
+
+    // the size of the stored revision data of one note, or of every note
+    fn stored_size(&self, note_id: Option<i64>) -> AppResult<u64> {
+        let size: i64 = self
+            .conn
+            .query_row(
+                "SELECT COALESCE(SUM(length(data)), 0) FROM note_revisions
+                 WHERE ?1 IS NULL OR note_id = ?1",
+                params![note_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| AppError::database("Failed to measure revisions", e))?;
+        Ok(size.max(0) as u64)
+    }
+
+    // revisions grouped by note, newest first within each note
+    fn revision_ages(&self, note_id: Option<i64>) -> AppResult<Vec<RevisionAge>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, note_id, created_at, length(data)
+                 FROM note_revisions
+                 WHERE ?1 IS NULL OR note_id = ?1
+                 ORDER BY note_id, created_at DESC, id DESC",
+            )
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let rows = stmt
+            .query_map(params![note_id], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            })
+            .map_err(|e| AppError::database("Failed to query revisions", e))?;
+
+        let mut revisions = Vec::new();
+        for row in rows {
+            let (id, note_id, created_at, size) =
+                row.map_err(|e| AppError::database("Failed to process revision row", e))?;
+            revisions.push(RevisionAge {
+                id,
+                note_id,
+                created_at: parse_timestamp(&created_at, "created_at")?,
+                size: size.max(0) as u64,
+            });
+        }
+
+        Ok(revisions)
+    }
+}
+
+// the ids of one note's revisions that the policy drops. `revisions` are (id, created_at),
+// newest first; within each interval of a rule the newest revision is the one kept
+fn revisions_to_prune(
+    revisions: &[(i64, DateTime<Utc>)],
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+) -> HashSet<i64> {
+    let mut kept_buckets: HashSet<(usize, i64)> = HashSet::new();
+    let mut pruned = HashSet::new();
+
+    for (id, created_at) in revisions {
+        let age = (now - *created_at).num_seconds().max(0);
+        let rule = policy
+            .rules
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| rule.max_age_secs.is_none_or(|max_age| age < max_age));
+
+        let keep = match rule {
+            None => false,
+            Some((_, rule)) if rule.interval_secs <= 0 => true,
+            Some((index, rule)) => {
+                let bucket = created_at.timestamp().div_euclid(rule.interval_secs);
+                kept_buckets.insert((index, bucket))
+            }
+        };
+        if !keep {
+            pruned.insert(*id);
+        }
+    }
+
+    pruned
+}
+
+fn validate_policy(policy: &RetentionPolicy) -> AppResult<()> {
+    if policy.rules.is_empty() {
+        return Err(AppError::validation(
+            "A retention policy needs at least one rule",
+        ));
+    }
+
+    let mut previous_max_age = 0;
+    for (index, rule) in policy.rules.iter().enumerate() {
+        if rule.interval_secs < 0 {
+            return Err(AppError::validation(
+                "Retention intervals can't be negative",
+            ));
+        }
+        match rule.max_age_secs {
+            Some(max_age) if max_age <= previous_max_age => {
+                return Err(AppError::validation(
+                    "Retention rules must be ordered by increasing age",
+                ));
+            }
+            Some(max_age) => previous_max_age = max_age,
+            None if index + 1 < policy.rules.len() => {
+                return Err(AppError::validation(
+                    "Only the last retention rule can cover revisions of any age",
+                ));
+            }
+            None => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::open_test_db;
+    use crate::features::notes::models::NoteInput;
+    use crate::features::notes::repository::NoteRepository;
+    use chrono::{Duration, TimeZone};
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 6, 15, 12, 0, 0).unwrap()
+    }
+
+    // revisions with the given ages in seconds, newest first, numbered from 1
+    fn revisions(ages: &[i64]) -> Vec<(i64, DateTime<Utc>)> {
+        ages.iter()
+            .enumerate()
+            .map(|(index, age)| (index as i64 + 1, now() - Duration::seconds(*age)))
+            .collect()
+    }
+
+    fn pruned(revisions: &[(i64, DateTime<Utc>)], policy: &RetentionPolicy) -> Vec<i64> {
+        let mut ids: Vec<i64> = revisions_to_prune(revisions, policy, now())
+            .into_iter()
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn recent_revisions_are_all_kept() {
+        let revisions = revisions(&[60, 120, 30 * 60]);
+        assert!(pruned(&revisions, &default_policy()).is_empty());
+    }
+
+    #[test]
+    fn newest_revision_of_each_interval_is_kept() {
+        // two revisions in the same hour between one hour and a day old, the newest stays
+        let hour_start = now().timestamp().div_euclid(HOUR) * HOUR;
+        let base = now().timestamp() - hour_start + 3 * HOUR;
+        let revisions = revisions(&[base + 60, base + 120, base + HOUR + 60]);
+
+        assert_eq!(pruned(&revisions, &default_policy()), vec![2]);
+    }
+
+    #[test]
+    fn revisions_older_than_every_rule_are_dropped() {
+        let policy = RetentionPolicy {
+            enabled: true,
+            rules: vec![RetentionRule {
+                max_age_secs: Some(DAY),
+                interval_secs: 0,
+            }],
+        };
+        let revisions = revisions(&[HOUR, 2 * DAY, 10 * DAY]);
+
+        assert_eq!(pruned(&revisions, &policy), vec![2, 3]);
+    }
+
+    #[test]
+    fn policies_are_validated() {
+        assert!(validate_policy(&default_policy()).is_ok());
+
+        let empty = RetentionPolicy {
+            enabled: true,
+            rules: Vec::new(),
+        };
+        assert!(validate_policy(&empty).is_err());
+
+        let unordered = RetentionPolicy {
+            enabled: true,
+            rules: vec![
+                RetentionRule {
+                    max_age_secs: Some(DAY),
+                    interval_secs: 0,
+                },
+                RetentionRule {
+                    max_age_secs: Some(HOUR),
+                    interval_secs: 0,
+                },
+            ],
+        };
+        assert!(validate_policy(&unordered).is_err());
+
+        let open_ended_first = RetentionPolicy {
+            enabled: true,
+            rules: vec![
+                RetentionRule {
+                    max_age_secs: None,
+                    interval_secs: 0,
+                },
+                RetentionRule {
+                    max_age_secs: Some(DAY),
+                    interval_secs: 0,
+                },
+            ],
+        };
+        assert!(validate_policy(&open_ended_first).is_err());
+    }
+
+    #[test]
+    fn pruned_bytes_are_net_of_rewritten_revisions() {
+        let conn = open_test_db();
+        let input = |content: &str| NoteInput {
+            title: "Essay".to_string(),
+            content: content.to_string(),
+            folder_id: None,
+            tags: Vec::new(),
+            is_pinned: false,
+            is_archived: false,
+            color: None,
+        };
+        // text that barely compresses, so a keyframe of it is much bigger than a delta
+        let mut seed: u64 = 42;
+        let text: String = (0..4000)
+            .map(|_| {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                (b'a' + (seed >> 33) as u8 % 26) as char
+            })
+            .collect();
+
+        let note = NoteRepository::new(&conn).create(input(&text)).unwrap();
+        let revisions = RevisionRepository::new(&conn);
+        // both in one weekly interval, so only the newer one is kept. it's a delta
+        // against the older one and becomes a keyframe when that one goes
+        revisions
+            .insert(note, &input(&text), "2020-01-01T00:00:00+00:00")
+            .unwrap();
+        revisions
+            .insert(
+                note,
+                &input(&format!("{} more", text)),
+                "2020-01-01T00:10:00+00:00",
+            )
+            .unwrap();
+        let repository = RetentionRepository::new(&conn);
+        let before = repository.stored_size(Some(note)).unwrap();
+
+        let report = repository.apply(Some(note)).unwrap();
+
+        assert_eq!(report.revisions_pruned, 1);
+        let after = repository.stored_size(Some(note)).unwrap();
+        assert_eq!(report.bytes_pruned, before - after);
+        assert!(report.bytes_pruned < before / 2);
+    }
+}
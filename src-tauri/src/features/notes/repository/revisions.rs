@@ -0,0 +1,613 @@
+use super::notes::{into_input, NoteRepository};
+use crate::db::time::parse_timestamp;
+use crate::error::{AppError, AppResult, EntityKind, ErrorCode};
+use crate::features::notes::delta::{apply_delta, decode_keyframe, encode_delta, encode_keyframe};
+use crate::features::notes::diff::diff_text;
+use crate::features::notes::models::{
+    DiffTag, NoteInput, NoteRevision, RevisionDiff, RevisionField, RevisionMetadata,
+};
+use chrono::Utc;
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::PathBuf;
+
+// a new keyframe starts once a delta chain is this long, which bounds how many deltas
+// reading a revision has to apply
+const KEYFRAME_INTERVAL: usize = 20;
+
+const REVISION_COLUMNS: &str = "id, note_id, base_revision_id, data, metadata, created_at";
+
+// what restoring a revision rolls back when no fields are picked
+const ALL_FIELDS: &[RevisionField] = &[
+    RevisionField::Title,
+    RevisionField::Content,
+    RevisionField::Folder,
+    RevisionField::Tags,
+    RevisionField::Color,
+    RevisionField::Pinned,
+    RevisionField::Archived,
+];
+
+// raw column values of a `note_revisions` row
+struct RevisionRow {
+    id: i64,
+    note_id: i64,
+    base_revision_id: Option<i64>,
+    data: Vec<u8>,
+    metadata: Option<String>,
+    created_at: String,
+}
+
+impl RevisionRow {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(RevisionRow {
+            id: row.get(0)?,
+            note_id: row.get(1)?,
+            base_revision_id: row.get(2)?,
+            data: row.get(3)?,
+            metadata: row.get(4)?,
+            created_at: row.get(5)?,
+        })
+    }
+
+    fn into_revision(self, content: String) -> AppResult<NoteRevision> {
+        let metadata = self
+            .metadata
+            .map(|metadata| {
+                serde_json::from_str(&metadata).map_err(|e| {
+                    AppError::new(
+                        ErrorCode::Database,
+                        format!("Invalid metadata for revision {}: {}", self.id, e),
+                    )
+                })
+            })
+            .transpose()?;
+
+        Ok(NoteRevision {
+            id: self.id,
+            note_id: self.note_id,
+            content,
+            metadata,
+            created_at: parse_timestamp(&self.created_at, "created_at")?,
+        })
+    }
+}
+
+// how to store `content` after `previous`, the content and chain length of the revision
+// before it: as a delta when the chain has room and the delta comes out smaller, else as a
+// keyframe. returns whether it's a delta, and the data
+fn encode_revision(previous: Option<(&str, usize)>, content: &str) -> io::Result<(bool, Vec<u8>)> {
+    let keyframe = encode_keyframe(content)?;
+
+    match previous {
+        Some((base, chain_length)) if chain_length < KEYFRAME_INTERVAL => {
+            let delta = encode_delta(base, content)?;
+            if delta.len() < keyframe.len() {
+                Ok((true, delta))
+            } else {
+                Ok((false, keyframe))
+            }
+        }
+        _ => Ok((false, keyframe)),
+    }
+}
+
+// the content of every revision in `rows`, by id. every delta's base must be in `rows` too;
+// each revision is decoded once however many deltas build on it
+fn decode_all(rows: &[RevisionRow]) -> AppResult<HashMap<i64, String>> {
+    let by_id: HashMap<i64, &RevisionRow> = rows.iter().map(|row| (row.id, row)).collect();
+    let mut contents: HashMap<i64, String> = HashMap::new();
+
+    for row in rows {
+        // walk back to a keyframe or to a revision that's already decoded
+        let mut chain = Vec::new();
+        let mut current = Some(row);
+        while let Some(revision) = current.filter(|r| !contents.contains_key(&r.id)) {
+            if chain.len() > rows.len() {
+                return Err(corrupt_chain(row.id));
+            }
+            chain.push(revision);
+            current = match revision.base_revision_id {
+                Some(base_id) => Some(*by_id.get(&base_id).ok_or_else(|| corrupt_chain(row.id))?),
+                None => None,
+            };
+        }
+
+        for revision in chain.into_iter().rev() {
+            let content = match revision.base_revision_id {
+                Some(base_id) => apply_delta(&contents[&base_id], &revision.data)?,
+                None => decode_keyframe(&revision.data)?,
+            };
+            contents.insert(revision.id, content);
+        }
+    }
+
+    Ok(contents)
+}
+
+fn corrupt_chain(revision_id: i64) -> AppError {
+    AppError::new(
+        ErrorCode::Database,
+        format!("Revision {} has a broken delta chain", revision_id),
+    )
+}
+
+// move the plain-text revisions of schema version 5 into the encoded table, each note's
+// revisions in date order so each one is a delta against the one before
+pub fn encode_legacy_revisions(conn: &Connection) -> rusqlite::Result<Vec<PathBuf>> {
+    {
+        let mut select = conn.prepare(
+            "SELECT id, note_id, content, created_at
+             FROM note_revisions_legacy
+             ORDER BY note_id, created_at, id",
+        )?;
+        let mut rows = select.query([])?;
+
+        // (note id, revision id, content, chain length) of the revision just written
+        let mut previous: Option<(i64, i64, String, usize)> = None;
+
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            let note_id: i64 = row.get(1)?;
+            let content: String = row.get(2)?;
+            let created_at: String = row.get(3)?;
+
+            let base = previous.as_ref().filter(|p| p.0 == note_id);
+            let (is_delta, data) = encode_revision(base.map(|p| (p.2.as_str(), p.3)), &content)
+                .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+            let (base_id, chain_length) = match base {
+                Some(base) if is_delta => (Some(base.1), base.3 + 1),
+                _ => (None, 1),
+            };
+
+            conn.execute(
+                "INSERT INTO note_revisions (id, note_id, base_revision_id, data, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![id, note_id, base_id, data, created_at],
+            )?;
+            previous = Some((note_id, id, content, chain_length));
+        }
+    }
+
+    conn.execute_batch("DROP TABLE note_revisions_legacy")?;
+    Ok(Vec::new())
+}
+
+pub struct RevisionRepository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> RevisionRepository<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        RevisionRepository { conn }
+    }
+
+    // all revisions of a note, newest first
+    pub fn get_for_note(&self, note_id: i64) -> AppResult<Vec<NoteRevision>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT {} FROM note_revisions WHERE note_id = ? ORDER BY created_at DESC",
+                REVISION_COLUMNS
+            ))
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let revision_rows = stmt
+            .query_map(params![note_id], RevisionRow::from_row)
+            .map_err(|e| AppError::database("Failed to query revisions", e))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| AppError::database("Failed to process revision row", e))?;
+
+        let mut contents = decode_all(&revision_rows)?;
+
+        let mut revisions = Vec::new();
+        for revision_row in revision_rows {
+            let content = contents.remove(&revision_row.id).unwrap_or_default();
+            revisions.push(revision_row.into_revision(content)?);
+        }
+
+        Ok(revisions)
+    }
+
+    // save `content` as a revision of the note, with the note's other fields as they are now
+    pub fn create(&self, note_id: i64, content: &str) -> AppResult<i64> {
+        let mut snapshot = into_input(NoteRepository::new(self.conn).get_by_id(note_id)?);
+        snapshot.content = content.to_string();
+        let revision_id = self.insert(note_id, &snapshot, &Utc::now().to_rfc3339())?;
+
+        info!(
+            "Created revision for note ID: {} with revision ID: {}",
+            note_id, revision_id
+        );
+        Ok(revision_id)
+    }
+
+    // store the whole state of a note as a revision. the content is stored as a delta
+    // against the note's latest revision where that pays off
+    pub fn insert(&self, note_id: i64, snapshot: &NoteInput, created_at: &str) -> AppResult<i64> {
+        let content = snapshot.content.as_str();
+        let latest: Option<i64> = self
+            .conn
+            .query_row(
+                "SELECT id FROM note_revisions WHERE note_id = ? ORDER BY id DESC LIMIT 1",
+                params![note_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| AppError::database("Failed to get latest revision", e))?;
+
+        let previous = match latest {
+            Some(latest_id) => {
+                let chain = self.load_chain(latest_id)?;
+                let mut contents = decode_all(&chain)?;
+                Some((
+                    latest_id,
+                    contents.remove(&latest_id).unwrap_or_default(),
+                    chain.len(),
+                ))
+            }
+            None => None,
+        };
+
+        let (is_delta, data) = encode_revision(
+            previous
+                .as_ref()
+                .map(|(_, base, chain_length)| (base.as_str(), *chain_length)),
+            content,
+        )
+        .map_err(|e| AppError::io("Failed to compress revision", e))?;
+        let base_revision_id = previous.filter(|_| is_delta).map(|(id, _, _)| id);
+
+        let metadata = serde_json::to_string(&RevisionMetadata {
+            title: snapshot.title.clone(),
+            folder_id: snapshot.folder_id,
+            tags: snapshot.tags.clone(),
+            is_pinned: snapshot.is_pinned,
+            is_archived: snapshot.is_archived,
+            color: snapshot.color.clone(),
+        })
+        .map_err(|e| AppError::validation(format!("Failed to serialize revision: {}", e)))?;
+
+        self.conn
+            .execute(
+                "INSERT INTO note_revisions (
+                    note_id, base_revision_id, data, metadata, created_at
+                ) VALUES (
+                    ?1, ?2, ?3, ?4, ?5
+                )",
+                params![note_id, base_revision_id, data, metadata, created_at],
+            )
+            .map_err(|e| AppError::database("Failed to create revision", e))?;
+
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    // roll a note back to a revision, saving its current state as a new revision first.
+    // `fields` picks what to roll back; None means everything the revision has
+    pub fn restore(&self, revision_id: i64, fields: Option<&[RevisionField]>) -> AppResult<()> {
+        let revision = self.get_by_id(revision_id)?;
+        let note_id = revision.note_id;
+
+        let fields = match (fields, &revision.metadata) {
+            (Some(fields), _) => fields,
+            (None, Some(_)) => ALL_FIELDS,
+            (None, None) => &[RevisionField::Content],
+        };
+
+        let notes = NoteRepository::new(self.conn);
+        let mut input = into_input(notes.get_by_id(note_id)?);
+
+        for field in fields {
+            if *field == RevisionField::Content {
+                input.content = revision.content.clone();
+                continue;
+            }
+            let Some(metadata) = &revision.metadata else {
+                return Err(AppError::validation(format!(
+                    "Revision {} only kept the note's content",
+                    revision_id
+                )));
+            };
+
+            match field {
+                RevisionField::Title => input.title = metadata.title.clone(),
+                RevisionField::Folder => {
+                    input.folder_id = self.existing_folder(metadata.folder_id)?
+                }
+                RevisionField::Tags => input.tags = metadata.tags.clone(),
+                RevisionField::Color => input.color = metadata.color.clone(),
+                RevisionField::Pinned => input.is_pinned = metadata.is_pinned,
+                RevisionField::Archived => input.is_archived = metadata.is_archived,
+                RevisionField::Content => {}
+            }
+        }
+
+        notes.update(note_id, input, true)?;
+
+        info!(
+            "Restored revision ID: {} for note ID: {}",
+            revision_id, note_id
+        );
+        Ok(())
+    }
+
+    pub fn delete(&self, revision_id: i64) -> AppResult<()> {
+        // get the note ID for logging
+        let note_id: i64 = self
+            .conn
+            .query_row(
+                "SELECT note_id FROM note_revisions WHERE id = ?",
+                params![revision_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| {
+                AppError::lookup(
+                    EntityKind::Revision,
+                    revision_id,
+                    "Failed to get note ID for revision",
+                    e,
+                )
+            })?;
+
+        // delete the revision
+        self.remove(&[revision_id])?;
+
+        info!(
+            "Deleted revision ID: {} for note ID: {}",
+            revision_id, note_id
+        );
+        Ok(())
+    }
+
+    pub fn get_by_id(&self, revision_id: i64) -> AppResult<NoteRevision> {
+        let mut chain = self.load_chain(revision_id)?;
+        let mut contents = decode_all(&chain)?;
+
+        let revision_row = chain.swap_remove(0);
+        let content = contents.remove(&revision_id).unwrap_or_default();
+        revision_row.into_revision(content)
+    }
+
+    // delete revisions by id, returning how many were removed. revisions stored as deltas
+    // against a removed one are rewritten as keyframes first so they can still be read
+    pub fn remove(&self, revision_ids: &[i64]) -> AppResult<usize> {
+        if revision_ids.is_empty() {
+            return Ok(0);
+        }
+
+        // one savepoint for all the statements, which also works inside a caller's transaction
+        self.conn
+            .execute_batch("SAVEPOINT remove_revisions")
+            .map_err(|e| AppError::database("Failed to start removing revisions", e))?;
+
+        let result = self.remove_in_savepoint(revision_ids);
+
+        let end = if result.is_ok() {
+            "RELEASE remove_revisions"
+        } else {
+            "ROLLBACK TO remove_revisions; RELEASE remove_revisions"
+        };
+        self.conn
+            .execute_batch(end)
+            .map_err(|e| AppError::database("Failed to finish removing revisions", e))?;
+
+        result
+    }
+
+    fn remove_in_savepoint(&self, revision_ids: &[i64]) -> AppResult<usize> {
+        let removing: HashSet<i64> = revision_ids.iter().copied().collect();
+
+        // decode the revisions that build on removed ones while their chains are intact
+        let mut dependents: Vec<(i64, String)> = Vec::new();
+        for revision_id in revision_ids {
+            let mut stmt = self
+                .conn
+                .prepare_cached("SELECT id FROM note_revisions WHERE base_revision_id = ?")
+                .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+            let dependent_ids = stmt
+                .query_map(params![revision_id], |row| row.get::<_, i64>(0))
+                .map_err(|e| AppError::database("Failed to query revisions", e))?
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .map_err(|e| AppError::database("Failed to process revision row", e))?;
+
+            for dependent_id in dependent_ids {
+                if !removing.contains(&dependent_id) {
+                    let chain = self.load_chain(dependent_id)?;
+                    let mut contents = decode_all(&chain)?;
+                    let content = contents.remove(&dependent_id).unwrap_or_default();
+                    dependents.push((dependent_id, content));
+                }
+            }
+        }
+
+        for (dependent_id, content) in dependents {
+            let data = encode_keyframe(&content)
+                .map_err(|e| AppError::io("Failed to compress revision", e))?;
+            self.conn
+                .execute(
+                    "UPDATE note_revisions SET base_revision_id = NULL, data = ? WHERE id = ?",
+                    params![data, dependent_id],
+                )
+                .map_err(|e| AppError::database("Failed to rewrite revision", e))?;
+        }
+
+        // removed revisions can build on each other, so unlink them all before deleting any
+        for revision_id in revision_ids {
+            self.conn
+                .execute(
+                    "UPDATE note_revisions SET base_revision_id = NULL WHERE id = ?",
+                    params![revision_id],
+                )
+                .map_err(|e| AppError::database("Failed to delete revision", e))?;
+        }
+
+        let mut removed = 0;
+        for revision_id in revision_ids {
+            removed += self
+                .conn
+                .execute(
+                    "DELETE FROM note_revisions WHERE id = ?",
+                    params![revision_id],
+                )
+                .map_err(|e| AppError::database("Failed to delete revision", e))?;
+        }
+
+        Ok(removed)
+    }
+
+    // replace the content of every revision of a note, leaving each one a keyframe
+    pub fn overwrite_for_note(&self, note_id: i64, content: &str) -> AppResult<()> {
+        let data =
+            encode_keyframe(content).map_err(|e| AppError::io("Failed to compress revision", e))?;
+
+        self.conn
+            .execute(
+                "UPDATE note_revisions SET base_revision_id = NULL, data = ? WHERE note_id = ?",
+                params![data, note_id],
+            )
+            .map_err(|e| AppError::database("Failed to overwrite revisions", e))?;
+
+        Ok(())
+    }
+
+    // what changed from one revision to another of the same note, or, without `to`, from the
+    // revision to the note's current content
+    pub fn diff(
+        &self,
+        from_revision_id: i64,
+        to_revision_id: Option<i64>,
+        context_lines: usize,
+    ) -> AppResult<RevisionDiff> {
+        let from = self.get_by_id(from_revision_id)?;
+
+        let new_content = match to_revision_id {
+            Some(to_revision_id) => {
+                let to = self.get_by_id(to_revision_id)?;
+                if to.note_id != from.note_id {
+                    return Err(AppError::validation(format!(
+                        "Revisions {} and {} belong to different notes",
+                        from_revision_id, to_revision_id
+                    )));
+                }
+                to.content
+            }
+            None => self
+                .conn
+                .query_row(
+                    "SELECT content FROM notes WHERE id = ?",
+                    params![from.note_id],
+                    |row| row.get(0),
+                )
+                .map_err(|e| {
+                    AppError::lookup(
+                        EntityKind::Note,
+                        from.note_id,
+                        "Failed to get current note content",
+                        e,
+                    )
+                })?,
+        };
+
+        let hunks = diff_text(&from.content, &new_content, context_lines);
+        let count = |tag: DiffTag| {
+            hunks
+                .iter()
+                .flat_map(|hunk| &hunk.lines)
+                .filter(|line| line.tag == tag)
+                .count()
+        };
+
+        Ok(RevisionDiff {
+            note_id: from.note_id,
+            from_revision_id,
+            to_revision_id,
+            insertions: count(DiffTag::Insert),
+            deletions: count(DiffTag::Delete),
+            hunks,
+        })
+    }
+
+    // delete all but the newest keep_count revisions of a note, returning how many were removed
+    pub fn clean_old(&self, note_id: i64, keep_count: u32) -> AppResult<u32> {
+        // everything past the newest keep_count
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id FROM note_revisions
+                 WHERE note_id = ?
+                 ORDER BY created_at DESC, id DESC
+                 LIMIT -1 OFFSET ?",
+            )
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+        let old_ids = stmt
+            .query_map(params![note_id, keep_count], |row| row.get::<_, i64>(0))
+            .map_err(|e| AppError::database("Failed to query revisions", e))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| AppError::database("Failed to process revision row", e))?;
+
+        let deleted_count = self.remove(&old_ids)?;
+
+        info!(
+            "Cleaned {} old revisions for note ID: {}",
+            deleted_count, note_id
+        );
+        Ok(deleted_count as u32)
+    }
+
+    // the folder a revision was in, or the root if that folder has since been deleted or
+    // moved to the trash
+    fn existing_folder(&self, folder_id: Option<i64>) -> AppResult<Option<i64>> {
+        let Some(folder_id) = folder_id else {
+            return Ok(None);
+        };
+
+        let exists: bool = self
+            .conn
+            .query_row(
+                "SELECT EXISTS (
+                    SELECT 1 FROM note_folders WHERE id = ? AND deleted_at IS NULL
+                )",
+                params![folder_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| AppError::database("Failed to check folder", e))?;
+
+        Ok(exists.then_some(folder_id))
+    }
+
+    // a revision followed by the revisions its delta builds on, back to a keyframe
+    fn load_chain(&self, revision_id: i64) -> AppResult<Vec<RevisionRow>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached(&format!(
+                "SELECT {} FROM note_revisions WHERE id = ?",
+                REVISION_COLUMNS
+            ))
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let mut chain: Vec<RevisionRow> = Vec::new();
+        let mut next = Some(revision_id);
+        while let Some(id) = next {
+            let revision_row = stmt
+                .query_row(params![id], RevisionRow::from_row)
+                .map_err(|e| match chain.first() {
+                    // a missing base is damage, not a bad id from the caller
+                    Some(first) => match e {
+                        rusqlite::Error::QueryReturnedNoRows => corrupt_chain(first.id),
+                        e => AppError::database("Failed to get revision", e),
+                    },
+                    None => AppError::lookup(EntityKind::Revision, id, "Failed to get revision", e),
+                })?;
+
+            next = revision_row.base_revision_id;
+            chain.push(revision_row);
+            if chain.len() > KEYFRAME_INTERVAL * 1000 {
+                return Err(corrupt_chain(revision_id));
+            }
+        }
+
+        Ok(chain)
+    }
+}
@@ -0,0 +1,430 @@
+use crate::db::settings::SettingsRepository;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::features::notes::models::{
+    AttachmentGcReport, AttachmentQuota, FolderStorageUsage, NoteStorageUsage, StorageUsage,
+};
+use crate::features::notes::repository::blobs::{Blob, BlobStore, BLOBS_DIR};
+use crate::features::notes::repository::thumbnails::THUMBNAILS_DIR;
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+// app_settings key the quota is saved under
+const QUOTA_KEY: &str = "attachment_quota";
+
+// the files directly in a directory under the app data directory, with their sizes. names
+// starting with a dot are temporary files, which uploads clean up themselves
+fn stored_files(app_data_dir: &Path, dir: &str) -> AppResult<Vec<(String, u64)>> {
+    let full_dir = app_data_dir.join(dir);
+    if !full_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut files = Vec::new();
+    let entries = fs::read_dir(&full_dir)
+        .map_err(|e| AppError::io("Failed to read attachments directory", e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| AppError::io("Failed to read attachments directory", e))?;
+        let metadata = entry
+            .metadata()
+            .map_err(|e| AppError::io("Failed to read file metadata", e))?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if metadata.is_file() && !name.starts_with('.') {
+            files.push((name, metadata.len()));
+        }
+    }
+    Ok(files)
+}
+
+// a folder's usage, started at zero the first time it's needed
+fn folder_entry<'m>(
+    usage: &'m mut HashMap<Option<i64>, FolderStorageUsage>,
+    folders: &HashMap<i64, (Option<i64>, String)>,
+    folder_id: Option<i64>,
+) -> &'m mut FolderStorageUsage {
+    usage
+        .entry(folder_id)
+        .or_insert_with(|| FolderStorageUsage {
+            folder_id,
+            name: folder_id
+                .and_then(|id| folders.get(&id))
+                .map(|(_, name)| name.clone())
+                .unwrap_or_default(),
+            attachment_count: 0,
+            bytes: 0,
+            total_bytes: 0,
+        })
+}
+
+// how much room attachments take, and the limits on it
+pub struct StorageRepository<'a> {
+    conn: &'a Connection,
+    app_data_dir: &'a Path,
+}
+
+impl<'a> StorageRepository<'a> {
+    pub fn new(conn: &'a Connection, app_data_dir: &'a Path) -> Self {
+        StorageRepository { conn, app_data_dir }
+    }
+
+    // the saved quota; no limits unless one was set
+    pub fn quota(&self) -> AppResult<AttachmentQuota> {
+        Ok(SettingsRepository::new(self.conn)
+            .get(QUOTA_KEY)?
+            .unwrap_or_default())
+    }
+
+    pub fn set_quota(&self, quota: &AttachmentQuota) -> AppResult<()> {
+        if quota.per_note_bytes == Some(0) || quota.total_bytes == Some(0) {
+            return Err(AppError::validation(
+                "A quota must allow at least one byte; leave it unset for no limit",
+            ));
+        }
+        SettingsRepository::new(self.conn).set(QUOTA_KEY, quota)?;
+
+        info!("Updated attachment quota");
+        Ok(())
+    }
+
+    // whether a note can get an attachment of a stored blob. content that was already
+    // stored takes no more room, so it only counts towards the note's own quota
+    pub fn check_quota(&self, note_id: i64, blob: &Blob) -> AppResult<()> {
+        let quota = self.quota()?;
+
+        if let Some(limit) = quota.per_note_bytes {
+            let used = self.sum(
+                "SELECT COALESCE(SUM(file_size), 0) FROM note_attachments
+                 WHERE note_id = ? AND is_linked = 0",
+                params![note_id],
+            )?;
+            if used + blob.file_size as u64 > limit {
+                return Err(AppError::new(
+                    ErrorCode::QuotaExceeded,
+                    format!(
+                        "The attachments of note {} would take {} bytes, more than the {} allowed",
+                        note_id,
+                        used + blob.file_size as u64,
+                        limit
+                    ),
+                ));
+            }
+        }
+
+        // a blob without references was stored for this attachment, and is already counted
+        if let Some(limit) = quota.total_bytes.filter(|_| blob.ref_count == 0) {
+            let used = self.sum(
+                "SELECT COALESCE(SUM(file_size), 0) FROM attachment_blobs",
+                [],
+            )?;
+            if used > limit {
+                return Err(AppError::new(
+                    ErrorCode::QuotaExceeded,
+                    format!(
+                        "Attachments would take {} bytes, more than the {} allowed",
+                        used, limit
+                    ),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    // attachment storage per note and per folder, biggest first
+    pub fn usage(&self) -> AppResult<StorageUsage> {
+        let stored_bytes = self.sum(
+            "SELECT COALESCE(SUM(file_size), 0) FROM attachment_blobs",
+            [],
+        )?;
+
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT n.id, n.title, n.folder_id, COUNT(a.id), SUM(a.file_size)
+                 FROM notes n
+                 JOIN note_attachments a ON a.note_id = n.id AND a.is_linked = 0
+                 WHERE n.deleted_at IS NULL
+                 GROUP BY n.id
+                 ORDER BY SUM(a.file_size) DESC, n.id",
+            )
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+        let notes = stmt
+            .query_map([], |row| {
+                Ok(NoteStorageUsage {
+                    note_id: row.get(0)?,
+                    title: row.get(1)?,
+                    folder_id: row.get(2)?,
+                    attachment_count: row.get::<_, i64>(3)? as usize,
+                    bytes: row.get::<_, i64>(4)? as u64,
+                })
+            })
+            .map_err(|e| AppError::database("Failed to query attachment usage", e))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| AppError::database("Failed to process attachment usage", e))?;
+
+        let folders = self.folder_usage(&notes)?;
+
+        Ok(StorageUsage {
+            stored_bytes,
+            attachment_count: notes.iter().map(|note| note.attachment_count).sum(),
+            quota: self.quota()?,
+            notes,
+            folders,
+        })
+    }
+
+    fn folder_usage(&self, notes: &[NoteStorageUsage]) -> AppResult<Vec<FolderStorageUsage>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, parent_id, name FROM note_folders WHERE deleted_at IS NULL")
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+        let folders: HashMap<i64, (Option<i64>, String)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))
+            .map_err(|e| AppError::database("Failed to query folders", e))?
+            .collect::<rusqlite::Result<_>>()
+            .map_err(|e| AppError::database("Failed to process folder row", e))?;
+
+        let mut usage = HashMap::new();
+
+        for note in notes {
+            // a note left in a trashed folder counts at the top level
+            let folder_id = note.folder_id.filter(|id| folders.contains_key(id));
+            let direct = folder_entry(&mut usage, &folders, folder_id);
+            direct.attachment_count += note.attachment_count;
+            direct.bytes += note.bytes;
+            direct.total_bytes += note.bytes;
+
+            // every folder above counts it too. the walk stops after as many steps as there
+            // are folders, in case the parent links ever form a loop
+            let mut parent = folder_id
+                .and_then(|id| folders.get(&id))
+                .and_then(|(parent_id, _)| *parent_id);
+            for _ in 0..folders.len() {
+                let Some(folder_id) = parent.filter(|id| folders.contains_key(id)) else {
+                    break;
+                };
+                folder_entry(&mut usage, &folders, Some(folder_id)).total_bytes += note.bytes;
+                parent = folders
+                    .get(&folder_id)
+                    .and_then(|(parent_id, _)| *parent_id);
+            }
+        }
+
+        let mut usage: Vec<FolderStorageUsage> = usage.into_values().collect();
+        usage.sort_by_key(|folder| (std::cmp::Reverse(folder.total_bytes), folder.folder_id));
+        Ok(usage)
+    }
+
+    // reconcile the attachments directory with the database both ways: delete files no
+    // row refers to and blobs no attachment refers to any more, such as those of purged
+    // notes, and report attachments whose file is gone. a dry run only reports
+    pub fn collect_garbage(&self, dry_run: bool) -> AppResult<AttachmentGcReport> {
+        let mut report = AttachmentGcReport {
+            dry_run,
+            ..Default::default()
+        };
+
+        report.ref_counts_fixed = self.fix_ref_counts(dry_run)?;
+
+        // (hash, file_path, file_size, references)
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT b.hash, b.file_path, b.file_size,
+                        (SELECT COUNT(*) FROM note_attachments a WHERE a.blob_hash = b.hash)
+                 FROM attachment_blobs b",
+            )
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+        let blobs = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            })
+            .map_err(|e| AppError::database("Failed to query attachment blobs", e))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| AppError::database("Failed to process attachment blob", e))?;
+
+        let mut referenced_files = HashSet::new();
+        let mut live_hashes = HashSet::new();
+        let mut unreferenced = Vec::new();
+        for (hash, file_path, file_size, references) in blobs {
+            referenced_files.insert(file_path.clone());
+            if references > 0 {
+                live_hashes.insert(hash);
+            } else {
+                if self.app_data_dir.join(&file_path).exists() {
+                    report.bytes_freed += file_size as u64;
+                }
+                unreferenced.push(hash);
+            }
+        }
+
+        // attachments from before blobs, whose file went missing then, keep their own path.
+        // linked files are outside the directory, and checked by LinkedFileRepository
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, file_path FROM note_attachments WHERE is_linked = 0 ORDER BY id")
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+        let attachments = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|e| AppError::database("Failed to query attachments", e))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| AppError::database("Failed to process attachment row", e))?;
+        for (id, file_path) in attachments {
+            if !self.app_data_dir.join(&file_path).exists() {
+                report.missing_files.push(id);
+            }
+            referenced_files.insert(file_path);
+        }
+
+        for (name, size) in stored_files(self.app_data_dir, BLOBS_DIR)? {
+            let file_path = format!("{}/{}", BLOBS_DIR, name);
+            if referenced_files.contains(&file_path) {
+                continue;
+            }
+            if !dry_run {
+                fs::remove_file(self.app_data_dir.join(&file_path))
+                    .map_err(|e| AppError::io("Failed to delete orphaned attachment", e))?;
+            }
+            report.bytes_freed += size;
+            report.orphaned_files.push(file_path);
+        }
+
+        // thumbnails are named {hash}_{size}.png
+        for (name, size) in stored_files(self.app_data_dir, THUMBNAILS_DIR)? {
+            let hash = name.split('_').next().unwrap_or_default();
+            if live_hashes.contains(hash) {
+                continue;
+            }
+            if !dry_run {
+                let full_path = self.app_data_dir.join(THUMBNAILS_DIR).join(&name);
+                if let Err(e) = fs::remove_file(&full_path) {
+                    warn!("Failed to delete thumbnail {:?}: {}", full_path, e);
+                    continue;
+                }
+            }
+            report.bytes_freed += size;
+            report.orphaned_thumbnails += 1;
+        }
+
+        report.unreferenced_blobs = unreferenced.len();
+        if !dry_run {
+            let blob_store = BlobStore::new(self.conn, self.app_data_dir);
+            for hash in &unreferenced {
+                blob_store.release(hash)?;
+            }
+        }
+
+        info!(
+            "Attachment garbage collection{}: {} orphaned files, {} unreferenced blobs, {} \
+             orphaned thumbnails, {} bytes freed, {} attachments missing their file",
+            if dry_run { " (dry run)" } else { "" },
+            report.orphaned_files.len(),
+            report.unreferenced_blobs,
+            report.orphaned_thumbnails,
+            report.bytes_freed,
+            report.missing_files.len()
+        );
+        Ok(report)
+    }
+
+    // ref_count is kept by triggers, so it can only drift through edits made outside the
+    // app; count what refers to each blob again
+    fn fix_ref_counts(&self, dry_run: bool) -> AppResult<usize> {
+        let actual = "(SELECT COUNT(*) FROM note_attachments a
+                       WHERE a.blob_hash = attachment_blobs.hash)";
+
+        let drifted = if dry_run {
+            self.sum(
+                &format!(
+                    "SELECT COUNT(*) FROM attachment_blobs WHERE ref_count != {}",
+                    actual
+                ),
+                [],
+            )? as usize
+        } else {
+            self.conn
+                .execute(
+                    &format!(
+                        "UPDATE attachment_blobs SET ref_count = {0} WHERE ref_count != {0}",
+                        actual
+                    ),
+                    [],
+                )
+                .map_err(|e| AppError::database("Failed to fix attachment references", e))?
+        };
+
+        if drifted > 0 {
+            warn!("{} attachment blobs had a wrong reference count", drifted);
+        }
+        Ok(drifted)
+    }
+
+    fn sum(&self, sql: &str, params: impl rusqlite::Params) -> AppResult<u64> {
+        self.conn
+            .query_row(sql, params, |row| row.get::<_, i64>(0))
+            .map(|value| value as u64)
+            .map_err(|e| AppError::database("Failed to measure attachment storage", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::{create_note, note_input, open_test_db, TestDir};
+    use crate::features::notes::models::NoteInput;
+    use crate::features::notes::repository::{
+        AttachmentRepository, FolderRepository, NoteRepository,
+    };
+    use chrono::Utc;
+
+    #[test]
+    fn usage_leaves_out_the_trash() {
+        let conn = open_test_db();
+        let dir = TestDir::create();
+        let folders = FolderRepository::new(&conn);
+        let projects = folders.create("Projects", None, None).unwrap();
+        let apto = folders.create("Apto", Some(projects), None).unwrap();
+        let attachments = AttachmentRepository::new(&conn, dir.path());
+
+        let live = create_note(
+            &conn,
+            NoteInput {
+                folder_id: Some(apto),
+                ..note_input("Live", "", &[])
+            },
+        );
+        attachments
+            .add_data(live, "a.txt", "text/plain", b"12345")
+            .unwrap();
+        let trashed = create_note(&conn, note_input("Trashed", "", &[]));
+        attachments
+            .add_data(trashed, "b.txt", "text/plain", b"123")
+            .unwrap();
+        NoteRepository::new(&conn).delete(trashed).unwrap();
+        conn.execute(
+            "UPDATE note_folders SET deleted_at = ? WHERE id = ?",
+            params![Utc::now().to_rfc3339(), projects],
+        )
+        .unwrap();
+
+        let usage = StorageRepository::new(&conn, dir.path()).usage().unwrap();
+
+        assert_eq!(usage.stored_bytes, 8);
+        assert_eq!(usage.attachment_count, 1);
+        assert_eq!(usage.notes.len(), 1);
+        assert_eq!(usage.notes[0].note_id, live);
+        assert_eq!(usage.folders.len(), 1);
+        assert_eq!(usage.folders[0].folder_id, Some(apto));
+        assert_eq!(usage.folders[0].name, "Apto");
+        assert_eq!(usage.folders[0].total_bytes, 5);
+    }
+}
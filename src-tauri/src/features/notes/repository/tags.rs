@@ -0,0 +1,228 @@
+use crate::error::{AppError, AppResult, EntityKind};
+use crate::features::notes::models::NoteTag;
+use log::info;
+use rusqlite::{params, Connection};
+
+pub struct NoteTagRepository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> NoteTagRepository<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        NoteTagRepository { conn }
+    }
+
+    // create a tag, or return the id of the existing tag with that name
+    pub fn create(&self, name: &str, color: Option<String>) -> AppResult<i64> {
+        // insert, but silently handle unique constraint violations
+        let changes = self
+            .conn
+            .execute(
+                "INSERT OR IGNORE INTO note_tags (name, color) VALUES (?, ?)",
+                params![name, color],
+            )
+            .map_err(|e| AppError::database("Failed to create note tag", e))?;
+
+        if changes > 0 {
+            // new tag created
+            let tag_id = self.conn.last_insert_rowid();
+            info!("Created note tag '{}' with ID: {}", name, tag_id);
+            Ok(tag_id)
+        } else {
+            // tag exists, get its ID
+            let tag_id: i64 = self
+                .conn
+                .query_row(
+                    "SELECT id FROM note_tags WHERE name = ?",
+                    params![name],
+                    |row| row.get(0),
+                )
+                .map_err(|e| AppError::database("Failed to get existing tag ID", e))?;
+
+            info!("Using existing note tag '{}' with ID: {}", name, tag_id);
+            Ok(tag_id)
+        }
+    }
+
+    pub fn get_all(&self) -> AppResult<Vec<NoteTag>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, name, color FROM note_tags")
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let tags_rows = stmt
+            .query_map([], |row| {
+                Ok(NoteTag {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    color: row.get(2)?,
+                })
+            })
+            .map_err(|e| AppError::database("Failed to query tags", e))?;
+
+        let mut tags = Vec::new();
+        for tag_result in tags_rows {
+            tags.push(tag_result.map_err(|e| AppError::database("Failed to process tag row", e))?);
+        }
+
+        Ok(tags)
+    }
+
+    pub fn update(&self, id: i64, name: &str, color: Option<String>) -> AppResult<()> {
+        // check for unique constraint before updating
+        let existing_id: Result<i64, rusqlite::Error> = self.conn.query_row(
+            "SELECT id FROM note_tags WHERE name = ? AND id != ?",
+            params![name, id],
+            |row| row.get(0),
+        );
+
+        if existing_id.is_ok() {
+            return Err(AppError::conflict(
+                EntityKind::NoteTag,
+                Some(id),
+                format!("Tag name '{}' already exists", name),
+            ));
+        }
+
+        // update the tag
+        let changed = self
+            .conn
+            .execute(
+                "UPDATE note_tags SET name = ?, color = ? WHERE id = ?",
+                params![name, color, id],
+            )
+            .map_err(|e| AppError::database("Failed to update note tag", e))?;
+
+        if changed == 0 {
+            return Err(AppError::not_found(EntityKind::NoteTag, id));
+        }
+
+        info!("Updated note tag with ID: {}", id);
+        Ok(())
+    }
+
+    // only tags that no note uses can be deleted
+    pub fn delete(&self, id: i64) -> AppResult<()> {
+        // check if the tag is used in any notes
+        let usage_count: i64 = self
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM note_tag_mappings WHERE tag_id = ?",
+                params![id],
+                |row| row.get(0),
+            )
+            .map_err(|e| AppError::database("Failed to check tag usage", e))?;
+
+        if usage_count > 0 {
+            return Err(AppError::conflict(
+                EntityKind::NoteTag,
+                Some(id),
+                format!("Cannot delete tag: it is used by {} notes", usage_count),
+            ));
+        }
+
+        // delete the tag
+        let changed = self
+            .conn
+            .execute("DELETE FROM note_tags WHERE id = ?", params![id])
+            .map_err(|e| AppError::database("Failed to delete note tag", e))?;
+
+        if changed == 0 {
+            return Err(AppError::not_found(EntityKind::NoteTag, id));
+        }
+
+        info!("Deleted note tag with ID: {}", id);
+        Ok(())
+    }
+
+    // ids of the notes carrying a tag
+    pub fn get_note_ids_by_tag(&self, tag_name: &str) -> AppResult<Vec<i64>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT m.note_id
+                 FROM note_tag_mappings m
+                 JOIN note_tags t ON m.tag_id = t.id
+                 JOIN notes n ON n.id = m.note_id AND n.deleted_at IS NULL
+                 WHERE t.name = ?",
+            )
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let note_rows = stmt
+            .query_map(params![tag_name], |row| row.get::<_, i64>(0))
+            .map_err(|e| AppError::database("Failed to query notes by tag", e))?;
+
+        let mut note_ids = Vec::new();
+        for note_id_result in note_rows {
+            note_ids.push(
+                note_id_result.map_err(|e| AppError::database("Failed to process note ID", e))?,
+            );
+        }
+
+        Ok(note_ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::{create_note, note_input, open_test_db};
+    use crate::error::ErrorCode;
+    use crate::features::notes::repository::NoteRepository;
+
+    #[test]
+    fn create_returns_the_existing_tag() {
+        let conn = open_test_db();
+        let tags = NoteTagRepository::new(&conn);
+
+        let id = tags.create("work", None).unwrap();
+        assert_eq!(tags.create("work", Some("#fff".to_string())).unwrap(), id);
+        assert_eq!(tags.get_all().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn rename_to_a_taken_name_conflicts() {
+        let conn = open_test_db();
+        let tags = NoteTagRepository::new(&conn);
+        tags.create("work", None).unwrap();
+        let home = tags.create("home", None).unwrap();
+
+        assert_eq!(
+            tags.update(home, "work", None).unwrap_err().code,
+            ErrorCode::Conflict
+        );
+        assert_eq!(
+            tags.update(99, "other", None).unwrap_err().code,
+            ErrorCode::NotFound
+        );
+    }
+
+    #[test]
+    fn tags_in_use_cant_be_deleted() {
+        let conn = open_test_db();
+        create_note(&conn, note_input("Tagged", "", &["work"]));
+        let tags = NoteTagRepository::new(&conn);
+        let id = tags.get_all().unwrap()[0].id;
+
+        assert_eq!(tags.delete(id).unwrap_err().code, ErrorCode::Conflict);
+
+        let unused = tags.create("unused", None).unwrap();
+        tags.delete(unused).unwrap();
+    }
+
+    #[test]
+    fn notes_by_tag() {
+        let conn = open_test_db();
+        let first = create_note(&conn, note_input("First", "", &["work"]));
+        create_note(&conn, note_input("Second", "", &["home"]));
+        let third = create_note(&conn, note_input("Third", "", &["work", "home"]));
+        let trashed = create_note(&conn, note_input("Trashed", "", &["work"]));
+        NoteRepository::new(&conn).delete(trashed).unwrap();
+
+        let mut ids = NoteTagRepository::new(&conn)
+            .get_note_ids_by_tag("work")
+            .unwrap();
+        ids.sort();
+        assert_eq!(ids, vec![first, third]);
+    }
+}
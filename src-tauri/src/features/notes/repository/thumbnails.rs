@@ -0,0 +1,264 @@
+use crate::error::{AppError, AppResult, EntityKind, ErrorCode};
+use crate::features::notes::models::{AttachmentThumbnail, ThumbnailReport};
+use crate::features::notes::thumbnails::{load_picture, supports, write_thumbnail, ThumbnailError};
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// directory thumbnails are kept in, relative to the app data directory
+pub const THUMBNAILS_DIR: &str = "attachment_thumbnails";
+
+// the only sizes kept on disk. a request is served the smallest one at least as big, so
+// asking for arbitrary sizes can't fill the disk with thumbnails
+pub const THUMBNAIL_SIZES: [u32; 4] = [128, 256, 512, 1024];
+
+// sizes made as soon as an attachment is added; the others are made the first time they're
+// asked for
+pub const PREGENERATED_SIZES: [u32; 2] = [128, 512];
+
+const MIN_SIZE: u32 = 16;
+const MAX_SIZE: u32 = 2048;
+
+fn snap_size(size: u32) -> u32 {
+    THUMBNAIL_SIZES
+        .into_iter()
+        .find(|snapped| *snapped >= size)
+        .unwrap_or(THUMBNAIL_SIZES[THUMBNAIL_SIZES.len() - 1])
+}
+
+// thumbnails belong to a blob, so attachments that share a file share its thumbnails too
+fn thumbnail_path(hash: &str, size: u32) -> String {
+    format!("{}/{}_{}.png", THUMBNAILS_DIR, hash, size)
+}
+
+// delete every size of a blob's thumbnails
+pub fn remove_thumbnails(app_data_dir: &Path, hash: &str) -> io::Result<()> {
+    let dir = app_data_dir.join(THUMBNAILS_DIR);
+    if !dir.exists() {
+        return Ok(());
+    }
+
+    let prefix = format!("{}_", hash);
+    for entry in fs::read_dir(&dir)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with(&prefix) {
+            fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+fn thumbnail_error(e: ThumbnailError) -> AppError {
+    AppError::new(ErrorCode::Io, format!("Failed to make thumbnail: {}", e))
+}
+
+pub struct ThumbnailRepository<'a> {
+    conn: &'a Connection,
+    app_data_dir: &'a Path,
+}
+
+impl<'a> ThumbnailRepository<'a> {
+    pub fn new(conn: &'a Connection, app_data_dir: &'a Path) -> Self {
+        ThumbnailRepository { conn, app_data_dir }
+    }
+
+    // a thumbnail fitting in a square of one of THUMBNAIL_SIZES, the smallest at least
+    // `size` pixels, made now if it wasn't yet. None for attachments of a type without
+    // thumbnails
+    pub fn get(&self, attachment_id: i64, size: u32) -> AppResult<Option<AttachmentThumbnail>> {
+        if !(MIN_SIZE..=MAX_SIZE).contains(&size) {
+            return Err(AppError::validation(format!(
+                "Thumbnail size must be between {} and {} pixels",
+                MIN_SIZE, MAX_SIZE
+            )));
+        }
+
+        let (blob, mime_type): (Option<(String, String)>, String) = self
+            .conn
+            .query_row(
+                "SELECT b.hash, b.file_path, a.file_type
+                 FROM note_attachments a
+                 LEFT JOIN attachment_blobs b ON b.hash = a.blob_hash
+                 WHERE a.id = ?",
+                params![attachment_id],
+                |row| {
+                    let hash: Option<String> = row.get(0)?;
+                    let file_path: Option<String> = row.get(1)?;
+                    Ok((hash.zip(file_path), row.get(2)?))
+                },
+            )
+            .map_err(|e| {
+                AppError::lookup(
+                    EntityKind::Attachment,
+                    attachment_id,
+                    "Failed to get attachment",
+                    e,
+                )
+            })?;
+
+        // attachments without a blob have no file to make one from
+        let Some((hash, blob_path)) = blob else {
+            return Ok(None);
+        };
+        if !supports(&mime_type) {
+            return Ok(None);
+        }
+
+        let size = snap_size(size);
+        let file_path = thumbnail_path(&hash, size);
+        let full_path = self.app_data_dir.join(&file_path);
+
+        let (width, height) = match imagesize::size(&full_path) {
+            Ok(existing) => (existing.width as u32, existing.height as u32),
+            Err(_) => {
+                let result = load_picture(&self.app_data_dir.join(&blob_path), &mime_type)
+                    .and_then(|picture| {
+                        create_dir(self.app_data_dir)?;
+                        write_thumbnail(&picture, size, &full_path)
+                    });
+                match result {
+                    Ok(dimensions) => dimensions,
+                    Err(ThumbnailError::Unsupported) => return Ok(None),
+                    Err(e) => return Err(thumbnail_error(e)),
+                }
+            }
+        };
+
+        Ok(Some(AttachmentThumbnail {
+            attachment_id,
+            file_path,
+            width,
+            height,
+        }))
+    }
+
+    // drop every thumbnail and make the usual sizes again for every stored file
+    pub fn regenerate_all(&self) -> AppResult<ThumbnailReport> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT b.hash, b.file_path, MIN(a.file_type)
+                 FROM attachment_blobs b
+                 JOIN note_attachments a ON a.blob_hash = b.hash
+                 GROUP BY b.hash",
+            )
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let blobs = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })
+            .map_err(|e| AppError::database("Failed to query attachments", e))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| AppError::database("Failed to process attachment row", e))?;
+
+        let mut report = ThumbnailReport::default();
+        for (hash, blob_path, mime_type) in blobs {
+            remove_thumbnails(self.app_data_dir, &hash)
+                .map_err(|e| AppError::io("Failed to delete thumbnails", e))?;
+
+            let source = self.app_data_dir.join(&blob_path);
+            match generate(self.app_data_dir, &hash, &source, &mime_type, true) {
+                Ok(()) => report.generated += 1,
+                Err(ThumbnailError::Unsupported) => report.unsupported += 1,
+                Err(e) => {
+                    warn!("No thumbnail for attachment blob {}: {}", hash, e);
+                    report.failed += 1;
+                }
+            }
+        }
+
+        info!(
+            "Regenerated thumbnails: {} generated, {} unsupported, {} failed",
+            report.generated, report.unsupported, report.failed
+        );
+        Ok(report)
+    }
+}
+
+fn create_dir(app_data_dir: &Path) -> Result<(), ThumbnailError> {
+    fs::create_dir_all(app_data_dir.join(THUMBNAILS_DIR))
+        .map_err(|e| ThumbnailError::Failed(e.to_string()))
+}
+
+// make the usual sizes for content about to be stored, from `source` wherever it is now.
+// thumbnails are only a convenience, so a file they can't be made from is logged and
+// otherwise ignored
+pub fn pregenerate_thumbnails(app_data_dir: &Path, hash: &str, source: &Path, mime_type: &str) {
+    if !supports(mime_type) {
+        return;
+    }
+
+    if let Err(e) = generate(app_data_dir, hash, source, mime_type, false) {
+        warn!("No thumbnail for attachment blob {}: {}", hash, e);
+    }
+}
+
+// write the pregenerated sizes, decoding the file once for all of them
+fn generate(
+    app_data_dir: &Path,
+    hash: &str,
+    source: &Path,
+    mime_type: &str,
+    overwrite: bool,
+) -> Result<(), ThumbnailError> {
+    let missing: Vec<u32> = PREGENERATED_SIZES
+        .into_iter()
+        .filter(|size| overwrite || !app_data_dir.join(thumbnail_path(hash, *size)).exists())
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let picture = load_picture(source, mime_type)?;
+    create_dir(app_data_dir)?;
+    for size in missing {
+        write_thumbnail(
+            &picture,
+            size,
+            &app_data_dir.join(thumbnail_path(hash, size)),
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::{create_note, note_input, open_test_db, TestDir};
+    use crate::features::notes::repository::AttachmentRepository;
+    use image::{ImageFormat, RgbImage};
+    use std::io::Cursor;
+
+    #[test]
+    fn sizes_are_rounded_up_to_the_kept_ones() {
+        let conn = open_test_db();
+        let dir = TestDir::create();
+        let note = create_note(&conn, note_input("note", "", &[]));
+        let mut png = Vec::new();
+        RgbImage::new(1200, 800)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        let attachment = AttachmentRepository::new(&conn, dir.path())
+            .add_data(note, "photo.png", "image/png", &png)
+            .unwrap();
+        let thumbnails = ThumbnailRepository::new(&conn, dir.path());
+
+        for (asked, kept) in [(100, 128), (200, 256), (300, 512), (512, 512), (2000, 1024)] {
+            let thumbnail = thumbnails.get(attachment, asked).unwrap().unwrap();
+            assert!(thumbnail.file_path.ends_with(&format!("_{}.png", kept)));
+            assert_eq!(thumbnail.width, kept);
+        }
+
+        let files = fs::read_dir(dir.path().join(THUMBNAILS_DIR))
+            .unwrap()
+            .count();
+        assert_eq!(files, THUMBNAIL_SIZES.len());
+    }
+}
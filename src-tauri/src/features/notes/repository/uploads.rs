@@ -0,0 +1,413 @@
+use crate::db::time::parse_timestamp;
+use crate::error::{AppError, AppResult, EntityKind};
+use crate::features::notes::repository::attachments::{
+    ensure_note_accepts, AttachmentRepository, PreparedAttachment,
+};
+use crate::features::notes::repository::blobs::BLOBS_DIR;
+use crate::features::notes::sniff::UNKNOWN_MIME_TYPE;
+use chrono::{Duration, Utc};
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// the most one IPC message may carry. bigger content is sent as an upload, in chunks of at
+// most this size
+pub const MAX_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+// uploads untouched for this long were abandoned by the frontend
+const STALE_UPLOAD_DAYS: i64 = 1;
+
+// where an upload's content is collected, in the blobs directory so storing it is a rename
+fn upload_file_name(upload_id: i64) -> String {
+    format!(".upload_{}", upload_id)
+}
+
+fn upload_path(upload_id: i64) -> String {
+    format!("{}/{}", BLOBS_DIR, upload_file_name(upload_id))
+}
+
+fn check_chunk_size(len: usize) -> AppResult<()> {
+    if len > MAX_CHUNK_SIZE {
+        return Err(AppError::validation(format!(
+            "At most {} bytes can be sent at once; send larger content as an upload",
+            MAX_CHUNK_SIZE
+        )));
+    }
+    Ok(())
+}
+
+// content from the frontend comes with whatever name it had, e.g. a path for a dropped file
+fn clean_file_name(file_name: &str) -> AppResult<String> {
+    let name = Path::new(file_name.trim())
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if name.is_empty() {
+        return Err(AppError::validation("Invalid file name"));
+    }
+    Ok(name)
+}
+
+// the type is what the frontend was told, e.g. by the clipboard; it's only used when the
+// content isn't recognised
+fn clean_file_type(file_type: &str) -> &str {
+    match file_type.trim() {
+        "" => UNKNOWN_MIME_TYPE,
+        file_type => file_type,
+    }
+}
+
+// content small enough to be sent in one message, read into an attachment before the
+// database is locked
+pub fn prepare_bytes(
+    app_data_dir: &Path,
+    file_name: &str,
+    file_type: &str,
+    data: &[u8],
+) -> AppResult<PreparedAttachment> {
+    check_chunk_size(data.len())?;
+    let file_name = clean_file_name(file_name)?;
+
+    PreparedAttachment::from_data(app_data_dir, &file_name, clean_file_type(file_type), data)
+}
+
+// an upload taken off the list with all its content received, to be read into an attachment
+// of the note it was started for
+pub struct ReceivedUpload {
+    pub note_id: i64,
+    file_name: String,
+    file_type: String,
+    path: PathBuf,
+}
+
+impl ReceivedUpload {
+    // reading the content needs no lock, see PreparedAttachment
+    pub fn prepare(self, app_data_dir: &Path) -> AppResult<PreparedAttachment> {
+        PreparedAttachment::from_incoming(
+            app_data_dir,
+            &self.file_name,
+            &self.file_type,
+            &self.path,
+        )
+    }
+}
+
+// attachments made from bytes the frontend holds, like a pasted image or content dropped
+// from a browser, without it writing a temporary file first
+pub struct UploadRepository<'a> {
+    conn: &'a Connection,
+    app_data_dir: &'a Path,
+}
+
+impl<'a> UploadRepository<'a> {
+    pub fn new(conn: &'a Connection, app_data_dir: &'a Path) -> Self {
+        UploadRepository { conn, app_data_dir }
+    }
+
+    // attach content small enough to be sent in one message
+    pub fn add_bytes(
+        &self,
+        note_id: i64,
+        file_name: &str,
+        file_type: &str,
+        data: &[u8],
+    ) -> AppResult<i64> {
+        let attachment = prepare_bytes(self.app_data_dir, file_name, file_type, data)?;
+        AttachmentRepository::new(self.conn, self.app_data_dir).add_prepared(note_id, attachment)
+    }
+
+    // start an upload to be sent with append and stored with finish
+    pub fn begin(&self, note_id: i64, file_name: &str, file_type: &str) -> AppResult<i64> {
+        let file_name = clean_file_name(file_name)?;
+
+        ensure_note_accepts(self.conn, note_id)?;
+
+        let now = Utc::now().to_rfc3339();
+        self.conn
+            .execute(
+                "INSERT INTO attachment_uploads (
+                    note_id, file_name, file_type, created_at, updated_at
+                ) VALUES (?1, ?2, ?3, ?4, ?4)",
+                params![note_id, file_name, clean_file_type(file_type), now],
+            )
+            .map_err(|e| AppError::database("Failed to start upload", e))?;
+        let upload_id = self.conn.last_insert_rowid();
+
+        let full_path = self.app_data_dir.join(upload_path(upload_id));
+        let created = fs::create_dir_all(self.app_data_dir.join(BLOBS_DIR))
+            .and_then(|_| fs::write(&full_path, []));
+        if let Err(e) = created {
+            self.delete_row(upload_id)?;
+            return Err(AppError::io("Failed to create upload file", e));
+        }
+
+        info!("Started upload {} of '{}'", upload_id, file_name);
+        Ok(upload_id)
+    }
+
+    // add the next chunk. `offset` is where the chunk starts, so a chunk sent twice is
+    // caught instead of being stored twice. returns the size received so far
+    pub fn append(&self, upload_id: i64, offset: i64, chunk: &[u8]) -> AppResult<i64> {
+        check_chunk_size(chunk.len())?;
+
+        let received_size = self.received_size(upload_id)?;
+        if offset != received_size {
+            return Err(AppError::validation(format!(
+                "Upload {} expects the chunk at offset {}, not {}",
+                upload_id, received_size, offset
+            )));
+        }
+
+        let full_path = self.app_data_dir.join(upload_path(upload_id));
+        // a file that's gone was cleaned up as stale, so it isn't recreated by append
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(&full_path)
+            .map_err(|e| AppError::io("Failed to open upload file", e))?;
+
+        // the file may be longer than received_size if a previous write failed half way
+        file.set_len(received_size as u64)
+            .and_then(|_| file.write_all(chunk))
+            .map_err(|e| AppError::io("Failed to write upload file", e))?;
+
+        let received_size = received_size + chunk.len() as i64;
+        self.conn
+            .execute(
+                "UPDATE attachment_uploads SET received_size = ?, updated_at = ? WHERE id = ?",
+                params![received_size, Utc::now().to_rfc3339(), upload_id],
+            )
+            .map_err(|e| AppError::database("Failed to update upload", e))?;
+
+        Ok(received_size)
+    }
+
+    // store what was received as an attachment of the note the upload was started for
+    pub fn finish(&self, upload_id: i64) -> AppResult<i64> {
+        let upload = self.take(upload_id)?;
+        let note_id = upload.note_id;
+        let attachment = upload.prepare(self.app_data_dir)?;
+
+        AttachmentRepository::new(self.conn, self.app_data_dir).add_prepared(note_id, attachment)
+    }
+
+    // end an upload, leaving what was received to be stored. the file is used up either way,
+    // so the upload is over even if storing it fails
+    pub fn take(&self, upload_id: i64) -> AppResult<ReceivedUpload> {
+        let (note_id, file_name, file_type, received_size): (i64, String, String, i64) = self
+            .conn
+            .query_row(
+                "SELECT note_id, file_name, file_type, received_size
+                 FROM attachment_uploads WHERE id = ?",
+                params![upload_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .map_err(|e| {
+                AppError::lookup(
+                    EntityKind::AttachmentUpload,
+                    upload_id,
+                    "Failed to get upload",
+                    e,
+                )
+            })?;
+        self.delete_row(upload_id)?;
+
+        let path = self.app_data_dir.join(upload_path(upload_id));
+        // drop whatever a failed write left past the last chunk
+        let truncated = OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_len(received_size as u64));
+        if let Err(e) = truncated {
+            let _ = fs::remove_file(&path);
+            return Err(AppError::io("Failed to open upload file", e));
+        }
+
+        info!(
+            "Finished upload {} of '{}' ({} bytes)",
+            upload_id, file_name, received_size
+        );
+        Ok(ReceivedUpload {
+            note_id,
+            file_name,
+            file_type,
+            path,
+        })
+    }
+
+    // give up on an upload and delete what was received
+    pub fn cancel(&self, upload_id: i64) -> AppResult<()> {
+        self.received_size(upload_id)?;
+        self.delete_row(upload_id)?;
+        self.remove_file(upload_id)?;
+
+        info!("Cancelled upload {}", upload_id);
+        Ok(())
+    }
+
+    // drop uploads the frontend abandoned, and temporary files left behind when the app
+    // stopped while storing something. meant for startup, when nothing is being stored
+    pub fn discard_stale(&self) -> AppResult<usize> {
+        let cutoff = Utc::now() - Duration::days(STALE_UPLOAD_DAYS);
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, updated_at FROM attachment_uploads")
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+        let uploads = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|e| AppError::database("Failed to query uploads", e))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| AppError::database("Failed to process upload row", e))?;
+
+        let mut live = HashSet::new();
+        let mut discarded = 0;
+        for (upload_id, updated_at) in uploads {
+            if parse_timestamp(&updated_at, "updated_at")? >= cutoff {
+                live.insert(upload_file_name(upload_id));
+                continue;
+            }
+            self.delete_row(upload_id)?;
+            discarded += 1;
+        }
+
+        // uploads that cascaded away with their note leave their file too
+        let blobs_dir = self.app_data_dir.join(BLOBS_DIR);
+        if blobs_dir.exists() {
+            let entries = fs::read_dir(&blobs_dir)
+                .map_err(|e| AppError::io("Failed to read attachments directory", e))?;
+            for entry in entries {
+                let entry =
+                    entry.map_err(|e| AppError::io("Failed to read attachments directory", e))?;
+                let name = entry.file_name().to_string_lossy().to_string();
+                let temporary = name.starts_with(".upload_") || name.starts_with(".incoming_");
+                if !temporary || live.contains(&name) {
+                    continue;
+                }
+                if let Err(e) = fs::remove_file(entry.path()) {
+                    warn!("Failed to delete temporary file {:?}: {}", entry.path(), e);
+                }
+            }
+        }
+
+        if discarded > 0 {
+            info!("Discarded {} abandoned uploads", discarded);
+        }
+        Ok(discarded)
+    }
+
+    fn received_size(&self, upload_id: i64) -> AppResult<i64> {
+        self.conn
+            .query_row(
+                "SELECT received_size FROM attachment_uploads WHERE id = ?",
+                params![upload_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| AppError::database("Failed to get upload", e))?
+            .ok_or_else(|| AppError::not_found(EntityKind::AttachmentUpload, upload_id))
+    }
+
+    fn delete_row(&self, upload_id: i64) -> AppResult<()> {
+        self.conn
+            .execute(
+                "DELETE FROM attachment_uploads WHERE id = ?",
+                params![upload_id],
+            )
+            .map_err(|e| AppError::database("Failed to delete upload", e))?;
+        Ok(())
+    }
+
+    fn remove_file(&self, upload_id: i64) -> AppResult<()> {
+        let full_path = self.app_data_dir.join(upload_path(upload_id));
+        if full_path.exists() {
+            fs::remove_file(&full_path)
+                .map_err(|e| AppError::io("Failed to delete upload file", e))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::{create_note, note_input, open_test_db, TestDir};
+    use crate::error::ErrorCode;
+
+    #[test]
+    fn an_upload_is_stored_chunk_by_chunk() {
+        let conn = open_test_db();
+        let dir = TestDir::create();
+        let note = create_note(&conn, note_input("note", "", &[]));
+        let uploads = UploadRepository::new(&conn, dir.path());
+
+        let upload = uploads
+            .begin(note, "/home/me/Downloads/greeting.txt", "")
+            .unwrap();
+        assert_eq!(uploads.append(upload, 0, b"hello ").unwrap(), 6);
+        // the same chunk sent again
+        let err = uploads.append(upload, 0, b"hello ").unwrap_err();
+        assert_eq!(err.code, ErrorCode::Validation);
+        assert_eq!(uploads.append(upload, 6, b"world").unwrap(), 11);
+
+        let attachment_id = uploads.finish(upload).unwrap();
+
+        let attachment = AttachmentRepository::new(&conn, dir.path())
+            .get_by_id(attachment_id)
+            .unwrap();
+        assert_eq!(attachment.file_name, "greeting.txt");
+        assert_eq!(attachment.file_type, "text/plain");
+        assert_eq!(
+            fs::read(dir.path().join(&attachment.file_path)).unwrap(),
+            b"hello world"
+        );
+        assert!(!dir.path().join(upload_path(upload)).exists());
+        assert_eq!(
+            uploads.finish(upload).unwrap_err().code,
+            ErrorCode::NotFound
+        );
+    }
+
+    #[test]
+    fn a_cancelled_upload_leaves_nothing() {
+        let conn = open_test_db();
+        let dir = TestDir::create();
+        let note = create_note(&conn, note_input("note", "", &[]));
+        let uploads = UploadRepository::new(&conn, dir.path());
+        let upload = uploads.begin(note, "clip.mp4", "video/mp4").unwrap();
+        uploads.append(upload, 0, b"frames").unwrap();
+
+        uploads.cancel(upload).unwrap();
+
+        assert!(!dir.path().join(upload_path(upload)).exists());
+        let err = uploads.append(upload, 6, b"more").unwrap_err();
+        assert_eq!(err.code, ErrorCode::NotFound);
+    }
+
+    #[test]
+    fn only_abandoned_uploads_and_leftovers_are_discarded() {
+        let conn = open_test_db();
+        let dir = TestDir::create();
+        let note = create_note(&conn, note_input("note", "", &[]));
+        let uploads = UploadRepository::new(&conn, dir.path());
+        let live = uploads.begin(note, "live.bin", "").unwrap();
+        let abandoned = uploads.begin(note, "abandoned.bin", "").unwrap();
+        conn.execute(
+            "UPDATE attachment_uploads SET updated_at = '2020-01-01T00:00:00Z' WHERE id = ?",
+            params![abandoned],
+        )
+        .unwrap();
+        let leftover = dir.write(&format!("{}/.incoming_1", BLOBS_DIR), b"partial");
+
+        assert_eq!(uploads.discard_stale().unwrap(), 1);
+
+        assert!(dir.path().join(upload_path(live)).exists());
+        assert!(!dir.path().join(upload_path(abandoned)).exists());
+        assert!(!leftover.exists());
+        uploads.append(live, 0, b"still going").unwrap();
+    }
+}
@@ -0,0 +1,208 @@
+// one searchable unit of the user's query, already quoted for FTS5
+struct Term {
+    text: String,
+    negated: bool,
+    or_before: bool, // joined to the previous term with OR instead of AND
+}
+
+// the FTS5 side of a search. `matching` goes straight into MATCH; `excluded` is only set
+// when the user gave nothing but exclusions, which FTS5 can't express on its own (its NOT
+// is binary), so callers subtract those matches from the result instead
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FtsQuery {
+    pub matching: Option<String>,
+    pub excluded: Option<String>,
+}
+
+// translate what the user typed into FTS5 MATCH expressions.
+//
+//   word        must contain word
+//   word*       word as a prefix
+//   "a phrase"  exact phrase (a trailing * makes the last word a prefix)
+//   -word       must not contain word (NOT word works too)
+//   a OR b      either one
+//
+// every term is quoted, so FTS5 syntax characters in the input are matched literally
+// instead of producing syntax errors
+pub fn to_fts_query(input: &str) -> FtsQuery {
+    let terms = parse_terms(input);
+
+    let mut positive = String::new();
+    let mut negative = Vec::new();
+    let mut has_or = false;
+
+    for term in &terms {
+        if term.negated {
+            negative.push(term.text.as_str());
+        } else {
+            if !positive.is_empty() {
+                if term.or_before {
+                    positive.push_str(" OR ");
+                    has_or = true;
+                } else {
+                    positive.push(' ');
+                }
+            }
+            positive.push_str(&term.text);
+        }
+    }
+
+    if positive.is_empty() {
+        return FtsQuery {
+            matching: None,
+            excluded: (!negative.is_empty()).then(|| negative.join(" OR ")),
+        };
+    }
+
+    if negative.is_empty() {
+        return FtsQuery {
+            matching: Some(positive),
+            excluded: None,
+        };
+    }
+
+    // NOT binds tighter than AND and OR, so group the positive side first
+    if has_or || terms.iter().filter(|t| !t.negated).count() > 1 {
+        positive = format!("({})", positive);
+    }
+
+    FtsQuery {
+        matching: Some(format!("{} NOT {}", positive, negative.join(" NOT "))),
+        excluded: None,
+    }
+}
+
+fn parse_terms(input: &str) -> Vec<Term> {
+    let mut terms = Vec::new();
+    let mut chars = input.chars().peekable();
+    let mut negate_next = false;
+    let mut or_next = false;
+
+    loop {
+        // skip whitespace between terms
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+
+        let Some(&c) = chars.peek() else {
+            break;
+        };
+
+        if c == '-' {
+            chars.next();
+            negate_next = true;
+            continue;
+        }
+
+        let (body, is_phrase) = if c == '"' {
+            chars.next();
+            let mut phrase = String::new();
+            // an unterminated phrase runs to the end of the input
+            for c in chars.by_ref() {
+                if c == '"' {
+                    break;
+                }
+                phrase.push(c);
+            }
+            (phrase, true)
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '"' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            (word, false)
+        };
+
+        // operators are only recognised as bare uppercase words
+        if !is_phrase {
+            match body.as_str() {
+                "OR" => {
+                    or_next = !terms.is_empty();
+                    continue;
+                }
+                "AND" => continue,
+                "NOT" => {
+                    negate_next = true;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        let (body, mut is_prefix) = match body.strip_suffix('*') {
+            Some(stripped) => (stripped.trim_end_matches('*').to_string(), true),
+            None => (body, false),
+        };
+
+        // `"phrase"*` asks for a prefix on the phrase's last word
+        if is_phrase && chars.peek() == Some(&'*') {
+            chars.next();
+            is_prefix = true;
+        }
+
+        if body.trim().is_empty() {
+            negate_next = false;
+            continue;
+        }
+
+        let mut text = format!("\"{}\"", body.replace('"', "\"\""));
+        if is_prefix {
+            text.push('*');
+        }
+
+        terms.push(Term {
+            text,
+            negated: negate_next,
+            or_before: or_next && !negate_next,
+        });
+        negate_next = false;
+        or_next = false;
+    }
+
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matching(input: &str) -> Option<String> {
+        to_fts_query(input).matching
+    }
+
+    #[test]
+    fn words_and_phrases_are_quoted() {
+        assert_eq!(matching("foo bar"), Some(r#""foo" "bar""#.to_string()));
+        assert_eq!(
+            matching(r#""a phrase" pre*"#),
+            Some(r#""a phrase" "pre"*"#.to_string())
+        );
+        assert_eq!(matching(r#"a"b"#), Some(r#""a" "b""#.to_string()));
+    }
+
+    #[test]
+    fn or_and_not() {
+        assert_eq!(matching("a OR b"), Some(r#""a" OR "b""#.to_string()));
+        assert_eq!(
+            matching("a OR b -c"),
+            Some(r#"("a" OR "b") NOT "c""#.to_string())
+        );
+        assert_eq!(matching("a NOT c"), Some(r#""a" NOT "c""#.to_string()));
+    }
+
+    #[test]
+    fn only_exclusions() {
+        assert_eq!(
+            to_fts_query("-a -b"),
+            FtsQuery {
+                matching: None,
+                excluded: Some(r#""a" OR "b""#.to_string()),
+            }
+        );
+        assert_eq!(to_fts_query("  "), FtsQuery::default());
+    }
+}
@@ -0,0 +1,7 @@
+pub mod fts;
+pub mod query;
+pub mod sql;
+
+pub use fts::{to_fts_query, FtsQuery};
+pub use query::{parse_search_query, SearchQuery};
+pub use sql::compile_search;
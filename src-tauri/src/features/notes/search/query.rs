@@ -0,0 +1,278 @@
+use crate::error::{AppError, AppResult};
+use chrono::NaiveDate;
+
+// which timestamp a date filter looks at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateField {
+    Created,
+    Updated,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Before,     // <
+    OnOrBefore, // <=
+    On,         // =
+    OnOrAfter,  // >=
+    After,      // >
+}
+
+impl Comparison {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            Comparison::Before => "<",
+            Comparison::OnOrBefore => "<=",
+            Comparison::On => "=",
+            Comparison::OnOrAfter => ">=",
+            Comparison::After => ">",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    Tag(String),                            // tag:work
+    Folder(String),                         // folder:"Projects/Apto", includes subfolders
+    Pinned,                                 // is:pinned
+    Archived,                               // is:archived
+    Date(DateField, Comparison, NaiveDate), // updated:>2026-01-01, before:2026-01-01
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterClause {
+    pub filter: Filter,
+    pub negated: bool, // written with a leading '-'
+}
+
+// a parsed search: structured filters plus whatever free text is left for full-text search
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    pub filters: Vec<FilterClause>,
+    pub text: String, // in the fts syntax, see search::fts
+}
+
+impl SearchQuery {
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty() && self.text.trim().is_empty()
+    }
+}
+
+// split a search box string into filters and free text.
+//
+//   tag:NAME              notes carrying the tag
+//   folder:PATH           notes in the folder or any of its subfolders, PATH from the root
+//                         with '/' between folder names, e.g. folder:"Projects/Apto"
+//   is:pinned, is:archived
+//   updated:OP DATE       OP is one of < <= = >= > (default =), DATE is YYYY-MM-DD
+//   created:OP DATE
+//   before:DATE, after:DATE   shorthand for updated:<DATE and updated:>DATE
+//
+// any filter can be negated with a leading '-'. values with spaces go in double quotes.
+// words with an unknown `key:` prefix are treated as ordinary search text
+pub fn parse_search_query(input: &str) -> AppResult<SearchQuery> {
+    let mut query = SearchQuery::default();
+    let mut text_parts = Vec::new();
+
+    for token in tokenize(input) {
+        let (negated, body) = match token.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, token.as_str()),
+        };
+
+        let filter = match body.split_once(':') {
+            Some((key, value)) if !key.contains('"') => parse_filter(key, &unquote(value))?,
+            _ => None,
+        };
+
+        match filter {
+            Some(filter) => query.filters.push(FilterClause { filter, negated }),
+            None => text_parts.push(token),
+        }
+    }
+
+    query.text = text_parts.join(" ");
+    Ok(query)
+}
+
+// None when `key` isn't a filter we know
+fn parse_filter(key: &str, value: &str) -> AppResult<Option<Filter>> {
+    let filter = match key.to_lowercase().as_str() {
+        "tag" => Filter::Tag(non_empty(key, value)?),
+        "folder" => Filter::Folder(non_empty(key, value)?.trim_matches('/').to_string()),
+        "is" => match value.to_lowercase().as_str() {
+            "pinned" => Filter::Pinned,
+            "archived" => Filter::Archived,
+            _ => {
+                return Err(AppError::validation(format!(
+                    "Unknown filter 'is:{}', expected is:pinned or is:archived",
+                    value
+                )))
+            }
+        },
+        "updated" | "created" => {
+            let field = if key.eq_ignore_ascii_case("created") {
+                DateField::Created
+            } else {
+                DateField::Updated
+            };
+            let (comparison, date) = parse_comparison(value);
+            Filter::Date(field, comparison, parse_date(key, date)?)
+        }
+        "before" => Filter::Date(
+            DateField::Updated,
+            Comparison::Before,
+            parse_date(key, value)?,
+        ),
+        "after" => Filter::Date(
+            DateField::Updated,
+            Comparison::After,
+            parse_date(key, value)?,
+        ),
+        _ => return Ok(None),
+    };
+
+    Ok(Some(filter))
+}
+
+fn parse_comparison(value: &str) -> (Comparison, &str) {
+    // two-character operators first so ">=" isn't read as ">"
+    for (prefix, comparison) in [
+        ("<=", Comparison::OnOrBefore),
+        (">=", Comparison::OnOrAfter),
+        ("<", Comparison::Before),
+        (">", Comparison::After),
+        ("=", Comparison::On),
+    ] {
+        if let Some(rest) = value.strip_prefix(prefix) {
+            return (comparison, rest);
+        }
+    }
+
+    (Comparison::On, value)
+}
+
+fn parse_date(key: &str, value: &str) -> AppResult<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+        AppError::validation(format!(
+            "Invalid date '{}' for {}:, expected YYYY-MM-DD",
+            value, key
+        ))
+    })
+}
+
+fn non_empty(key: &str, value: &str) -> AppResult<String> {
+    if value.trim().is_empty() {
+        return Err(AppError::validation(format!("{}: needs a value", key)));
+    }
+    Ok(value.to_string())
+}
+
+fn unquote(value: &str) -> String {
+    value.replace('"', "")
+}
+
+// split on whitespace, keeping double-quoted runs (including their quotes) together
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in input.chars() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+            current.push(c);
+        } else if c.is_whitespace() && !in_quotes {
+            if !current.is_empty() {
+                tokens.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(c);
+        }
+    }
+
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn splits_filters_from_text() {
+        let query = parse_search_query("budget tag:work -is:archived review").unwrap();
+
+        assert_eq!(query.text, "budget review");
+        assert_eq!(
+            query.filters,
+            vec![
+                FilterClause {
+                    filter: Filter::Tag("work".to_string()),
+                    negated: false,
+                },
+                FilterClause {
+                    filter: Filter::Archived,
+                    negated: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn quoted_values_keep_their_spaces() {
+        let query = parse_search_query(r#"folder:"/Projects/Apto v2/" "exact phrase""#).unwrap();
+
+        assert_eq!(
+            query.filters[0].filter,
+            Filter::Folder("Projects/Apto v2".to_string())
+        );
+        assert_eq!(query.text, r#""exact phrase""#);
+    }
+
+    #[test]
+    fn date_filters() {
+        let query = parse_search_query("updated:>=2026-01-01 created:2025-12-31 before:2026-03-01")
+            .unwrap();
+        let filters: Vec<Filter> = query.filters.into_iter().map(|c| c.filter).collect();
+
+        assert_eq!(
+            filters,
+            vec![
+                Filter::Date(
+                    DateField::Updated,
+                    Comparison::OnOrAfter,
+                    date("2026-01-01")
+                ),
+                Filter::Date(DateField::Created, Comparison::On, date("2025-12-31")),
+                Filter::Date(DateField::Updated, Comparison::Before, date("2026-03-01")),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_text() {
+        let query = parse_search_query("http://example.com note:x").unwrap();
+
+        assert!(query.filters.is_empty());
+        assert_eq!(query.text, "http://example.com note:x");
+    }
+
+    #[test]
+    fn invalid_filters_are_rejected() {
+        assert!(parse_search_query("is:deleted").is_err());
+        assert!(parse_search_query("after:yesterday").is_err());
+        assert!(parse_search_query("tag:").is_err());
+    }
+
+    #[test]
+    fn empty_query() {
+        assert!(parse_search_query("   ").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+use super::fts::to_fts_query;
+use super::query::{DateField, Filter, SearchQuery};
+use crate::features::notes::repository::notes::note_columns;
+use rusqlite::types::Value;
+
+// every folder with its path from the root, e.g. "Projects/Apto". a trashed folder, and
+// so everything below it, has no path
+const FOLDER_PATHS_CTE: &str = "WITH RECURSIVE folder_paths(id, path) AS (
+        SELECT id, name FROM note_folders WHERE parent_id IS NULL AND deleted_at IS NULL
+        UNION ALL
+        SELECT f.id, p.path || '/' || f.name
+        FROM note_folders f JOIN folder_paths p ON f.parent_id = p.id
+        WHERE f.deleted_at IS NULL
+    )";
+
+// length of the content preview when there's no full-text match to build a snippet from
+const PREVIEW_LENGTH: u32 = 200;
+
+// a complete statement for a parsed query. the selected columns are NOTE_COLUMNS
+// (aliased to `n`) followed by rank, title highlight, snippet, and the id and file name of
+// the attachment the snippet comes from
+pub struct CompiledSearch {
+    pub sql: String,
+    pub params: Vec<Value>,
+}
+
+pub fn compile_search(query: &SearchQuery, limit: u32) -> CompiledSearch {
+    // trashed notes never show up in search
+    let mut conditions = vec!["n.deleted_at IS NULL".to_string()];
+    let mut params = Vec::new();
+
+    // text matches in the note itself or in the text of one of its attachments. the
+    // matching terms go into the hits subquery, which comes before the conditions
+    let fts_query = to_fts_query(&query.text);
+    if let Some(matching) = &fts_query.matching {
+        params.push(Value::Text(matching.clone()));
+        params.push(Value::Text(matching.clone()));
+    }
+    if let Some(excluded) = &fts_query.excluded {
+        conditions
+            .push("n.id NOT IN (SELECT rowid FROM notes_fts WHERE notes_fts MATCH ?)".to_string());
+        conditions.push(
+            "n.id NOT IN (SELECT a.note_id FROM attachments_fts
+                          JOIN note_attachments a ON a.id = attachments_fts.rowid
+                          WHERE attachments_fts MATCH ?)"
+                .to_string(),
+        );
+        params.push(Value::Text(excluded.clone()));
+        params.push(Value::Text(excluded.clone()));
+    }
+
+    let mut needs_folder_paths = false;
+
+    for clause in &query.filters {
+        let condition = match &clause.filter {
+            Filter::Tag(name) => {
+                params.push(Value::Text(name.clone()));
+                "EXISTS (SELECT 1 FROM note_tag_mappings m
+                         JOIN note_tags t ON t.id = m.tag_id
+                         WHERE m.note_id = n.id AND t.name = ? COLLATE NOCASE)"
+                    .to_string()
+            }
+            Filter::Folder(path) => {
+                needs_folder_paths = true;
+                // the folder itself, then everything below it
+                params.push(Value::Text(path.clone()));
+                params.push(Value::Text(format!("{}/%", escape_like(path))));
+                "COALESCE(n.folder_id IN (SELECT id FROM folder_paths
+                          WHERE path = ? COLLATE NOCASE OR path LIKE ? ESCAPE '\\'), 0)"
+                    .to_string()
+            }
+            Filter::Pinned => "n.is_pinned = 1".to_string(),
+            Filter::Archived => "n.is_archived = 1".to_string(),
+            Filter::Date(field, comparison, date) => {
+                let column = match field {
+                    DateField::Created => "n.created_at",
+                    DateField::Updated => "n.updated_at",
+                };
+                params.push(Value::Text(date.format("%Y-%m-%d").to_string()));
+                // timestamps are stored as RFC 3339 in UTC, so the first 10 characters are the day
+                format!("substr({}, 1, 10) {} ?", column, comparison.as_sql())
+            }
+        };
+
+        if clause.negated {
+            conditions.push(format!("NOT ({})", condition));
+        } else {
+            conditions.push(condition);
+        }
+    }
+
+    let where_clause = format!("WHERE {}", conditions.join("\n AND "));
+
+    let with_clause = if needs_folder_paths {
+        FOLDER_PATHS_CTE
+    } else {
+        ""
+    };
+
+    let sql = if fts_query.matching.is_some() {
+        // a title hit is worth much more than a body hit, and an attachment's name more than
+        // its text. a note shows up once, with its best hit
+        format!(
+            "{}
+             SELECT {}, h.rank, COALESCE(h.title_highlight, n.title), h.snippet,
+                    h.attachment_id, h.attachment_name
+             FROM (
+                 SELECT *, ROW_NUMBER() OVER (PARTITION BY note_id ORDER BY rank) AS position
+                 FROM (
+                     SELECT rowid AS note_id,
+                            bm25(notes_fts, 10.0, 1.0) AS rank,
+                            highlight(notes_fts, 0, '<mark>', '</mark>') AS title_highlight,
+                            snippet(notes_fts, 1, '<mark>', '</mark>', '…', 24) AS snippet,
+                            NULL AS attachment_id,
+                            NULL AS attachment_name
+                     FROM notes_fts
+                     WHERE notes_fts MATCH ?
+                     UNION ALL
+                     SELECT a.note_id,
+                            bm25(attachments_fts, 5.0, 1.0),
+                            NULL,
+                            snippet(attachments_fts, -1, '<mark>', '</mark>', '…', 24),
+                            a.id,
+                            a.file_name
+                     FROM attachments_fts
+                     JOIN note_attachments a ON a.id = attachments_fts.rowid
+                     WHERE attachments_fts MATCH ?
+                 )
+             ) h
+             JOIN notes n ON n.id = h.note_id
+             {}
+             AND h.position = 1
+             ORDER BY h.rank
+             LIMIT ?",
+            with_clause,
+            note_columns("n"),
+            where_clause
+        )
+    } else {
+        // nothing to rank by, so newest first
+        format!(
+            "{}
+             SELECT {}, 0.0, n.title, substr(n.content, 1, {}), NULL, NULL
+             FROM notes n
+             {}
+             ORDER BY n.updated_at DESC
+             LIMIT ?",
+            with_clause,
+            note_columns("n"),
+            PREVIEW_LENGTH,
+            where_clause
+        )
+    };
+
+    params.push(Value::Integer(limit as i64));
+
+    CompiledSearch { sql, params }
+}
+
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::notes::search::parse_search_query;
+
+    fn compile(input: &str) -> CompiledSearch {
+        compile_search(&parse_search_query(input).unwrap(), 20)
+    }
+
+    #[test]
+    fn text_queries_rank_by_fts_matches() {
+        let compiled = compile("budget tag:work");
+
+        assert!(compiled.sql.contains("notes_fts MATCH ?"));
+        assert!(compiled.sql.contains("ORDER BY h.rank"));
+        assert!(!compiled.sql.contains("folder_paths"));
+        // the match for notes and for attachments, the tag, then the limit
+        assert_eq!(
+            compiled.params,
+            vec![
+                Value::Text("\"budget\"".to_string()),
+                Value::Text("\"budget\"".to_string()),
+                Value::Text("work".to_string()),
+                Value::Integer(20),
+            ]
+        );
+    }
+
+    #[test]
+    fn filter_only_queries_are_newest_first() {
+        let compiled = compile("-is:pinned");
+
+        assert!(!compiled.sql.contains("MATCH"));
+        assert!(compiled.sql.contains("NOT (n.is_pinned = 1)"));
+        assert!(compiled.sql.contains("ORDER BY n.updated_at DESC"));
+        assert_eq!(compiled.params, vec![Value::Integer(20)]);
+    }
+
+    #[test]
+    fn folder_filters_match_the_subtree() {
+        let compiled = compile("folder:Projects/100%");
+
+        assert!(compiled.sql.starts_with("WITH RECURSIVE folder_paths"));
+        assert_eq!(
+            &compiled.params[..2],
+            &[
+                Value::Text("Projects/100%".to_string()),
+                Value::Text("Projects/100\\%/%".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn exclusions_alone_subtract_matches() {
+        let compiled = compile("-draft");
+
+        assert!(compiled
+            .sql
+            .contains("n.id NOT IN (SELECT rowid FROM notes_fts"));
+        assert_eq!(compiled.params.len(), 3);
+    }
+}
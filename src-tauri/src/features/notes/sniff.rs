@@ -0,0 +1,273 @@
+use crate::features::notes::models::AttachmentMetadata;
+use encoding_rs::Encoding;
+use infer::MatcherType;
+use log::debug;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+// how much of a file is read to recognise its type
+const SNIFF_LEN: u64 = 8 * 1024;
+
+// how much of a text file is read to guess its encoding
+const ENCODING_SAMPLE_LEN: u64 = 1024 * 1024;
+
+// extensions of programs, scripts, installers and shortcuts on Windows, macOS and Linux
+const EXECUTABLE_EXTENSIONS: &[&str] = &[
+    "exe", "com", "scr", "pif", "cpl", "msi", "msp", "msc", "bat", "cmd", "ps1", "psm1", "vbs",
+    "vbe", "js", "jse", "wsf", "wsh", "hta", "lnk", "url", "reg", "inf", "jar", "app", "command",
+    "tool", "pkg", "dmg", "scpt", "workflow", "sh", "bash", "zsh", "csh", "ksh", "run", "bin",
+    "appimage", "desktop", "deb", "rpm", "py", "pyw", "pl", "rb",
+];
+
+// the MIME type of content that couldn't be recognised
+pub const UNKNOWN_MIME_TYPE: &str = "application/octet-stream";
+
+// what a file's content says it is
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentType {
+    pub mime_type: String,
+    pub extension: Option<&'static str>, // the usual extension, for types known by magic bytes
+    kind: Kind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Image,
+    Pdf,
+    Media,
+    Text,
+    Executable,
+    Other,
+}
+
+impl ContentType {
+    // whether the type came from the content's magic bytes rather than a guess
+    pub fn is_recognised(&self) -> bool {
+        self.extension.is_some()
+    }
+
+    pub fn is_text(&self) -> bool {
+        self.kind == Kind::Text
+    }
+
+    pub fn is_pdf(&self) -> bool {
+        self.kind == Kind::Pdf
+    }
+
+    // programs and scripts, which run rather than being shown when opened
+    pub fn is_executable(&self) -> bool {
+        self.kind == Kind::Executable
+    }
+}
+
+// recognise a file by its magic bytes. formats without any, like plain text, fall back to
+// the file name: content without NUL bytes is text, with a MIME type from the extension
+pub fn sniff(path: &Path, file_name: &str) -> io::Result<ContentType> {
+    let mut head = Vec::new();
+    File::open(path)?.take(SNIFF_LEN).read_to_end(&mut head)?;
+
+    if let Some(found) = infer::get(&head) {
+        let kind = match found.matcher_type() {
+            MatcherType::App => Kind::Executable,
+            _ if found.mime_type() == "text/x-shellscript" => Kind::Executable,
+            MatcherType::Image => Kind::Image,
+            MatcherType::Audio | MatcherType::Video => Kind::Media,
+            _ if found.mime_type() == "application/pdf" => Kind::Pdf,
+            MatcherType::Text => Kind::Text,
+            _ => Kind::Other,
+        };
+        return Ok(ContentType {
+            mime_type: found.mime_type().to_string(),
+            extension: Some(found.extension()),
+            kind,
+        });
+    }
+
+    // UTF-16 text is full of NUL bytes, but starts with a byte order mark
+    let is_text = Encoding::for_bom(&head).is_some() || !head.contains(&0);
+    let extension = Path::new(file_name)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase());
+
+    let (mime_type, kind) = if is_text {
+        (text_mime_type(extension.as_deref()), Kind::Text)
+    } else {
+        (UNKNOWN_MIME_TYPE, Kind::Other)
+    };
+
+    Ok(ContentType {
+        mime_type: mime_type.to_string(),
+        extension: None,
+        kind,
+    })
+}
+
+// whether the system runs a file with this name, or hands it to an interpreter, when it's
+// opened. the extension decides that, whatever the content is
+pub fn runs_as_program(file_name: &str) -> bool {
+    let Some(extension) = Path::new(file_name).extension() else {
+        return false;
+    };
+    let extension = extension.to_string_lossy().to_lowercase();
+    EXECUTABLE_EXTENSIONS.contains(&extension.as_str())
+}
+
+fn text_mime_type(extension: Option<&str>) -> &'static str {
+    match extension {
+        Some("md" | "markdown") => "text/markdown",
+        Some("csv") => "text/csv",
+        Some("tsv") => "text/tab-separated-values",
+        Some("html" | "htm") => "text/html",
+        Some("css") => "text/css",
+        Some("js") => "text/javascript",
+        Some("json") => "application/json",
+        Some("xml") => "application/xml",
+        Some("svg") => "image/svg+xml",
+        _ => "text/plain",
+    }
+}
+
+// read what applies to the content's type. it's best effort: a file that can't be parsed
+// just has no metadata, and shouldn't keep it from being attached
+pub fn extract_metadata(path: &Path, content_type: &ContentType) -> AttachmentMetadata {
+    let mut metadata = AttachmentMetadata::default();
+
+    let result = match content_type.kind {
+        Kind::Image => imagesize::size(path)
+            .map(|size| {
+                metadata.width = u32::try_from(size.width).ok();
+                metadata.height = u32::try_from(size.height).ok();
+            })
+            .map_err(|e| e.to_string()),
+        Kind::Pdf => lopdf::Document::load(path)
+            .map(|document| metadata.page_count = Some(document.get_pages().len() as u32))
+            .map_err(|e| e.to_string()),
+        Kind::Media => media_duration(path, content_type)
+            .map(|duration| metadata.duration_secs = duration)
+            .map_err(|e| e.to_string()),
+        Kind::Text => text_encoding(path)
+            .map(|encoding| metadata.text_encoding = Some(encoding.to_string()))
+            .map_err(|e| e.to_string()),
+        Kind::Executable | Kind::Other => Ok(()),
+    };
+
+    if let Err(e) = result {
+        debug!("Couldn't read metadata from {:?}: {}", path, e);
+    }
+
+    metadata
+}
+
+// the length of the longest track whose length the container records
+fn media_duration(
+    path: &Path,
+    content_type: &ContentType,
+) -> symphonia::core::errors::Result<Option<f64>> {
+    let source = MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+    let mut hint = Hint::new();
+    hint.mime_type(&content_type.mime_type);
+    if let Some(extension) = content_type.extension {
+        hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe().format(
+        &hint,
+        source,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+
+    let duration = probed
+        .format
+        .tracks()
+        .iter()
+        .filter_map(|track| {
+            let params = &track.codec_params;
+            let time = params.time_base?.calc_time(params.n_frames?);
+            Some(time.seconds as f64 + time.frac)
+        })
+        .reduce(f64::max);
+
+    Ok(duration)
+}
+
+// from a byte order mark, else UTF-8 if the sample is valid UTF-8, else a guess from the
+// byte statistics
+pub fn text_encoding(path: &Path) -> io::Result<&'static str> {
+    let mut sample = Vec::new();
+    File::open(path)?
+        .take(ENCODING_SAMPLE_LEN)
+        .read_to_end(&mut sample)?;
+
+    if let Some((encoding, _)) = Encoding::for_bom(&sample) {
+        return Ok(encoding.name());
+    }
+
+    // the sample may end in the middle of a character
+    let valid_utf8 = match std::str::from_utf8(&sample) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    };
+    if valid_utf8 {
+        return Ok(encoding_rs::UTF_8.name());
+    }
+
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(&sample, sample.len() < ENCODING_SAMPLE_LEN as usize);
+    Ok(detector.guess(None, false).name())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::TestDir;
+
+    #[test]
+    fn the_content_wins_over_the_extension() {
+        let dir = TestDir::create();
+        let mut png = Vec::new();
+        image::RgbImage::new(2, 2)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let path = dir.write("upload", &png);
+
+        let content_type = sniff(&path, "notes.txt").unwrap();
+
+        assert_eq!(content_type.mime_type, "image/png");
+        assert_eq!(content_type.extension, Some("png"));
+        assert!(!content_type.is_text());
+    }
+
+    #[test]
+    fn a_script_is_executable_whatever_its_name() {
+        let dir = TestDir::create();
+        let path = dir.write("upload", b"#!/bin/sh\nrm -rf ~/\n");
+
+        assert!(sniff(&path, "notes.txt").unwrap().is_executable());
+        assert!(runs_as_program("Invoice.PDF.exe"));
+        assert!(!runs_as_program("notes.txt"));
+    }
+
+    #[test]
+    fn text_is_told_from_binary_without_magic_bytes() {
+        let dir = TestDir::create();
+        let utf16 = dir.write("utf16", &[0xFF, 0xFE, b'h', 0, b'i', 0]);
+        let binary = dir.write("binary", &[1, 0, 2, 0, 3]);
+        let csv = dir.write("csv", b"a,b\n1,2\n");
+
+        let content_type = sniff(&utf16, "notes.txt").unwrap();
+        assert!(content_type.is_text());
+        assert_eq!(content_type.mime_type, "text/plain");
+        assert!(!content_type.is_recognised());
+        assert_eq!(
+            sniff(&binary, "notes.md").unwrap().mime_type,
+            UNKNOWN_MIME_TYPE
+        );
+        assert_eq!(sniff(&csv, "data.CSV").unwrap().mime_type, "text/csv");
+    }
+}
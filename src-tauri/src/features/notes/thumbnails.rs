@@ -0,0 +1,91 @@
+use image::{DynamicImage, ImageFormat, ImageReader};
+use std::fmt;
+use std::path::Path;
+
+// why a thumbnail couldn't be made
+#[derive(Debug)]
+pub enum ThumbnailError {
+    Unsupported,    // not a type thumbnails are made for, or a PDF without a usable image
+    Failed(String), // the file couldn't be read or decoded
+}
+
+impl fmt::Display for ThumbnailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThumbnailError::Unsupported => write!(f, "no thumbnail for this type"),
+            ThumbnailError::Failed(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+fn failed(e: impl fmt::Display) -> ThumbnailError {
+    ThumbnailError::Failed(e.to_string())
+}
+
+// whether thumbnails are made for a MIME type at all
+pub fn supports(mime_type: &str) -> bool {
+    mime_type == "application/pdf" || decodable_format(mime_type).is_some()
+}
+
+fn decodable_format(mime_type: &str) -> Option<ImageFormat> {
+    ImageFormat::from_mime_type(mime_type).filter(|format| format.reading_enabled())
+}
+
+// decode the picture thumbnails of a file are made from
+pub fn load_picture(source: &Path, mime_type: &str) -> Result<DynamicImage, ThumbnailError> {
+    if mime_type == "application/pdf" {
+        return first_page_image(source);
+    }
+
+    let format = decodable_format(mime_type).ok_or(ThumbnailError::Unsupported)?;
+    let mut reader = ImageReader::open(source).map_err(failed)?;
+    reader.set_format(format);
+    reader.decode().map_err(failed)
+}
+
+// scale a picture to fit in a `size` square and write it to `destination` as a PNG. pictures
+// are never scaled up. returns the thumbnail's width and height
+pub fn write_thumbnail(
+    picture: &DynamicImage,
+    size: u32,
+    destination: &Path,
+) -> Result<(u32, u32), ThumbnailError> {
+    let thumbnail = if picture.width() > size || picture.height() > size {
+        picture.thumbnail(size, size)
+    } else {
+        picture.clone()
+    };
+
+    thumbnail
+        .save_with_format(destination, ImageFormat::Png)
+        .map_err(failed)?;
+    Ok((thumbnail.width(), thumbnail.height()))
+}
+
+// rendering PDF pages would take a full PDF engine, so a PDF's thumbnail is the largest
+// JPEG image on its first page, which is what a scanned document's page is. other PDFs
+// have none
+fn first_page_image(source: &Path) -> Result<DynamicImage, ThumbnailError> {
+    let document = lopdf::Document::load(source).map_err(failed)?;
+    let (_, first_page) = document
+        .get_pages()
+        .into_iter()
+        .next()
+        .ok_or(ThumbnailError::Unsupported)?;
+
+    let images = document
+        .get_page_images(first_page)
+        .map_err(|_| ThumbnailError::Unsupported)?;
+    let largest_jpeg = images
+        .iter()
+        .filter(|image| {
+            image
+                .filters
+                .as_ref()
+                .is_some_and(|filters| filters.len() == 1 && filters[0] == "DCTDecode")
+        })
+        .max_by_key(|image| image.width * image.height)
+        .ok_or(ThumbnailError::Unsupported)?;
+
+    image::load_from_memory_with_format(largest_jpeg.content, ImageFormat::Jpeg).map_err(failed)
+}
@@ -0,0 +1,80 @@
+use crate::db::init::DbState;
+use crate::error::AppError;
+use crate::features::journal::repository::JournalRepository;
+use crate::features::notes::commands::attachments::app_data_dir;
+use crate::features::trash::models::{TrashItem, TrashKind, TrashPolicy, TrashReport};
+use crate::features::trash::repository::TrashRepository;
+use tauri::{AppHandle, State};
+
+#[tauri::command]
+pub async fn get_trash(
+    app_handle: AppHandle,
+    db_state: State<'_, DbState>,
+) -> Result<Vec<TrashItem>, AppError> {
+    let conn = db_state.0.lock()?;
+
+    let app_data_dir = app_data_dir(&app_handle)?;
+    TrashRepository::new(&conn, &app_data_dir).list()
+}
+
+#[tauri::command]
+pub async fn restore_from_trash(
+    kind: TrashKind,
+    id: i64,
+    app_handle: AppHandle,
+    db_state: State<'_, DbState>,
+) -> Result<(), AppError> {
+    let conn = db_state.0.lock()?;
+
+    let app_data_dir = app_data_dir(&app_handle)?;
+    JournalRepository::new(&conn).record("restore_from_trash", || {
+        TrashRepository::new(&conn, &app_data_dir).restore(kind, id)
+    })
+}
+
+#[tauri::command]
+pub async fn purge_from_trash(
+    kind: TrashKind,
+    id: i64,
+    app_handle: AppHandle,
+    db_state: State<'_, DbState>,
+) -> Result<(), AppError> {
+    let conn = db_state.0.lock()?;
+
+    let app_data_dir = app_data_dir(&app_handle)?;
+    TrashRepository::new(&conn, &app_data_dir).purge(kind, id)
+}
+
+#[tauri::command]
+pub async fn empty_trash(
+    app_handle: AppHandle,
+    db_state: State<'_, DbState>,
+) -> Result<TrashReport, AppError> {
+    let conn = db_state.0.lock()?;
+
+    let app_data_dir = app_data_dir(&app_handle)?;
+    TrashRepository::new(&conn, &app_data_dir).empty()
+}
+
+#[tauri::command]
+pub async fn get_trash_policy(
+    app_handle: AppHandle,
+    db_state: State<'_, DbState>,
+) -> Result<TrashPolicy, AppError> {
+    let conn = db_state.0.lock()?;
+
+    let app_data_dir = app_data_dir(&app_handle)?;
+    TrashRepository::new(&conn, &app_data_dir).policy()
+}
+
+#[tauri::command]
+pub async fn set_trash_policy(
+    policy: TrashPolicy,
+    app_handle: AppHandle,
+    db_state: State<'_, DbState>,
+) -> Result<(), AppError> {
+    let conn = db_state.0.lock()?;
+
+    let app_data_dir = app_data_dir(&app_handle)?;
+    TrashRepository::new(&conn, &app_data_dir).set_policy(&policy)
+}
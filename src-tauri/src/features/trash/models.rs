@@ -0,0 +1,34 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// what can be moved to the trash
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrashKind {
+    Note,
+    Folder,
+    Habit,
+}
+
+// an item in the trash view
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrashItem {
+    pub kind: TrashKind,           // what the item is
+    pub id: i64,                   // id of the note, folder or habit
+    pub name: String,              // note title, or folder or habit name
+    pub deleted_at: DateTime<Utc>, // when it was moved to the trash
+}
+
+// how long items stay in the trash before they're purged for good
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrashPolicy {
+    pub auto_empty_days: Option<u32>, // None keeps them until the trash is emptied by hand
+}
+
+// what emptying the trash purged
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TrashReport {
+    pub notes_purged: usize,
+    pub folders_purged: usize,
+    pub habits_purged: usize,
+}
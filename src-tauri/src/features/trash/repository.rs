@@ -0,0 +1,431 @@
+use crate::db::settings::SettingsRepository;
+use crate::db::time::parse_timestamp;
+use crate::error::{AppError, AppResult, EntityKind};
+use crate::features::journal::repository::JournalRepository;
+use crate::features::notes::repository::BlobStore;
+use crate::features::trash::models::{TrashItem, TrashKind, TrashPolicy, TrashReport};
+use chrono::{Duration, Utc};
+use log::info;
+use rusqlite::{params, Connection};
+use std::cmp::Reverse;
+use std::path::Path;
+
+// app_settings key the policy is saved under
+const POLICY_KEY: &str = "trash";
+
+const ALL_KINDS: [TrashKind; 3] = [TrashKind::Note, TrashKind::Folder, TrashKind::Habit];
+
+// a month in the trash, unless the policy was changed
+pub fn default_policy() -> TrashPolicy {
+    TrashPolicy {
+        auto_empty_days: Some(30),
+    }
+}
+
+// the table behind a kind of item, and the column shown as its name
+fn table_of(kind: TrashKind) -> (&'static str, &'static str) {
+    match kind {
+        TrashKind::Note => ("notes", "title"),
+        TrashKind::Folder => ("note_folders", "name"),
+        TrashKind::Habit => ("habits", "name"),
+    }
+}
+
+fn entity_of(kind: TrashKind) -> EntityKind {
+    match kind {
+        TrashKind::Note => EntityKind::Note,
+        TrashKind::Folder => EntityKind::Folder,
+        TrashKind::Habit => EntityKind::Habit,
+    }
+}
+
+pub struct TrashRepository<'a> {
+    conn: &'a Connection,
+    app_data_dir: &'a Path,
+}
+
+impl<'a> TrashRepository<'a> {
+    pub fn new(conn: &'a Connection, app_data_dir: &'a Path) -> Self {
+        TrashRepository { conn, app_data_dir }
+    }
+
+    // everything in the trash, most recently deleted first
+    pub fn list(&self) -> AppResult<Vec<TrashItem>> {
+        let mut items = Vec::new();
+
+        for kind in ALL_KINDS {
+            let (table, name_column) = table_of(kind);
+            let mut stmt = self
+                .conn
+                .prepare(&format!(
+                    "SELECT id, {}, deleted_at FROM {} WHERE deleted_at IS NOT NULL",
+                    name_column, table
+                ))
+                .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                })
+                .map_err(|e| AppError::database("Failed to query trash", e))?;
+
+            for row in rows {
+                let (id, name, deleted_at) =
+                    row.map_err(|e| AppError::database("Failed to process trash row", e))?;
+                items.push(TrashItem {
+                    kind,
+                    id,
+                    name,
+                    deleted_at: parse_timestamp(&deleted_at, "deleted_at")?,
+                });
+            }
+        }
+
+        items.sort_by_key(|item| Reverse(item.deleted_at));
+        Ok(items)
+    }
+
+    // take an item out of the trash. a note or folder whose folder is in the trash too
+    // brings that folder back with it, so it reappears where it was
+    pub fn restore(&self, kind: TrashKind, id: i64) -> AppResult<()> {
+        let (table, _) = table_of(kind);
+
+        let changed = self
+            .conn
+            .execute(
+                &format!(
+                    "UPDATE {} SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL",
+                    table
+                ),
+                params![id],
+            )
+            .map_err(|e| AppError::database("Failed to restore from trash", e))?;
+
+        if changed == 0 {
+            return Err(AppError::not_found(entity_of(kind), id));
+        }
+
+        let parent_sql = match kind {
+            TrashKind::Note => Some("SELECT folder_id FROM notes WHERE id = ?"),
+            TrashKind::Folder => Some("SELECT parent_id FROM note_folders WHERE id = ?"),
+            TrashKind::Habit => None,
+        };
+        if let Some(parent_sql) = parent_sql {
+            let folder_id: Option<i64> = self
+                .conn
+                .query_row(parent_sql, params![id], |row| row.get(0))
+                .map_err(|e| AppError::database("Failed to get parent folder", e))?;
+            self.restore_folders_up_from(folder_id)?;
+        }
+
+        info!("Restored {:?} with ID: {} from the trash", kind, id);
+        Ok(())
+    }
+
+    // delete a trashed item for good, with its revisions, attachments or completions
+    pub fn purge(&self, kind: TrashKind, id: i64) -> AppResult<()> {
+        let (table, _) = table_of(kind);
+
+        let in_trash: bool = self
+            .conn
+            .query_row(
+                &format!(
+                    "SELECT EXISTS (SELECT 1 FROM {} WHERE id = ? AND deleted_at IS NOT NULL)",
+                    table
+                ),
+                params![id],
+                |row| row.get(0),
+            )
+            .map_err(|e| AppError::database("Failed to look up trashed item", e))?;
+
+        if !in_trash {
+            return Err(AppError::not_found(entity_of(kind), id));
+        }
+
+        self.purge_ids(kind, &[id])?;
+        JournalRepository::new(self.conn).seal("purge_from_trash")?;
+
+        info!("Purged {:?} with ID: {} from the trash", kind, id);
+        Ok(())
+    }
+
+    // purge everything in the trash
+    pub fn empty(&self) -> AppResult<TrashReport> {
+        let report = self.purge_deleted_before(None, "empty_trash")?;
+
+        info!(
+            "Emptied the trash: {} notes, {} folders, {} habits",
+            report.notes_purged, report.folders_purged, report.habits_purged
+        );
+        Ok(report)
+    }
+
+    // the saved policy, or the default one if none was saved
+    pub fn policy(&self) -> AppResult<TrashPolicy> {
+        Ok(SettingsRepository::new(self.conn)
+            .get(POLICY_KEY)?
+            .unwrap_or_else(default_policy))
+    }
+
+    pub fn set_policy(&self, policy: &TrashPolicy) -> AppResult<()> {
+        if policy.auto_empty_days == Some(0) {
+            return Err(AppError::validation(
+                "Items must stay in the trash for at least a day",
+            ));
+        }
+        SettingsRepository::new(self.conn).set(POLICY_KEY, policy)?;
+
+        info!("Updated trash policy");
+        Ok(())
+    }
+
+    // purge the items that have been in the trash longer than the policy allows
+    pub fn apply_policy(&self) -> AppResult<TrashReport> {
+        let Some(days) = self.policy()?.auto_empty_days else {
+            return Ok(TrashReport::default());
+        };
+
+        let cutoff = Utc::now() - Duration::days(days as i64);
+        let report = self.purge_deleted_before(Some(&cutoff.to_rfc3339()), "apply_trash_policy")?;
+
+        info!(
+            "Purged {} notes, {} folders and {} habits trashed over {} days ago",
+            report.notes_purged, report.folders_purged, report.habits_purged, days
+        );
+        Ok(report)
+    }
+
+    // purge trashed items deleted before `cutoff`, or all of them, sealing the undo history
+    // under `label` if anything went. deleted_at is only ever written as RFC 3339 in UTC, so
+    // comparing the text compares the dates
+    fn purge_deleted_before(&self, cutoff: Option<&str>, label: &str) -> AppResult<TrashReport> {
+        let mut report = TrashReport::default();
+
+        for kind in ALL_KINDS {
+            let (table, _) = table_of(kind);
+            let mut stmt = self
+                .conn
+                .prepare(&format!(
+                    "SELECT id FROM {}
+                     WHERE deleted_at IS NOT NULL AND (?1 IS NULL OR deleted_at < ?1)",
+                    table
+                ))
+                .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+            let ids = stmt
+                .query_map(params![cutoff], |row| row.get::<_, i64>(0))
+                .map_err(|e| AppError::database("Failed to query trash", e))?
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .map_err(|e| AppError::database("Failed to process trash row", e))?;
+
+            let purged = self.purge_ids(kind, &ids)?;
+            match kind {
+                TrashKind::Note => report.notes_purged = purged,
+                TrashKind::Folder => report.folders_purged = purged,
+                TrashKind::Habit => report.habits_purged = purged,
+            }
+        }
+
+        if report.notes_purged + report.folders_purged + report.habits_purged > 0 {
+            JournalRepository::new(self.conn).seal(label)?;
+        }
+
+        Ok(report)
+    }
+
+    // delete the rows in one savepoint, then release the blobs of the purged notes'
+    // attachments. the attachment rows go with their notes by cascade, which leaves the
+    // blobs unreferenced but still on disk
+    fn purge_ids(&self, kind: TrashKind, ids: &[i64]) -> AppResult<usize> {
+        let blob_hashes = match kind {
+            TrashKind::Note => self.attachment_blobs_of(ids)?,
+            _ => Vec::new(),
+        };
+
+        self.conn
+            .execute_batch("SAVEPOINT purge_trash")
+            .map_err(|e| AppError::database("Failed to start purging the trash", e))?;
+
+        let result = self.delete_ids(kind, ids);
+
+        let end = if result.is_ok() {
+            "RELEASE purge_trash"
+        } else {
+            "ROLLBACK TO purge_trash; RELEASE purge_trash"
+        };
+        self.conn
+            .execute_batch(end)
+            .map_err(|e| AppError::database("Failed to finish purging the trash", e))?;
+        let purged = result?;
+
+        let blobs = BlobStore::new(self.conn, self.app_data_dir);
+        for hash in &blob_hashes {
+            blobs.release(hash)?;
+        }
+
+        Ok(purged)
+    }
+
+    fn delete_ids(&self, kind: TrashKind, ids: &[i64]) -> AppResult<usize> {
+        let (table, _) = table_of(kind);
+
+        // subfolders would cascade with their parent; move them to the top level instead so
+        // they're only purged once they're due themselves. notes already fall back to the root
+        if kind == TrashKind::Folder {
+            for id in ids {
+                self.conn
+                    .execute(
+                        "UPDATE note_folders SET parent_id = NULL WHERE parent_id = ?",
+                        params![id],
+                    )
+                    .map_err(|e| AppError::database("Failed to detach subfolders", e))?;
+            }
+        }
+
+        let mut purged = 0;
+        for id in ids {
+            purged += self
+                .conn
+                .execute(
+                    &format!(
+                        "DELETE FROM {} WHERE id = ? AND deleted_at IS NOT NULL",
+                        table
+                    ),
+                    params![id],
+                )
+                .map_err(|e| AppError::database("Failed to purge from trash", e))?;
+        }
+
+        Ok(purged)
+    }
+
+    // the blobs behind the attachments of trashed notes, each once
+    fn attachment_blobs_of(&self, note_ids: &[i64]) -> AppResult<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT a.blob_hash FROM note_attachments a
+                 JOIN notes n ON n.id = a.note_id
+                 WHERE a.note_id = ? AND a.blob_hash IS NOT NULL AND n.deleted_at IS NOT NULL",
+            )
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let mut hashes: Vec<String> = Vec::new();
+        for note_id in note_ids {
+            let rows = stmt
+                .query_map(params![note_id], |row| row.get::<_, String>(0))
+                .map_err(|e| AppError::database("Failed to query attachments", e))?;
+            for hash in rows {
+                let hash =
+                    hash.map_err(|e| AppError::database("Failed to process attachment row", e))?;
+                if !hashes.contains(&hash) {
+                    hashes.push(hash);
+                }
+            }
+        }
+
+        Ok(hashes)
+    }
+
+    // restore a folder and the trashed folders above it
+    fn restore_folders_up_from(&self, folder_id: Option<i64>) -> AppResult<()> {
+        let Some(folder_id) = folder_id else {
+            return Ok(());
+        };
+
+        // UNION rather than UNION ALL, so a parent cycle ends the walk
+        self.conn
+            .execute(
+                "WITH RECURSIVE ancestors(id) AS (
+                     SELECT ?
+                     UNION
+                     SELECT f.parent_id FROM note_folders f
+                     JOIN ancestors a ON f.id = a.id
+                     WHERE f.parent_id IS NOT NULL
+                 )
+                 UPDATE note_folders SET deleted_at = NULL
+                 WHERE id IN ancestors AND deleted_at IS NOT NULL",
+                params![folder_id],
+            )
+            .map_err(|e| AppError::database("Failed to restore parent folders", e))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::{create_note, note_input, open_test_db, TestDir};
+    use crate::error::ErrorCode;
+    use crate::features::notes::repository::{AttachmentRepository, NoteRepository};
+
+    fn blob_hash(conn: &Connection, attachment_id: i64) -> String {
+        conn.query_row(
+            "SELECT blob_hash FROM note_attachments WHERE id = ?",
+            params![attachment_id],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn purging_a_note_releases_its_attachment_blobs() {
+        let conn = open_test_db();
+        let dir = TestDir::create();
+        let attachments = AttachmentRepository::new(&conn, dir.path());
+        let blobs = BlobStore::new(&conn, dir.path());
+
+        let trashed = create_note(&conn, note_input("trashed", "", &[]));
+        let kept = create_note(&conn, note_input("kept", "", &[]));
+        let only_trashed = attachments
+            .add_data(trashed, "a.txt", "text/plain", b"only in the trash")
+            .unwrap();
+        let shared = attachments
+            .add_data(trashed, "b.txt", "text/plain", b"in both notes")
+            .unwrap();
+        attachments
+            .add_data(kept, "b.txt", "text/plain", b"in both notes")
+            .unwrap();
+
+        let only_trashed = blobs.get(&blob_hash(&conn, only_trashed)).unwrap().unwrap();
+        let shared = blobs.get(&blob_hash(&conn, shared)).unwrap().unwrap();
+
+        NoteRepository::new(&conn).delete(trashed).unwrap();
+        TrashRepository::new(&conn, dir.path())
+            .purge(TrashKind::Note, trashed)
+            .unwrap();
+
+        assert!(blobs.get(&only_trashed.hash).unwrap().is_none());
+        assert!(!dir.path().join(&only_trashed.file_path).exists());
+
+        let shared_after = blobs.get(&shared.hash).unwrap().unwrap();
+        assert_eq!(shared_after.ref_count, 1);
+        assert!(dir.path().join(&shared.file_path).exists());
+    }
+
+    #[test]
+    fn purging_a_note_outside_the_trash_keeps_its_blobs() {
+        let conn = open_test_db();
+        let dir = TestDir::create();
+        let note = create_note(&conn, note_input("live", "", &[]));
+        let attachment = AttachmentRepository::new(&conn, dir.path())
+            .add_data(note, "a.txt", "text/plain", b"still needed")
+            .unwrap();
+        let hash = blob_hash(&conn, attachment);
+
+        let err = TrashRepository::new(&conn, dir.path())
+            .purge(TrashKind::Note, note)
+            .unwrap_err();
+
+        assert_eq!(err.code, ErrorCode::NotFound);
+        assert!(BlobStore::new(&conn, dir.path())
+            .get(&hash)
+            .unwrap()
+            .is_some());
+    }
+}
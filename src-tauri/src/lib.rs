@@ -2,14 +2,15 @@ use std::sync::Mutex;
 use tauri::{Emitter, Manager, Theme};
 use window_vibrancy::apply_acrylic;
 
-mod db;
+pub mod db;
+pub mod error;
 use db::init::*;
 
 // ui imports
 mod ui;
 use ui::theme::*;
 
-mod features;
+pub mod features;
 
 // habits imports
 use features::habits::commands::crud::{
@@ -28,7 +29,11 @@ use features::habits::commands::tag::{create_tag, delete_tag, get_all_tags, upda
 
 // notes imports
 use features::notes::commands::attachments::{
-    add_attachment, delete_attachment, get_attachment_by_id, get_note_attachments, open_attachment,
+    add_attachment, add_attachment_from_bytes, app_data_dir, append_attachment_upload,
+    begin_attachment_upload, cancel_attachment_upload, check_linked_attachments,
+    delete_attachment, finish_attachment_upload, get_attachment_by_id, get_attachment_thumbnail,
+    get_note_attachments, link_attachment, open_attachment, regenerate_attachment_thumbnails,
+    relink_attachment,
 };
 use features::notes::commands::crud::{
     create_note, delete_note, get_note_by_id, get_notes, get_notes_by_folder,
@@ -38,13 +43,35 @@ use features::notes::commands::folders::{
     create_folder, delete_folder, get_all_subfolders_recursive, get_folder_by_id, get_folders,
     get_subfolders, update_folder,
 };
+use features::notes::commands::graph::get_note_graph;
+use features::notes::commands::imports::{import_enex, import_notes};
+use features::notes::commands::links::{
+    get_note_backlinks, get_note_links, get_unresolved_links, rename_note,
+};
+use features::notes::commands::markdown::{export_notes_markdown, import_markdown_vault};
 use features::notes::commands::revisions::{
-    clean_old_revisions, create_revision, delete_revision, get_note_revisions, get_revision_by_id,
-    restore_revision,
+    apply_revision_retention, clean_old_revisions, create_revision, delete_revision, diff_revisions,
+    get_note_revisions, get_revision_by_id, get_revision_retention, restore_revision,
+    set_revision_retention,
+};
+use features::notes::commands::storage::{
+    collect_attachment_garbage, get_attachment_quota, get_attachment_storage_usage,
+    set_attachment_quota,
 };
 use features::notes::commands::tags::{
     create_note_tag, delete_note_tag, get_all_note_tags, get_notes_by_tag, update_note_tag,
 };
+use features::notes::repository::{RetentionRepository, UploadRepository};
+
+// trash imports
+use features::trash::commands::{
+    empty_trash, get_trash, get_trash_policy, purge_from_trash, restore_from_trash,
+    set_trash_policy,
+};
+use features::trash::repository::TrashRepository;
+
+// undo/redo imports
+use features::journal::commands::{get_journal_state, redo, undo};
 
 // for testing...
 #[tauri::command]
@@ -59,6 +86,26 @@ pub fn run() {
         .setup(|app| {
             let db_conn =
                 initialize_database(&app.handle()).expect("Failed to initialize database");
+
+            // thin out revision history by the retention policy
+            if let Err(e) = RetentionRepository::new(&db_conn).apply(None) {
+                log::warn!("Failed to apply revision retention: {}", e);
+            }
+
+            // purge what has been in the trash longer than the policy keeps it
+            if let Err(e) = app_data_dir(app.handle())
+                .and_then(|dir| TrashRepository::new(&db_conn, &dir).apply_policy())
+            {
+                log::warn!("Failed to empty the trash: {}", e);
+            }
+
+            // drop uploads the frontend abandoned and temporary files left by a crash
+            if let Err(e) = app_data_dir(app.handle())
+                .and_then(|dir| UploadRepository::new(&db_conn, &dir).discard_stale())
+            {
+                log::warn!("Failed to discard stale uploads: {}", e);
+            }
+
             app.manage(DbState(Mutex::new(db_conn)));
 
             let window = app.get_webview_window("main").unwrap();
@@ -146,6 +193,17 @@ pub fn run() {
             update_note_tag,
             delete_note_tag,
             get_notes_by_tag,
+            // note link commands
+            get_note_links,
+            get_note_backlinks,
+            get_unresolved_links,
+            rename_note,
+            get_note_graph,
+            // note import/export commands
+            export_notes_markdown,
+            import_markdown_vault,
+            import_enex,
+            import_notes,
             // note revision commands
             get_note_revisions,
             create_revision,
@@ -153,12 +211,41 @@ pub fn run() {
             delete_revision,
             get_revision_by_id,
             clean_old_revisions,
+            diff_revisions,
+            get_revision_retention,
+            set_revision_retention,
+            apply_revision_retention,
             // note attachment commands
             add_attachment,
+            add_attachment_from_bytes,
+            begin_attachment_upload,
+            append_attachment_upload,
+            finish_attachment_upload,
+            cancel_attachment_upload,
+            link_attachment,
+            check_linked_attachments,
+            relink_attachment,
             get_note_attachments,
             delete_attachment,
             get_attachment_by_id,
-            open_attachment
+            open_attachment,
+            get_attachment_thumbnail,
+            regenerate_attachment_thumbnails,
+            collect_attachment_garbage,
+            get_attachment_storage_usage,
+            get_attachment_quota,
+            set_attachment_quota,
+            // trash commands
+            get_trash,
+            restore_from_trash,
+            purge_from_trash,
+            empty_trash,
+            get_trash_policy,
+            set_trash_policy,
+            // undo/redo commands
+            undo,
+            redo,
+            get_journal_state
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,30 @@
+// mirrors `AppError` in src-tauri/src/error.rs
+export type ErrorCode =
+  | "NotFound"
+  | "Conflict"
+  | "Validation"
+  | "Database"
+  | "Io"
+  | "QuotaExceeded"
+  | "NeedsConfirmation";
+
+export interface AppError {
+  code: ErrorCode;
+  entity: string | null;
+  id: number | null;
+  message: string;
+}
+
+export function isAppError(error: unknown): error is AppError {
+  return (
+    typeof error === "object" &&
+    error !== null &&
+    "code" in error &&
+    "message" in error
+  );
+}
+
+// human readable text for anything thrown by `invoke`
+export function describeError(error: unknown): string {
+  return isAppError(error) ? error.message : String(error);
+}